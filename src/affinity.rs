@@ -0,0 +1,153 @@
+//! Similarity between patterns, for placing new ones next to their kin
+//!
+//! [`Placement::Affinity`](crate::ignore::Placement::Affinity) inserts a new
+//! pattern right after the existing entries it most resembles, so related
+//! rules stay clustered without explicit sections. Two patterns are similar
+//! when they share leading directories (`build/` and `build/*.o`), and to a
+//! lesser degree when they share a file extension or an extension family
+//! (`*.o` and `*.so`).
+
+use crate::{
+    ignore::{group_marker_heading, template_marker_name},
+    ignore_file, sort,
+};
+
+/// Extensions that belong together in an ignore file
+const EXTENSION_FAMILIES: &[&[&str]] = &[
+    &[
+        "o", "obj", "a", "lib", "so", "dylib", "dll", "exe", "pdb", "ko",
+    ],
+    &["pyc", "pyo", "pyd"],
+    &["class", "jar", "war", "ear"],
+    &["swp", "swo", "swn", "bak", "orig", "rej", "tmp"],
+    &["zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "rar"],
+    &["log", "out"],
+];
+
+/// How alike two patterns are; 0 means unrelated. Each shared leading
+/// directory outweighs any extension match.
+pub fn similarity(a: &str, b: &str) -> u32 {
+    let shared_directories = directories(a)
+        .iter()
+        .zip(directories(b).iter())
+        .take_while(|(x, y)| x == y)
+        .count() as u32;
+    let extension_score = match (extension(a), extension(b)) {
+        (Some(x), Some(y)) if x.eq_ignore_ascii_case(y) => 2,
+        (Some(x), Some(y)) if same_family(x, y) => 1,
+        _ => 0,
+    };
+    shared_directories * 3 + extension_score
+}
+
+/// Index after which `pattern` belongs among `lines`: the last line of the
+/// most similar existing entry. `None` when nothing is similar, or when the
+/// pattern is a negation (a negation moved before a later pattern could stop
+/// re-including anything). Comments, negations, template blocks and
+/// `--group` blocks are never chosen.
+pub fn insertion_point(lines: &[String], pattern: &str) -> Option<usize> {
+    if pattern.starts_with('!') {
+        return None;
+    }
+    let mut best: Option<(u32, usize)> = None;
+    let mut in_block = false;
+    let mut in_group = false;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if template_marker_name(trimmed, "# BEGIN").is_some() {
+            in_block = true;
+            continue;
+        }
+        if template_marker_name(trimmed, "# END").is_some() {
+            in_block = false;
+            continue;
+        }
+        if group_marker_heading(trimmed).is_some() {
+            in_group = true;
+            continue;
+        }
+        if trimmed.is_empty() {
+            in_group = false;
+            continue;
+        }
+        if in_block || in_group || ignore_file::is_comment(line) || trimmed.starts_with('!') {
+            continue;
+        }
+        let score = similarity(trimmed, pattern);
+        // Ties go to the later line, so the pattern joins the end of a cluster
+        if score > 0 && best.map_or(true, |(top, _)| score >= top) {
+            best = Some((score, index));
+        }
+    }
+    best.map(|(_, index)| index)
+}
+
+/// Leading directories of a pattern, without anchors or `**`: `build/` and
+/// `/build/*.o` both give `["build"]`
+fn directories(pattern: &str) -> Vec<&str> {
+    let pattern = pattern.trim_start_matches('/');
+    let (path, is_dir) = match pattern.strip_suffix('/') {
+        Some(path) => (path, true),
+        None => (pattern, false),
+    };
+    let mut components: Vec<&str> = path
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != "**")
+        .collect();
+    if !is_dir {
+        components.pop();
+    }
+    components
+}
+
+/// Extension of a file pattern, unless it is a wildcard (`*.py[co]`)
+fn extension(pattern: &str) -> Option<&str> {
+    sort::extension(pattern).filter(|ext| !ext.contains(['*', '?', '[']))
+}
+
+fn same_family(a: &str, b: &str) -> bool {
+    EXTENSION_FAMILIES.iter().any(|family| {
+        family.iter().any(|ext| ext.eq_ignore_ascii_case(a))
+            && family.iter().any(|ext| ext.eq_ignore_ascii_case(b))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert!(similarity("build/", "build/*.o") > similarity("*.o", "*.so"));
+        assert!(similarity("*.o", "*.o") > similarity("*.o", "*.so"));
+        assert_eq!(similarity("*.log", "node_modules/"), 0);
+        assert_eq!(similarity("/docs/_build/", "docs/api/"), 3);
+    }
+
+    #[test]
+    fn test_insertion_point() {
+        let content = lines(&[
+            "# Build",
+            "target/",
+            "*.o",
+            "*.so",
+            "",
+            "# Logs",
+            "*.log",
+            "!keep.log",
+            "",
+            "# git-ignore group: Generated",
+            "*.pb.go",
+        ]);
+        assert_eq!(insertion_point(&content, "*.dll"), Some(3));
+        assert_eq!(insertion_point(&content, "target/debug/"), Some(1));
+        assert_eq!(insertion_point(&content, "debug.log"), Some(6));
+        assert_eq!(insertion_point(&content, "x.pb.go"), None);
+        assert_eq!(insertion_point(&content, "!*.so"), None);
+        assert_eq!(insertion_point(&content, ".env"), None);
+    }
+}
@@ -0,0 +1,187 @@
+//! Syntax of gitattributes lines
+//!
+//! `.gitattributes`, `.git/info/attributes` and the global attributes file
+//! are edited with the same engine as ignore files (deduplication,
+//! sections, sorted insertion, atomic writes); only the line syntax
+//! differs. Each line is a pattern followed by whitespace-separated
+//! attributes: `text`, `-diff`, `!merge` or `eol=lf`. A line whose pattern
+//! is `[attr]name` defines a macro attribute.
+
+use crate::{PatternIssue, PatternRule, PatternSeverity};
+
+/// Split an attributes line into its pattern and attributes. Quoted
+/// patterns (`"with space" text`) keep their quotes. Returns `None` for
+/// blank lines and comments.
+pub fn parse_attribute_line(line: &str) -> Option<(&str, Vec<&str>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let pattern_end = if line.starts_with('"') {
+        let mut escaped = false;
+        line.char_indices()
+            .skip(1)
+            .find(|&(_, c)| {
+                let closes = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                closes
+            })
+            .map_or(line.len(), |(i, _)| i + 1)
+    } else {
+        line.find(char::is_whitespace).unwrap_or(line.len())
+    };
+    let (pattern, rest) = line.split_at(pattern_end);
+    Some((pattern, rest.split_whitespace().collect()))
+}
+
+/// Rewrite an attributes line with single spaces between its fields, so
+/// the same line written with different spacing is a duplicate
+pub fn normalize_attribute_line(line: &str) -> String {
+    match parse_attribute_line(line) {
+        Some((pattern, attributes)) if !attributes.is_empty() => {
+            format!("{pattern} {}", attributes.join(" "))
+        }
+        _ => line.trim().to_string(),
+    }
+}
+
+/// Validate attributes lines about to be added
+pub fn validate_attribute_lines(lines: &[String]) -> Vec<PatternIssue> {
+    let mut issues = Vec::new();
+    for line in lines {
+        let mut issue = |rule: PatternRule, severity: PatternSeverity, message: String| {
+            issues.push(PatternIssue {
+                pattern: line.clone(),
+                severity,
+                message,
+                rule,
+            });
+        };
+
+        if line.contains(['\n', '\r']) {
+            issue(
+                PatternRule::LineBreak,
+                PatternSeverity::Error,
+                "Contains line breaks".to_string(),
+            );
+            continue;
+        }
+        let Some((pattern, attributes)) = parse_attribute_line(line) else {
+            continue;
+        };
+
+        if let Some(name) = pattern.strip_prefix("[attr]") {
+            if !is_attribute_name(name) {
+                issue(
+                    PatternRule::InvalidAttribute,
+                    PatternSeverity::Error,
+                    format!("'{name}' is not a valid macro name"),
+                );
+            }
+        } else if pattern.starts_with('!') {
+            issue(
+                PatternRule::NegatedAttributePattern,
+                PatternSeverity::Error,
+                "Negative patterns are forbidden in gitattributes; unset attributes with '-attr' or '!attr' instead".to_string(),
+            );
+        } else if pattern.ends_with('/') || pattern.ends_with("/\"") {
+            issue(
+                PatternRule::DirectoryAttributePattern,
+                PatternSeverity::Warning,
+                format!(
+                    "Never matches files; use '{}**' for everything inside the directory",
+                    pattern.trim_end_matches('"')
+                ),
+            );
+        }
+
+        if attributes.is_empty() {
+            issue(
+                PatternRule::MissingAttributes,
+                PatternSeverity::Error,
+                "No attributes given; add at least one, e.g. 'binary' or 'text eol=lf'".to_string(),
+            );
+        }
+        for attribute in attributes {
+            if let Some(problem) = attribute_problem(attribute) {
+                issue(
+                    PatternRule::InvalidAttribute,
+                    PatternSeverity::Error,
+                    format!("'{attribute}': {problem}"),
+                );
+            }
+        }
+    }
+    issues
+}
+
+/// What is wrong with one attribute assignment, if anything
+fn attribute_problem(attribute: &str) -> Option<&'static str> {
+    let (prefix, rest) = match attribute.chars().next() {
+        Some(c @ ('-' | '!')) => (Some(c), &attribute[1..]),
+        _ => (None, attribute),
+    };
+    let (name, value) = match rest.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (rest, None),
+    };
+    if !is_attribute_name(name) {
+        return Some(
+            "attribute names use letters, digits, '-', '_' and '.', and don't start with '-'",
+        );
+    }
+    if prefix.is_some() && value.is_some() {
+        return Some("an unset or unspecified attribute can't have a value");
+    }
+    None
+}
+
+fn is_attribute_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(line: &str) -> Vec<String> {
+        validate_attribute_lines(&[line.to_string()])
+            .iter()
+            .map(|issue| issue.rule.id().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_attribute_line() {
+        assert_eq!(
+            parse_attribute_line("*.png  binary -diff"),
+            Some(("*.png", vec!["binary", "-diff"]))
+        );
+        assert_eq!(
+            parse_attribute_line(r#""a \"b\" c" text"#),
+            Some((r#""a \"b\" c""#, vec!["text"]))
+        );
+        assert_eq!(parse_attribute_line("# comment"), None);
+        assert_eq!(
+            normalize_attribute_line(" *.sh\ttext   eol=lf "),
+            "*.sh text eol=lf"
+        );
+    }
+
+    #[test]
+    fn test_validate_attribute_lines() {
+        assert!(rules("* text=auto").is_empty());
+        assert!(rules("*.bin -diff !merge linguist-generated").is_empty());
+        assert!(rules("[attr]binary -diff -merge -text").is_empty());
+        assert_eq!(rules("!*.md text"), vec!["GI010"]);
+        assert_eq!(rules("*.md"), vec!["GI011"]);
+        assert_eq!(rules("*.md -eol=lf"), vec!["GI012"]);
+        assert_eq!(rules("*.md te$t"), vec!["GI012"]);
+        assert_eq!(rules("docs/ linguist-documentation"), vec!["GI013"]);
+    }
+}
@@ -0,0 +1,110 @@
+//! Tracked files that the ignore rules match
+//!
+//! Git never ignores a tracked file, so a pattern added after a file was
+//! committed silently does nothing for it. Such files are usually mistakes:
+//! build output or local settings committed by accident, or a pattern that
+//! is broader than intended. [`tracked_ignored`] finds them, together with
+//! the rule in any scope that matches each one.
+
+use crate::{git, json, lint::LintFinding, sarif, PatternRule, PatternSeverity};
+use std::path::Path;
+
+/// A tracked file and the rule that would ignore it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackedIgnored {
+    /// Path relative to the repository root
+    pub path: String,
+    pub rule: git::IgnoreMatch,
+}
+
+/// Find the tracked files of the current repository that its ignore files
+/// (repository, nested, `info/exclude` and global) match, in index order
+pub fn tracked_ignored() -> anyhow::Result<Vec<TrackedIgnored>> {
+    let files = git::list_tracked_ignored(&[])?;
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    // Tracked files are never reported as ignored unless the index is left out
+    let checks = git::check_ignore(&files, true)?;
+    Ok(checks
+        .into_iter()
+        .filter(|check| check.is_ignored())
+        .filter_map(|check| {
+            Some(TrackedIgnored {
+                path: check.path,
+                rule: check.matched?,
+            })
+        })
+        .collect())
+}
+
+/// Render findings as a JSON array, with sources relative to `root` when
+/// they are inside it
+pub fn to_json(findings: &[TrackedIgnored], root: &Path) -> String {
+    json::array(findings.iter().map(|finding| {
+        let source = finding
+            .rule
+            .source
+            .strip_prefix(root)
+            .unwrap_or(&finding.rule.source);
+        json::Object::new()
+            .str("path", &finding.path)
+            .str("pattern", &finding.rule.pattern)
+            .str("source", &source.to_string_lossy())
+            .num("line", finding.rule.line_number)
+            .str("scope", &finding.rule.scope.to_string())
+            .build()
+    }))
+}
+
+/// Render findings as a SARIF log, each one a GI018 result at the rule
+/// that matches the tracked file
+pub fn to_sarif(findings: &[TrackedIgnored], root: &Path) -> String {
+    let findings: Vec<LintFinding> = findings
+        .iter()
+        .map(|finding| LintFinding {
+            rule: PatternRule::TrackedIgnored,
+            severity: PatternSeverity::Warning,
+            message: format!(
+                "Tracked file '{}' matches this pattern, which has no effect on it while it is tracked",
+                finding.path
+            ),
+            file: finding.rule.source.clone(),
+            line: finding.rule.line_number,
+            text: finding.rule.pattern.clone(),
+            suggestion: Some("git ignore untrack removes it from the index".to_string()),
+        })
+        .collect();
+    sarif::to_sarif(&findings, root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scope;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_reports() {
+        let findings = vec![TrackedIgnored {
+            path: "target/debug/app".to_string(),
+            rule: git::IgnoreMatch {
+                source: PathBuf::from("/repo/.gitignore"),
+                scope: Scope::Repo,
+                line_number: 2,
+                pattern: "target/".to_string(),
+            },
+        }];
+        assert_eq!(
+            to_json(&findings, Path::new("/repo")),
+            r#"[{"path":"target/debug/app","pattern":"target/","source":".gitignore","line":2,"scope":"repo"}]"#
+        );
+        assert_eq!(to_json(&[], Path::new("/repo")), "[]");
+
+        let report = to_sarif(&findings, Path::new("/repo"));
+        assert!(report.contains(r#""ruleId":"GI018","ruleIndex":17,"level":"warning","message":{"text":"Tracked file 'target/debug/app' matches this pattern"#));
+        assert!(report
+            .contains(r#""uri":".gitignore","uriBaseId":"%SRCROOT%"},"region":{"startLine":2}"#));
+    }
+}
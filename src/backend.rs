@@ -0,0 +1,863 @@
+//! The layer between [`git`](crate::git) and an implementation of git
+//!
+//! Finding a repository's directories and reading config values go through
+//! a [`Backend`], so an implementation that doesn't need the `git`
+//! executable can replace the default [`Subprocess`] backend without
+//! touching the rest of the crate: `Gix` (`gix` feature) or `Git2` (`git2`
+//! feature). Commands that change the repository or its config still run
+//! `git`.
+
+use crate::{
+    git::{self, RepoContext},
+    Error,
+};
+use anyhow::{bail, Context};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Where a repository's files are, as a backend finds them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// The git directory; in a linked worktree, its private one
+    pub git_dir: PathBuf,
+    /// The directory shared by all worktrees of the repository
+    pub common_dir: PathBuf,
+    /// Root of the work tree; `None` for a bare repository
+    pub work_tree: Option<PathBuf>,
+}
+
+/// The repository a config lookup sees
+#[derive(Debug, Clone, Copy)]
+pub enum Location<'a> {
+    /// The current repository, or none outside one
+    Current,
+    /// A repository found earlier
+    Repo(&'a RepoContext),
+    /// The repository at this path, ignoring `GIT_DIR` and `GIT_WORK_TREE`
+    /// of this process
+    Isolated(&'a Path),
+}
+
+/// Which config files a lookup reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFile {
+    /// All of them, as git reads them
+    Any,
+    System,
+    Global,
+    Local,
+    Worktree,
+}
+
+/// How a config value is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    /// The value as written
+    Raw,
+    /// `true` or `false`, whichever spelling was written
+    Bool,
+    /// A path, with a leading `~` expanded
+    Path,
+}
+
+/// Repository discovery and config reads
+pub trait Backend: Send + Sync {
+    /// Short name for diagnostics
+    fn name(&self) -> &'static str;
+
+    /// The repository containing `dir`. With `explicit`, the given git
+    /// directory and work tree are used instead of searching, as
+    /// `GIT_DIR` and `GIT_WORK_TREE` do.
+    fn discover(&self, dir: &Path, explicit: Option<(&Path, &Path)>) -> anyhow::Result<Layout>;
+
+    /// Every value of `key` in config order, empty when it is unset
+    fn config_values(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        key: &str,
+        value_type: ValueType,
+    ) -> anyhow::Result<Vec<String>>;
+
+    /// The entries whose keys match the regular expression `pattern`, in
+    /// config order. Section and variable names come back lowercased;
+    /// subsections keep their case.
+    fn config_entries(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        pattern: &str,
+    ) -> anyhow::Result<Vec<(String, String)>>;
+}
+
+/// The backend git.rs uses, chosen at compile time: `Gix` with the `gix`
+/// feature, `Git2` with the `git2` feature, [`Subprocess`] otherwise
+pub fn active() -> &'static dyn Backend {
+    #[cfg(feature = "gix")]
+    return &Gix;
+    #[cfg(all(feature = "git2", not(feature = "gix")))]
+    return &Git2;
+    #[cfg(not(any(feature = "git2", feature = "gix")))]
+    return &Subprocess;
+}
+
+/// Runs the `git` executable
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Subprocess;
+
+impl Subprocess {
+    /// A `git` command for `location`, and the arguments that must come
+    /// before the subcommand
+    fn command(location: Location<'_>) -> (Command, Vec<String>) {
+        match location {
+            Location::Current => (git::git(), Vec::new()),
+            Location::Repo(repo) => (repo.command(), Vec::new()),
+            Location::Isolated(dir) => {
+                let mut command = git::git();
+                // Whatever points git at the current repository must not
+                // leak into it
+                command.env_remove("GIT_DIR").env_remove("GIT_WORK_TREE");
+                (
+                    command,
+                    vec!["-C".to_string(), dir.to_string_lossy().into_owned()],
+                )
+            }
+        }
+    }
+
+    fn file_flag(file: ConfigFile) -> Option<&'static str> {
+        match file {
+            ConfigFile::Any => None,
+            ConfigFile::System => Some("--system"),
+            ConfigFile::Global => Some("--global"),
+            ConfigFile::Local => Some("--local"),
+            ConfigFile::Worktree => Some("--worktree"),
+        }
+    }
+}
+
+impl Backend for Subprocess {
+    fn name(&self) -> &'static str {
+        "subprocess"
+    }
+
+    fn discover(&self, dir: &Path, explicit: Option<(&Path, &Path)>) -> anyhow::Result<Layout> {
+        let command = || {
+            let mut command = Command::new("git");
+            command.current_dir(dir);
+            if let Some((git_dir, work_tree)) = explicit {
+                command
+                    .env("GIT_DIR", git_dir)
+                    .env("GIT_WORK_TREE", work_tree);
+            }
+            command
+        };
+
+        let (output, cwd) = git::run_git_output(
+            command(),
+            &["rev-parse", "--absolute-git-dir", "--git-common-dir"],
+        )
+        .context("Failed to find git directory")?;
+        // Only here does a failing git mean there is no repository
+        if !output.status.success() {
+            bail!(Error::NotARepository(format!(
+                "{} (cwd: {})",
+                String::from_utf8_lossy(&output.stderr).trim(),
+                cwd.display()
+            )));
+        }
+        let output = String::from_utf8_lossy(&output.stdout);
+        let mut lines = output.lines();
+        let (Some(git_dir), Some(common_dir)) = (lines.next(), lines.next()) else {
+            bail!(Error::GitFailed(format!(
+                "unexpected git rev-parse output: {}",
+                output.trim()
+            )));
+        };
+        // git prints a relative common directory relative to `dir`
+        let git_dir = git::validate_git_path(&dir.join(git_dir))?;
+        let common_dir = git::validate_git_path(&dir.join(common_dir))?;
+
+        // Fails in a bare repository, and inside the git directory
+        let work_tree = match git::run_git_with(command(), &["rev-parse", "--show-toplevel"]) {
+            Ok(output) if !output.trim().is_empty() => {
+                Some(git::validate_git_path(Path::new(output.trim()))?)
+            }
+            _ => None,
+        };
+        Ok(Layout {
+            git_dir,
+            common_dir,
+            work_tree,
+        })
+    }
+
+    fn config_values(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        key: &str,
+        value_type: ValueType,
+    ) -> anyhow::Result<Vec<String>> {
+        let (command, mut args) = Self::command(location);
+        args.push("config".to_string());
+        args.extend(Self::file_flag(file).map(str::to_string));
+        match value_type {
+            ValueType::Raw => {}
+            ValueType::Bool => args.push("--type=bool".to_string()),
+            ValueType::Path => args.push("--path".to_string()),
+        }
+        args.extend(["-z", "--get-all", key].map(str::to_string));
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        // Each value ends in a NUL, so values may hold newlines
+        Ok(match git::read_config_with(command, &args)? {
+            Some(output) => output
+                .strip_suffix('\0')
+                .unwrap_or(&output)
+                .split('\0')
+                .map(str::to_string)
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn config_entries(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        pattern: &str,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let (command, mut args) = Self::command(location);
+        args.push("config".to_string());
+        args.extend(Self::file_flag(file).map(str::to_string));
+        args.extend(["--get-regexp", pattern].map(str::to_string));
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        Ok(git::config_entries(git::read_config_with(command, &args)?))
+    }
+}
+
+/// The git directory and work tree a library backend opens instead of
+/// searching from `dir`: `explicit`, else `GIT_DIR` and `GIT_WORK_TREE`.
+/// Relative values are relative to `dir`, as they are for [`Subprocess`].
+#[cfg(any(feature = "git2", feature = "gix"))]
+fn explicit_dirs(
+    dir: &Path,
+    explicit: Option<(&Path, &Path)>,
+) -> Option<(PathBuf, Option<PathBuf>)> {
+    match explicit {
+        Some((git_dir, work_tree)) => Some((git_dir.into(), Some(work_tree.into()))),
+        None => std::env::var_os("GIT_DIR").map(|git_dir| {
+            let work_tree = std::env::var_os("GIT_WORK_TREE").map(|root| dir.join(root));
+            (dir.join(git_dir), work_tree)
+        }),
+    }
+}
+
+/// The [`Layout`] a library backend found from `dir`, resolved as
+/// [`Subprocess`] resolves git's output
+#[cfg(any(feature = "git2", feature = "gix"))]
+fn library_layout(
+    dir: &Path,
+    git_dir: &Path,
+    common_dir: &Path,
+    work_tree: Option<&Path>,
+) -> anyhow::Result<Layout> {
+    let git_dir = git::validate_git_path(git_dir)?;
+    let common_dir = git::validate_git_path(common_dir)?;
+    let work_tree = work_tree.map(git::validate_git_path).transpose()?;
+    // git finds no work tree from inside the git directory
+    let inside_git_dir = crate::paths::canonicalize(dir)
+        .is_ok_and(|dir| dir.starts_with(&git_dir) || dir.starts_with(&common_dir));
+    Ok(Layout {
+        git_dir,
+        common_dir,
+        work_tree: work_tree.filter(|_| !inside_git_dir),
+    })
+}
+
+/// A config value of `key` as `value_type` asks for it, `value` being
+/// `None` for a key written without `=` (which is true)
+#[cfg(any(feature = "git2", feature = "gix"))]
+fn typed_value(
+    key: &str,
+    value: Option<String>,
+    value_type: ValueType,
+    parse_bool: impl Fn(&str) -> Option<bool>,
+) -> anyhow::Result<String> {
+    let Some(value) = value else {
+        return Ok(match value_type {
+            ValueType::Bool => "true".to_string(),
+            ValueType::Raw | ValueType::Path => String::new(),
+        });
+    };
+    Ok(match value_type {
+        ValueType::Raw => value,
+        ValueType::Bool => match parse_bool(&value) {
+            Some(flag) => flag.to_string(),
+            None => bail!(Error::config(format!(
+                "bad boolean config value '{value}' for '{key}'"
+            ))),
+        },
+        ValueType::Path => match (value.strip_prefix('~'), crate::paths::home_dir()) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+                format!("{}{rest}", home.display())
+            }
+            _ => value,
+        },
+    })
+}
+
+/// Reads repositories and config through libgit2 (`git2` feature),
+/// without starting `git`.
+///
+/// Config files are read one by one in the order git reads them, so
+/// multi-valued keys come back in the same order and `GIT_CONFIG_GLOBAL`,
+/// `GIT_CONFIG_SYSTEM` and `GIT_CONFIG_NOSYSTEM` apply as they do for git.
+/// `includeIf` sections are not evaluated.
+#[cfg(feature = "git2")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Git2;
+
+#[cfg(feature = "git2")]
+impl Git2 {
+    /// The git directory and common directory of the repository
+    /// `location` names, `None` outside one
+    fn repository(location: Location<'_>) -> Option<(PathBuf, PathBuf)> {
+        let dirs = |repo: &RepoContext| {
+            (
+                repo.git_dir().to_path_buf(),
+                repo.common_dir().to_path_buf(),
+            )
+        };
+        match location {
+            Location::Current => RepoContext::current().ok().map(dirs),
+            Location::Repo(repo) => Some(dirs(repo)),
+            Location::Isolated(dir) => {
+                let repo = git2::Repository::open_ext(
+                    dir,
+                    git2::RepositoryOpenFlags::empty(),
+                    std::iter::empty::<&std::ffi::OsStr>(),
+                )
+                .ok()?;
+                let git_dir = repo.path().to_path_buf();
+                let common_dir = common_dir(&git_dir);
+                Some((git_dir, common_dir))
+            }
+        }
+    }
+
+    /// The existing config files `file` stands for at `location`, in the
+    /// order git reads them
+    fn config_files(location: Location<'_>, file: ConfigFile) -> anyhow::Result<Vec<PathBuf>> {
+        let any = file == ConfigFile::Any;
+        let mut files = Vec::new();
+        let no_system = std::env::var("GIT_CONFIG_NOSYSTEM")
+            .is_ok_and(|value| git2::Config::parse_bool(value).unwrap_or(false));
+        if (any || file == ConfigFile::System) && !no_system {
+            files.extend(
+                std::env::var_os("GIT_CONFIG_SYSTEM")
+                    .map(PathBuf::from)
+                    .or_else(|| git2::Config::find_system().ok()),
+            );
+        }
+        if any || file == ConfigFile::Global {
+            match std::env::var_os("GIT_CONFIG_GLOBAL") {
+                Some(global) => files.push(PathBuf::from(global)),
+                None => {
+                    let home = crate::paths::home_dir();
+                    files.extend(
+                        std::env::var_os("XDG_CONFIG_HOME")
+                            .filter(|xdg| !xdg.is_empty())
+                            .map(PathBuf::from)
+                            .or_else(|| home.as_ref().map(|home| home.join(".config")))
+                            .map(|config| config.join("git").join("config")),
+                    );
+                    files.extend(home.map(|home| home.join(".gitconfig")));
+                }
+            }
+        }
+        if any || matches!(file, ConfigFile::Local | ConfigFile::Worktree) {
+            match Self::repository(location) {
+                Some((git_dir, common_dir)) => {
+                    if file != ConfigFile::Worktree {
+                        files.push(common_dir.join("config"));
+                    }
+                    if file != ConfigFile::Local {
+                        files.push(git_dir.join("config.worktree"));
+                    }
+                }
+                None if any => {}
+                None => bail!(Error::config("not in a git repository")),
+            }
+        }
+        files.retain(|path| path.is_file());
+        Ok(files)
+    }
+
+    fn open_config(path: &Path) -> anyhow::Result<git2::Config> {
+        git2::Config::open(path).map_err(|error| {
+            Error::config(format!("{}: {}", path.display(), error.message())).into()
+        })
+    }
+}
+
+/// The common directory of `git_dir`: the one its `commondir` file names,
+/// as in a linked worktree, or `git_dir` itself
+#[cfg(feature = "git2")]
+fn common_dir(git_dir: &Path) -> PathBuf {
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => crate::paths::canonicalize(&git_dir.join(common.trim_end()))
+            .unwrap_or_else(|_| git_dir.join(common.trim_end())),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+#[cfg(feature = "git2")]
+impl Backend for Git2 {
+    fn name(&self) -> &'static str {
+        "git2"
+    }
+
+    fn discover(&self, dir: &Path, explicit: Option<(&Path, &Path)>) -> anyhow::Result<Layout> {
+        let not_a_repository = |error: git2::Error| {
+            Error::NotARepository(format!("{} (cwd: {})", error.message(), dir.display()))
+        };
+        let (repo, work_tree) = match explicit_dirs(dir, explicit) {
+            Some((git_dir, work_tree)) => {
+                let repo = git2::Repository::open(&git_dir).map_err(not_a_repository)?;
+                // Without a work tree, git takes `dir` as its top unless
+                // the repository is bare
+                let work_tree = work_tree.or_else(|| (!repo.is_bare()).then(|| dir.to_path_buf()));
+                (repo, work_tree)
+            }
+            None => {
+                let repo = git2::Repository::open_ext(
+                    dir,
+                    git2::RepositoryOpenFlags::FROM_ENV,
+                    std::iter::empty::<&std::ffi::OsStr>(),
+                )
+                .map_err(not_a_repository)?;
+                let work_tree = repo.workdir().map(Path::to_path_buf);
+                (repo, work_tree)
+            }
+        };
+        library_layout(
+            dir,
+            repo.path(),
+            &common_dir(repo.path()),
+            work_tree.as_deref(),
+        )
+    }
+
+    fn config_values(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        key: &str,
+        value_type: ValueType,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut values = Vec::new();
+        for path in Self::config_files(location, file)? {
+            let config = Self::open_config(&path)?;
+            let mut entries = match config.multivar(key, None) {
+                Ok(entries) => entries,
+                Err(error) if error.code() == git2::ErrorCode::NotFound => continue,
+                Err(error) => bail!(Error::config(format!("{key}: {}", error.message()))),
+            };
+            while let Some(entry) = entries.next() {
+                let entry = entry.map_err(|error| Error::config(error.message().to_string()))?;
+                let raw = String::from_utf8_lossy(entry.value_bytes()).into_owned();
+                values.push(typed_value(
+                    key,
+                    entry.has_value().then_some(raw),
+                    value_type,
+                    |value| git2::Config::parse_bool(value).ok(),
+                )?);
+            }
+        }
+        Ok(values)
+    }
+
+    fn config_entries(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        pattern: &str,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let mut found = Vec::new();
+        for path in Self::config_files(location, file)? {
+            let config = Self::open_config(&path)?;
+            let mut entries = config
+                .entries(Some(pattern))
+                .map_err(|error| Error::config(format!("{pattern}: {}", error.message())))?;
+            while let Some(entry) = entries.next() {
+                let entry = entry.map_err(|error| Error::config(error.message().to_string()))?;
+                found.push((
+                    String::from_utf8_lossy(entry.name_bytes()).into_owned(),
+                    String::from_utf8_lossy(entry.value_bytes()).into_owned(),
+                ));
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Reads repositories and config with gitoxide (`gix` feature), in pure
+/// Rust and without starting `git`.
+///
+/// Each level's config files are the ones gitoxide locates, which honors
+/// `GIT_CONFIG_GLOBAL`, `GIT_CONFIG_SYSTEM` and `GIT_CONFIG_NOSYSTEM`;
+/// `includeIf` sections are not evaluated. The key patterns of
+/// [`config_entries`](Backend::config_entries) are Rust regular
+/// expressions, which agree with git's for the patterns this crate uses.
+#[cfg(feature = "gix")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gix;
+
+#[cfg(feature = "gix")]
+impl Gix {
+    /// The git directory and common directory of the repository
+    /// `location` names, `None` outside one
+    fn repository(location: Location<'_>) -> Option<(PathBuf, PathBuf)> {
+        let dirs = |repo: &RepoContext| {
+            (
+                repo.git_dir().to_path_buf(),
+                repo.common_dir().to_path_buf(),
+            )
+        };
+        match location {
+            Location::Current => RepoContext::current().ok().map(dirs),
+            Location::Repo(repo) => Some(dirs(repo)),
+            // Unlike discovery from the environment, this ignores GIT_DIR
+            Location::Isolated(dir) => gix::discover(dir).ok().map(|repo| {
+                (
+                    repo.git_dir().to_path_buf(),
+                    repo.common_dir().to_path_buf(),
+                )
+            }),
+        }
+    }
+
+    /// The config files `file` stands for at `location`, read in the order
+    /// git reads them
+    fn config(
+        location: Location<'_>,
+        file: ConfigFile,
+    ) -> anyhow::Result<gix::config::File<'static>> {
+        use gix::config::{file::Metadata, Source};
+
+        let sources: &[Source] = match file {
+            ConfigFile::Any => &[
+                Source::System,
+                Source::Git,
+                Source::User,
+                Source::Local,
+                Source::Worktree,
+            ],
+            ConfigFile::System => &[Source::System],
+            ConfigFile::Global => &[Source::Git, Source::User],
+            ConfigFile::Local => &[Source::Local],
+            ConfigFile::Worktree => &[Source::Worktree],
+        };
+        let repository = match Self::repository(location) {
+            None if matches!(file, ConfigFile::Local | ConfigFile::Worktree) => {
+                bail!(Error::config("not in a git repository"))
+            }
+            repository => repository,
+        };
+
+        let mut metas: Vec<Metadata> = Vec::new();
+        for &source in sources {
+            let path = match (source, &repository) {
+                (Source::Local, Some((_, common_dir))) => common_dir.join("config"),
+                (Source::Worktree, Some((git_dir, _))) => git_dir.join("config.worktree"),
+                (Source::Local | Source::Worktree, None) => continue,
+                (source, _) => match source.storage_location(&mut |name| std::env::var_os(name)) {
+                    Some(path) => path.into_owned(),
+                    None => continue,
+                },
+            };
+            // `GIT_CONFIG_GLOBAL` names one file for both global sources
+            if path.is_file() && !metas.iter().any(|meta| meta.path.as_ref() == Some(&path)) {
+                metas.push(Metadata {
+                    path: Some(path),
+                    source,
+                    level: 0,
+                    trust: gix::sec::Trust::Full,
+                });
+            }
+        }
+        let home = crate::paths::home_dir();
+        let options = gix::config::file::init::Options {
+            includes: gix::config::file::includes::Options::follow_without_conditional(
+                home.as_deref(),
+            ),
+            ..Default::default()
+        };
+        Ok(gix::config::File::from_paths_metadata(metas, options)
+            .map_err(|error| Error::config(error.to_string()))?
+            .unwrap_or_default())
+    }
+
+    /// Every entry of `config` in order, keyed as `git config` prints keys
+    /// (section and variable names lowercased), with `None` for a key
+    /// written without `=`
+    fn entries(config: &gix::config::File<'_>) -> Vec<(String, Option<String>)> {
+        let mut entries = Vec::new();
+        for section in config.sections() {
+            let header = section.header();
+            let mut prefix = header.name().to_string().to_lowercase();
+            if let Some(subsection) = header.subsection_name() {
+                prefix = format!("{prefix}.{subsection}");
+            }
+            let body = section.body();
+            let pairs: Vec<(String, String)> = body
+                .clone()
+                .into_iter()
+                .map(|(name, value)| (name.to_string().to_lowercase(), value.to_string()))
+                .collect();
+            for (index, (name, value)) in pairs.iter().enumerate() {
+                // Only the last occurrence of a name can be told apart
+                // from an empty value
+                let last = !pairs[index + 1..].iter().any(|(other, _)| other == name);
+                let implicit = last && body.value_implicit(name) == Some(None);
+                entries.push((
+                    format!("{prefix}.{name}"),
+                    (!implicit).then(|| value.clone()),
+                ));
+            }
+        }
+        entries
+    }
+}
+
+#[cfg(feature = "gix")]
+impl Backend for Gix {
+    fn name(&self) -> &'static str {
+        "gix"
+    }
+
+    fn discover(&self, dir: &Path, explicit: Option<(&Path, &Path)>) -> anyhow::Result<Layout> {
+        let not_a_repository = |error: &dyn std::fmt::Display| {
+            Error::NotARepository(format!("{error} (cwd: {})", dir.display()))
+        };
+        match explicit_dirs(dir, explicit) {
+            Some((git_dir, work_tree)) => {
+                let repo = gix::open(&git_dir).map_err(|error| not_a_repository(&error))?;
+                // Without a work tree, git takes `dir` as its top unless
+                // the repository is bare
+                let work_tree = work_tree.or_else(|| (!repo.is_bare()).then(|| dir.to_path_buf()));
+                library_layout(dir, repo.git_dir(), repo.common_dir(), work_tree.as_deref())
+            }
+            None => {
+                let repo = gix::discover(dir).map_err(|error| not_a_repository(&error))?;
+                library_layout(dir, repo.git_dir(), repo.common_dir(), repo.workdir())
+            }
+        }
+    }
+
+    fn config_values(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        key: &str,
+        value_type: ValueType,
+    ) -> anyhow::Result<Vec<String>> {
+        let (Some((section, _)), Some((rest, name))) = (key.split_once('.'), key.rsplit_once('.'))
+        else {
+            bail!(Error::config(format!(
+                "key does not contain a section: {key}"
+            )));
+        };
+        // Section and variable names are case-insensitive, subsections not
+        let key = match rest.split_once('.') {
+            Some((_, subsection)) => format!(
+                "{}.{subsection}.{}",
+                section.to_lowercase(),
+                name.to_lowercase()
+            ),
+            None => format!("{}.{}", section.to_lowercase(), name.to_lowercase()),
+        };
+        Self::entries(&Self::config(location, file)?)
+            .into_iter()
+            .filter(|(entry, _)| *entry == key)
+            .map(|(_, value)| {
+                typed_value(&key, value, value_type, |value| {
+                    gix::config::Boolean::try_from(gix::bstr::BStr::new(value))
+                        .ok()
+                        .map(|flag| flag.0)
+                })
+            })
+            .collect()
+    }
+
+    fn config_entries(
+        &self,
+        location: Location<'_>,
+        file: ConfigFile,
+        pattern: &str,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let pattern = regex::Regex::new(pattern)
+            .map_err(|error| Error::config(format!("invalid key pattern: {error}")))?;
+        Ok(Self::entries(&Self::config(location, file)?)
+            .into_iter()
+            .filter(|(key, _)| pattern.is_match(key))
+            .map(|(key, value)| (key, value.unwrap_or_default()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subprocess_config_values() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init", "--quiet"]);
+        git(&["config", "--add", "ignore.test", "one\ntwo"]);
+        git(&["config", "--add", "ignore.test", ""]);
+        git(&["config", "ignore.flag", "yes"]);
+
+        let backend = Subprocess;
+        let layout = backend.discover(dir.path(), None).unwrap();
+        let root = crate::paths::canonicalize(dir.path()).unwrap();
+        assert_eq!(layout.work_tree.as_deref(), Some(root.as_path()));
+        assert_eq!(layout.git_dir, root.join(".git"));
+
+        let at = Location::Isolated(dir.path());
+        let values = |key, value_type| {
+            backend
+                .config_values(at, ConfigFile::Local, key, value_type)
+                .unwrap()
+        };
+        assert_eq!(values("ignore.test", ValueType::Raw), ["one\ntwo", ""]);
+        assert_eq!(values("ignore.flag", ValueType::Bool), ["true"]);
+        assert!(values("ignore.unset", ValueType::Raw).is_empty());
+        assert_eq!(
+            backend
+                .config_entries(at, ConfigFile::Local, "^ignore\\.flag$")
+                .unwrap(),
+            [("ignore.flag".to_string(), "yes".to_string())]
+        );
+    }
+
+    /// The same lookups through `backend` and [`Subprocess`] agree
+    #[cfg(any(feature = "git2", feature = "gix"))]
+    fn assert_matches_subprocess(backend: &dyn Backend) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init", "--quiet"]);
+        git(&["config", "--add", "ignore.test", "one"]);
+        git(&["config", "--add", "ignore.test", "two"]);
+        git(&["config", "ignore.flag", "on"]);
+        git(&["config", "ignore.Sub.pattern", "*.log"]);
+        git(&["config", "ignore.path", "~/ignore"]);
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+
+        let layout = backend.discover(&dir.path().join("src"), None).unwrap();
+        assert_eq!(
+            layout,
+            Subprocess.discover(&dir.path().join("src"), None).unwrap()
+        );
+        let inside = dir.path().join(".git");
+        assert_eq!(
+            backend.discover(&inside, None).unwrap(),
+            Subprocess.discover(&inside, None).unwrap()
+        );
+        assert!(backend.discover(&std::env::temp_dir(), None).is_err());
+
+        let at = Location::Isolated(dir.path());
+        for file in [ConfigFile::Any, ConfigFile::Local] {
+            for (key, value_type) in [
+                ("ignore.test", ValueType::Raw),
+                ("ignore.flag", ValueType::Bool),
+                ("ignore.path", ValueType::Path),
+                ("ignore.unset", ValueType::Raw),
+            ] {
+                assert_eq!(
+                    backend.config_values(at, file, key, value_type).unwrap(),
+                    Subprocess.config_values(at, file, key, value_type).unwrap(),
+                    "{key}"
+                );
+            }
+            assert_eq!(
+                backend.config_entries(at, file, "^ignore\\.").unwrap(),
+                Subprocess.config_entries(at, file, "^ignore\\.").unwrap()
+            );
+        }
+
+        // A linked worktree has its own git directory but shares the config
+        let linked = tempfile::TempDir::new().unwrap();
+        let worktree = linked.path().join("wt");
+        git(&["config", "extensions.worktreeConfig", "true"]);
+        git(&[
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "-m",
+            "initial",
+        ]);
+        git(&["worktree", "add", "--quiet", worktree.to_str().unwrap()]);
+        Command::new("git")
+            .args(["config", "--worktree", "ignore.flag", "off"])
+            .current_dir(&worktree)
+            .output()
+            .unwrap();
+        assert_eq!(
+            backend.discover(&worktree, None).unwrap(),
+            Subprocess.discover(&worktree, None).unwrap()
+        );
+        let at = Location::Isolated(&worktree);
+        for file in [ConfigFile::Any, ConfigFile::Local, ConfigFile::Worktree] {
+            assert_eq!(
+                backend.config_entries(at, file, "^ignore\\.").unwrap(),
+                Subprocess.config_entries(at, file, "^ignore\\.").unwrap(),
+                "{file:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "git2")]
+    #[test]
+    fn test_git2_matches_subprocess() {
+        assert_matches_subprocess(&Git2);
+    }
+
+    #[cfg(feature = "gix")]
+    #[test]
+    fn test_gix_matches_subprocess() {
+        assert_matches_subprocess(&Gix);
+    }
+
+    #[test]
+    fn test_active_backend() {
+        let expected = if cfg!(feature = "gix") {
+            "gix"
+        } else if cfg!(feature = "git2") {
+            "git2"
+        } else {
+            "subprocess"
+        };
+        assert_eq!(active().name(), expected);
+    }
+}
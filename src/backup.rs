@@ -0,0 +1,231 @@
+//! Timestamped safety copies of ignore files before they change
+//!
+//! With a [`BackupPolicy`] installed, every rewrite of an existing file
+//! first saves its old content as `<name>.bak.<UTC timestamp>`, e.g.
+//! `.gitignore.bak.20240501T120000`. Backups of files in the work tree go
+//! to `$GIT_DIR/git-ignore/backups/`, below the file's path in the work
+//! tree, so they never show up as untracked files; other files are backed
+//! up next to themselves. A configured backup directory replaces both,
+//! mirroring each file's absolute path. Only the newest
+//! [`keep`](BackupPolicy::keep) copies of each file are kept.
+
+use crate::{git, paths, timestamp::utc_timestamp};
+use anyhow::Context;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+use tracing::info;
+
+/// Copies of each file kept when no limit is configured
+pub const DEFAULT_KEEP: usize = 5;
+
+/// Policy installed by [`set_policy`]
+static POLICY: Mutex<Option<BackupPolicy>> = Mutex::new(None);
+
+/// Where backups go and how many are kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BackupPolicy {
+    /// Directory for backups, or `None` for the git directory (files in
+    /// the work tree) or next to the file (anything else)
+    pub dir: Option<PathBuf>,
+    /// Backups kept per file, oldest removed first; 0 keeps all of them
+    pub keep: usize,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        BackupPolicy {
+            dir: None,
+            keep: DEFAULT_KEEP,
+        }
+    }
+}
+
+/// Back up files before every later change to them, or stop with `None`
+pub fn set_policy(policy: Option<BackupPolicy>) {
+    *POLICY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = policy;
+}
+
+/// The policy changes are currently made under
+pub fn policy() -> Option<BackupPolicy> {
+    POLICY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Save `old_content` of `path` under the installed policy, if any
+pub(crate) fn before_write(path: &Path, old_content: &str) -> anyhow::Result<()> {
+    if let Some(policy) = policy() {
+        let backup = back_up(path, old_content, &policy)?;
+        info!(path = %backup.display(), "backup written");
+    }
+    Ok(())
+}
+
+/// Write `content` as a new backup of `path` and drop the backups beyond
+/// the policy's limit. Returns the new backup's path.
+pub fn back_up(path: &Path, content: &str, policy: &BackupPolicy) -> anyhow::Result<PathBuf> {
+    let dir = backup_dir(path, policy)?;
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+    let stamp: String = utc_timestamp(SystemTime::now())
+        .chars()
+        .take(19)
+        .filter(|c| *c != '-' && *c != ':')
+        .collect();
+    let prefix = backup_prefix(path)?;
+    let mut backup = dir.join(format!("{prefix}{stamp}"));
+    // Several changes within one second each get their own copy
+    let mut counter = 2;
+    while backup.exists() {
+        backup = dir.join(format!("{prefix}{stamp}-{counter}"));
+        counter += 1;
+    }
+    fs::write(&backup, content)
+        .with_context(|| format!("Failed to write backup: {}", backup.display()))?;
+
+    if policy.keep > 0 {
+        let backups = list_backups(path, policy)?;
+        let excess = backups.len().saturating_sub(policy.keep);
+        for old in &backups[..excess] {
+            fs::remove_file(old)
+                .with_context(|| format!("Failed to remove old backup: {}", old.display()))?;
+        }
+    }
+    Ok(backup)
+}
+
+/// Existing backups of `path` under `policy`, oldest first
+pub fn list_backups(path: &Path, policy: &BackupPolicy) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = backup_dir(path, policy)?;
+    let prefix = backup_prefix(path)?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut backups: Vec<((String, u32), PathBuf)> = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read: {}", dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(key) = name.strip_prefix(&prefix).and_then(order_key) {
+            backups.push((key, entry.path()));
+        }
+    }
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Sort key of a backup suffix (`20240501T120000` or `20240501T120000-3`),
+/// or `None` if the suffix isn't one
+fn order_key(suffix: &str) -> Option<(String, u32)> {
+    let (stamp, counter) = match suffix.split_once('-') {
+        Some((stamp, counter)) => (stamp, counter.parse().ok()?),
+        None => (suffix, 1),
+    };
+    let valid = stamp.len() == 15
+        && stamp
+            .char_indices()
+            .all(|(i, c)| if i == 8 { c == 'T' } else { c.is_ascii_digit() });
+    valid.then(|| (stamp.to_string(), counter))
+}
+
+fn backup_prefix(path: &Path) -> anyhow::Result<String> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("Invalid file path: {}", path.display()))?;
+    Ok(format!("{}.bak.", name.to_string_lossy()))
+}
+
+/// The directory backups of `path` go to: the policy's directory extended
+/// by the file's absolute location so that same-named files from different
+/// places don't mix, or by default [`repository_backup_dir`] or the file's
+/// own directory
+fn backup_dir(path: &Path, policy: &BackupPolicy) -> anyhow::Result<PathBuf> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        git::current_dir()?.join(path)
+    };
+    let parent = absolute
+        .parent()
+        .with_context(|| format!("Invalid file path: {}", path.display()))?;
+    Ok(match &policy.dir {
+        None => repository_backup_dir(parent).unwrap_or_else(|| parent.to_path_buf()),
+        Some(dir) => parent
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part),
+                _ => None,
+            })
+            .fold(dir.clone(), |dir, part| dir.join(part)),
+    })
+}
+
+/// `$GIT_DIR/git-ignore/backups/` extended by `dir`'s path in its work
+/// tree (see [`git::private_dir`]), or `None` when `dir` is outside any
+/// work tree or inside the git directory, where a backup next to the file
+/// is out of the way
+fn repository_backup_dir(dir: &Path) -> Option<PathBuf> {
+    let dir = paths::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let (private, relative) = git::private_dir(&dir)?;
+    Some(private.join("backups").join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_back_up_and_prune() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join(".gitignore");
+        let policy = BackupPolicy { dir: None, keep: 2 };
+
+        let first = back_up(&file, "one\n", &policy).unwrap();
+        let name = first.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(order_key(name.strip_prefix(".gitignore.bak.").unwrap()).is_some());
+        back_up(&file, "two\n", &policy).unwrap();
+        let third = back_up(&file, "three\n", &policy).unwrap();
+
+        let backups = list_backups(&file, &policy).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[1], third);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "two\n");
+        assert!(!first.exists());
+    }
+
+    #[test]
+    fn test_backup_dir_mirrors_location() {
+        let temp = TempDir::new().unwrap();
+        let store = temp.path().join("backups");
+        let policy = BackupPolicy {
+            dir: Some(store.clone()),
+            keep: 0,
+        };
+        let a = temp.path().join("a/.gitignore");
+        let b = temp.path().join("b/.gitignore");
+        let backup_a = back_up(&a, "a\n", &policy).unwrap();
+        back_up(&b, "b\n", &policy).unwrap();
+
+        assert!(backup_a.starts_with(&store));
+        assert!(backup_a.parent().unwrap().ends_with("a"));
+        assert_eq!(list_backups(&a, &policy).unwrap(), vec![backup_a]);
+        assert_eq!(list_backups(&b, &policy).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_order_key() {
+        assert!(order_key("20240501T120000-10") > order_key("20240501T120000-2"));
+        assert!(order_key("20240501T120000-2") > order_key("20240501T120000"));
+        assert_eq!(order_key("20240501T1200"), None);
+        assert_eq!(order_key("old"), None);
+    }
+}
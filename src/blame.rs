@@ -0,0 +1,219 @@
+//! Who added each pattern of an ignore file, and in which commit
+//!
+//! [`blame_patterns`] runs `git blame --line-porcelain` on a tracked
+//! ignore file and keeps the pattern lines, so an overly broad rule can be
+//! traced back to the change that introduced it.
+
+use crate::{
+    git,
+    ignore_file::{classify_line, EntryKind},
+    paths,
+    timestamp::civil_from_days,
+    Error,
+};
+use anyhow::{bail, Context};
+use std::path::Path;
+
+/// The commit that last changed one line of a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineBlame {
+    /// 1-based line number in the current file
+    pub line_number: usize,
+    /// The line, without its line ending
+    pub text: String,
+    /// Full commit id; all zeros for a change not committed yet
+    pub commit: String,
+    pub author: String,
+    /// Author email, without the angle brackets
+    pub author_mail: String,
+    /// Author time in seconds since the Unix epoch
+    pub author_time: i64,
+    /// Author time zone as git writes it, e.g. `+0200`
+    pub author_tz: String,
+    /// First line of the commit message
+    pub summary: String,
+}
+
+impl LineBlame {
+    /// Whether the line is part of a commit rather than a local change
+    pub fn is_committed(&self) -> bool {
+        self.commit.chars().any(|c| c != '0')
+    }
+
+    /// Abbreviated commit id
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+
+    /// The author date as `YYYY-MM-DD`, in the author's time zone
+    pub fn date(&self) -> String {
+        let offset = parse_tz_offset(&self.author_tz).unwrap_or(0);
+        let (year, month, day) = civil_from_days((self.author_time + offset).div_euclid(86_400));
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// Seconds east of UTC for a `+hhmm`/`-hhmm` zone
+fn parse_tz_offset(tz: &str) -> Option<i64> {
+    let (sign, digits) = if let Some(digits) = tz.strip_prefix('+') {
+        (1, digits)
+    } else {
+        (-1, tz.strip_prefix('-')?)
+    };
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parse `git blame --line-porcelain` output into one entry per line
+pub fn parse_line_porcelain(output: &str) -> Vec<LineBlame> {
+    let mut lines = Vec::new();
+    let mut current: Option<LineBlame> = None;
+    for line in output.lines() {
+        if let Some(text) = line.strip_prefix('\t') {
+            if let Some(mut blame) = current.take() {
+                blame.text = text.to_string();
+                lines.push(blame);
+            }
+            continue;
+        }
+        let Some(blame) = current.as_mut() else {
+            // Header: <commit> <original line> <final line> [<group size>]
+            let fields: Vec<&str> = line.split(' ').collect();
+            let is_commit = |id: &str| id.len() >= 40 && id.bytes().all(|b| b.is_ascii_hexdigit());
+            let header = match fields.as_slice() {
+                [commit, _, line_number, ..] if is_commit(commit) => {
+                    line_number.parse().ok().map(|number| (commit, number))
+                }
+                _ => None,
+            };
+            if let Some((commit, line_number)) = header {
+                current = Some(LineBlame {
+                    line_number,
+                    commit: commit.to_string(),
+                    ..LineBlame::default()
+                });
+            }
+            continue;
+        };
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => blame.author = value.to_string(),
+            "author-mail" => {
+                blame.author_mail = value
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            }
+            "author-time" => blame.author_time = value.parse().unwrap_or_default(),
+            "author-tz" => blame.author_tz = value.to_string(),
+            "summary" => blame.summary = value.to_string(),
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Blame the pattern lines of `file`, skipping blank lines and comments.
+/// The file has to be tracked by git.
+pub fn blame_patterns(file: &Path) -> anyhow::Result<Vec<LineBlame>> {
+    if !file.is_file() {
+        bail!("File not found: {}", file.display());
+    }
+    let root = git::get_repo_root()?;
+    let file =
+        paths::canonicalize(file).with_context(|| format!("Failed to read: {}", file.display()))?;
+    let relative = file
+        .strip_prefix(&root)
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+    if !git::list_tracked_files()?.contains(&relative) {
+        bail!(Error::config(format!(
+            "{} isn't tracked by git, so it has no history to blame",
+            file.display()
+        )));
+    }
+    Ok(parse_line_porcelain(&git::blame_porcelain(&file)?)
+        .into_iter()
+        .filter(|blame| classify_line(&blame.text) == EntryKind::Pattern)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORCELAIN: &str = "\
+3f1c2a9d8e7b6a5f4e3d2c1b0a9f8e7d6c5b4a39 1 1 2
+author Jane Doe
+author-mail <jane@example.com>
+author-time 1700000000
+author-tz -0500
+committer Jane Doe
+committer-mail <jane@example.com>
+committer-time 1700000000
+committer-tz -0500
+summary Ignore build output
+filename .gitignore
+\t# Build
+3f1c2a9d8e7b6a5f4e3d2c1b0a9f8e7d6c5b4a39 2 2
+author Jane Doe
+author-mail <jane@example.com>
+author-time 1700000000
+author-tz -0500
+committer Jane Doe
+committer-mail <jane@example.com>
+committer-time 1700000000
+committer-tz -0500
+summary Ignore build output
+filename .gitignore
+\ttarget/
+0000000000000000000000000000000000000000 3 3 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 1700100000
+author-tz +0000
+committer Not Committed Yet
+committer-mail <not.committed.yet>
+committer-time 1700100000
+committer-tz +0000
+summary Version of .gitignore from .gitignore
+previous 3f1c2a9d8e7b6a5f4e3d2c1b0a9f8e7d6c5b4a39 .gitignore
+filename .gitignore
+\t*
+";
+
+    #[test]
+    fn test_parse_line_porcelain() {
+        let lines = parse_line_porcelain(PORCELAIN);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].line_number, 2);
+        assert_eq!(lines[1].text, "target/");
+        assert_eq!(lines[1].author, "Jane Doe");
+        assert_eq!(lines[1].author_mail, "jane@example.com");
+        assert_eq!(lines[1].summary, "Ignore build output");
+        assert_eq!(lines[1].short_commit(), "3f1c2a9");
+        assert!(lines[1].is_committed());
+        assert!(!lines[2].is_committed());
+        assert_eq!(lines[2].text, "*");
+    }
+
+    #[test]
+    fn test_blame_date_uses_author_time_zone() {
+        let blame = |time: i64, tz: &str| LineBlame {
+            author_time: time,
+            author_tz: tz.to_string(),
+            ..LineBlame::default()
+        };
+        // 2023-11-14 22:13:20 UTC
+        assert_eq!(blame(1_700_000_000, "+0000").date(), "2023-11-14");
+        assert_eq!(blame(1_700_000_000, "+0200").date(), "2023-11-15");
+        assert_eq!(blame(1_700_000_000, "-0500").date(), "2023-11-14");
+        assert_eq!(parse_tz_offset("-0130"), Some(-5400));
+        assert_eq!(parse_tz_offset("0100"), None);
+    }
+}
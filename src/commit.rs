@@ -0,0 +1,178 @@
+//! Edit `.gitignore` by writing commits directly, without a work tree
+//!
+//! Hooks and server-side bots usually run in bare repositories, where there
+//! is no `.gitignore` on disk to edit. [`commit_patterns`] reads the file
+//! from the tip of a branch, adds the patterns and records the result as a
+//! new commit on that branch using git's plumbing commands. The branch is
+//! only moved if nobody else updated it meanwhile; otherwise the change is
+//! re-applied on top of the new tip.
+
+use crate::{
+    git,
+    ignore::{self, Placement},
+    Error, RepoContext,
+};
+use anyhow::bail;
+use tracing::{debug_span, info, warn};
+
+/// Path of the file commits edit, relative to the repository root
+const GITIGNORE: &str = ".gitignore";
+
+/// How many times a commit is rebuilt when the branch moves underneath it
+const MAX_COMMIT_ATTEMPTS: usize = 5;
+
+/// Where and how to record the change
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommitOptions {
+    /// Branch to commit to (short name or full ref); defaults to the branch
+    /// `HEAD` points to. A branch that does not exist yet is created from
+    /// `start_point`.
+    pub branch: Option<String>,
+    /// Commit a new branch starts from; defaults to `HEAD`
+    pub start_point: Option<String>,
+    /// Author and committer as `Name <email>`; defaults to git's
+    /// configured identity
+    pub author: Option<String>,
+    /// Commit message; defaults to one listing the added patterns
+    pub message: Option<String>,
+    pub avoid_duplicates: bool,
+    pub placement: Placement,
+}
+
+/// Result of [`commit_patterns`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommitOutcome {
+    /// Full name of the updated ref, e.g. `refs/heads/main`
+    pub reference: String,
+    /// The new commit, or `None` if every pattern was already present
+    pub commit: Option<String>,
+    /// Patterns added by the commit
+    pub added: Vec<String>,
+}
+
+/// Add `patterns` to the `.gitignore` at the tip of a branch by committing
+/// the change.
+///
+/// Refuses to move the branch checked out in a non-bare repository, since
+/// that would leave its index and work tree out of step.
+pub fn commit_patterns(
+    patterns: &[String],
+    options: &CommitOptions,
+) -> anyhow::Result<CommitOutcome> {
+    let reference = git::resolve_branch_ref(options.branch.as_deref())?;
+    let _span = debug_span!("commit_patterns", %reference).entered();
+
+    if !git::is_bare_repository(RepoContext::current()?)?
+        && git::current_branch_ref()?.as_deref() == Some(reference.as_str())
+    {
+        bail!(Error::config(format!("{reference} is checked out here; commit mode would leave the work tree behind (edit .gitignore directly or name another --branch)")));
+    }
+
+    let author = options.author.as_deref().map(parse_identity).transpose()?;
+    let label = format!("{reference}:{GITIGNORE}");
+
+    for attempt in 1..=MAX_COMMIT_ATTEMPTS {
+        // A new branch starts from the start point, HEAD by default
+        let tip = git::resolve_commit(&reference)?;
+        let parent = match (&tip, &options.start_point) {
+            (Some(tip), _) => Some(tip.clone()),
+            (None, Some(start)) => Some(
+                git::resolve_commit(start)?
+                    .ok_or_else(|| Error::config(format!("{start} does not name a commit")))?,
+            ),
+            (None, None) => git::resolve_commit("HEAD")?,
+        };
+        let content = match &parent {
+            Some(parent) => git::read_committed_file(parent, GITIGNORE)?.unwrap_or_default(),
+            None => String::new(),
+        };
+
+        let (updated, added) = ignore::add_patterns_to_content(
+            &label,
+            &content,
+            patterns,
+            options.avoid_duplicates,
+            options.placement.clone(),
+        )?;
+        if added.is_empty() {
+            return Ok(CommitOutcome {
+                reference,
+                commit: None,
+                added,
+            });
+        }
+
+        let message = options
+            .message
+            .clone()
+            .unwrap_or_else(|| default_message(&added));
+        let commit = git::commit_file(parent.as_deref(), GITIGNORE, &updated, &message, author)?;
+        if git::update_ref_if_unchanged(&reference, &commit, tip.as_deref())? {
+            info!(%reference, %commit, "committed patterns");
+            return Ok(CommitOutcome {
+                reference,
+                commit: Some(commit),
+                added,
+            });
+        }
+        warn!(%reference, attempt, "branch moved during commit, retrying");
+    }
+
+    bail!("Concurrent edit: {reference} kept moving while committing; try again")
+}
+
+/// Commit message summarising the added patterns
+pub(crate) fn default_message(added: &[String]) -> String {
+    let mut message = if added.len() == 1 {
+        format!("Ignore {}", added[0])
+    } else {
+        format!("Ignore {} patterns", added.len())
+    };
+    message.push_str("\n\nAdded to .gitignore by git-ignore:\n\n");
+    for pattern in added {
+        message.push_str(&format!("    {pattern}\n"));
+    }
+    message
+}
+
+/// Split `Name <email>` into its parts
+fn parse_identity(identity: &str) -> anyhow::Result<(&str, &str)> {
+    let parsed = identity
+        .strip_suffix('>')
+        .and_then(|rest| rest.split_once('<'))
+        .map(|(name, email)| (name.trim(), email.trim()))
+        .filter(|(name, email)| !name.is_empty() && !email.is_empty());
+    match parsed {
+        Some(identity) => Ok(identity),
+        None => {
+            bail!(Error::config(format!(
+                "--author must look like 'Name <email>', got '{identity}'"
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identity() {
+        assert_eq!(
+            parse_identity("Ignore Bot <bot@example.com>").unwrap(),
+            ("Ignore Bot", "bot@example.com")
+        );
+        assert!(parse_identity("bot@example.com").is_err());
+        assert!(parse_identity("<bot@example.com>").is_err());
+    }
+
+    #[test]
+    fn test_default_message() {
+        assert!(default_message(&["*.log".to_string()]).starts_with("Ignore *.log\n\n"));
+        let message = default_message(&["a".to_string(), "b/".to_string()]);
+        assert!(message.starts_with("Ignore 2 patterns\n"));
+        assert!(message.ends_with("    a\n    b/\n"));
+    }
+}
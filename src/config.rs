@@ -0,0 +1,107 @@
+//! Tool configuration read from git config
+//!
+//! Settings live under the `ignore.` section so they can be set per
+//! repository (`git config ignore.sorted true`) or per user
+//! (`git config --global ignore.sorted true`), with git's usual precedence.
+
+use crate::{
+    backup, git,
+    sets::{self, PatternSet},
+    sort::SortStrategy,
+    Error, Scope,
+};
+use anyhow::Context;
+use std::path::PathBuf;
+
+/// Settings that change the tool's default behavior
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// `ignore.sorted`: insert new patterns at their sorted position instead
+    /// of appending them to the end of the file
+    pub sorted: bool,
+    /// `ignore.affinity`: insert new patterns next to the most similar
+    /// existing ones (see [`crate::affinity`]); `ignore.sorted` wins
+    pub affinity: bool,
+    /// `ignore.sortStrategy`: order used by `sort` and sorted insertion
+    /// (see [`SortStrategy`])
+    pub sort_strategy: SortStrategy,
+    /// `ignore.sectionOrder`: section headings `sort` arranges in this
+    /// order (multi-valued)
+    pub section_order: Vec<String>,
+    /// `ignore.defaultTarget`: file patterns go to when no scope flag is
+    /// given (`repo`, `local` or `global`)
+    pub default_target: Option<Scope>,
+    /// `ignore.validator`: external command that may veto or rewrite
+    /// patterns before they are written (see [`crate::hook`])
+    pub validator: Option<String>,
+    /// `ignore.noCreate`: fail instead of creating a missing target file
+    pub no_create: bool,
+    /// `ignore.autoGlobal`, `ignore.autoLocal` and `ignore.autoRepo`:
+    /// patterns `--auto-target` sends to that scope (see [`crate::route`])
+    pub auto_targets: Vec<(Scope, String)>,
+    /// `ignore.protected`: patterns that removal and cleanup refuse to
+    /// delete unless forced (multi-valued)
+    pub protected: Vec<String>,
+    /// `ignore.backup`: save a timestamped copy of a file before changing
+    /// it (see [`crate::backup`])
+    pub backup: bool,
+    /// `ignore.backupDir`: where backups go instead of the git directory;
+    /// relative values are resolved like `core.excludesFile`
+    pub backup_dir: Option<PathBuf>,
+    /// `ignore.backupKeep`: backups kept per file, 0 for all
+    pub backup_keep: usize,
+    /// `ignore.<name>.pattern`: named pattern sets that `@name` stands for
+    /// (see [`crate::sets`])
+    pub sets: Vec<PatternSet>,
+}
+
+impl Config {
+    /// Load configuration from the effective git config
+    pub fn load() -> anyhow::Result<Config> {
+        Ok(Config {
+            sorted: git::get_config_bool("ignore.sorted")?.unwrap_or(false),
+            affinity: git::get_config_bool("ignore.affinity")?.unwrap_or(false),
+            sort_strategy: git::get_config_value("ignore.sortStrategy")?
+                .map(|value| value.parse())
+                .transpose()
+                .context(Error::config("invalid ignore.sortStrategy"))?
+                .unwrap_or_default(),
+            section_order: git::get_config_values("ignore.sectionOrder")?,
+            default_target: git::get_config_value("ignore.defaultTarget")?
+                .map(|value| value.parse())
+                .transpose()
+                .context(Error::config("invalid ignore.defaultTarget"))?,
+            validator: git::get_config_value("ignore.validator")?
+                .filter(|command| !command.trim().is_empty()),
+            no_create: git::get_config_bool("ignore.noCreate")?.unwrap_or(false),
+            auto_targets: load_auto_targets()?,
+            protected: git::get_config_values("ignore.protected")?,
+            backup: git::get_config_bool("ignore.backup")?.unwrap_or(false),
+            backup_dir: git::get_config_value("ignore.backupDir")?
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| git::resolve_config_path(&dir))
+                .transpose()?,
+            backup_keep: git::get_config_value("ignore.backupKeep")?
+                .map(|value| value.parse())
+                .transpose()
+                .context(Error::config("invalid ignore.backupKeep"))?
+                .unwrap_or(backup::DEFAULT_KEEP),
+            sets: sets::load()?,
+        })
+    }
+}
+
+fn load_auto_targets() -> anyhow::Result<Vec<(Scope, String)>> {
+    let mut targets = Vec::new();
+    for (key, scope) in [
+        ("ignore.autoGlobal", Scope::Global),
+        ("ignore.autoLocal", Scope::Local),
+        ("ignore.autoRepo", Scope::Repo),
+    ] {
+        for value in git::get_config_values(key)? {
+            targets.push((scope, value));
+        }
+    }
+    Ok(targets)
+}
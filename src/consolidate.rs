@@ -0,0 +1,197 @@
+//! Find repository patterns that belong in the global gitignore
+//!
+//! Editor swap files and operating system clutter tend to be copied into
+//! every project's `.gitignore`. When the same pattern of that kind shows up
+//! in most of a person's repositories it is a personal concern: one line in
+//! the global gitignore covers every clone, including the repositories that
+//! never listed it. `prune-global` looks across repositories for such
+//! patterns, classifying them like `--auto-target` does (see
+//! [`route`](crate::route)).
+
+use crate::{
+    ignore::{self, normalize_pattern_for_dedup},
+    route, Error, Observer, Scope,
+};
+use anyhow::{bail, Context};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A pattern repeated across repositories that could move to the global
+/// gitignore
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Candidate {
+    /// The pattern, spelled as in the first repository that has it
+    pub pattern: String,
+    /// Repositories whose `.gitignore` lists the pattern
+    pub repositories: Vec<PathBuf>,
+    /// Why the pattern is considered personal, from [`route::route_pattern`]
+    pub reason: String,
+}
+
+/// Find the git repositories under `root`, up to `max_depth` directories
+/// below it (`root` itself is depth 0). Hidden directories are skipped, and
+/// the search doesn't descend into a repository once found.
+pub fn discover_repositories(root: &Path, max_depth: usize) -> anyhow::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    visit(root, max_depth, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+fn visit(dir: &Path, depth_left: usize, found: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if dir.join(".git").exists() {
+        found.push(dir.to_path_buf());
+        return Ok(());
+    }
+    if depth_left == 0 {
+        return Ok(());
+    }
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Failed to read: {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read: {}", dir.display()))?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        // Symlinks are skipped so a link back up the tree can't loop
+        if !hidden && entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            visit(&entry.path(), depth_left - 1, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Patterns in the top-level `.gitignore` of at least `min_share` (0 to 1)
+/// of `repositories`, and of at least two, that
+/// [`route::route_pattern`] sends to the global gitignore. `overrides` are
+/// the `ignore.auto*` entries. Most widespread first.
+pub fn find_candidates(
+    repositories: &[PathBuf],
+    min_share: f64,
+    overrides: &[(Scope, String)],
+) -> anyhow::Result<Vec<Candidate>> {
+    find_candidates_observed(repositories, min_share, overrides, &mut ())
+}
+
+/// [`find_candidates`], reporting [`Observer::progress`] as each
+/// repository is read
+pub fn find_candidates_observed(
+    repositories: &[PathBuf],
+    min_share: f64,
+    overrides: &[(Scope, String)],
+    observer: &mut dyn Observer,
+) -> anyhow::Result<Vec<Candidate>> {
+    let mut seen: HashMap<String, Candidate> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    for (index, repository) in repositories.iter().enumerate() {
+        if !repository.join(".git").exists() {
+            bail!(Error::NotARepository(repository.display().to_string()));
+        }
+        let mut patterns: Vec<String> =
+            ignore::read_ignore_patterns(&repository.join(".gitignore"))?
+                .into_iter()
+                .collect();
+        patterns.sort();
+        for pattern in patterns {
+            let key = normalize_pattern_for_dedup(&pattern).to_string();
+            let candidate = seen.entry(key.clone()).or_insert_with(|| {
+                order.push(key);
+                Candidate {
+                    pattern,
+                    repositories: Vec::new(),
+                    reason: String::new(),
+                }
+            });
+            if !candidate.repositories.contains(repository) {
+                candidate.repositories.push(repository.clone());
+            }
+        }
+        observer.progress(repository, index + 1, repositories.len());
+    }
+
+    let needed = ((repositories.len() as f64 * min_share).ceil() as usize).max(2);
+    let mut candidates: Vec<Candidate> = order
+        .into_iter()
+        .filter_map(|key| seen.remove(&key))
+        .filter(|candidate| candidate.repositories.len() >= needed)
+        .filter_map(|mut candidate| {
+            let route = route::route_pattern(&candidate.pattern, overrides);
+            (route.scope == Scope::Global).then(|| {
+                candidate.reason = route.reason;
+                candidate
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.repositories
+            .len()
+            .cmp(&a.repositories.len())
+            .then_with(|| a.pattern.cmp(&b.pattern))
+    });
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn repository(root: &Path, name: &str, gitignore: &str) -> PathBuf {
+        let path = root.join(name);
+        fs::create_dir_all(path.join(".git")).unwrap();
+        fs::write(path.join(".gitignore"), gitignore).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_discover_repositories() {
+        let temp = TempDir::new().unwrap();
+        let a = repository(temp.path(), "a", "");
+        let b = repository(temp.path(), "work/b", "");
+        repository(temp.path(), "a/vendor/nested", "");
+        repository(temp.path(), ".cache/hidden", "");
+        repository(temp.path(), "deep/er/still/c", "");
+
+        let found = discover_repositories(temp.path(), 2).unwrap();
+        assert_eq!(found, vec![a, b]);
+    }
+
+    #[test]
+    fn test_find_candidates() {
+        let temp = TempDir::new().unwrap();
+        let repositories = vec![
+            repository(temp.path(), "a", ".DS_Store\n*.swp\ntarget/\n"),
+            repository(temp.path(), "b", ".DS_Store\n.idea/\ntarget/\n"),
+            repository(temp.path(), "c", ".DS_Store\n.idea\n*.swp\n"),
+            repository(temp.path(), "d", "node_modules/\n"),
+        ];
+
+        let candidates = find_candidates(&repositories, 0.5, &[]).unwrap();
+        let found: Vec<(&str, usize)> = candidates
+            .iter()
+            .map(|c| (c.pattern.as_str(), c.repositories.len()))
+            .collect();
+        // target/ is shared by half too, but it is a project artifact
+        assert_eq!(found, vec![(".DS_Store", 3), ("*.swp", 2), (".idea/", 2)]);
+        assert_eq!(candidates[0].reason, "operating system file");
+
+        let candidates = find_candidates(&repositories, 0.75, &[]).unwrap();
+        assert_eq!(candidates.len(), 1);
+
+        let overrides = vec![(Scope::Repo, ".idea".to_string())];
+        let candidates = find_candidates(&repositories, 0.5, &overrides).unwrap();
+        assert!(candidates.iter().all(|c| c.pattern != ".idea/"));
+
+        struct Progress(Vec<(usize, usize)>);
+        impl Observer for Progress {
+            fn progress(&mut self, _item: &Path, done: usize, total: usize) {
+                self.0.push((done, total));
+            }
+        }
+        let mut progress = Progress(Vec::new());
+        find_candidates_observed(&repositories, 0.5, &[], &mut progress).unwrap();
+        assert_eq!(progress.0, [(1, 4), (2, 4), (3, 4), (4, 4)]);
+    }
+}
@@ -0,0 +1,268 @@
+//! The full chain of rules behind whether a path is ignored
+//!
+//! `git check-ignore` names only the rule that wins. [`decision_chain`]
+//! lists every rule that matches along the way, in the order git weighs
+//! them: the global excludes file, `.git/info/exclude`, then each
+//! `.gitignore` from the root down, each source overriding the ones before
+//! it and, within a file, later lines overriding earlier ones. Parent
+//! directories are decided first; once one of them is excluded git doesn't
+//! look inside it, so nothing below can re-include the path.
+
+use crate::{
+    git::{IgnoreCheck, IgnoreMatch},
+    rules::{self, Rule, RuleSource},
+    unignore::root_relative,
+    RepoContext, Scope,
+};
+use std::path::{Path, PathBuf};
+
+/// A rule that matched while deciding a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChainStep {
+    pub rule: Rule,
+    /// What the rule matched: the path itself or one of its parent
+    /// directories, relative to the repository root
+    pub matched: String,
+    /// Whether `matched` is a directory
+    pub is_dir: bool,
+}
+
+/// Every rule that matched a path or its parents, in evaluation order
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecisionChain {
+    /// The path, relative to the repository root
+    pub path: String,
+    pub is_dir: bool,
+    pub steps: Vec<ChainStep>,
+    /// Index into `steps` of the rule that decides, if any: the last rule
+    /// matching the path itself, or the one excluding a parent directory
+    pub deciding: Option<usize>,
+}
+
+impl DecisionChain {
+    /// Whether the rules ignore the path
+    pub fn is_ignored(&self) -> bool {
+        self.decided_by()
+            .is_some_and(|step| !step.rule.pattern.negated)
+    }
+
+    /// The step that decides the outcome
+    pub fn decided_by(&self) -> Option<&ChainStep> {
+        self.deciding.map(|index| &self.steps[index])
+    }
+}
+
+/// The decision chain for `path`, relative to the current directory, in
+/// the current repository. The path needn't exist; a trailing `/`, or an
+/// existing directory, is checked as a directory. Only ignore rules are
+/// considered: git never ignores tracked files, whatever the rules say.
+pub fn decision_chain(path: &str) -> anyhow::Result<DecisionChain> {
+    Ok(current_chain(path)?.0)
+}
+
+/// Whether `path`, relative to the current directory, would be ignored in
+/// the current repository, and by which pattern. The global excludes
+/// file, `.git/info/exclude` and every `.gitignore` on the way to the path
+/// are weighed as in [`decision_chain`]; `matched` is the deciding
+/// pattern, a negation if the path is re-included, and `None` if no
+/// pattern applies. Like `git check-ignore --no-index`, this looks at the
+/// patterns only, so a tracked file can still be reported as ignored.
+///
+/// ```no_run
+/// let check = git_ignore_tool::is_ignored("target/debug/app")?;
+/// if check.is_ignored() {
+///     let matched = check.matched.unwrap();
+///     println!("{}:{}: {}", matched.source.display(), matched.line_number, matched.pattern);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn is_ignored(path: &str) -> anyhow::Result<IgnoreCheck> {
+    let (chain, sources) = current_chain(path)?;
+    Ok(IgnoreCheck {
+        path: path.to_string(),
+        matched: deciding_match(&chain, &sources),
+    })
+}
+
+/// The deciding rule of `chain` as git reports it, with the scope of the
+/// source it came from
+fn deciding_match(chain: &DecisionChain, sources: &[RuleSource]) -> Option<IgnoreMatch> {
+    chain.decided_by().map(|step| IgnoreMatch {
+        scope: sources
+            .iter()
+            .find(|source| source.path == step.rule.source)
+            .map_or(Scope::Repo, |source| source.scope),
+        source: step.rule.source.clone(),
+        line_number: step.rule.line_number,
+        pattern: step.rule.pattern.to_string(),
+    })
+}
+
+/// [`decision_chain`] and the rule sources it was worked out from
+fn current_chain(path: &str) -> anyhow::Result<(DecisionChain, Vec<RuleSource>)> {
+    let repo = RepoContext::current()?;
+    let relative = root_relative(path)?;
+    let is_dir = relative.ends_with('/') || repo.root()?.join(&relative).is_dir();
+    let relative = relative.trim_end_matches('/');
+    let parent = relative.rsplit_once('/').map_or("", |(parent, _)| parent);
+    let sources = rules::effective_ignore_rules(repo, Path::new(parent))?;
+    Ok((decision_chain_from(&sources, relative, is_dir), sources))
+}
+
+/// The decision chain for `path`, relative to `root`, using `.gitignore`
+/// files under `root` plus the given repository-wide ignore files, listed
+/// from lowest to highest precedence
+pub fn decision_chain_in(
+    root: &Path,
+    base_files: &[PathBuf],
+    path: &str,
+    is_dir: bool,
+) -> anyhow::Result<DecisionChain> {
+    // Which scope each base file has doesn't enter the decision
+    let base_files: Vec<(Scope, PathBuf)> = base_files
+        .iter()
+        .map(|file| (Scope::Local, file.clone()))
+        .collect();
+    let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    let sources = rules::effective_rules_in(root, &base_files, parent)?;
+    Ok(decision_chain_from(&sources, path, is_dir))
+}
+
+/// The decision chain for `path` (relative to the repository root) under
+/// the rules of `sources`, as [`effective_ignore_rules`] lists them for
+/// the path's directory
+///
+/// [`effective_ignore_rules`]: rules::effective_ignore_rules
+pub fn decision_chain_from(sources: &[RuleSource], path: &str, is_dir: bool) -> DecisionChain {
+    let mut chain = DecisionChain {
+        path: path.to_string(),
+        is_dir,
+        steps: Vec::new(),
+        deciding: None,
+    };
+    let rules: Vec<&Rule> = sources.iter().flat_map(|source| &source.rules).collect();
+    let components: Vec<&str> = path.split('/').collect();
+    for depth in 1..=components.len() {
+        let current = components[..depth].join("/");
+        let current_is_dir = depth < components.len() || is_dir;
+        let first = chain.steps.len();
+        chain.steps.extend(
+            rules
+                .iter()
+                .filter(|rule| rule.matches(&current, current_is_dir))
+                .map(|rule| ChainStep {
+                    rule: (*rule).clone(),
+                    matched: current.clone(),
+                    is_dir: current_is_dir,
+                }),
+        );
+        let last = (chain.steps.len() > first).then(|| chain.steps.len() - 1);
+
+        if depth == components.len() {
+            chain.deciding = last;
+        } else if last.is_some_and(|index| !chain.steps[index].rule.pattern.negated) {
+            // An excluded directory is not looked into
+            chain.deciding = last;
+            break;
+        }
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn steps(chain: &DecisionChain) -> Vec<(String, String)> {
+        chain
+            .steps
+            .iter()
+            .map(|step| (step.rule.pattern.to_string(), step.matched.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_decision_chain_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let global = root.join("global-ignore");
+        fs::write(&global, "*.log\n").unwrap();
+        fs::write(root.join(".gitignore"), "!keep.log\n*.tmp\n").unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/.gitignore"), "keep.log\n").unwrap();
+
+        let base_files = [global];
+        let chain = decision_chain_in(root, &base_files, "src/keep.log", false).unwrap();
+        assert_eq!(
+            steps(&chain),
+            vec![
+                ("*.log".to_string(), "src/keep.log".to_string()),
+                ("!keep.log".to_string(), "src/keep.log".to_string()),
+                ("keep.log".to_string(), "src/keep.log".to_string()),
+            ]
+        );
+        assert_eq!(chain.deciding, Some(2));
+        assert!(chain.is_ignored());
+
+        let chain = decision_chain_in(root, &base_files, "keep.log", false).unwrap();
+        assert_eq!(chain.deciding, Some(1));
+        assert!(!chain.is_ignored());
+    }
+
+    #[test]
+    fn test_decision_chain_stops_at_excluded_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join(".gitignore"), "build/\n!build/keep.txt\n").unwrap();
+        fs::create_dir(root.join("build")).unwrap();
+        fs::write(root.join("build/.gitignore"), "!keep.txt\n").unwrap();
+
+        let chain = decision_chain_in(root, &[], "build/keep.txt", false).unwrap();
+        assert_eq!(
+            steps(&chain),
+            vec![("build/".to_string(), "build".to_string())]
+        );
+        assert!(chain.is_ignored());
+
+        let chain = decision_chain_in(root, &[], "src/main.rs", false).unwrap();
+        assert!(chain.steps.is_empty());
+        assert!(!chain.is_ignored());
+    }
+
+    #[test]
+    fn test_deciding_match_scope() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let global = root.join("global-ignore");
+        let exclude = root.join("exclude");
+        fs::write(&global, "*.log\n*.swp\n").unwrap();
+        fs::write(&exclude, "notes/\n").unwrap();
+        fs::write(root.join(".gitignore"), "# keep\n!keep.log\n").unwrap();
+        let base_files = [(Scope::Global, global), (Scope::Local, exclude)];
+        let check = |path: &str| {
+            let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+            let sources = rules::effective_rules_in(root, &base_files, parent).unwrap();
+            let chain = decision_chain_from(&sources, path, false);
+            deciding_match(&chain, &sources)
+                .map(|matched| (matched.scope, matched.line_number, matched.pattern))
+        };
+
+        assert_eq!(
+            check("a.swp"),
+            Some((Scope::Global, 2, "*.swp".to_string()))
+        );
+        assert_eq!(
+            check("notes/todo.txt"),
+            Some((Scope::Local, 1, "notes/".to_string()))
+        );
+        assert_eq!(
+            check("keep.log"),
+            Some((Scope::Repo, 2, "!keep.log".to_string()))
+        );
+        assert_eq!(check("src/main.rs"), None);
+    }
+}
@@ -0,0 +1,455 @@
+//! Detect which language templates are relevant to a repository
+//!
+//! Works like GitHub Linguist: tracked files are classified by extension,
+//! vendored and generated paths are skipped, and each language's share of
+//! the source bytes becomes its confidence. Build manifests such as
+//! `Cargo.toml` count as evidence too, so a fresh project with a manifest
+//! but little code is still recognised. Polyglot repositories get one
+//! entry per language.
+//!
+//! Languages are named after the matching template in
+//! [github/gitignore](https://github.com/github/gitignore) (`Rust`, `Node`,
+//! `VisualStudio`, ...).
+//!
+//! [`suggest`] turns the detected languages into a short list of patterns
+//! for their build output and dependency directories (`Cargo.toml` calls
+//! for `target/`, `package.json` for `node_modules/`, Python sources for
+//! `__pycache__/`).
+
+use crate::git;
+use std::collections::BTreeMap;
+
+/// Languages below this share of source bytes are left out unless a
+/// manifest names them
+pub const MIN_CONFIDENCE: f64 = 0.05;
+
+/// Confidence a manifest file gives a language on its own
+const MANIFEST_CONFIDENCE: f64 = 0.5;
+
+/// Source file extensions and the template they belong to
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("py", "Python"),
+    ("pyi", "Python"),
+    ("ipynb", "Python"),
+    ("js", "Node"),
+    ("mjs", "Node"),
+    ("cjs", "Node"),
+    ("jsx", "Node"),
+    ("ts", "Node"),
+    ("tsx", "Node"),
+    ("go", "Go"),
+    ("java", "Java"),
+    ("kt", "Kotlin"),
+    ("kts", "Kotlin"),
+    ("scala", "Scala"),
+    ("c", "C"),
+    ("cc", "C++"),
+    ("cpp", "C++"),
+    ("cxx", "C++"),
+    ("hh", "C++"),
+    ("hpp", "C++"),
+    ("hxx", "C++"),
+    ("cs", "VisualStudio"),
+    ("fs", "VisualStudio"),
+    ("vb", "VisualStudio"),
+    ("rb", "Ruby"),
+    ("swift", "Swift"),
+    ("dart", "Dart"),
+    ("ex", "Elixir"),
+    ("exs", "Elixir"),
+    ("hs", "Haskell"),
+    ("r", "R"),
+    ("jl", "Julia"),
+    ("lua", "Lua"),
+    ("pl", "Perl"),
+    ("pm", "Perl"),
+    ("tex", "TeX"),
+];
+
+/// Manifest and build files that identify a language by name
+const MANIFESTS: &[(&str, &str)] = &[
+    ("Cargo.toml", "Rust"),
+    ("pyproject.toml", "Python"),
+    ("setup.py", "Python"),
+    ("requirements.txt", "Python"),
+    ("package.json", "Node"),
+    ("go.mod", "Go"),
+    ("pom.xml", "Java"),
+    ("build.gradle", "Java"),
+    ("build.gradle.kts", "Kotlin"),
+    ("build.sbt", "Scala"),
+    ("CMakeLists.txt", "C++"),
+    ("Gemfile", "Ruby"),
+    ("Package.swift", "Swift"),
+    ("pubspec.yaml", "Dart"),
+    ("mix.exs", "Elixir"),
+    ("stack.yaml", "Haskell"),
+    ("DESCRIPTION", "R"),
+    ("Project.toml", "Julia"),
+];
+
+/// Directories whose contents are third-party or generated and say nothing
+/// about the project's own languages
+const VENDORED_DIRS: &[&str] = &[
+    "vendor",
+    "node_modules",
+    "third_party",
+    "thirdparty",
+    "external",
+    "deps",
+    "dist",
+    "build",
+    "target",
+    ".venv",
+    "venv",
+];
+
+/// Build output, dependency and cache paths each language leaves in the
+/// work tree. Editor and OS files belong in the global gitignore and are
+/// not suggested.
+const ARTIFACTS: &[(&str, &[&str])] = &[
+    ("Rust", &["target/"]),
+    (
+        "Python",
+        &["__pycache__/", "*.py[cod]", "*.egg-info/", ".venv/"],
+    ),
+    ("Node", &["node_modules/"]),
+    ("Java", &["*.class"]),
+    ("Kotlin", &["*.class", ".gradle/", "build/"]),
+    ("Scala", &["*.class", "target/"]),
+    ("C", &["*.o", "*.a", "*.so"]),
+    ("C++", &["*.o", "*.a", "*.so"]),
+    ("VisualStudio", &["bin/", "obj/", ".vs/"]),
+    ("Ruby", &[".bundle/"]),
+    ("Swift", &[".build/"]),
+    ("Dart", &[".dart_tool/"]),
+    ("Elixir", &["_build/", "deps/"]),
+    ("Haskell", &[".stack-work/", "dist-newstyle/"]),
+    ("R", &[".Rhistory", ".RData"]),
+    ("TeX", &["*.aux", "*.toc", "*.synctex.gz"]),
+];
+
+/// Output directories that depend on the build tool rather than the
+/// language
+const BUILD_TOOL_ARTIFACTS: &[(&str, &[&str])] = &[
+    ("pom.xml", &["target/"]),
+    ("build.gradle", &[".gradle/", "build/"]),
+    ("build.gradle.kts", &[".gradle/", "build/"]),
+    ("CMakeLists.txt", &["build/"]),
+];
+
+/// How relevant one language template is to a repository
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LanguageScore {
+    /// Template name, as in github/gitignore
+    pub template: String,
+    /// Number of source files in the language
+    pub files: usize,
+    /// Total size of those files in bytes
+    pub bytes: u64,
+    /// Manifest files naming the language, relative to the repository root
+    pub manifests: Vec<String>,
+    /// From 0 to 1: the language's share of source bytes, raised to at
+    /// least one half when a manifest names it
+    pub confidence: f64,
+}
+
+/// A pattern proposed for a repository, with the evidence behind it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestion {
+    pub pattern: String,
+    /// Templates of the languages that call for the pattern
+    pub templates: Vec<String>,
+    /// Manifest or source files that gave those languages away, e.g.
+    /// `Rust (Cargo.toml)` or `Python (3 files)`
+    pub reason: String,
+}
+
+/// Rank the language templates relevant to the current repository, most
+/// confident first
+pub fn detect_languages() -> anyhow::Result<Vec<LanguageScore>> {
+    Ok(rank_languages(
+        &work_tree_files(git::list_tracked_files()?)?,
+    ))
+}
+
+/// Like [`detect_languages`], but counting untracked files too, so a
+/// project that has nothing committed yet is still recognised. Files
+/// already ignored are left out.
+pub fn detect_work_tree_languages() -> anyhow::Result<Vec<LanguageScore>> {
+    let mut paths = git::list_tracked_files()?;
+    paths.extend(git::list_untracked_files()?);
+    Ok(rank_languages(&work_tree_files(paths)?))
+}
+
+/// Propose patterns for the build output and dependency directories of
+/// the languages [`detect_work_tree_languages`] finds
+pub fn suggest() -> anyhow::Result<Vec<Suggestion>> {
+    Ok(suggest_patterns(&detect_work_tree_languages()?))
+}
+
+/// Patterns for the build output and dependency directories of
+/// `languages`, in the order the languages are ranked. A pattern several
+/// languages share is listed once with all of them as the reason.
+pub fn suggest_patterns(languages: &[LanguageScore]) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for language in languages {
+        let mut patterns: Vec<&str> = ARTIFACTS
+            .iter()
+            .filter(|(template, _)| *template == language.template)
+            .flat_map(|(_, patterns)| patterns.iter().copied())
+            .collect();
+        for manifest in &language.manifests {
+            let name = manifest.rsplit('/').next().unwrap_or(manifest);
+            for (_, tool_patterns) in BUILD_TOOL_ARTIFACTS
+                .iter()
+                .filter(|(file, _)| *file == name)
+            {
+                patterns.extend(tool_patterns.iter().copied());
+            }
+        }
+
+        let evidence = match language.manifests.first() {
+            Some(manifest) => format!("{} ({manifest})", language.template),
+            None if language.files == 1 => format!("{} (1 file)", language.template),
+            None => format!("{} ({} files)", language.template, language.files),
+        };
+        for pattern in patterns {
+            match suggestions.iter_mut().find(|s| s.pattern == pattern) {
+                Some(existing) if existing.templates.contains(&language.template) => {}
+                Some(existing) => {
+                    existing.templates.push(language.template.clone());
+                    existing.reason = format!("{}, {evidence}", existing.reason);
+                }
+                None => suggestions.push(Suggestion {
+                    pattern: pattern.to_string(),
+                    templates: vec![language.template.clone()],
+                    reason: evidence.clone(),
+                }),
+            }
+        }
+    }
+    suggestions
+}
+
+/// Pair repository-relative paths with their size in the work tree
+fn work_tree_files(paths: Vec<String>) -> anyhow::Result<Vec<(String, u64)>> {
+    let repo_root = git::get_repo_root()?;
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            // Files deleted from the work tree but still tracked count as empty
+            let size = std::fs::symlink_metadata(repo_root.join(&path))
+                .map(|metadata| metadata.len())
+                .unwrap_or(0);
+            (path, size)
+        })
+        .collect())
+}
+
+/// Rank language templates for a list of `(path, size in bytes)` pairs,
+/// with `/`-separated paths relative to the repository root
+pub fn rank_languages(files: &[(String, u64)]) -> Vec<LanguageScore> {
+    let mut totals: BTreeMap<&'static str, LanguageScore> = BTreeMap::new();
+    let mut headers = (0usize, 0u64);
+
+    for (path, size) in files {
+        if is_vendored(path) {
+            continue;
+        }
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        if let Some((_, template)) = MANIFESTS.iter().find(|(manifest, _)| *manifest == name) {
+            let entry = language_entry(&mut totals, template);
+            entry.manifests.push(path.clone());
+        }
+
+        let Some(extension) = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+        else {
+            continue;
+        };
+        if extension == "h" {
+            // Shared by C and C++; attributed once the sources are counted
+            headers.0 += 1;
+            headers.1 += size;
+            continue;
+        }
+        if let Some((_, template)) = EXTENSIONS.iter().find(|(ext, _)| *ext == extension) {
+            let entry = language_entry(&mut totals, template);
+            entry.files += 1;
+            entry.bytes += size;
+        }
+    }
+
+    if headers.0 > 0 {
+        let has_c = totals.get("C").is_some_and(|c| c.files > 0);
+        let has_cpp = totals.get("C++").is_some_and(|cpp| cpp.files > 0);
+        let template = if has_cpp && !has_c { "C++" } else { "C" };
+        let entry = language_entry(&mut totals, template);
+        entry.files += headers.0;
+        entry.bytes += headers.1;
+    }
+
+    let total_bytes: u64 = totals.values().map(|language| language.bytes).sum();
+    let mut ranked: Vec<LanguageScore> = totals
+        .into_values()
+        .map(|mut language| {
+            let share = if total_bytes == 0 {
+                0.0
+            } else {
+                language.bytes as f64 / total_bytes as f64
+            };
+            language.confidence = if language.manifests.is_empty() {
+                share
+            } else {
+                share.max(MANIFEST_CONFIDENCE)
+            };
+            language
+        })
+        .filter(|language| language.confidence >= MIN_CONFIDENCE)
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| a.template.cmp(&b.template))
+    });
+    ranked
+}
+
+fn language_entry<'a>(
+    totals: &'a mut BTreeMap<&'static str, LanguageScore>,
+    template: &'static str,
+) -> &'a mut LanguageScore {
+    totals.entry(template).or_insert_with(|| LanguageScore {
+        template: template.to_string(),
+        files: 0,
+        bytes: 0,
+        manifests: Vec::new(),
+        confidence: 0.0,
+    })
+}
+
+/// Whether `path` lies in a vendored or generated directory, or is a
+/// minified bundle
+fn is_vendored(path: &str) -> bool {
+    let mut components: Vec<&str> = path.split('/').collect();
+    let name = components.pop().unwrap_or_default();
+    name.contains(".min.") || components.iter().any(|dir| VENDORED_DIRS.contains(dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, u64)]) -> Vec<(String, u64)> {
+        entries
+            .iter()
+            .map(|(path, size)| (path.to_string(), *size))
+            .collect()
+    }
+
+    fn templates(scores: &[LanguageScore]) -> Vec<(&str, f64)> {
+        scores
+            .iter()
+            .map(|score| (score.template.as_str(), (score.confidence * 100.0).round()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rank_polyglot_by_bytes() {
+        let scores = rank_languages(&files(&[
+            ("src/main.rs", 7000),
+            ("src/lib.rs", 1000),
+            ("scripts/gen.py", 2000),
+            ("README.md", 50_000),
+            ("tools/x.sh", 10),
+        ]));
+        assert_eq!(templates(&scores), vec![("Rust", 80.0), ("Python", 20.0)]);
+        assert_eq!(scores[0].files, 2);
+        assert_eq!(scores[0].bytes, 8000);
+    }
+
+    #[test]
+    fn test_manifest_raises_confidence() {
+        let scores = rank_languages(&files(&[
+            ("Cargo.toml", 300),
+            ("web/package.json", 400),
+            ("web/index.ts", 100),
+            ("src/main.rs", 9900),
+        ]));
+        assert_eq!(templates(&scores), vec![("Rust", 99.0), ("Node", 50.0)]);
+        assert_eq!(scores[1].manifests, vec!["web/package.json"]);
+
+        // A manifest alone is enough
+        let scores = rank_languages(&files(&[("go.mod", 20)]));
+        assert_eq!(templates(&scores), vec![("Go", 50.0)]);
+    }
+
+    #[test]
+    fn test_vendored_and_minor_languages_skipped() {
+        let scores = rank_languages(&files(&[
+            ("app.py", 10_000),
+            ("vendor/lib/big.go", 1_000_000),
+            ("static/app.min.js", 500_000),
+            ("node_modules/x/index.js", 1_000),
+            ("helper.rb", 100),
+        ]));
+        assert_eq!(templates(&scores), vec![("Python", 99.0)]);
+    }
+
+    #[test]
+    fn test_suggest_patterns() {
+        let languages = rank_languages(&files(&[
+            ("Cargo.toml", 300),
+            ("src/main.rs", 5000),
+            ("tools/gen.py", 2000),
+            ("native/a.c", 500),
+            ("native/b.cpp", 500),
+            ("docs/README.md", 100),
+        ]));
+        let suggestions = suggest_patterns(&languages);
+        let patterns: Vec<&str> = suggestions.iter().map(|s| s.pattern.as_str()).collect();
+        assert_eq!(
+            patterns,
+            vec![
+                "target/",
+                "__pycache__/",
+                "*.py[cod]",
+                "*.egg-info/",
+                ".venv/",
+                "*.o",
+                "*.a",
+                "*.so"
+            ]
+        );
+        assert_eq!(suggestions[0].reason, "Rust (Cargo.toml)");
+        assert_eq!(suggestions[1].reason, "Python (1 file)");
+        assert_eq!(suggestions[5].templates, vec!["C", "C++"]);
+        assert_eq!(suggestions[5].reason, "C (1 file), C++ (1 file)");
+
+        // The build tool adds its own output directory
+        let languages = rank_languages(&files(&[("pom.xml", 100), ("App.java", 100)]));
+        let patterns: Vec<String> = suggest_patterns(&languages)
+            .into_iter()
+            .map(|s| s.pattern)
+            .collect();
+        assert_eq!(patterns, vec!["*.class", "target/"]);
+
+        // Languages with nothing to build leave nothing to suggest
+        assert!(suggest_patterns(&rank_languages(&files(&[("go.mod", 20)]))).is_empty());
+    }
+
+    #[test]
+    fn test_headers_follow_sources() {
+        let scores = rank_languages(&files(&[("a.cpp", 100), ("a.h", 100)]));
+        assert_eq!(templates(&scores), vec![("C++", 100.0)]);
+        let scores = rank_languages(&files(&[("a.c", 100), ("a.h", 100)]));
+        assert_eq!(templates(&scores), vec![("C", 100.0)]);
+    }
+}
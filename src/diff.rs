@@ -0,0 +1,203 @@
+//! Compare the patterns of two ignore sources
+//!
+//! Used by `git ignore diff` to untangle setups where the same rules are
+//! spread over several files. Patterns are paired up by
+//! [`equivalence_key`], so `build` in one file and `/build/` in another
+//! count as different while `docs/api` and `/docs/api/` count as the same.
+//! A negation in one source that re-includes paths a pattern in the other
+//! ignores is reported as a conflict: which one wins depends on the order
+//! git reads the files in.
+
+use crate::{
+    explain::sample_paths,
+    ignore::{self, IgnoreEntry},
+    merge::equivalence_key,
+    pattern::Pattern,
+};
+use std::{collections::HashSet, path::Path};
+
+/// Which of the two compared sources a pattern comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A negation in one source fighting a pattern in the other
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffConflict {
+    /// Source of the negation; the pattern is from the other one
+    pub negation_side: Side,
+    pub negation: IgnoreEntry,
+    pub pattern: IgnoreEntry,
+    /// A path both match (directories end with `/`)
+    pub example: String,
+}
+
+/// How two sources differ, each list in file order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceDiff {
+    pub only_left: Vec<IgnoreEntry>,
+    pub only_right: Vec<IgnoreEntry>,
+    /// Equivalent patterns present in both, as (left, right)
+    pub common: Vec<(IgnoreEntry, IgnoreEntry)>,
+    pub conflicts: Vec<DiffConflict>,
+}
+
+impl SourceDiff {
+    /// Whether the sources hold the same patterns without conflicts
+    pub fn is_empty(&self) -> bool {
+        self.only_left.is_empty() && self.only_right.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// Compare two ignore files; a file that doesn't exist has no patterns
+pub fn diff_files(left: &Path, right: &Path) -> anyhow::Result<SourceDiff> {
+    Ok(diff_entries(
+        &ignore::read_ignore_entries(left)?,
+        &ignore::read_ignore_entries(right)?,
+    ))
+}
+
+/// Compare two lists of entries. A pattern repeated within one source is
+/// only considered where it first appears.
+pub fn diff_entries(left: &[IgnoreEntry], right: &[IgnoreEntry]) -> SourceDiff {
+    let left = first_occurrences(left);
+    let right = first_occurrences(right);
+    let mut diff = SourceDiff::default();
+
+    for (key, entry) in &left {
+        match right.iter().find(|(other, _)| other == key) {
+            Some((_, other)) => diff.common.push(((*entry).clone(), (*other).clone())),
+            None => diff.only_left.push((*entry).clone()),
+        }
+    }
+    diff.only_right = right
+        .iter()
+        .filter(|(key, _)| !left.iter().any(|(other, _)| other == key))
+        .map(|(_, entry)| (*entry).clone())
+        .collect();
+
+    diff.conflicts = negation_conflicts(&left, &right, Side::Left);
+    diff.conflicts
+        .extend(negation_conflicts(&right, &left, Side::Right));
+    diff
+}
+
+/// Entries with their equivalence keys, skipping repeats
+fn first_occurrences(entries: &[IgnoreEntry]) -> Vec<(String, &IgnoreEntry)> {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .map(|entry| (equivalence_key(&entry.pattern), entry))
+        .filter(|(key, _)| seen.insert(key.clone()))
+        .collect()
+}
+
+/// Negations in `negations` that re-include a path some positive pattern
+/// in `patterns` ignores; one conflict per negation. A pattern the
+/// negation's own source also has is left out: that source already
+/// carves its exception out of it.
+fn negation_conflicts(
+    negations: &[(String, &IgnoreEntry)],
+    patterns: &[(String, &IgnoreEntry)],
+    side: Side,
+) -> Vec<DiffConflict> {
+    let positives: Vec<(&IgnoreEntry, Pattern)> = patterns
+        .iter()
+        .filter(|(key, _)| !negations.iter().any(|(own, _)| own == key))
+        .filter_map(|(_, entry)| {
+            let parsed = entry.parsed.clone()?;
+            (!parsed.is_negation()).then_some((*entry, parsed))
+        })
+        .collect();
+
+    let mut conflicts = Vec::new();
+    for (_, entry) in negations {
+        let Some(negation) = entry.parsed.clone().filter(Pattern::is_negation) else {
+            continue;
+        };
+        let negation_samples = sample_paths(&negation);
+        let found = positives.iter().find_map(|(positive_entry, positive)| {
+            negation_samples
+                .iter()
+                .find(|(path, is_dir)| positive.matches(path, *is_dir))
+                .cloned()
+                .or_else(|| {
+                    sample_paths(positive)
+                        .into_iter()
+                        .find(|(path, is_dir)| negation.matches(path, *is_dir))
+                })
+                .map(|example| (*positive_entry, example))
+        });
+        if let Some((pattern, (path, is_dir))) = found {
+            conflicts.push(DiffConflict {
+                negation_side: side,
+                negation: (*entry).clone(),
+                pattern: pattern.clone(),
+                example: if is_dir { format!("{path}/") } else { path },
+            });
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(patterns: &[&str]) -> Vec<IgnoreEntry> {
+        patterns
+            .iter()
+            .enumerate()
+            .map(|(index, pattern)| IgnoreEntry {
+                line_number: index + 1,
+                pattern: pattern.to_string(),
+                raw: pattern.to_string(),
+                parsed: pattern.parse().ok(),
+                comments: Vec::new(),
+                section: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_entries() {
+        let diff = diff_entries(
+            &entries(&["*.log", "build/", "/docs/api", "*.log", ".env"]),
+            &entries(&["build", "docs/api/", "!keep.log", "node_modules/"]),
+        );
+        let patterns =
+            |list: &[IgnoreEntry]| list.iter().map(|e| e.pattern.clone()).collect::<Vec<_>>();
+        assert_eq!(patterns(&diff.only_left), vec!["*.log", ".env"]);
+        assert_eq!(
+            patterns(&diff.only_right),
+            vec!["!keep.log", "node_modules/"]
+        );
+        assert_eq!(diff.common.len(), 2);
+        assert_eq!(diff.common[0].0.pattern, "build/");
+        assert_eq!(diff.common[0].1.pattern, "build");
+        assert_eq!(diff.common[1].1.line_number, 2);
+
+        assert_eq!(diff.conflicts.len(), 1);
+        let conflict = &diff.conflicts[0];
+        assert_eq!(conflict.negation_side, Side::Right);
+        assert_eq!(conflict.negation.pattern, "!keep.log");
+        assert_eq!(conflict.pattern.pattern, "*.log");
+        assert_eq!(conflict.example, "keep.log");
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_identical_sources() {
+        let diff = diff_entries(
+            &entries(&["*.log", "!keep.log", "/docs/api/"]),
+            &entries(&["*.log", "!keep.log", "docs/api"]),
+        );
+        assert!(diff.is_empty());
+        assert_eq!(diff.common.len(), 3);
+    }
+}
@@ -0,0 +1,130 @@
+//! Errors callers may want to tell apart
+//!
+//! Functions still return `anyhow::Result`, but a failure that falls into
+//! one of the categories below carries an [`Error`], possibly under added
+//! context. Use `error.downcast_ref::<Error>()` to find it instead of
+//! inspecting the message; the CLI maps each variant to an exit code.
+
+use std::{io, path::PathBuf, time::Duration};
+
+/// What went wrong, for failures a caller can act on. Each message names
+/// its category, so it reads on its own without a label in front.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The `git` executable couldn't be started
+    #[error("Git not found in PATH")]
+    GitNotFound(#[source] io::Error),
+    /// There is no git repository where one is needed
+    #[error("Not in a git repository: {0}")]
+    NotARepository(String),
+    /// The repository has no work tree, as in a bare repository
+    #[error("Failed to find repository root: {} has no work tree", .0.display())]
+    NoWorkTree(PathBuf),
+    /// A work tree file such as `.gitignore` was requested in a bare
+    /// repository; the local and global scopes still work there
+    #[error(
+        "{} is a bare repository with no work tree file to change; \
+         use the local scope (--local) or global scope (--global) instead",
+        .0.display()
+    )]
+    BareRepository(PathBuf),
+    /// A git command failed
+    #[error("Git command failed: {0}")]
+    GitFailed(String),
+    /// A git command didn't finish in time and was killed
+    #[error(
+        "Git command timed out after {}s: {command} \
+         (raise it with --git-timeout or GIT_IGNORE_TIMEOUT; 0 waits forever)",
+        .timeout.as_secs_f64()
+    )]
+    GitTimeout {
+        /// The command line, starting with `git`
+        command: String,
+        /// How long the command was given
+        timeout: Duration,
+    },
+    /// The global gitignore can't be found or doesn't exist
+    #[error("No global gitignore {0}")]
+    NoGlobalGitignore(String),
+    /// Invalid configuration, or options that can't work together
+    #[error("Configuration error: {0}")]
+    Config(String),
+    /// Patterns failed validation; one message per failure, which may be
+    /// empty when the failures were already reported
+    #[error("Pattern validation failed{}", validation_details(.0))]
+    ValidationFailed(Vec<String>),
+    /// A check command found problems
+    #[error("Check failed: {0}")]
+    CheckFailed(String),
+    /// The file to change doesn't exist and wasn't created
+    #[error("Target file does not exist: {0}")]
+    TargetMissing(String),
+    /// Reading or writing a file failed; `action` is e.g. `read` or
+    /// `write to`
+    #[error("Failed to {action} {}: {source}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+fn validation_details(messages: &[String]) -> String {
+    if messages.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", messages.join("; "))
+    }
+}
+
+impl Error {
+    /// [`Error::Config`] with `message`
+    pub fn config(message: impl Into<String>) -> Error {
+        Error::Config(message.into())
+    }
+
+    /// [`Error::CheckFailed`] with `message`
+    pub fn check_failed(message: impl Into<String>) -> Error {
+        Error::CheckFailed(message.into())
+    }
+
+    /// [`Error::Io`] for `path`
+    pub fn io(action: &'static str, path: impl Into<PathBuf>, source: io::Error) -> Error {
+        Error::Io {
+            action,
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_survives_context() {
+        let error = anyhow::Error::from(Error::config("invalid ignore.forge"))
+            .context("Failed to open pull request");
+        assert_eq!(error.to_string(), "Failed to open pull request");
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::Config(detail)) if detail == "invalid ignore.forge"
+        ));
+
+        let error = anyhow::Error::from(Error::ValidationFailed(Vec::new()));
+        assert_eq!(error.to_string(), "Pattern validation failed");
+        let error = Error::io(
+            "read",
+            "/tmp/.gitignore",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Failed to read /tmp/.gitignore: permission denied"
+        );
+        assert!(std::error::Error::source(&error).is_some());
+    }
+}
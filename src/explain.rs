@@ -0,0 +1,355 @@
+//! Plain-English explanations of gitignore patterns
+//!
+//! Used by `git ignore why <pattern>`. Every example path is checked with
+//! [`Pattern::matches`], so the examples can't drift from the matcher.
+
+use crate::{pattern::Segment, Pattern};
+
+/// Maximum number of example paths shown on each side
+const MAX_EXAMPLES: usize = 3;
+
+/// Concrete paths built from one pattern; each `**` would otherwise triple
+/// them
+const MAX_EXPANSIONS: usize = 27;
+
+/// What a pattern means, in words and by example
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Explanation {
+    /// The pattern as written
+    pub pattern: String,
+    /// One sentence per notable aspect of the pattern
+    pub notes: Vec<String>,
+    /// Example paths the pattern matches (directories end with `/`)
+    pub matches: Vec<String>,
+    /// Similar-looking paths the pattern does not match
+    pub non_matches: Vec<String>,
+}
+
+/// Explain `pattern`, including example paths it would and wouldn't match
+pub fn explain(pattern: &Pattern) -> Explanation {
+    let mut notes = Vec::new();
+    let segments = &pattern.segments;
+    let text = pattern.to_string();
+
+    if pattern.negated {
+        notes.push(
+            "Negated (`!`): re-includes paths that an earlier pattern excluded. It cannot \
+             re-include anything inside a directory that is itself excluded."
+                .to_string(),
+        );
+    }
+
+    if segments.is_empty() {
+        notes.push("A bare `/` names the directory itself and matches nothing.".to_string());
+    } else if segments == &[Segment::DoubleStar] {
+        notes.push("`**` on its own matches every path.".to_string());
+    } else if text.trim_start_matches('!').starts_with('/') {
+        notes.push(
+            "Anchored by the leading `/`: matched against the path relative to the directory \
+             of the ignore file, so it does not match in subdirectories."
+                .to_string(),
+        );
+    } else if pattern.anchored {
+        notes.push(
+            "Contains a `/`, so it is anchored: matched against the full path relative to \
+             the directory of the ignore file, not against names in subdirectories."
+                .to_string(),
+        );
+    } else {
+        notes.push(
+            "Contains no `/` (apart from a trailing one), so it matches a name at any depth \
+             below the directory of the ignore file."
+                .to_string(),
+        );
+    }
+
+    if pattern.dir_only {
+        notes.push(
+            "Trailing `/`: matches directories only (and so everything inside them), never \
+             files of the same name."
+                .to_string(),
+        );
+    }
+
+    let last = segments.len().saturating_sub(1);
+    for (index, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::DoubleStar if segments.len() == 1 => {}
+            Segment::DoubleStar if index == 0 => notes.push(
+                "Leading `**/`: matches in any directory, including the top level.".to_string(),
+            ),
+            Segment::DoubleStar if index == last => notes.push(
+                "Trailing `/**`: matches everything inside the directory, but not the \
+                 directory itself."
+                    .to_string(),
+            ),
+            Segment::DoubleStar => {
+                notes.push("`/**/` in the middle: matches zero or more directories.".to_string())
+            }
+            Segment::Glob(glob) => notes.push(describe_glob(glob)),
+            Segment::Literal(literal) => {
+                if segment.to_string().contains('\\') || literal.starts_with(['#', '!']) {
+                    notes.push(format!(
+                        "The name `{literal}` is matched literally: the backslash escape \
+                         removes the special meaning of the character after it."
+                    ));
+                }
+            }
+        }
+    }
+
+    let (matches, non_matches) = examples(pattern);
+    Explanation {
+        pattern: text,
+        notes,
+        matches,
+        non_matches,
+    }
+}
+
+/// Describe the wildcards in one glob segment
+fn describe_glob(glob: &str) -> String {
+    let mut parts = Vec::new();
+    if glob.contains("**") {
+        parts.push("`**` next to other characters acts like a single `*`");
+    } else if glob.contains('*') {
+        parts.push("`*` matches any run of characters except `/`");
+    }
+    if glob.contains('?') {
+        parts.push("`?` matches exactly one character except `/`");
+    }
+    if glob.contains('[') {
+        parts.push("`[...]` matches one character from the set (`[!...]` negates it)");
+    }
+    format!("In `{glob}`: {}.", parts.join("; "))
+}
+
+/// Pick a concrete path component matching `segment`
+fn concrete_component(segment: &Segment) -> String {
+    match segment {
+        Segment::DoubleStar => "dir".to_string(),
+        Segment::Literal(text) => text.clone(),
+        Segment::Glob(glob) => {
+            let mut out = String::new();
+            let mut chars = glob.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '*' => {
+                        while chars.peek() == Some(&'*') {
+                            chars.next();
+                        }
+                        out.push_str("example");
+                    }
+                    '?' => out.push('x'),
+                    '\\' => out.extend(chars.next()),
+                    '[' => {
+                        let mut class = String::from("[");
+                        for next in chars.by_ref() {
+                            class.push(next);
+                            if next == ']' && class.len() > 2 {
+                                break;
+                            }
+                        }
+                        let class_segment = Segment::Glob(class);
+                        let pick = ('a'..='z')
+                            .chain('A'..='Z')
+                            .chain('0'..='9')
+                            .chain(['_', '-', '.'])
+                            .find(|c| class_segment.matches_component(&c.to_string()))
+                            .unwrap_or('x');
+                        out.push(pick);
+                    }
+                    other => out.push(other),
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Concrete paths built from the pattern, expanding `**` to zero, one or
+/// two directories. Consecutive `**` match what one does, so they expand
+/// once, and at most [`MAX_EXPANSIONS`] paths are kept.
+fn expansions(segments: &[Segment]) -> Vec<Vec<String>> {
+    let mut paths: Vec<Vec<String>> = vec![Vec::new()];
+    let mut previous = None;
+    for segment in segments {
+        let repeated = *segment == Segment::DoubleStar && previous == Some(segment);
+        previous = Some(segment);
+        if repeated {
+            continue;
+        }
+        let options: Vec<Vec<String>> = match segment {
+            Segment::DoubleStar => vec![
+                Vec::new(),
+                vec!["a".to_string()],
+                vec!["a".to_string(), "b".to_string()],
+            ],
+            other => vec![vec![concrete_component(other)]],
+        };
+        paths = paths
+            .iter()
+            .flat_map(|prefix| {
+                options.iter().map(move |option| {
+                    let mut path = prefix.clone();
+                    path.extend(option.iter().cloned());
+                    path
+                })
+            })
+            .take(MAX_EXPANSIONS)
+            .collect();
+    }
+    paths.retain(|path| !path.is_empty());
+    paths
+}
+
+/// Candidate paths close to the pattern, as (path, is_dir)
+fn candidates(pattern: &Pattern) -> Vec<(String, bool)> {
+    let is_dir = pattern.dir_only;
+    let mut out = Vec::new();
+    for components in expansions(&pattern.segments) {
+        let path = components.join("/");
+        let name = components.last().cloned().unwrap_or_default();
+        let parent = components[..components.len() - 1].join("/");
+        let sibling = |new_name: &str| {
+            if parent.is_empty() {
+                new_name.to_string()
+            } else {
+                format!("{parent}/{new_name}")
+            }
+        };
+
+        out.push((path.clone(), is_dir));
+        if is_dir {
+            // The same name as a file
+            out.push((path.clone(), false));
+        }
+        out.push((format!("src/{path}"), is_dir));
+        out.push((format!("src/nested/{path}"), is_dir));
+        out.push((sibling(&format!("{name}.bak")), is_dir));
+        out.push((sibling(&format!("other-{name}")), is_dir));
+        out.push((sibling("other.txt"), false));
+    }
+    out
+}
+
+/// Concrete paths the positive form of `pattern` matches, as
+/// (path, is_dir), for comparing patterns with each other
+pub(crate) fn sample_paths(pattern: &Pattern) -> Vec<(String, bool)> {
+    let mut positive = pattern.clone();
+    positive.negated = false;
+    let mut samples: Vec<(String, bool)> = Vec::new();
+    for (path, is_dir) in candidates(&positive) {
+        if positive.matches(&path, is_dir) && !samples.contains(&(path.clone(), is_dir)) {
+            samples.push((path, is_dir));
+        }
+    }
+    samples
+}
+
+/// Example paths the pattern does and doesn't match
+fn examples(pattern: &Pattern) -> (Vec<String>, Vec<String>) {
+    // Match against the positive form; negation only changes the outcome
+    let mut positive = pattern.clone();
+    positive.negated = false;
+
+    let mut matches: Vec<String> = Vec::new();
+    let mut non_matches: Vec<String> = Vec::new();
+    for (path, is_dir) in candidates(&positive) {
+        let shown = if is_dir {
+            format!("{path}/")
+        } else if positive.dir_only {
+            format!("{path} (a file)")
+        } else {
+            path.clone()
+        };
+        let bucket = if positive.matches(&path, is_dir) {
+            &mut matches
+        } else {
+            &mut non_matches
+        };
+        if bucket.len() < MAX_EXAMPLES && !bucket.contains(&shown) {
+            bucket.push(shown);
+        }
+    }
+    (matches, non_matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain_text(text: &str) -> Explanation {
+        explain(&Pattern::parse(text).unwrap())
+    }
+
+    #[test]
+    fn test_explain_unanchored_glob() {
+        let explanation = explain_text("*.log");
+        assert!(explanation.notes[0].contains("at any depth"));
+        assert!(explanation.notes[1].contains("`*` matches any run"));
+        assert_eq!(
+            explanation.matches,
+            vec!["example.log", "src/example.log", "src/nested/example.log"]
+        );
+        assert!(explanation
+            .non_matches
+            .contains(&"example.log.bak".to_string()));
+    }
+
+    #[test]
+    fn test_explain_anchored_directory() {
+        let explanation = explain_text("/build/");
+        assert!(explanation.notes[0].contains("leading `/`"));
+        assert!(explanation.notes[1].contains("directories only"));
+        assert_eq!(explanation.matches, vec!["build/"]);
+        assert!(explanation.non_matches.contains(&"src/build/".to_string()));
+        assert!(explanation
+            .non_matches
+            .contains(&"build (a file)".to_string()));
+    }
+
+    #[test]
+    fn test_explain_double_star_positions() {
+        let notes = explain_text("**/logs").notes.join("\n");
+        assert!(notes.contains("Leading `**/`"));
+
+        let explanation = explain_text("docs/**");
+        assert!(explanation.notes.join("\n").contains("Trailing `/**`"));
+        assert!(explanation.matches.contains(&"docs/a".to_string()));
+
+        let explanation = explain_text("!a/**/b");
+        assert!(explanation.notes[0].starts_with("Negated"));
+        assert!(explanation.notes.join("\n").contains("zero or more"));
+        assert_eq!(explanation.matches[0], "a/b");
+    }
+
+    #[test]
+    fn test_double_star_expansion_is_bounded() {
+        let pattern = Pattern::parse(&"**/x/".repeat(40)).unwrap();
+        assert!(expansions(&pattern.segments).len() <= MAX_EXPANSIONS);
+        assert!(!explain(&pattern).matches.is_empty());
+
+        // `a/**/**/b` expands like `a/**/b`
+        let collapsed = Pattern::parse("a/**/b").unwrap();
+        let repeated = Pattern::parse("a/**/**/**/b").unwrap();
+        assert_eq!(
+            expansions(&repeated.segments),
+            expansions(&collapsed.segments)
+        );
+    }
+
+    #[test]
+    fn test_examples_match_the_matcher() {
+        for text in ["[Bb]uild/", "file?.txt", "src/*.rs", "[!a]x", "\\#notes"] {
+            let pattern = Pattern::parse(text).unwrap();
+            let explanation = explain(&pattern);
+            assert!(!explanation.matches.is_empty(), "no match for {text}");
+            for example in &explanation.matches {
+                let is_dir = example.ends_with('/');
+                assert!(pattern.matches(example.trim_end_matches('/'), is_dir));
+            }
+        }
+    }
+}
@@ -0,0 +1,511 @@
+//! Propose an ignore change as a pull request (feature `forge`)
+//!
+//! [`create_pull_request`] commits the patterns to a new branch with
+//! [`crate::commit`], pushes the branch and opens a pull request on GitHub
+//! or a merge request on GitLab. The description lists each added pattern
+//! with the reason [`crate::route`] gives for it, so reviewers see why it
+//! belongs in `.gitignore`.
+//!
+//! API requests go through the `curl` command, just as repository access
+//! goes through `git`, so the feature adds no dependencies. Tokens are read
+//! from `GITHUB_TOKEN` (or `GH_TOKEN`) and `GITLAB_TOKEN` and handed to
+//! curl on its standard input, never on its command line.
+
+use crate::{
+    commit::{self, CommitOptions},
+    git, json,
+    route::route_pattern,
+    Error, Scope,
+};
+use anyhow::{bail, Context};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+use tracing::{debug_span, info};
+
+/// Prefix of the branches pull requests are opened from
+const BRANCH_PREFIX: &str = "git-ignore/";
+
+/// A supported code hosting service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Forge {
+    GitHub,
+    GitLab,
+}
+
+impl Forge {
+    /// What the forge calls a proposed change
+    pub fn request_noun(self) -> &'static str {
+        match self {
+            Forge::GitHub => "pull request",
+            Forge::GitLab => "merge request",
+        }
+    }
+
+    /// Environment variables an API token is read from, in order
+    fn token_variables(self) -> &'static [&'static str] {
+        match self {
+            Forge::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+            Forge::GitLab => &["GITLAB_TOKEN"],
+        }
+    }
+}
+
+impl std::str::FromStr for Forge {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "github" => Ok(Forge::GitHub),
+            "gitlab" => Ok(Forge::GitLab),
+            other => bail!("Unknown forge '{other}' (expected github or gitlab)"),
+        }
+    }
+}
+
+impl std::fmt::Display for Forge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Forge::GitHub => "GitHub",
+            Forge::GitLab => "GitLab",
+        })
+    }
+}
+
+/// A repository on a forge, as named by a git remote URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForgeRemote {
+    pub forge: Forge,
+    /// Host name, with the port for HTTP URLs that give one
+    pub host: String,
+    /// Repository path on the host, e.g. `owner/repo` or `group/sub/repo`
+    pub project: String,
+}
+
+impl ForgeRemote {
+    /// Parse an HTTPS, SSH or scp-style remote URL. `forge` names the
+    /// service; without it the host must be `github.com` or `gitlab.com`.
+    /// Any other host, however it is named, needs `forge`: guessing from
+    /// the name would send the token to whoever owns it.
+    pub fn parse(url: &str, forge: Option<Forge>) -> anyhow::Result<ForgeRemote> {
+        let not_hosted = || Error::config(format!("remote URL {url} is not hosted on a forge"));
+
+        let (host, path) = match url.split_once("://") {
+            Some((scheme, rest)) => {
+                let (authority, path) = rest.split_once('/').ok_or_else(not_hosted)?;
+                let host = authority.rsplit('@').next().unwrap_or(authority);
+                let host = if scheme.starts_with("http") {
+                    host
+                } else {
+                    host.split(':').next().unwrap_or(host)
+                };
+                (host, path)
+            }
+            // scp-like syntax: [user@]host:path
+            None => {
+                let (authority, path) = url.split_once(':').ok_or_else(not_hosted)?;
+                if authority.contains('/') {
+                    return Err(not_hosted().into());
+                }
+                (authority.rsplit('@').next().unwrap_or(authority), path)
+            }
+        };
+
+        let project = path.trim_matches('/');
+        let project = project.strip_suffix(".git").unwrap_or(project);
+        if host.is_empty() || !project.contains('/') {
+            return Err(not_hosted().into());
+        }
+
+        let host_name = host.split(':').next().unwrap_or(host);
+        let forge = match forge {
+            Some(forge) => forge,
+            None if host_name.eq_ignore_ascii_case("github.com") => Forge::GitHub,
+            None if host_name.eq_ignore_ascii_case("gitlab.com") => Forge::GitLab,
+            None => bail!(Error::config(format!(
+                "can't tell which forge hosts {host}; set ignore.forge to github or gitlab"
+            ))),
+        };
+
+        Ok(ForgeRemote {
+            forge,
+            host: host.to_string(),
+            project: project.to_string(),
+        })
+    }
+
+    /// Base URL of the forge's REST API
+    pub fn api_url(&self) -> String {
+        match self.forge {
+            Forge::GitHub if self.host == "github.com" => "https://api.github.com".to_string(),
+            Forge::GitHub => format!("https://{}/api/v3", self.host),
+            Forge::GitLab => format!("https://{}/api/v4", self.host),
+        }
+    }
+}
+
+/// What to propose and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PullRequestOptions {
+    /// Remote to push to and open the request on
+    pub remote: String,
+    /// Branch the request targets; defaults to the branch `HEAD` points to
+    pub base: Option<String>,
+    /// How to make the commit. Its branch defaults to one named after the
+    /// patterns under `git-ignore/`, started from `base`.
+    pub commit: CommitOptions,
+}
+
+impl Default for PullRequestOptions {
+    fn default() -> Self {
+        PullRequestOptions {
+            remote: "origin".to_string(),
+            base: None,
+            commit: CommitOptions::default(),
+        }
+    }
+}
+
+/// An opened pull or merge request
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PullRequest {
+    pub forge: Forge,
+    /// Web page of the request
+    pub url: String,
+    /// Full name of the pushed branch
+    pub branch: String,
+    /// Full name of the targeted branch
+    pub base: String,
+    pub commit: String,
+    /// Patterns added by the commit
+    pub added: Vec<String>,
+}
+
+/// Commit `patterns` to a branch, push it and open a pull request into the
+/// base branch. Returns `None`, without pushing, when the branch already
+/// has every pattern.
+///
+/// `overrides` are the `ignore.auto*` entries used to explain each pattern
+/// in the description (see [`route_pattern`]).
+pub fn create_pull_request(
+    patterns: &[String],
+    options: &PullRequestOptions,
+    overrides: &[(Scope, String)],
+) -> anyhow::Result<Option<PullRequest>> {
+    let url = git::get_remote_url(&options.remote)?;
+    let forge = git::get_config_value("ignore.forge")?
+        .map(|value| value.parse())
+        .transpose()
+        .context(Error::config("invalid ignore.forge"))?;
+    let remote = ForgeRemote::parse(&url, forge)?;
+    let api_url = git::get_config_value("ignore.forgeApiUrl")?
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| remote.api_url());
+    // Check for a token before anything is committed or pushed
+    let token = find_token(remote.forge)?;
+    let _span = debug_span!("create_pull_request", remote = %options.remote).entered();
+
+    let base = git::resolve_branch_ref(options.base.as_deref())?;
+    let mut commit_options = options.commit.clone();
+    commit_options
+        .branch
+        .get_or_insert_with(|| branch_name(patterns));
+    commit_options.start_point = Some(base.clone());
+    let outcome = commit::commit_patterns(patterns, &commit_options)?;
+    let Some(commit) = outcome.commit else {
+        return Ok(None);
+    };
+
+    git::push_ref(&options.remote, &outcome.reference)?;
+    info!(remote = %options.remote, reference = %outcome.reference, "pushed branch");
+
+    let message = commit_options
+        .message
+        .unwrap_or_else(|| commit::default_message(&outcome.added));
+    let title = message.lines().next().unwrap_or_default();
+    let head = short_branch(&outcome.reference);
+    let target = short_branch(&base);
+    let description = describe(&outcome.added, overrides);
+
+    let (endpoint, body, headers) = match remote.forge {
+        Forge::GitHub => (
+            format!("{api_url}/repos/{}/pulls", remote.project),
+            json::Object::new()
+                .str("title", title)
+                .str("head", head)
+                .str("base", target)
+                .str("body", &description)
+                .build(),
+            vec![
+                format!("Authorization: Bearer {token}"),
+                "Accept: application/vnd.github+json".to_string(),
+            ],
+        ),
+        Forge::GitLab => (
+            format!(
+                "{api_url}/projects/{}/merge_requests",
+                remote.project.replace('/', "%2F")
+            ),
+            json::Object::new()
+                .str("title", title)
+                .str("source_branch", head)
+                .str("target_branch", target)
+                .str("description", &description)
+                .build(),
+            vec![format!("PRIVATE-TOKEN: {token}")],
+        ),
+    };
+    let response = post_json(remote.forge, &endpoint, &headers, &body)?;
+    let url_field = match remote.forge {
+        Forge::GitHub => "html_url",
+        Forge::GitLab => "web_url",
+    };
+    let url = json::string_field(&response, url_field).with_context(|| {
+        format!(
+            "Forge error: {} did not return the {} address",
+            remote.forge,
+            remote.forge.request_noun()
+        )
+    })?;
+
+    Ok(Some(PullRequest {
+        forge: remote.forge,
+        url,
+        branch: outcome.reference,
+        base,
+        commit,
+        added: outcome.added,
+    }))
+}
+
+/// Markdown description listing the added patterns and why each belongs
+/// in `.gitignore`
+fn describe(added: &[String], overrides: &[(Scope, String)]) -> String {
+    let mut description = format!(
+        "Adds {} to `.gitignore`:\n\n| Pattern | Rationale |\n| --- | --- |\n",
+        if added.len() == 1 {
+            "1 pattern".to_string()
+        } else {
+            format!("{} patterns", added.len())
+        }
+    );
+    for pattern in added {
+        let route = route_pattern(pattern, overrides);
+        let mut rationale = route.reason;
+        match route.scope {
+            Scope::Repo => {}
+            Scope::Local => rationale.push_str("; usually kept in .git/info/exclude"),
+            Scope::Global | Scope::System => {
+                rationale.push_str("; usually kept in the global gitignore")
+            }
+        }
+        description.push_str(&format!(
+            "| `{}` | {rationale} |\n",
+            pattern.replace('|', "\\|")
+        ));
+    }
+    description.push_str("\nOpened by git-ignore.\n");
+    description
+}
+
+/// Default branch for a pull request adding `patterns`
+fn branch_name(patterns: &[String]) -> String {
+    let first = patterns.first().map(String::as_str).unwrap_or_default();
+    let mut slug = String::new();
+    for c in first.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let slug = if slug.is_empty() { "patterns" } else { slug };
+    match patterns.len() {
+        0 | 1 => format!("{BRANCH_PREFIX}{slug}"),
+        n => format!("{BRANCH_PREFIX}{slug}-and-{}-more", n - 1),
+    }
+}
+
+fn short_branch(reference: &str) -> &str {
+    reference.strip_prefix("refs/heads/").unwrap_or(reference)
+}
+
+fn find_token(forge: Forge) -> anyhow::Result<String> {
+    let variables = forge.token_variables();
+    variables
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|token| !token.is_empty()))
+        .with_context(|| {
+            Error::config(format!(
+                "set {} to open {}s on {forge}",
+                variables.join(" or "),
+                forge.request_noun()
+            ))
+        })
+}
+
+/// POST `body` to `url` with curl and return the response body. The whole
+/// request, token included, is passed as a curl config on stdin.
+fn post_json(forge: Forge, url: &str, headers: &[String], body: &str) -> anyhow::Result<String> {
+    let _span = debug_span!("forge_request", %url).entered();
+    let mut config = format!("url = {}\n", curl_quote(url));
+    for header in headers
+        .iter()
+        .map(String::as_str)
+        .chain(["Content-Type: application/json", "User-Agent: git-ignore"])
+    {
+        config.push_str(&format!("header = {}\n", curl_quote(header)));
+    }
+    config.push_str(&format!("data-binary = {}\n", curl_quote(body)));
+
+    let mut child = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--config",
+            "-",
+            "--write-out",
+            "\n%{http_code}",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            Error::config(format!(
+                "curl not found in PATH; it is needed to open {}s",
+                forge.request_noun()
+            ))
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config.as_bytes())
+            .context("Forge error: failed to pass the request to curl")?;
+    }
+    let output = child
+        .wait_with_output()
+        .context("Forge error: curl did not finish")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (response, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+    if !output.status.success() {
+        bail!(
+            "Forge error: request to {forge} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    if !status.trim().starts_with('2') {
+        let detail = json::string_field(response, "message")
+            .or_else(|| json::string_field(response, "error"))
+            .unwrap_or_else(|| response.trim().to_string());
+        bail!(
+            "Forge error: {forge} answered HTTP {}: {detail}",
+            status.trim()
+        );
+    }
+    Ok(response.to_string())
+}
+
+/// Quote a value for a curl config file
+fn curl_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(url: &str) -> (Forge, String, String) {
+        let remote = ForgeRemote::parse(url, None).unwrap();
+        (remote.forge, remote.host, remote.project)
+    }
+
+    #[test]
+    fn test_parse_remote_urls() {
+        let expected = (Forge::GitHub, "github.com".to_string(), "o/r".to_string());
+        assert_eq!(remote("https://github.com/o/r.git"), expected);
+        assert_eq!(remote("git@github.com:o/r.git"), expected);
+        assert_eq!(remote("ssh://git@github.com:22/o/r"), expected);
+        assert_eq!(remote("https://token@github.com/o/r/"), expected);
+
+        assert_eq!(
+            remote("https://gitlab.com/group/sub/r.git"),
+            (
+                Forge::GitLab,
+                "gitlab.com".to_string(),
+                "group/sub/r".to_string()
+            )
+        );
+        let remote = ForgeRemote::parse(
+            "https://gitlab.example.com:8443/group/sub/r.git",
+            Some(Forge::GitLab),
+        )
+        .unwrap();
+        assert_eq!(remote.host, "gitlab.example.com:8443");
+        assert_eq!(remote.project, "group/sub/r");
+
+        // Only the public hosts are recognised by name
+        assert!(ForgeRemote::parse("https://github.evil.com/o/r", None).is_err());
+        assert!(ForgeRemote::parse("git@gitlab.example.com:o/r", None).is_err());
+
+        assert!(ForgeRemote::parse("/srv/git/r.git", None).is_err());
+        assert!(ForgeRemote::parse("git.example.com:o/r", None).is_err());
+        let remote = ForgeRemote::parse("git.example.com:o/r", Some(Forge::GitLab)).unwrap();
+        assert_eq!(remote.api_url(), "https://git.example.com/api/v4");
+    }
+
+    #[test]
+    fn test_api_urls() {
+        let github = ForgeRemote::parse("git@github.com:o/r", None).unwrap();
+        assert_eq!(github.api_url(), "https://api.github.com");
+        let enterprise =
+            ForgeRemote::parse("https://github.corp.example/o/r", Some(Forge::GitHub)).unwrap();
+        assert_eq!(enterprise.api_url(), "https://github.corp.example/api/v3");
+    }
+
+    #[test]
+    fn test_branch_name() {
+        let patterns = |list: &[&str]| list.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert_eq!(branch_name(&patterns(&["*.log"])), "git-ignore/log");
+        assert_eq!(
+            branch_name(&patterns(&["/Build Output/", "*.o", "*.a"])),
+            "git-ignore/build-output-and-2-more"
+        );
+        assert_eq!(branch_name(&patterns(&["*"])), "git-ignore/patterns");
+    }
+
+    #[test]
+    fn test_describe() {
+        let description = describe(&["target/".to_string(), ".DS_Store".to_string()], &[]);
+        assert!(description.starts_with("Adds 2 patterns to `.gitignore`:\n"));
+        assert!(description.contains("| `target/` | project file, shared with the team |\n"));
+        assert!(description.contains(
+            "| `.DS_Store` | operating system file; usually kept in the global gitignore |\n"
+        ));
+    }
+
+    #[test]
+    fn test_curl_quote() {
+        assert_eq!(curl_quote(r#"{"a":"b\n"}"#), r#""{\"a\":\"b\\n\"}""#);
+    }
+}
@@ -0,0 +1,233 @@
+//! Tidy an ignore file without changing what it ignores
+//!
+//! `fmt` parses the file into an [`IgnoreDocument`], so every comment
+//! travels with the pattern below it, then:
+//!
+//! - strips stray whitespace and leaves exactly one blank line between
+//!   sections;
+//! - removes repeated patterns, keeping the copy that decides the outcome
+//!   when a negation sits between them;
+//! - merges sections that share a heading (only in files without
+//!   negations, where moving a pattern can't change what a negation
+//!   re-includes);
+//! - sorts the patterns of each section and orders the sections the way
+//!   [`sort_content`] does.
+//!
+//! Template blocks and `--group` blocks keep their lines in the order they
+//! were written.
+
+use crate::{
+    ignore::{DocumentEntry, DocumentSection, IgnoreDocument},
+    sort::{sort_content, SortStrategy},
+};
+use std::collections::{HashMap, HashSet};
+
+/// Format ignore file content (see the module documentation)
+pub fn format_content(content: &str, strategy: SortStrategy, section_order: &[String]) -> String {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut document = IgnoreDocument::parse(content);
+    remove_duplicates(&mut document);
+    let has_negations = content
+        .lines()
+        .any(|line| line.trim_start().starts_with('!'));
+    if !has_negations {
+        merge_same_headings(&mut document);
+    }
+    for section in &mut document.sections {
+        if let DocumentSection::Block { entries, .. } = section {
+            sort_entries(entries, strategy);
+        }
+    }
+    sort_content(&document.render(newline), strategy, section_order)
+}
+
+/// Where a pattern occurs: section index and, outside template and group
+/// blocks, the entry index within the section
+type Occurrence = (usize, Option<usize>);
+
+/// Remove patterns written more than once. Of two copies, the later one
+/// is kept if a pattern of the opposite kind (negation or not) lies
+/// between them, since it decides the outcome for the paths both match;
+/// otherwise the first is kept. Lines in template and group blocks are
+/// never removed. Comments of a removed entry move to the entry after it.
+fn remove_duplicates(document: &mut IgnoreDocument) {
+    let mut occurrences: Vec<(Occurrence, &str)> = Vec::new();
+    for (index, section) in document.sections.iter().enumerate() {
+        match section {
+            DocumentSection::Block { entries, .. } => occurrences.extend(
+                entries
+                    .iter()
+                    .enumerate()
+                    .map(|(entry, e)| ((index, Some(entry)), e.pattern.as_str())),
+            ),
+            DocumentSection::Fixed(lines) => occurrences.extend(
+                lines
+                    .iter()
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| ((index, None), line.as_str())),
+            ),
+        }
+    }
+
+    let mut removed: HashSet<Occurrence> = HashSet::new();
+    let mut kept: HashMap<&str, usize> = HashMap::new();
+    for (position, &(occurrence, pattern)) in occurrences.iter().enumerate() {
+        let Some(&earlier) = kept.get(pattern) else {
+            kept.insert(pattern, position);
+            continue;
+        };
+        let negated = pattern.starts_with('!');
+        let opposite_between = occurrences[earlier + 1..position]
+            .iter()
+            .any(|(_, other)| other.starts_with('!') != negated);
+        let earlier_occurrence = occurrences[earlier].0;
+        if !opposite_between && occurrence.1.is_some() {
+            removed.insert(occurrence);
+        } else {
+            if earlier_occurrence.1.is_some() {
+                removed.insert(earlier_occurrence);
+            }
+            kept.insert(pattern, position);
+        }
+    }
+    if removed.is_empty() {
+        return;
+    }
+
+    for (index, section) in document.sections.iter_mut().enumerate() {
+        let DocumentSection::Block {
+            entries, trailing, ..
+        } = section
+        else {
+            continue;
+        };
+        let mut orphaned: Vec<String> = Vec::new();
+        let mut remaining = Vec::new();
+        for (entry_index, mut entry) in std::mem::take(entries).into_iter().enumerate() {
+            if removed.contains(&(index, Some(entry_index))) {
+                orphaned.append(&mut entry.comments);
+            } else {
+                orphaned.append(&mut entry.comments);
+                entry.comments = std::mem::take(&mut orphaned);
+                remaining.push(entry);
+            }
+        }
+        orphaned.append(trailing);
+        *trailing = orphaned;
+        *entries = remaining;
+    }
+}
+
+/// Append each section to the first earlier one with the same heading
+/// (compared without regard to case)
+fn merge_same_headings(document: &mut IgnoreDocument) {
+    let mut merged: Vec<DocumentSection> = Vec::new();
+    for section in std::mem::take(&mut document.sections) {
+        let target = section.heading().and_then(|name| {
+            merged.iter().position(|earlier| {
+                earlier
+                    .heading()
+                    .is_some_and(|other| other.eq_ignore_ascii_case(name))
+            })
+        });
+        let (
+            Some(target),
+            DocumentSection::Block {
+                heading,
+                mut entries,
+                trailing,
+            },
+        ) = (target, section.clone())
+        else {
+            merged.push(section);
+            continue;
+        };
+        let DocumentSection::Block {
+            entries: target_entries,
+            trailing: target_trailing,
+            ..
+        } = &mut merged[target]
+        else {
+            unreachable!("only blocks have headings");
+        };
+        // The shared first heading line is dropped; the rest stays with
+        // the patterns it introduced
+        let rest = heading[1..].to_vec();
+        match entries.first_mut() {
+            Some(first) => {
+                first.comments.splice(0..0, rest);
+                target_entries.append(&mut entries);
+            }
+            None => target_trailing.extend(rest),
+        }
+        target_trailing.extend(trailing);
+    }
+    document.sections = merged;
+}
+
+/// Sort each run of consecutive plain patterns, and each run of
+/// consecutive negations, keeping comments with their patterns
+fn sort_entries(entries: &mut [DocumentEntry], strategy: SortStrategy) {
+    let mut start = 0;
+    while start < entries.len() {
+        let negated = entries[start].is_negation();
+        let end = entries[start..]
+            .iter()
+            .position(|entry| entry.is_negation() != negated)
+            .map_or(entries.len(), |offset| start + offset);
+        entries[start..end].sort_by(|a, b| strategy.compare(&a.pattern, &b.pattern));
+        start = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(content: &str) -> String {
+        format_content(content, SortStrategy::Lexicographic, &[])
+    }
+
+    #[test]
+    fn test_format_normalizes_and_sorts() {
+        let content = "\n\n# Build  \n  target/\n# generated docs\ndocs/api/\nbuild/   \n\n\n\n*.log\n*.tmp\\ \n";
+        assert_eq!(
+            format(content),
+            "# Build\nbuild/\n# generated docs\ndocs/api/\ntarget/\n\n*.log\n*.tmp\\ \n"
+        );
+        assert_eq!(format(&format(content)), format(content));
+    }
+
+    #[test]
+    fn test_format_removes_duplicates() {
+        // Comments of the removed copy move to the next entry
+        assert_eq!(
+            format("*.log\nb\n# again\n*.log\na\n"),
+            "*.log\n# again\na\nb\n"
+        );
+        // The later copy wins once a negation lies between them
+        assert_eq!(format("*.log\n!keep.log\n*.log\n"), "!keep.log\n*.log\n");
+        // Template blocks are left alone
+        let template = "# BEGIN git-ignore template: Rust\ntarget/\n\n*.rs.bk\n# END git-ignore template: Rust\n\ntarget/\n";
+        assert_eq!(
+            format(template),
+            "# BEGIN git-ignore template: Rust\ntarget/\n\n*.rs.bk\n# END git-ignore template: Rust\n"
+        );
+    }
+
+    #[test]
+    fn test_format_merges_sections() {
+        let content = "# Logs\n*.log\n\n# Build\nbuild/\n\n# logs\n# rotated\n*.gz\n";
+        assert_eq!(
+            format(content),
+            "# Logs\n# rotated\n*.gz\n*.log\n\n# Build\nbuild/\n"
+        );
+        // Sections stay apart when a negation could be affected
+        let negated = "# Logs\n*.log\n\n# Build\n!build/keep\n\n# Logs\n*.gz\n";
+        assert_eq!(format(negated), negated);
+    }
+}
@@ -1,77 +1,851 @@
 //! Git repository utilities for path detection and resolution
 
+use crate::{
+    backend::{self, ConfigFile, Layout, Location, ValueType},
+    jj::{self, JjWorkspace},
+    nested::{NestedKind, NestedRepository},
+    paths, Error, Scope,
+};
 use anyhow::{bail, Context};
 use std::{
     env,
+    io::{self, Read, Write},
     path::{Path, PathBuf},
-    process::Command,
-    sync::OnceLock,
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
+use tracing::{debug, debug_span, warn};
+
+/// The repository around the current directory, resolved on first use
+static CURRENT_REPO: OnceLock<RepoContext> = OnceLock::new();
+
+/// The current directory, for taking relative paths against
+pub(crate) fn current_dir() -> anyhow::Result<PathBuf> {
+    env::current_dir().context("Failed to read: current directory")
+}
+
+/// jj workspace whose git repository commands should use
+static JJ_WORKSPACE: OnceLock<Option<JjWorkspace>> = OnceLock::new();
+
+/// The jj workspace around the current directory, unless `GIT_DIR` already
+/// says where the repository is
+fn jj_workspace() -> Option<&'static JjWorkspace> {
+    JJ_WORKSPACE
+        .get_or_init(|| {
+            if env::var_os("GIT_DIR").is_some() {
+                return None;
+            }
+            let workspace = jj::find_workspace(&current_dir().ok()?)?;
+            debug!(root = %workspace.root.display(), "jj workspace");
+            Some(workspace)
+        })
+        .as_ref()
+}
+
+/// A `git` command, pointed at the backing repository when run inside a
+/// non-colocated jj workspace
+pub(crate) fn git() -> Command {
+    let mut command = Command::new("git");
+    if let Some(JjWorkspace {
+        root,
+        git_dir: Some(git_dir),
+    }) = jj_workspace()
+    {
+        command.env("GIT_DIR", git_dir).env("GIT_WORK_TREE", root);
+    }
+    command
+}
+
+/// How long a git command may run when nothing else is configured.
+///
+/// The timeout applies to every git command the crate runs except pushes
+/// ([`push_ref`]), whose duration depends on the network and the remote
+/// rather than on this machine, so they always run to completion.
+pub const DEFAULT_GIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Marks [`GIT_TIMEOUT_MS`] as not set by [`set_git_timeout`]
+const TIMEOUT_UNSET: u64 = u64::MAX;
+
+/// Timeout chosen with [`set_git_timeout`] in milliseconds, 0 for none
+static GIT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(TIMEOUT_UNSET);
+
+/// Limit how long each local git command may run before it is killed and
+/// [`Error::GitTimeout`] is reported; `None` waits forever. Overrides the
+/// `GIT_IGNORE_TIMEOUT` environment variable (seconds) and
+/// [`DEFAULT_GIT_TIMEOUT`].
+pub fn set_git_timeout(timeout: Option<Duration>) {
+    let millis = timeout.map_or(0, |timeout| {
+        u64::try_from(timeout.as_millis()).unwrap_or(TIMEOUT_UNSET - 1)
+    });
+    GIT_TIMEOUT_MS.store(millis, Ordering::Relaxed);
+}
+
+/// The timeout git commands currently run with
+pub fn git_timeout() -> Option<Duration> {
+    let millis = match GIT_TIMEOUT_MS.load(Ordering::Relaxed) {
+        TIMEOUT_UNSET => match env::var("GIT_IGNORE_TIMEOUT") {
+            Ok(value) => match value.trim().parse::<u64>() {
+                Ok(seconds) => seconds.saturating_mul(1000),
+                Err(_) => {
+                    warn!(%value, "ignoring invalid GIT_IGNORE_TIMEOUT");
+                    return Some(DEFAULT_GIT_TIMEOUT);
+                }
+            },
+            Err(_) => return Some(DEFAULT_GIT_TIMEOUT),
+        },
+        millis => millis,
+    };
+    (millis > 0).then(|| Duration::from_millis(millis))
+}
+
+/// Run `command` (whose arguments are `args`) to completion, feeding it
+/// `input`, and kill it if it runs longer than `timeout`.
+fn run_with_timeout(
+    mut command: Command,
+    args: &[&str],
+    input: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> anyhow::Result<Output> {
+    let child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(Error::GitNotFound)?;
 
-/// Cache for git directory path
-static GIT_DIR_CACHE: OnceLock<PathBuf> = OnceLock::new();
+    match wait_with_timeout(child, input, timeout) {
+        Ok(Some(output)) => Ok(output),
+        Ok(None) => bail!(Error::GitTimeout {
+            command: format!("git {}", args.join(" ")),
+            timeout: timeout.unwrap_or_default(),
+        }),
+        Err(e) => bail!(Error::GitFailed(format!("git {}: {e}", args.join(" ")))),
+    }
+}
+
+/// Feed `input` to the spawned `child`, collect whatever of its output is
+/// piped and wait for it to exit, killing it once `timeout` has passed.
+/// Returns `None` if it had to be killed. A child that exits without
+/// reading all of its input is left for its exit status to judge.
+///
+/// Output is drained on separate threads so a chatty command can't block
+/// on a full pipe while we wait for it.
+pub(crate) fn wait_with_timeout(
+    mut child: Child,
+    input: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> io::Result<Option<Output>> {
+    let writer = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => {
+            let input = input.to_vec();
+            Some(thread::spawn(move || stdin.write_all(&input)))
+        }
+        _ => None,
+    };
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
 
-/// Cache for git common directory path
-static GIT_COMMON_DIR_CACHE: OnceLock<PathBuf> = OnceLock::new();
+    let status = match timeout {
+        Some(timeout) => wait_until(&mut child, timeout)?,
+        None => Some(child.wait()?),
+    };
+    let Some(status) = status else {
+        // The readers are left behind: a helper the command started may
+        // still hold the pipes open
+        return Ok(None);
+    };
 
-/// Cache for repository root path
-static REPO_ROOT_CACHE: OnceLock<PathBuf> = OnceLock::new();
+    if let Some(Ok(Err(e))) = writer.map(thread::JoinHandle::join) {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            return Err(e);
+        }
+    }
+    let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    Ok(Some(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    }))
+}
 
-/// Execute git command and return stdout
-fn run_git_command(args: &[&str]) -> anyhow::Result<String> {
-    let output = Command::new("git")
-        .args(args)
-        .output()
-        .with_context(|| "Git not found in PATH")?;
+/// Wait for `child` to exit, killing it once `timeout` has passed.
+/// Returns `None` if it had to be killed.
+fn wait_until(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    // Start polling fast: most git commands finish in a few milliseconds
+    let mut pause = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            warn!(pid = child.id(), "killing command after timeout");
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(pause.min(deadline - now));
+        pause = (pause * 2).min(Duration::from_millis(50));
+    }
+}
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+/// Run `command` with `args` and return its stdout, which may be empty
+pub(crate) fn run_git_with(command: Command, args: &[&str]) -> anyhow::Result<String> {
+    let (output, cwd) = run_git_output(command, args)?;
+    if !output.status.success() {
+        bail!(Error::GitFailed(format!(
+            "git {}: {} (cwd: {})",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim(),
+            cwd.display()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run `command` with `args` whatever its exit status, returning its
+/// output and the directory it ran in
+pub(crate) fn run_git_output(
+    mut command: Command,
+    args: &[&str],
+) -> anyhow::Result<(Output, PathBuf)> {
+    let _span = debug_span!("git_command", args = %args.join(" ")).entered();
+    let cwd = command
+        .get_current_dir()
+        .map(Path::to_path_buf)
+        .or_else(|| current_dir().ok())
+        .unwrap_or_else(|| PathBuf::from("."));
+    command.args(args);
+    let output = run_with_timeout(command, args, None, git_timeout())?;
+    if !output.status.success() {
+        debug!(status = %output.status, stderr = %String::from_utf8_lossy(&output.stderr).trim(), "git command failed");
+    }
+    Ok((output, cwd))
+}
+
+/// Execute a plumbing command with optional stdin and extra environment,
+/// returning its trimmed stdout
+fn run_plumbing(
+    args: &[&str],
+    input: Option<&[u8]>,
+    envs: &[(&str, &str)],
+) -> anyhow::Result<String> {
+    run_plumbing_with(git(), args, input, envs)
+}
+
+/// [`run_plumbing`] with a prepared `command`
+fn run_plumbing_with(
+    command: Command,
+    args: &[&str],
+    input: Option<&[u8]>,
+    envs: &[(&str, &str)],
+) -> anyhow::Result<String> {
+    run_plumbing_until(command, args, input, envs, git_timeout())
+}
+
+/// [`run_plumbing_with`], killing the command after `timeout` rather than
+/// [`git_timeout`]
+fn run_plumbing_until(
+    mut command: Command,
+    args: &[&str],
+    input: Option<&[u8]>,
+    envs: &[(&str, &str)],
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
+    let _span = debug_span!("git_command", args = %args.join(" ")).entered();
+    command.args(args).envs(envs.iter().copied());
+    let output = run_with_timeout(command, args, input, timeout)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        bail!(Error::GitFailed(format!(
+            "git {}: {}",
+            args.join(" "),
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Read a single git config value, returning `None` when the key is unset
+pub fn get_config_value(key: &str) -> anyhow::Result<Option<String>> {
+    Ok(get_config_values(key)?.pop())
+}
+
+/// Read every value of a multi-valued git config key, in config order
+pub fn get_config_values(key: &str) -> anyhow::Result<Vec<String>> {
+    backend::active().config_values(Location::Current, ConfigFile::Any, key, ValueType::Raw)
+}
+
+/// Read every value of a multi-valued git config key as the repository at
+/// `repository` sees it, rather than the current one
+pub fn get_config_values_in(repository: &Path, key: &str) -> anyhow::Result<Vec<String>> {
+    backend::active().config_values(
+        Location::Isolated(repository),
+        ConfigFile::Any,
+        key,
+        ValueType::Raw,
+    )
+}
 
-        bail!(
-            "Not in a git repository (cwd: {}): {}",
-            cwd.display(),
+/// Read a git config value interpreted as a boolean (`true`, `yes`, `on`, `1`, ...)
+pub fn get_config_bool(key: &str) -> anyhow::Result<Option<bool>> {
+    Ok(backend::active()
+        .config_values(Location::Current, ConfigFile::Any, key, ValueType::Bool)?
+        .pop()
+        .map(|value| value == "true"))
+}
+
+/// Run a `git config` lookup with `command`, mapping git's "key not found"
+/// status to `None`
+pub(crate) fn read_config_with(
+    mut command: Command,
+    args: &[&str],
+) -> anyhow::Result<Option<String>> {
+    let _span = debug_span!("git_config", args = %args.join(" ")).entered();
+    command.args(args);
+    let output = run_with_timeout(command, args, None, git_timeout())?;
+
+    // git config exits with status 1 when the key doesn't exist
+    if output.status.code() == Some(1) {
+        return Ok(None);
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(Error::config(format!(
+            "git {}: {}",
+            args.join(" "),
             stderr.trim()
-        );
+        )));
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let result = stdout.trim();
+    Ok(Some(stdout.trim_end_matches(['\n', '\r']).to_string()))
+}
 
-    if result.is_empty() {
-        bail!("Git command returned empty output: git {}", args.join(" "));
-    }
+/// List the repository-local config entries whose keys match `pattern`
+/// (a regular expression, as accepted by `git config --get-regexp`)
+pub fn get_local_config_entries(pattern: &str) -> anyhow::Result<Vec<(String, String)>> {
+    backend::active().config_entries(Location::Current, ConfigFile::Local, pattern)
+}
 
-    Ok(result.to_string())
+/// List the effective config entries whose keys match `pattern`, in config
+/// order; see [`get_local_config_entries`]
+pub fn get_config_entries(pattern: &str) -> anyhow::Result<Vec<(String, String)>> {
+    backend::active().config_entries(Location::Current, ConfigFile::Any, pattern)
+}
+
+/// The `key value` lines `git config --get-regexp` prints, split. Section
+/// and variable names come back lowercased; subsections keep their case.
+pub(crate) fn config_entries(output: Option<String>) -> Vec<(String, String)> {
+    output
+        .unwrap_or_default()
+        .lines()
+        .map(|line| match line.split_once(' ') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (line.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Set a config value in the repository's local config
+pub fn set_local_config_value(key: &str, value: &str) -> anyhow::Result<()> {
+    set_config("--local", key, value)
+}
+
+/// Set a config value in the user's global config
+pub fn set_global_config_value(key: &str, value: &str) -> anyhow::Result<()> {
+    set_config("--global", key, value)
+}
+
+fn set_config(scope: &str, key: &str, value: &str) -> anyhow::Result<()> {
+    let (output, _) = run_git_output(git(), &["config", scope, key, value])?;
+    if !output.status.success() {
+        bail!(Error::config(format!(
+            "failed to set {key}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
 }
 
 /// Validate that git returned a reasonable path
-fn validate_git_path(path: &Path) -> anyhow::Result<PathBuf> {
-    let resolved = path
-        .canonicalize()
+pub(crate) fn validate_git_path(path: &Path) -> anyhow::Result<PathBuf> {
+    let resolved = paths::canonicalize(path)
         .with_context(|| format!("Invalid path returned by git: {}", path.display()))?;
 
     Ok(resolved)
 }
 
-/// Resolve a path-valued `git rev-parse <rev_parse_arg>` invocation, caching
-/// the validated result.
-fn cached_git_path(
-    cache: &OnceLock<PathBuf>,
-    rev_parse_arg: &str,
-    error_context: &'static str,
-) -> anyhow::Result<PathBuf> {
-    if let Some(cached) = cache.get() {
-        return Ok(cached.clone());
+/// The resolved locations of one git repository.
+///
+/// Everything is looked up once, when the context is created, and the
+/// repository's git commands run in its own work tree whatever the current
+/// directory is, so one process can work on several repositories side by
+/// side. The free functions of this module act on [`RepoContext::current`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoContext {
+    /// Root of the work tree; `None` for a bare repository
+    work_tree: Option<PathBuf>,
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    /// Whether commands name the repository through `GIT_DIR` and
+    /// `GIT_WORK_TREE`, as they must in a non-colocated jj workspace or
+    /// when `GIT_DIR` was set for this process
+    explicit: bool,
+}
+
+impl RepoContext {
+    /// The repository containing `dir`. Inside a non-colocated jj
+    /// workspace, unless `GIT_DIR` is set, that is the git repository
+    /// backing it.
+    pub fn discover(dir: &Path) -> anyhow::Result<RepoContext> {
+        let _span = debug_span!("discover_repository", dir = %dir.display()).entered();
+        let workspace = if env::var_os("GIT_DIR").is_some() {
+            None
+        } else {
+            jj::find_workspace(dir)
+        };
+        // Say why git can't help, rather than "not a git repository"
+        if let Some(JjWorkspace {
+            root,
+            git_dir: None,
+        }) = &workspace
+        {
+            bail!(Error::NotARepository(format!(
+                "the jj repository at {} doesn't use the git backend",
+                root.display()
+            )));
+        }
+        let explicit = match &workspace {
+            Some(JjWorkspace {
+                root,
+                git_dir: Some(git_dir),
+            }) => Some((git_dir.as_path(), root.as_path())),
+            _ => None,
+        };
+        let Layout {
+            git_dir,
+            common_dir,
+            work_tree,
+        } = backend::active().discover(dir, explicit)?;
+        debug!(git_dir = %git_dir.display(), work_tree = %work_tree.as_deref().unwrap_or(Path::new("")).display(), "repository");
+        Ok(RepoContext {
+            work_tree,
+            git_dir,
+            common_dir,
+            explicit: workspace.is_some() || env::var_os("GIT_DIR").is_some(),
+        })
+    }
+
+    /// The repository around the current directory, discovered on first use
+    /// and kept for the rest of the process
+    pub fn current() -> anyhow::Result<&'static RepoContext> {
+        if let Some(repo) = CURRENT_REPO.get() {
+            return Ok(repo);
+        }
+        let cwd = current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let repo = RepoContext::discover(&cwd)?;
+        // Only cache if we succeed
+        Ok(CURRENT_REPO.get_or_init(|| repo))
+    }
+
+    /// Root of the work tree
+    pub fn root(&self) -> anyhow::Result<&Path> {
+        self.work_tree
+            .as_deref()
+            .ok_or_else(|| Error::NoWorkTree(self.git_dir.clone()).into())
+    }
+
+    /// The git directory. In a linked worktree, this is the
+    /// worktree-private administrative directory (`.git/worktrees/<name>`);
+    /// see [`get_git_dir`].
+    pub fn git_dir(&self) -> &Path {
+        &self.git_dir
+    }
+
+    /// The git directory shared by the main repository and all of its
+    /// worktrees, where `info/exclude` lives; see [`get_git_common_dir`]
+    pub fn common_dir(&self) -> &Path {
+        &self.common_dir
+    }
+
+    /// Whether this is a linked worktree (one created with `git worktree
+    /// add`) rather than the main worktree
+    pub fn is_linked_worktree(&self) -> bool {
+        self.git_dir != self.common_dir
+    }
+
+    /// The top-level `.gitignore`
+    pub fn gitignore_path(&self) -> anyhow::Result<PathBuf> {
+        Ok(self.bare_checked_root()?.join(".gitignore"))
+    }
+
+    /// The top-level `.gitattributes`
+    pub fn gitattributes_path(&self) -> anyhow::Result<PathBuf> {
+        Ok(self.bare_checked_root()?.join(".gitattributes"))
+    }
+
+    /// [`root`](Self::root), failing with [`Error::BareRepository`]
+    /// instead when there is no work tree to hold a scope file
+    fn bare_checked_root(&self) -> anyhow::Result<&Path> {
+        self.work_tree
+            .as_deref()
+            .ok_or_else(|| Error::BareRepository(self.git_dir.clone()).into())
+    }
+
+    /// The shared `info/exclude` file
+    pub fn exclude_file_path(&self) -> PathBuf {
+        self.common_dir.join("info").join("exclude")
+    }
+
+    /// The shared `info/attributes` file
+    pub fn info_attributes_path(&self) -> PathBuf {
+        self.common_dir.join("info").join("attributes")
+    }
+
+    /// The excludes file git reads for this repository, as
+    /// [`get_excludes_file`] finds it, but with this repository's config
+    pub fn excludes_file(&self) -> anyhow::Result<Option<PathBuf>> {
+        excludes_file_from(
+            backend::active()
+                .config_values(
+                    Location::Repo(self),
+                    ConfigFile::Any,
+                    "core.excludesFile",
+                    ValueType::Path,
+                )?
+                .pop(),
+        )
+    }
+
+    /// The config entries matching `pattern` as this repository sees them;
+    /// see [`get_config_entries`]
+    pub fn config_entries(&self, pattern: &str) -> anyhow::Result<Vec<(String, String)>> {
+        backend::active().config_entries(Location::Repo(self), ConfigFile::Any, pattern)
+    }
+
+    /// A `git` command run in this repository
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new("git");
+        command.current_dir(self.work_tree.as_ref().unwrap_or(&self.git_dir));
+        if self.explicit {
+            command.env("GIT_DIR", &self.git_dir);
+            if let Some(root) = &self.work_tree {
+                command.env("GIT_WORK_TREE", root);
+            }
+        }
+        command
+    }
+
+    /// List the tracked files, relative to the root and `/`-separated
+    pub fn list_tracked_files(&self) -> anyhow::Result<Vec<String>> {
+        self.root()?;
+        let output = run_git_with(self.command(), &["ls-files", "-z"])
+            .context("Failed to list tracked files")?;
+        Ok(split_nul(&output))
+    }
+
+    /// Untracked files that no ignore file excludes, relative to the root
+    pub fn list_untracked_files(&self) -> anyhow::Result<Vec<String>> {
+        self.root()?;
+        let output = run_git_with(
+            self.command(),
+            &["ls-files", "-z", "--others", "--exclude-standard"],
+        )
+        .context("Failed to list untracked files")?;
+        Ok(split_nul(&output))
+    }
+
+    /// Every file in the work tree, tracked, untracked or ignored, relative
+    /// to the root
+    pub fn list_work_tree_files(&self) -> anyhow::Result<Vec<String>> {
+        self.root()?;
+        let output = run_git_with(self.command(), &["ls-files", "-z", "--cached", "--others"])
+            .context("Failed to list work tree files")?;
+        Ok(split_nul(&output))
+    }
+
+    /// Tracked files that ignore rules match; see [`list_tracked_ignored`]
+    pub fn list_tracked_ignored(&self, patterns: &[String]) -> anyhow::Result<Vec<String>> {
+        self.root()?;
+        let mut args = vec!["ls-files", "-z", "--cached", "--ignored"];
+        if patterns.is_empty() {
+            args.push("--exclude-standard");
+        }
+        for pattern in patterns {
+            args.extend(["--exclude", pattern.as_str()]);
+        }
+        let output = run_git_with(self.command(), &args).context("Failed to list tracked files")?;
+        Ok(split_nul(&output))
+    }
+
+    /// Tracked files each pattern matches on its own; see
+    /// [`tracked_by_pattern`]
+    pub fn tracked_by_pattern(
+        &self,
+        patterns: &[String],
+    ) -> anyhow::Result<Vec<(String, Vec<String>)>> {
+        let mut found = Vec::new();
+        for pattern in patterns {
+            // A negation alone matches nothing
+            if pattern.trim().is_empty() || pattern.starts_with('!') {
+                continue;
+            }
+            let files = self.list_tracked_ignored(std::slice::from_ref(pattern))?;
+            if !files.is_empty() {
+                found.push((pattern.clone(), files));
+            }
+        }
+        Ok(found)
+    }
+
+    /// Remove `paths` (relative to the root) from the index while keeping
+    /// them in the work tree, like `git rm -r --cached`
+    pub fn untrack_files(&self, paths: &[String]) -> anyhow::Result<()> {
+        self.root()?;
+        let mut input = Vec::new();
+        for path in paths {
+            input.extend_from_slice(path.as_bytes());
+            input.push(0);
+        }
+        run_plumbing_with(
+            self.command(),
+            &["update-index", "--force-remove", "-z", "--stdin"],
+            Some(&input),
+            &[],
+        )
+        .context("Failed to untrack files")?;
+        Ok(())
+    }
+
+    /// Untracked entries of `git status`; see [`list_untracked_status`]
+    pub fn list_untracked_status(&self) -> anyhow::Result<Vec<String>> {
+        self.root()?;
+        let output = run_git_with(
+            self.command(),
+            &["status", "--porcelain", "-z", "--untracked-files=normal"],
+        )
+        .context("Failed to read git status")?;
+
+        let mut untracked = Vec::new();
+        let mut records = output.split('\0').filter(|record| !record.is_empty());
+        while let Some(record) = records.next() {
+            if let Some(path) = record.strip_prefix("?? ") {
+                untracked.push(path.to_string());
+            } else if record.get(..2).is_some_and(|xy| xy.contains(['R', 'C'])) {
+                // Renames and copies are followed by their source path
+                records.next();
+            }
+        }
+        Ok(untracked)
+    }
+
+    /// `git blame --line-porcelain` output for `file`; see
+    /// [`blame_porcelain`]
+    pub fn blame_porcelain(&self, file: &Path) -> anyhow::Result<String> {
+        self.root()?;
+        let file = file.to_string_lossy();
+        run_plumbing_with(
+            self.command(),
+            &["blame", "--line-porcelain", "--", &file],
+            None,
+            &[],
+        )
+    }
+
+    /// Every `.gitignore` file in the work tree, tracked or not (but not
+    /// ignored), with the top-level one first
+    pub fn find_gitignore_files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        self.find_work_tree_files(".gitignore")
+    }
+
+    /// Every `.gitattributes` file in the work tree, tracked or not (but
+    /// not ignored), with the top-level one first
+    pub fn find_gitattributes_files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        self.find_work_tree_files(".gitattributes")
+    }
+
+    fn find_work_tree_files(&self, name: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let repo_root = self.root()?;
+        let pathspec = format!(":(glob)**/{name}");
+        let output = run_git_with(
+            self.command(),
+            &[
+                "ls-files",
+                "-z",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+                "--",
+                &pathspec,
+            ],
+        )
+        .context("Failed to list work tree files")?;
+
+        let mut relative: Vec<&str> = output.split('\0').filter(|p| !p.is_empty()).collect();
+        relative.sort();
+        relative.dedup();
+        // Shallower files first, so the top-level .gitignore leads
+        relative.sort_by_key(|path| path.matches('/').count());
+
+        Ok(relative
+            .into_iter()
+            .map(|path| repo_root.join(path))
+            .collect())
+    }
+
+    /// Git repositories inside the work tree; see
+    /// [`find_nested_repositories`]
+    pub fn find_nested_repositories(&self) -> anyhow::Result<Vec<NestedRepository>> {
+        let repo_root = self.root()?;
+        let mut repositories = Vec::new();
+
+        // git lists a nested repository as one entry with a trailing slash
+        // instead of descending into it
+        let untracked = run_git_with(
+            self.command(),
+            &["ls-files", "-z", "--others", "--exclude-standard"],
+        )?;
+        for path in untracked.split('\0') {
+            if let Some(path) = path.strip_suffix('/') {
+                if repo_root.join(path).join(".git").exists() {
+                    repositories.push(NestedRepository {
+                        path: path.to_string(),
+                        kind: NestedKind::Embedded,
+                    });
+                }
+            }
+        }
+
+        let staged = run_git_with(self.command(), &["ls-files", "-z", "--stage"])?;
+        for entry in staged.split('\0') {
+            if let Some((info, path)) = entry.split_once('\t') {
+                if info.starts_with("160000 ") {
+                    repositories.push(NestedRepository {
+                        path: path.to_string(),
+                        kind: NestedKind::Submodule,
+                    });
+                }
+            }
+        }
+        Ok(repositories)
+    }
+
+    /// Ask git which pattern decides whether each of `paths`, relative to
+    /// the root, is ignored; see [`check_ignore`]
+    pub fn check_ignore(
+        &self,
+        paths: &[String],
+        no_index: bool,
+    ) -> anyhow::Result<Vec<IgnoreCheck>> {
+        self.check_ignore_with(self.command(), paths, no_index)
     }
 
-    let output = run_git_command(&["rev-parse", rev_parse_arg]).context(error_context)?;
-    let path = PathBuf::from(output);
-    let validated = validate_git_path(&path)?;
+    fn check_ignore_with(
+        &self,
+        mut command: Command,
+        paths: &[String],
+        no_index: bool,
+    ) -> anyhow::Result<Vec<IgnoreCheck>> {
+        let repo_root = self.root()?;
+        let mut args = vec![
+            "check-ignore",
+            "--verbose",
+            "--non-matching",
+            "-z",
+            "--stdin",
+        ];
+        if no_index {
+            args.push("--no-index");
+        }
+        let mut input = Vec::new();
+        for path in paths {
+            input.extend_from_slice(path.as_bytes());
+            input.push(0);
+        }
+
+        let _span = debug_span!("git_command", args = %args.join(" ")).entered();
+        command.args(&args);
+        let output = run_with_timeout(command, &args, Some(&input), git_timeout())?;
+        // 1 only means that nothing was ignored
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(Error::GitFailed(format!(
+                "git {}: {}",
+                args.join(" "),
+                stderr.trim()
+            )));
+        }
+
+        // Records are <source> NUL <line> NUL <pattern> NUL <path> NUL, with
+        // the first three empty when nothing matched
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let fields: Vec<&str> = stdout.split('\0').collect();
+        let mut checks = Vec::new();
+        let mut excludes_scope = None;
+        for record in fields.chunks_exact(4) {
+            let [source, line, pattern, path] = [record[0], record[1], record[2], record[3]];
+            let matched = if source.is_empty() {
+                None
+            } else {
+                let source = repo_root.join(source);
+                let scope = if source.starts_with(&self.common_dir) {
+                    Scope::Local
+                } else if source.starts_with(repo_root)
+                    && source.file_name().is_some_and(|name| name == ".gitignore")
+                {
+                    Scope::Repo
+                } else {
+                    *excludes_scope.get_or_insert_with(|| excludes_file_scope(&source))
+                };
+                Some(IgnoreMatch {
+                    source,
+                    scope,
+                    line_number: line
+                        .parse()
+                        .with_context(|| format!("Unexpected git check-ignore output: {line}"))?,
+                    pattern: pattern.to_string(),
+                })
+            };
+            checks.push(IgnoreCheck {
+                path: path.to_string(),
+                matched,
+            });
+        }
+        Ok(checks)
+    }
+}
 
-    // Only cache if we succeed
-    let _ = cache.set(validated.clone());
-    Ok(validated)
+/// Split NUL-terminated git output into its non-empty entries
+fn split_nul(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
 /// Get the absolute path to the git directory (.git folder or file).
@@ -82,11 +856,7 @@ fn cached_git_path(
 /// shared location regardless of which worktree is active (e.g.
 /// `info/exclude`), use `get_git_common_dir` instead.
 pub fn get_git_dir() -> anyhow::Result<PathBuf> {
-    cached_git_path(
-        &GIT_DIR_CACHE,
-        "--absolute-git-dir",
-        "Failed to find git directory",
-    )
+    Ok(RepoContext::current()?.git_dir().to_path_buf())
 }
 
 /// Get the absolute path to the git common directory.
@@ -97,70 +867,231 @@ pub fn get_git_dir() -> anyhow::Result<PathBuf> {
 /// and all of its worktrees. Callers that need `info/exclude` must resolve
 /// it against this path rather than `get_git_dir`.
 pub fn get_git_common_dir() -> anyhow::Result<PathBuf> {
-    cached_git_path(
-        &GIT_COMMON_DIR_CACHE,
-        "--git-common-dir",
-        "Failed to find git common directory",
-    )
+    Ok(RepoContext::current()?.common_dir().to_path_buf())
+}
+
+/// Where this tool keeps its own files (backups, locks, temporary files)
+/// for files in `dir`: `<git dir>/git-ignore` of the work tree `dir` is in,
+/// with `dir`'s path inside that work tree.
+///
+/// The repository is found from `dir` alone, by walking up to the nearest
+/// `.git` directory or `gitdir:` file, so every process editing a file
+/// agrees on the place whatever directory it runs in. `None` when `dir` is
+/// inside a git directory or in no work tree; callers then use `dir`
+/// itself.
+pub(crate) fn private_dir(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    for ancestor in dir.ancestors() {
+        if ancestor.file_name().is_some_and(|name| name == ".git") {
+            return None;
+        }
+        let dot_git = ancestor.join(".git");
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else if dot_git.is_file() {
+            let content = std::fs::read_to_string(&dot_git).ok()?;
+            let target = content.trim_end().strip_prefix("gitdir:")?.trim();
+            ancestor.join(paths::native_path(PathBuf::from(target)))
+        } else {
+            continue;
+        };
+        let relative = dir.strip_prefix(ancestor).ok()?;
+        return Some((git_dir.join("git-ignore"), relative.to_path_buf()));
+    }
+    None
 }
 
 /// Get the absolute path to the repository root
 pub fn get_repo_root() -> anyhow::Result<PathBuf> {
-    cached_git_path(
-        &REPO_ROOT_CACHE,
-        "--show-toplevel",
-        "Failed to find repository root",
-    )
+    Ok(RepoContext::current()?.root()?.to_path_buf())
 }
 
-/// Get path to global gitignore file
-pub fn get_global_gitignore_path() -> Option<PathBuf> {
-    // Try to get configured global gitignore
-    if let Ok(output) = run_git_command(&["config", "--global", "core.excludesfile"]) {
-        let path = PathBuf::from(output);
-        let expanded = if path.starts_with("~") {
-            let home = env::var_os("HOME")?;
-            PathBuf::from(home).join(path.strip_prefix("~").unwrap())
-        } else if !path.is_absolute() {
-            let home = env::var_os("HOME")?;
-            PathBuf::from(home).join(&path)
-        } else {
-            path
-        };
+/// The global `core.excludesFile` setting (from `~/.gitconfig` and
+/// `$XDG_CONFIG_HOME/git/config`, or the file `GIT_CONFIG_GLOBAL` names)
+/// with `~` and environment variables expanded (see the README for the rules), resolved with
+/// [`resolve_config_path`]. The file may not exist.
+///
+/// Fails if the value refers to a variable that is not set.
+pub fn get_configured_global_excludes_file() -> anyhow::Result<Option<PathBuf>> {
+    configured_excludes_file(ConfigFile::Global)
+}
 
-        if expanded.exists() {
-            return Some(expanded);
-        }
+/// The system `core.excludesFile` setting (from `/etc/gitconfig`, or the
+/// file `GIT_CONFIG_SYSTEM` names), expanded and resolved like
+/// [`get_configured_global_excludes_file`]. The file may not exist.
+pub fn get_configured_system_excludes_file() -> anyhow::Result<Option<PathBuf>> {
+    configured_excludes_file(ConfigFile::System)
+}
+
+/// `core.excludesFile` from one config `file`; a config file that exists
+/// but can't be read or parsed is an error
+fn configured_excludes_file(file: ConfigFile) -> anyhow::Result<Option<PathBuf>> {
+    let value = backend::active()
+        .config_values(Location::Current, file, "core.excludesFile", ValueType::Raw)?
+        .pop()
+        .filter(|value| !value.trim().is_empty());
+    value
+        .map(|value| resolve_config_path(value.trim()))
+        .transpose()
+}
+
+/// Where a system excludes file conventionally goes: `gitignore` beside the
+/// system git config, so `/etc/gitignore` on most distributions
+pub fn default_system_gitignore_path() -> PathBuf {
+    env::var_os("GIT_CONFIG_SYSTEM")
+        .and_then(|config| PathBuf::from(config).parent().map(Path::to_path_buf))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| PathBuf::from("/etc"))
+        .join("gitignore")
+}
+
+/// The system excludes file. Git reads no such file unless the system
+/// config names one, so an unset `core.excludesFile` is a configuration
+/// error suggesting [`default_system_gitignore_path`].
+pub fn get_system_gitignore_path() -> anyhow::Result<PathBuf> {
+    match get_configured_system_excludes_file()? {
+        Some(path) => Ok(path),
+        None => bail!(Error::config(format!(
+            "no system gitignore configured. Run: git config --system core.excludesFile {}",
+            default_system_gitignore_path().display()
+        ))),
     }
+}
 
-    // Check default locations
-    if let Some(xdg_config) = env::var_os("XDG_CONFIG_HOME") {
-        let path = PathBuf::from(xdg_config).join("git").join("ignore");
-        if path.exists() {
-            return Some(path);
-        }
+/// The scope of the excludes file at `path`: [`Scope::System`] when the
+/// system config names it, [`Scope::Global`] otherwise
+pub fn excludes_file_scope(path: &Path) -> Scope {
+    match get_configured_system_excludes_file() {
+        Ok(Some(system)) if system == path => Scope::System,
+        _ => Scope::Global,
     }
+}
 
-    if let Some(home) = env::var_os("HOME") {
-        let home_path = PathBuf::from(&home);
+/// Expand a path-valued setting such as `core.excludesFile` and resolve a
+/// relative result the way git does: against the directory git runs in,
+/// which is the top of the work tree inside a repository and the current
+/// directory elsewhere.
+pub fn resolve_config_path(value: &str) -> anyhow::Result<PathBuf> {
+    let expanded = paths::expand_path(value)?;
+    if expanded.is_absolute() {
+        return Ok(expanded);
+    }
+    let base = match get_repo_root() {
+        Ok(root) => root,
+        Err(_) => current_dir()?,
+    };
+    Ok(base.join(expanded))
+}
 
-        let path = home_path.join(".config").join("git").join("ignore");
-        if path.exists() {
-            return Some(path);
+/// The global gitignore as git finds it, if it exists: the file the global
+/// `core.excludesFile` names, or [`default_global_gitignore_path`] when the
+/// setting is absent
+pub fn get_global_gitignore_path() -> Option<PathBuf> {
+    // Try to get configured global gitignore
+    match get_configured_global_excludes_file() {
+        Ok(Some(path)) if path.exists() => return Some(path),
+        Ok(Some(path)) => {
+            // Git reads nothing else while the setting is present
+            warn!(path = %path.display(), "core.excludesFile does not exist");
+            return None;
         }
-
-        let path = home_path.join(".gitignore_global");
-        if path.exists() {
-            return Some(path);
+        Ok(None) => {}
+        Err(e) => {
+            // Falling back would pick a file git does not read
+            warn!(error = %e, "cannot resolve core.excludesFile");
+            return None;
         }
+    }
 
-        let path = home_path.join(".gitignore");
-        if path.exists() {
-            return Some(path);
-        }
+    // Git's default when the setting is absent, and nothing else
+    default_global_gitignore_path().filter(|path| path.exists())
+}
+
+/// Whether the current directory is inside a linked worktree (one created
+/// with `git worktree add`) rather than the main worktree
+pub fn is_linked_worktree() -> anyhow::Result<bool> {
+    Ok(RepoContext::current()?.is_linked_worktree())
+}
+
+/// Get path to the exclude file private to the current linked worktree,
+/// `$GIT_DIR/info/exclude`.
+///
+/// Git only reads the shared `info/exclude` in the common directory, so
+/// this file takes effect once [`enable_worktree_excludes_file`] has pointed
+/// the worktree's `core.excludesFile` at it.
+pub fn get_worktree_exclude_file_path() -> anyhow::Result<PathBuf> {
+    if !is_linked_worktree()? {
+        bail!(Error::config(
+            "--worktree needs a linked worktree; in the main worktree use --local on its own"
+        ));
     }
+    Ok(get_git_dir()?.join("info").join("exclude"))
+}
 
-    None
+/// Make git read `path` as the current worktree's excludes file by setting
+/// `core.excludesFile` in the per-worktree config, enabling the
+/// `extensions.worktreeConfig` repository extension if necessary.
+///
+/// Returns `false` if the worktree was already configured this way.
+pub fn enable_worktree_excludes_file(path: &Path) -> anyhow::Result<bool> {
+    if get_config_bool("extensions.worktreeConfig")? != Some(true) {
+        set_local_config_value("extensions.worktreeConfig", "true")?;
+    }
+
+    let value = path.to_string_lossy();
+    let current = backend::active()
+        .config_values(
+            Location::Current,
+            ConfigFile::Worktree,
+            "core.excludesFile",
+            ValueType::Raw,
+        )?
+        .pop();
+    if current.as_deref() == Some(value.as_ref()) {
+        return Ok(false);
+    }
+    set_config("--worktree", "core.excludesFile", &value)?;
+    Ok(true)
+}
+
+/// Location git reads the global ignore file from when `core.excludesFile`
+/// is unset: `$XDG_CONFIG_HOME/git/ignore`, or `~/.config/git/ignore`
+pub fn default_global_gitignore_path() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME").filter(|xdg| !xdg.is_empty()) {
+        Some(xdg_config) => Some(
+            paths::native_path(PathBuf::from(xdg_config))
+                .join("git")
+                .join("ignore"),
+        ),
+        None => paths::home_dir().map(|home| home.join(".config").join("git").join("ignore")),
+    }
+}
+
+/// The excludes file git itself reads: `core.excludesFile` from any config
+/// scope (resolved with [`resolve_config_path`]), or the default location
+/// when unset; `None` when the file doesn't exist. Unlike
+/// [`get_global_gitignore_path`], a repository's own config may set it.
+pub fn get_excludes_file() -> anyhow::Result<Option<PathBuf>> {
+    excludes_file_from(
+        backend::active()
+            .config_values(
+                Location::Current,
+                ConfigFile::Any,
+                "core.excludesFile",
+                ValueType::Path,
+            )?
+            .pop(),
+    )
+}
+
+/// The excludes file for a `core.excludesFile` value, if the file exists
+fn excludes_file_from(value: Option<String>) -> anyhow::Result<Option<PathBuf>> {
+    let configured = value
+        .filter(|value| !value.is_empty())
+        .map(|value| resolve_config_path(&value))
+        .transpose()?;
+    Ok(configured
+        .or_else(default_global_gitignore_path)
+        .filter(|path| path.is_file()))
 }
 
 /// Get path to repository's .git/info/exclude file
@@ -169,10 +1100,322 @@ pub fn get_exclude_file_path() -> anyhow::Result<PathBuf> {
     Ok(git_common_dir.join("info").join("exclude"))
 }
 
+/// The template directory `git init` and `git clone` copy into new
+/// repositories: `$GIT_TEMPLATE_DIR`, else `init.templateDir` with `~` and
+/// environment variables expanded. Relative values are taken relative to
+/// the home directory. `None` when neither is set, in which case git uses
+/// its built-in templates, which this tool does not edit.
+pub fn get_template_dir() -> anyhow::Result<Option<PathBuf>> {
+    let value = match env::var("GIT_TEMPLATE_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+    {
+        Some(dir) => dir,
+        None => match get_config_value("init.templateDir")?.filter(|value| !value.is_empty()) {
+            Some(value) => value,
+            None => return Ok(None),
+        },
+    };
+    let expanded = paths::expand_path(&value)?;
+    if expanded.is_absolute() {
+        return Ok(Some(expanded));
+    }
+    Ok(paths::home_dir().map(|home| home.join(expanded)))
+}
+
+/// The `info/exclude` file inside the template directory, which becomes
+/// `.git/info/exclude` of every repository created from it
+pub fn get_template_exclude_file_path() -> anyhow::Result<PathBuf> {
+    match get_template_dir()? {
+        Some(dir) => Ok(dir.join("info").join("exclude")),
+        None => bail!(Error::config("no template directory is configured; set one with `git config --global init.templateDir ~/.git-template`")),
+    }
+}
+
+/// List the tracked files of the repository, relative to its root and
+/// `/`-separated
+pub fn list_tracked_files() -> anyhow::Result<Vec<String>> {
+    RepoContext::current()?.list_tracked_files()
+}
+
+/// `git blame --line-porcelain` output for `file`, which must be tracked.
+/// Lines changed since the last commit are attributed to the all-zero
+/// commit, as git does.
+pub fn blame_porcelain(file: &Path) -> anyhow::Result<String> {
+    RepoContext::current()?.blame_porcelain(file)
+}
+
+/// Untracked files in the work tree that no ignore file excludes, relative
+/// to the repository root
+pub fn list_untracked_files() -> anyhow::Result<Vec<String>> {
+    RepoContext::current()?.list_untracked_files()
+}
+
+/// Every file in the work tree, tracked, untracked or ignored, relative to
+/// the repository root
+pub fn list_work_tree_files() -> anyhow::Result<Vec<String>> {
+    RepoContext::current()?.list_work_tree_files()
+}
+
+/// Tracked files that ignore rules match: those of the repository's ignore
+/// files, or just `patterns` when any are given. Paths are relative to the
+/// repository root; a file inside a matched directory is listed.
+pub fn list_tracked_ignored(patterns: &[String]) -> anyhow::Result<Vec<String>> {
+    RepoContext::current()?.list_tracked_ignored(patterns)
+}
+
+/// The patterns of `patterns` that match tracked files, each with the files
+/// it matches (relative to the repository root). Git keeps tracking such
+/// files whatever the ignore files say, so adding the pattern alone
+/// doesn't ignore them.
+pub fn tracked_by_pattern(patterns: &[String]) -> anyhow::Result<Vec<(String, Vec<String>)>> {
+    RepoContext::current()?.tracked_by_pattern(patterns)
+}
+
+/// Remove `paths` (relative to the repository root) from the index while
+/// keeping them in the work tree, like `git rm -r --cached`
+pub fn untrack_files(paths: &[String]) -> anyhow::Result<()> {
+    RepoContext::current()?.untrack_files(paths)
+}
+
+/// Untracked entries of `git status`, relative to the repository root. A
+/// directory with nothing tracked in it is one entry ending in `/`.
+pub fn list_untracked_status() -> anyhow::Result<Vec<String>> {
+    RepoContext::current()?.list_untracked_status()
+}
+
+/// Whether `repo` is bare (has no work tree)
+pub fn is_bare_repository(repo: &RepoContext) -> anyhow::Result<bool> {
+    let output = run_git_with(repo.command(), &["rev-parse", "--is-bare-repository"])?;
+    Ok(output.trim() == "true")
+}
+
+/// Full ref name of `branch` (`main` becomes `refs/heads/main`), or of the
+/// branch `HEAD` points to when `branch` is `None`
+pub fn resolve_branch_ref(branch: Option<&str>) -> anyhow::Result<String> {
+    match branch {
+        Some(branch) if branch.starts_with("refs/") => Ok(branch.to_string()),
+        Some(branch) => Ok(format!("refs/heads/{branch}")),
+        None => run_plumbing(&["symbolic-ref", "--quiet", "HEAD"], None, &[]).context(
+            Error::config("HEAD is detached; name a branch with --branch"),
+        ),
+    }
+}
+
+/// The branch checked out in the current work tree, as a full ref name
+pub fn current_branch_ref() -> anyhow::Result<Option<String>> {
+    Ok(run_plumbing(&["symbolic-ref", "--quiet", "HEAD"], None, &[]).ok())
+}
+
+/// Commit id `reference` points to, or `None` if it does not exist yet
+pub fn resolve_commit(reference: &str) -> anyhow::Result<Option<String>> {
+    let spec = format!("{reference}^{{commit}}");
+    Ok(run_plumbing(&["rev-parse", "--verify", "--quiet", &spec], None, &[]).ok())
+}
+
+/// Content of the file at `path` in `commit`, or `None` if it has none
+pub fn read_committed_file(commit: &str, path: &str) -> anyhow::Result<Option<String>> {
+    let spec = format!("{commit}:{path}");
+    if run_plumbing(&["cat-file", "-e", &spec], None, &[]).is_err() {
+        return Ok(None);
+    }
+    let args = ["cat-file", "blob", &spec];
+    let mut command = git();
+    command.args(args);
+    let output = run_with_timeout(command, &args, None, git_timeout())?;
+    if !output.status.success() {
+        bail!(Error::GitFailed(format!(
+            "git cat-file blob {spec}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Commit `content` as the top-level file `name` on top of `parent` (or as
+/// a root commit), returning the new commit id. Nothing else in the tree
+/// changes, and no work tree or index is needed.
+///
+/// `author` is `(name, email)` and is used for the committer too; without
+/// it git's configured identity applies.
+pub fn commit_file(
+    parent: Option<&str>,
+    name: &str,
+    content: &str,
+    message: &str,
+    author: Option<(&str, &str)>,
+) -> anyhow::Result<String> {
+    let blob = run_plumbing(
+        &["hash-object", "-w", "--stdin"],
+        Some(content.as_bytes()),
+        &[],
+    )?;
+
+    // Rebuild the top-level tree with the one entry replaced
+    let mut entries = String::new();
+    if let Some(parent) = parent {
+        let listing = run_plumbing(&["ls-tree", "-z", parent], None, &[])?;
+        for entry in listing.split('\0').filter(|entry| !entry.is_empty()) {
+            if entry.split_once('\t').map(|(_, path)| path) != Some(name) {
+                entries.push_str(entry);
+                entries.push('\0');
+            }
+        }
+    }
+    entries.push_str(&format!("100644 blob {blob}\t{name}\0"));
+    let tree = run_plumbing(&["mktree", "-z"], Some(entries.as_bytes()), &[])?;
+
+    let mut args = vec!["commit-tree", tree.as_str(), "-F", "-"];
+    if let Some(parent) = parent {
+        args.extend(["-p", parent]);
+    }
+    let envs: Vec<(&str, &str)> = match author {
+        Some((author_name, email)) => vec![
+            ("GIT_AUTHOR_NAME", author_name),
+            ("GIT_AUTHOR_EMAIL", email),
+            ("GIT_COMMITTER_NAME", author_name),
+            ("GIT_COMMITTER_EMAIL", email),
+        ],
+        None => Vec::new(),
+    };
+    run_plumbing(&args, Some(message.as_bytes()), &envs)
+}
+
+/// Point `reference` at `commit`, provided it still points at `expected`
+/// (`None`: the ref must not exist yet). Returns `false` if the ref moved
+/// in the meantime.
+pub fn update_ref_if_unchanged(
+    reference: &str,
+    commit: &str,
+    expected: Option<&str>,
+) -> anyhow::Result<bool> {
+    let old = expected.unwrap_or("");
+    match run_plumbing(&["update-ref", reference, commit, old], None, &[]) {
+        Ok(_) => Ok(true),
+        Err(e) if resolve_commit(reference)?.as_deref() != expected => {
+            debug!(%reference, error = %e, "ref moved during update");
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// URL of `remote`
+pub fn get_remote_url(remote: &str) -> anyhow::Result<String> {
+    run_plumbing(&["remote", "get-url", remote], None, &[])
+}
+
+/// Push `reference` to the same name on `remote`. Unlike other git
+/// commands, the push is not bound by [`git_timeout`]: a large push over a
+/// slow link can take as long as it needs.
+pub fn push_ref(remote: &str, reference: &str) -> anyhow::Result<()> {
+    let refspec = format!("{reference}:{reference}");
+    run_plumbing_until(
+        git(),
+        &["push", "--quiet", remote, &refspec],
+        None,
+        &[],
+        None,
+    )?;
+    Ok(())
+}
+
+/// Find every `.gitignore` file in the working tree, tracked or not (but
+/// not ignored), with the top-level one first
+pub fn find_gitignore_files() -> anyhow::Result<Vec<PathBuf>> {
+    RepoContext::current()?.find_gitignore_files()
+}
+
+/// Find every `.gitattributes` file in the working tree, tracked or not
+/// (but not ignored), with the top-level one first
+pub fn find_gitattributes_files() -> anyhow::Result<Vec<PathBuf>> {
+    RepoContext::current()?.find_gitattributes_files()
+}
+
+/// Git repositories inside the current work tree: untracked directories
+/// with their own `.git` that aren't ignored yet, and submodules
+pub fn find_nested_repositories() -> anyhow::Result<Vec<NestedRepository>> {
+    RepoContext::current()?.find_nested_repositories()
+}
+
+/// What git decided about one path given to [`check_ignore`], or what
+/// [`is_ignored`](crate::is_ignored) works out the same way
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgnoreCheck {
+    /// The path as given
+    pub path: String,
+    /// The last pattern matching the path, if any
+    pub matched: Option<IgnoreMatch>,
+}
+
+impl IgnoreCheck {
+    /// Whether the path is ignored: the deciding pattern isn't a negation
+    pub fn is_ignored(&self) -> bool {
+        self.matched
+            .as_ref()
+            .is_some_and(|matched| !matched.pattern.starts_with('!'))
+    }
+}
+
+/// The pattern that decided whether a path is ignored, and where it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgnoreMatch {
+    /// The file the pattern is in
+    pub source: PathBuf,
+    /// Which kind of ignore file that is
+    pub scope: Scope,
+    /// 1-based line of the pattern in `source`
+    pub line_number: usize,
+    /// The pattern as written, `!` included for a negation
+    pub pattern: String,
+}
+
+/// Ask git which pattern, if any, decides whether each of `paths` is
+/// ignored. Paths are relative to the current directory and needn't exist;
+/// a trailing `/` checks a path as a directory. Tracked files are never
+/// ignored unless `no_index` is set, which checks the patterns alone.
+pub fn check_ignore(paths: &[String], no_index: bool) -> anyhow::Result<Vec<IgnoreCheck>> {
+    RepoContext::current()?.check_ignore_with(git(), paths, no_index)
+}
+
 /// Get path to repository's .gitignore file
 pub fn get_gitignore_path() -> anyhow::Result<PathBuf> {
-    let repo_root = get_repo_root()?;
-    Ok(repo_root.join(".gitignore"))
+    RepoContext::current()?.gitignore_path()
+}
+
+/// Get path to repository's top-level .gitattributes file
+pub fn get_gitattributes_path() -> anyhow::Result<PathBuf> {
+    RepoContext::current()?.gitattributes_path()
+}
+
+/// Get path to repository's `info/attributes` file, the attributes
+/// counterpart of `info/exclude`
+pub fn get_info_attributes_path() -> anyhow::Result<PathBuf> {
+    let git_common_dir = get_git_common_dir()?;
+    Ok(git_common_dir.join("info").join("attributes"))
+}
+
+/// The global attributes file: `core.attributesFile` from the global
+/// config (expanded and resolved like `core.excludesFile`), or git's default
+/// `$XDG_CONFIG_HOME/git/attributes` (`~/.config/git/attributes`)
+pub fn get_global_attributes_path() -> anyhow::Result<PathBuf> {
+    if let Some(value) = backend::active()
+        .config_values(
+            Location::Current,
+            ConfigFile::Global,
+            "core.attributesFile",
+            ValueType::Raw,
+        )?
+        .pop()
+        .filter(|value| !value.is_empty())
+    {
+        return resolve_config_path(&value);
+    }
+    default_global_gitignore_path()
+        .map(|ignore| ignore.with_file_name("attributes"))
+        .ok_or_else(|| anyhow::anyhow!("No global gitattributes location: HOME is not set"))
 }
 
 #[cfg(test)]
@@ -181,9 +1424,13 @@ mod tests {
     use std::env;
 
     #[test]
-    fn test_run_git_command_failure() {
-        let result = run_git_command(&["nonexistent-command"]);
-        assert!(result.is_err());
+    fn test_run_git_with_failure() {
+        let error = run_git_with(git(), &["nonexistent-command"]).unwrap_err();
+        // Only repository discovery reports a missing repository
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::GitFailed(detail)) if detail.starts_with("git nonexistent-command: ")
+        ));
     }
 
     #[test]
@@ -197,10 +1444,77 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_repo_contexts_are_independent() {
+        let dirs = [
+            tempfile::TempDir::new().unwrap(),
+            tempfile::TempDir::new().unwrap(),
+        ];
+        let mut repos = Vec::new();
+        for (dir, pattern) in dirs.iter().zip(["*.log", "*.tmp"]) {
+            Command::new("git")
+                .args(["init", "--quiet"])
+                .current_dir(dir.path())
+                .output()
+                .unwrap();
+            std::fs::write(dir.path().join(".gitignore"), format!("{pattern}\n")).unwrap();
+            std::fs::create_dir(dir.path().join("src")).unwrap();
+            repos.push(RepoContext::discover(&dir.path().join("src")).unwrap());
+        }
+
+        for (repo, dir) in repos.iter().zip(&dirs) {
+            let root = paths::canonicalize(dir.path()).unwrap();
+            assert_eq!(repo.root().unwrap(), root);
+            assert_eq!(repo.git_dir(), root.join(".git"));
+            assert_eq!(repo.exclude_file_path(), root.join(".git/info/exclude"));
+            assert!(!repo.is_linked_worktree());
+            assert_eq!(
+                repo.find_gitignore_files().unwrap(),
+                [root.join(".gitignore")]
+            );
+        }
+
+        let paths = ["a.log".to_string(), "a.tmp".to_string()];
+        let ignored = |repo: &RepoContext| -> Vec<bool> {
+            let checks = repo.check_ignore(&paths, false).unwrap();
+            checks.iter().map(IgnoreCheck::is_ignored).collect()
+        };
+        assert_eq!(ignored(&repos[0]), [true, false]);
+        assert_eq!(ignored(&repos[1]), [false, true]);
+    }
+
     #[test]
     fn test_get_global_gitignore_path() {
         // This test might fail if no global gitignore is configured
         // but should not panic
         let _ = get_global_gitignore_path();
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_with_timeout() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "cat; echo done >&2"]);
+        let output = run_with_timeout(
+            command,
+            &["cat"],
+            Some(b"input"),
+            Some(Duration::from_secs(10)),
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"input");
+        assert_eq!(output.stderr, b"done\n");
+
+        let started = Instant::now();
+        let mut command = Command::new("sleep");
+        command.arg("10");
+        let error = run_with_timeout(command, &["fetch"], None, Some(Duration::from_millis(100)))
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::GitTimeout { command, .. }) if command == "git fetch"
+        ));
+        assert!(error.to_string().contains("timed out after 0.1s"));
+    }
 }
@@ -0,0 +1,206 @@
+//! External validator hook run before patterns are written
+//!
+//! Organizations can enforce their own rules by configuring a command in
+//! `ignore.validator`. The command is run through `sh -c` with:
+//!
+//! - the target ignore file path as `$1` (also exported as
+//!   `GIT_IGNORE_TARGET`, with the scope name in `GIT_IGNORE_SCOPE`),
+//! - the proposed patterns on stdin, one per line.
+//!
+//! Exiting non-zero vetoes the write; anything the command prints on stderr
+//! is shown to the user. If the command prints patterns on stdout, they
+//! replace the proposed ones, which lets a hook rewrite patterns as well as
+//! reject them. Blank lines are skipped and the rest are cleaned up like
+//! typed patterns, so an escaped trailing space survives. The CLI validates
+//! rewritten patterns again before writing.
+//! A hook still running after the git command timeout
+//! ([`git_timeout`](crate::git::git_timeout)) is killed, which vetoes the
+//! write too.
+
+use crate::{git, ignore, Error, Scope};
+use anyhow::{bail, Context};
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
+use tracing::{debug_span, info};
+
+/// Name of a scope as passed to validator hooks
+fn scope_name(scope: Scope) -> &'static str {
+    match scope {
+        Scope::Repo => "repo",
+        Scope::Local => "local",
+        Scope::Global => "global",
+        Scope::System => "system",
+    }
+}
+
+/// Run the configured validator command over `patterns` destined for
+/// `target`, returning the (possibly rewritten) patterns to write.
+pub fn run_validator(
+    command: &str,
+    target: &Path,
+    scope: Scope,
+    patterns: &[String],
+) -> anyhow::Result<Vec<String>> {
+    run_validator_within(command, target, scope, patterns, git::git_timeout())
+}
+
+/// [`run_validator`], killing the hook once `timeout` has passed
+fn run_validator_within(
+    command: &str,
+    target: &Path,
+    scope: Scope,
+    patterns: &[String],
+    timeout: Option<Duration>,
+) -> anyhow::Result<Vec<String>> {
+    let _span = debug_span!("validator_hook", %command).entered();
+
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .arg("git-ignore-validator")
+        .arg(target)
+        .env("GIT_IGNORE_TARGET", target)
+        .env("GIT_IGNORE_SCOPE", scope_name(scope))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("Failed to run validator hook: {command}"))?;
+
+    let input: String = patterns.iter().map(|p| format!("{p}\n")).collect();
+    let Some(output) = git::wait_with_timeout(child, Some(input.as_bytes()), timeout)
+        .with_context(|| format!("Failed to run validator hook: {command}"))?
+    else {
+        bail!(Error::ValidationFailed(vec![format!(
+            "validator hook '{command}' timed out after {}s",
+            timeout.unwrap_or_default().as_secs_f64()
+        )]));
+    };
+
+    if !output.status.success() {
+        info!(status = %output.status, "validator rejected patterns");
+        bail!(Error::ValidationFailed(vec![format!(
+            "rejected by validator hook '{command}'"
+        )]));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rewritten: Vec<String> = stdout
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .map(ignore::sanitize_pattern)
+        .collect();
+
+    if rewritten.is_empty() {
+        Ok(patterns.to_vec())
+    } else {
+        info!(count = rewritten.len(), "validator rewrote patterns");
+        Ok(rewritten)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn patterns(items: &[&str]) -> Vec<String> {
+        items.iter().map(|p| p.to_string()).collect()
+    }
+
+    /// Write a validator script and return a command running it
+    fn script(dir: &TempDir, body: &str) -> String {
+        let path = dir.path().join("validator.sh");
+        std::fs::write(&path, format!("{body}\n")).unwrap();
+        format!("sh {} \"$1\"", path.display())
+    }
+
+    #[test]
+    fn test_validator_accepts_unchanged() {
+        let result = run_validator(
+            "cat >/dev/null; true",
+            Path::new(".gitignore"),
+            Scope::Repo,
+            &patterns(&["*.log"]),
+        );
+        assert_eq!(result.unwrap(), patterns(&["*.log"]));
+    }
+
+    #[test]
+    fn test_validator_rewrites() {
+        let dir = TempDir::new().unwrap();
+        let command = script(&dir, "sed 's/^/\\//'");
+        let result = run_validator(
+            &command,
+            Path::new(".gitignore"),
+            Scope::Repo,
+            &patterns(&["build", "dist"]),
+        );
+        assert_eq!(result.unwrap(), patterns(&["/build", "/dist"]));
+    }
+
+    #[test]
+    fn test_validator_rewrite_keeps_escaped_spaces() {
+        let dir = TempDir::new().unwrap();
+        let command = script(
+            &dir,
+            "cat >/dev/null; printf '%s\\r\\n' 'name\\ ' '' ' ' 'notes.txt  ' \"$(printf '*.tmp\\t')\"",
+        );
+        let result = run_validator(
+            &command,
+            Path::new(".gitignore"),
+            Scope::Repo,
+            &patterns(&["name"]),
+        );
+        assert_eq!(
+            result.unwrap(),
+            patterns(&["name\\ ", "notes.txt", "*.tmp\t"])
+        );
+    }
+
+    #[test]
+    fn test_validator_veto_and_arguments() {
+        let dir = TempDir::new().unwrap();
+        let command = script(
+            &dir,
+            "test \"$GIT_IGNORE_SCOPE\" = local && test \"$1\" = exclude && ! grep -q pem",
+        );
+
+        let result = run_validator(
+            &command,
+            Path::new("exclude"),
+            Scope::Local,
+            &patterns(&["*.pem"]),
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("rejected by validator hook"));
+
+        let result = run_validator(
+            &command,
+            Path::new("exclude"),
+            Scope::Local,
+            &patterns(&["*.log"]),
+        );
+        assert_eq!(result.unwrap(), patterns(&["*.log"]));
+    }
+
+    #[test]
+    fn test_validator_timeout() {
+        let started = std::time::Instant::now();
+        let result = run_validator_within(
+            "exec sleep 30",
+            Path::new(".gitignore"),
+            Scope::Repo,
+            &patterns(&["*.log"]),
+            Some(Duration::from_millis(200)),
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+    }
+}
@@ -1,18 +1,73 @@
 //! Core ignore file management functionality
 
-use crate::{PatternIssue, PatternSeverity, PatternValidationLevel};
+use crate::{
+    affinity, backup, format, git,
+    ignore_file::{self, EntryKind, IgnoreFile},
+    lock, negation,
+    observe::{Observer, SkipReason},
+    paths,
+    pattern::{self, Pattern},
+    sort::{self, SortStrategy},
+    store::{self, FsStore, IgnoreStore, StagedWrite, WriteOutcome},
+    unified_diff::unified_diff,
+    validation::RuleSet,
+    Error, PatternIssue, PatternRule, PatternSeverity, PatternValidationLevel,
+};
 use anyhow::{bail, Context};
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
     collections::HashSet,
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader, BufWriter, Write},
-    path::Path,
+    fs::OpenOptions,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{self, AtomicUsize},
 };
+use tracing::{debug, debug_span, info, warn};
 
 /// Sanitize a pattern to prevent file corruption
-fn sanitize_pattern(pattern: &str) -> String {
-    // Remove newlines and carriage returns that could break file format
-    pattern.replace(['\n', '\r'], "").trim().to_string()
+pub(crate) fn sanitize_pattern(pattern: &str) -> String {
+    // Remove newlines and carriage returns that could break file format;
+    // escaped trailing spaces and trailing tabs are part of the pattern
+    let pattern = pattern.replace(['\n', '\r'], "");
+    pattern::trim_unescaped_trailing_spaces(pattern.trim_start()).to_string()
+}
+
+/// Split piped input into patterns.
+///
+/// Newline-delimited input skips blank lines and tolerates CRLF endings.
+/// NUL-delimited input (as produced by `git ls-files -z`) takes every item
+/// as an exact file name, escaped with [`pattern::escape_literal`] so that a
+/// leading `#` or `!`, wildcards and trailing spaces match the name instead
+/// of being read as syntax; an item containing a line break is rejected
+/// because an ignore file cannot express it.
+pub fn parse_pattern_input(input: &[u8], null_delimited: bool) -> anyhow::Result<Vec<String>> {
+    let text = std::str::from_utf8(input).context("Failed to read input: not valid UTF-8")?;
+
+    if !null_delimited {
+        return Ok(text
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect());
+    }
+
+    let mut patterns = Vec::new();
+    for (index, item) in text.split('\0').enumerate() {
+        if item.is_empty() {
+            continue;
+        }
+        if item.contains(['\n', '\r']) {
+            bail!(
+                "Invalid input item {}: contains a line break, which an ignore file cannot express: {:?}",
+                index + 1,
+                item
+            );
+        }
+        patterns.push(pattern::escape_literal(item));
+    }
+    Ok(patterns)
 }
 
 /// Normalize a pattern for duplicate comparison.
@@ -20,21 +75,30 @@ fn sanitize_pattern(pattern: &str) -> String {
 /// A trailing slash only restricts a gitignore pattern to matching
 /// directories; it doesn't change what directory it matches. Patterns that
 /// differ solely by a trailing slash (e.g. "build" and "build/") are treated
-/// as the same entry so one doesn't get added alongside the other.
-fn normalize_pattern_for_dedup(pattern: &str) -> &str {
-    if pattern == "/" {
-        pattern
+/// as the same entry so one doesn't get added alongside the other. Other
+/// spellings of the same pattern, such as `**/build` for `build` or a
+/// needless escape, compare equal through [`Pattern::normalize`]; text that
+/// doesn't parse as a pattern is compared as written.
+pub(crate) fn normalize_pattern_for_dedup(pattern: &str) -> Cow<'_, str> {
+    let Ok(parsed) = pattern.parse::<Pattern>() else {
+        return Cow::Borrowed(pattern);
+    };
+    let mut normalized = parsed.normalize();
+    normalized.dir_only = false;
+    let normalized = normalized.to_string();
+    if normalized == pattern {
+        Cow::Borrowed(pattern)
     } else {
-        pattern.strip_suffix('/').unwrap_or(pattern)
+        Cow::Owned(normalized)
     }
 }
 
 /// Validate that file path is safe to write to
 fn validate_file_path(file_path: &Path, base_dir: Option<&Path>) -> anyhow::Result<()> {
     let resolved = if file_path.exists() {
-        file_path.canonicalize()
+        paths::canonicalize(file_path)
     } else if let Some(parent) = file_path.parent() {
-        parent.canonicalize().and_then(|p| {
+        paths::canonicalize(parent).and_then(|p| {
             file_path
                 .file_name()
                 .ok_or_else(|| {
@@ -51,8 +115,7 @@ fn validate_file_path(file_path: &Path, base_dir: Option<&Path>) -> anyhow::Resu
     .with_context(|| format!("Invalid file path: {}", file_path.display()))?;
 
     if let Some(base) = base_dir {
-        let base_resolved = base
-            .canonicalize()
+        let base_resolved = paths::canonicalize(base)
             .with_context(|| format!("Invalid base directory: {}", base.display()))?;
 
         if !resolved.starts_with(base_resolved) {
@@ -67,30 +130,282 @@ fn validate_file_path(file_path: &Path, base_dir: Option<&Path>) -> anyhow::Resu
 }
 
 /// Read patterns from ignore file
+///
+/// The distinct pattern texts of [`read_ignore_entries`]; use that to keep
+/// order, duplicates and comments.
 pub fn read_ignore_patterns(file_path: &Path) -> anyhow::Result<HashSet<String>> {
-    if !file_path.exists() {
-        return Ok(HashSet::new());
-    }
+    let _span = debug_span!("read_ignore_patterns", path = %file_path.display()).entered();
+    Ok(read_ignore_entries(file_path)?
+        .into_iter()
+        .map(|entry| entry.pattern)
+        .collect())
+}
 
-    let file = File::open(file_path)
-        .with_context(|| format!("Failed to open ignore file: {}", file_path.display()))?;
+/// A pattern line read from an ignore file, in file order
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgnoreEntry {
+    /// 1-based line number in the file
+    pub line_number: usize,
+    /// The pattern text, trimmed
+    pub pattern: String,
+    /// The line as written
+    pub raw: String,
+    /// The pattern parsed, or `None` for a line that isn't one, such as a
+    /// lone `!`
+    pub parsed: Option<Pattern>,
+    /// Comment lines written directly above the pattern, trimmed
+    pub comments: Vec<String>,
+    /// Heading of the section the pattern belongs to: the first comment of
+    /// its block of lines, or the template name inside a template block
+    pub section: Option<String>,
+}
 
-    let reader = BufReader::new(file);
-    let mut patterns = HashSet::new();
+/// Read patterns from an ignore file in order, with their line numbers and
+/// section headings and comments. Unlike `read_ignore_patterns`,
+/// duplicates are kept.
+pub fn read_ignore_entries(file_path: &Path) -> anyhow::Result<Vec<IgnoreEntry>> {
+    let _span = debug_span!("read_ignore_entries", path = %file_path.display()).entered();
+    match read_optional(file_path)? {
+        Some(content) => Ok(parse_ignore_entries(&content)),
+        None => Ok(Vec::new()),
+    }
+}
 
-    for line in reader.lines() {
-        let line =
-            line.with_context(|| format!("Failed to read line from: {}", file_path.display()))?;
+/// The pattern lines of ignore file content (see [`read_ignore_entries`])
+pub(crate) fn parse_ignore_entries(content: &str) -> Vec<IgnoreEntry> {
+    let mut entries = Vec::new();
+    let mut section: Option<String> = None;
+    let mut template: Option<String> = None;
+    let mut block_start = true;
+    let mut comments: Vec<String> = Vec::new();
 
+    for (index, line) in content.lines().enumerate() {
         let trimmed = line.trim();
 
-        // Skip empty lines and comments
-        if !trimmed.is_empty() && !trimmed.starts_with('#') {
-            patterns.insert(trimmed.to_string());
+        if let Some(name) = template_marker_name(trimmed, "# BEGIN") {
+            template = Some(name.to_string());
+            comments.clear();
+            continue;
+        }
+        if template_marker_name(trimmed, "# END").is_some() {
+            template = None;
+            block_start = true;
+            section = None;
+            comments.clear();
+            continue;
+        }
+
+        let kind = ignore_file::classify_line(line);
+        if kind == EntryKind::Blank {
+            block_start = true;
+            comments.clear();
+        } else if kind == EntryKind::Comment {
+            let comment = &trimmed[1..];
+            if block_start {
+                let heading = group_marker_heading(trimmed).unwrap_or(comment.trim());
+                section = (!heading.is_empty()).then(|| heading.to_string());
+                block_start = false;
+            }
+            comments.push(trimmed.to_string());
+        } else {
+            if block_start {
+                section = None;
+                block_start = false;
+            }
+            let pattern = ignore_file::line_pattern(line).unwrap_or(trimmed);
+            entries.push(IgnoreEntry {
+                line_number: index + 1,
+                pattern: pattern.to_string(),
+                raw: line.to_string(),
+                parsed: pattern.parse().ok(),
+                comments: std::mem::take(&mut comments),
+                section: template
+                    .as_ref()
+                    .map(|name| format!("{name} template"))
+                    .or_else(|| section.clone()),
+            });
         }
     }
 
-    Ok(patterns)
+    entries
+}
+
+/// A pattern with the comment lines written directly above it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DocumentEntry {
+    pub comments: Vec<String>,
+    pub pattern: String,
+}
+
+impl DocumentEntry {
+    /// Whether the pattern re-includes paths
+    pub fn is_negation(&self) -> bool {
+        self.pattern.starts_with('!')
+    }
+}
+
+/// A block of an [`IgnoreDocument`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DocumentSection {
+    /// Consecutive non-blank lines
+    Block {
+        /// Comments before the first pattern
+        heading: Vec<String>,
+        entries: Vec<DocumentEntry>,
+        /// Comments after the last pattern
+        trailing: Vec<String>,
+    },
+    /// A template block or `--group` block, whose lines keep their order;
+    /// a template block may contain blank lines
+    Fixed(Vec<String>),
+}
+
+impl DocumentSection {
+    /// The first line of the heading without its `#`, as `list` shows it
+    pub fn heading(&self) -> Option<&str> {
+        let DocumentSection::Block { heading, .. } = self else {
+            return None;
+        };
+        let heading = heading.first()?.strip_prefix('#')?.trim();
+        (!heading.is_empty()).then_some(heading)
+    }
+}
+
+/// Ignore file content parsed into sections, keeping each comment next to
+/// the pattern it describes so that patterns can be reordered or removed
+/// without losing their documentation. Lines are stored with surrounding
+/// whitespace removed (escaped trailing spaces excepted); blank lines
+/// only survive inside template blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgnoreDocument {
+    pub sections: Vec<DocumentSection>,
+}
+
+impl IgnoreDocument {
+    pub fn parse(content: &str) -> IgnoreDocument {
+        let mut sections = Vec::new();
+        let mut block: Vec<String> = Vec::new();
+        let mut template: Option<Vec<String>> = None;
+
+        for raw in content.lines() {
+            let line = normalize_document_line(raw);
+            if let Some(lines) = &mut template {
+                let end = template_marker_name(&line, "# END").is_some();
+                lines.push(line);
+                if end {
+                    sections.extend(template.take().map(DocumentSection::Fixed));
+                }
+            } else if template_marker_name(&line, "# BEGIN").is_some() {
+                sections.extend(parse_document_block(std::mem::take(&mut block)));
+                template = Some(vec![line]);
+            } else if line.is_empty() {
+                sections.extend(parse_document_block(std::mem::take(&mut block)));
+            } else {
+                block.push(line);
+            }
+        }
+        // An unterminated template block keeps its lines as they are
+        sections.extend(template.map(DocumentSection::Fixed));
+        sections.extend(parse_document_block(block));
+        IgnoreDocument { sections }
+    }
+
+    /// The document as file content: sections separated by one blank line
+    pub fn render(&self, newline: &str) -> String {
+        let mut blocks: Vec<Vec<&str>> = Vec::new();
+        for section in &self.sections {
+            let lines: Vec<&str> = match section {
+                DocumentSection::Block {
+                    heading,
+                    entries,
+                    trailing,
+                } => heading
+                    .iter()
+                    .map(String::as_str)
+                    .chain(entries.iter().flat_map(|entry| {
+                        entry
+                            .comments
+                            .iter()
+                            .map(String::as_str)
+                            .chain([entry.pattern.as_str()])
+                    }))
+                    .chain(trailing.iter().map(String::as_str))
+                    .collect(),
+                DocumentSection::Fixed(lines) => lines.iter().map(String::as_str).collect(),
+            };
+            if !lines.is_empty() {
+                blocks.push(lines);
+            }
+        }
+        let mut content = blocks
+            .iter()
+            .map(|lines| lines.join(newline))
+            .collect::<Vec<_>>()
+            .join(&format!("{newline}{newline}"));
+        if !content.is_empty() {
+            content.push_str(newline);
+        }
+        content
+    }
+}
+
+/// A line with leading whitespace and unescaped trailing spaces removed;
+/// an indented `#` keeps its indentation so it stays a pattern
+fn normalize_document_line(line: &str) -> String {
+    match ignore_file::classify_line(line) {
+        EntryKind::Blank => String::new(),
+        EntryKind::Comment => line.trim_end().to_string(),
+        EntryKind::Pattern => ignore_file::line_pattern(line)
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Split a block of non-blank lines into heading, entries and trailing
+/// comments; a `--group` block is kept as written
+fn parse_document_block(lines: Vec<String>) -> Option<DocumentSection> {
+    if lines.is_empty() {
+        return None;
+    }
+    if lines
+        .first()
+        .is_some_and(|line| group_marker_heading(line).is_some())
+    {
+        return Some(DocumentSection::Fixed(lines));
+    }
+    let mut heading = Vec::new();
+    let mut entries: Vec<DocumentEntry> = Vec::new();
+    let mut pending = Vec::new();
+    for line in lines {
+        if line.starts_with('#') {
+            pending.push(line);
+        } else if entries.is_empty() && heading.is_empty() {
+            heading = std::mem::take(&mut pending);
+            entries.push(DocumentEntry {
+                comments: Vec::new(),
+                pattern: line,
+            });
+        } else {
+            entries.push(DocumentEntry {
+                comments: std::mem::take(&mut pending),
+                pattern: line,
+            });
+        }
+    }
+    if entries.is_empty() {
+        // A block of only comments, such as a file header
+        heading = pending;
+        pending = Vec::new();
+    }
+    Some(DocumentSection::Block {
+        heading,
+        entries,
+        trailing: pending,
+    })
 }
 
 /// Write patterns to ignore file
@@ -103,6 +418,7 @@ pub fn write_ignore_patterns(
         return Ok(());
     }
 
+    let _span = debug_span!("write_ignore_patterns", path = %file_path.display(), count = patterns.len(), append).entered();
     validate_file_path(file_path, None)?;
 
     // Sanitize all patterns before writing
@@ -118,8 +434,7 @@ pub fn write_ignore_patterns(
 
     // Ensure parent directory exists
     if let Some(parent) = file_path.parent() {
-        std::fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        std::fs::create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
     }
 
     let mut file = if append {
@@ -135,7 +450,7 @@ pub fn write_ignore_patterns(
             .truncate(true)
             .open(file_path)
     }
-    .with_context(|| format!("Failed to write to: {}", file_path.display()))?;
+    .map_err(|e| Error::io("write to", file_path, e))?;
 
     // Handle newline for append mode
     if append && file.metadata().map(|m| m.len()).unwrap_or(0) > 0 {
@@ -153,27 +468,39 @@ pub fn write_ignore_patterns(
         .flush()
         .with_context(|| format!("Failed to flush writes to: {}", file_path.display()))?;
 
+    info!(path = %file_path.display(), "patterns written");
     Ok(())
 }
 
-/// Add patterns to an ignore file, optionally avoiding duplicates
-pub fn add_patterns_to_ignore_file(
+/// Compute which patterns `add_patterns_to_ignore_file` would write, without
+/// touching the file.
+///
+/// Patterns are sanitized, empty ones dropped, and (when `avoid_duplicates`
+/// is set) anything already present in the file or earlier in the batch is
+/// skipped.
+pub fn plan_patterns_to_add(
     file_path: &Path,
     new_patterns: &[String],
     avoid_duplicates: bool,
-    _validation_level: PatternValidationLevel,
 ) -> anyhow::Result<Vec<String>> {
-    if new_patterns.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    // Skip validation - patterns should be pre-validated by caller
-    // The validation_level parameter is kept for API compatibility
+    let existing = if avoid_duplicates {
+        read_ignore_patterns(file_path)?
+    } else {
+        HashSet::new()
+    };
+    Ok(plan_against(&existing, new_patterns, avoid_duplicates))
+}
 
+/// Plan additions against an already-read set of existing patterns
+fn plan_against(
+    existing: &HashSet<String>,
+    new_patterns: &[String],
+    avoid_duplicates: bool,
+) -> Vec<String> {
     let mut seen: HashSet<String> = if avoid_duplicates {
-        read_ignore_patterns(file_path)?
-            .into_iter()
-            .map(|p| normalize_pattern_for_dedup(&p).to_string())
+        existing
+            .iter()
+            .map(|p| normalize_pattern_for_dedup(p).to_string())
             .collect()
     } else {
         HashSet::new()
@@ -194,15 +521,1445 @@ pub fn add_patterns_to_ignore_file(
         patterns_to_add.push(sanitized);
     }
 
-    if !patterns_to_add.is_empty() {
-        write_ignore_patterns(file_path, &patterns_to_add, true)?;
+    patterns_to_add
+}
+
+/// Patterns in ignore file content, skipping blank lines and comments
+fn parse_ignore_patterns(content: &str) -> HashSet<String> {
+    parse_ignore_entries(content)
+        .into_iter()
+        .map(|entry| entry.pattern)
+        .collect()
+}
+
+/// Where newly added patterns are placed in an ignore file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Placement {
+    /// Append new patterns to the end of the file
+    #[default]
+    Append,
+    /// Insert each new pattern at its position, in the given order, within
+    /// the last section of the file
+    Sorted(SortStrategy),
+    /// Insert each new pattern after the existing entries it most
+    /// resembles (see [`crate::affinity`]), appending it when nothing is
+    /// similar
+    Affinity,
+    /// Append new patterns as one block under a shared comment (see
+    /// [`group_marker`]). Sorted insertion never adds to a group.
+    Group(String),
+}
+
+/// Replace the whole content of an ignore file
+pub fn write_ignore_file(file_path: &Path, content: &str) -> anyhow::Result<()> {
+    let _span = debug_span!("write_ignore_file", path = %file_path.display()).entered();
+    let _lock = lock::lock(file_path)?;
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
     }
+    validate_file_path(file_path, None)?;
+    if let Some(old) = read_optional(file_path)?.filter(|old| old != content) {
+        backup::before_write(file_path, &old)?;
+    }
+
+    std::fs::write(file_path, content).map_err(|e| Error::io("write to", file_path, e))?;
+
+    info!(path = %file_path.display(), "ignore file rewritten");
+    Ok(())
+}
+
+/// Split file content into lines, remembering the line ending to write back
+fn split_lines(content: &str) -> (Vec<String>, &'static str) {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    (content.lines().map(str::to_string).collect(), newline)
+}
+
+/// Insert `pattern` at its sorted position within the last section (block
+/// of consecutive non-blank lines) of `lines`.
+///
+/// Only the run of plain patterns after the section's last comment or
+/// negation is considered sortable: moving a pattern in front of a negation
+/// could change which paths the negation re-includes. New negations are
+/// likewise kept after every plain pattern in the section. A group is
+/// never added to; the pattern starts a new section after it instead.
+fn insert_sorted(lines: &mut Vec<String>, pattern: &str, strategy: SortStrategy) {
+    let section_start = lines
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map_or(0, |blank| blank + 1);
+    if section_start == lines.len() {
+        lines.push(pattern.to_string());
+        return;
+    }
+    if group_marker_heading(&lines[section_start]).is_some() {
+        lines.push(String::new());
+        lines.push(pattern.to_string());
+        return;
+    }
+
+    let negated = pattern.starts_with('!');
+    let region_start = lines[section_start..]
+        .iter()
+        .rposition(|line| {
+            ignore_file::is_comment(line) || (line.trim_start().starts_with('!') != negated)
+        })
+        .map_or(section_start, |barrier| section_start + barrier + 1);
+
+    let position = lines[region_start..]
+        .iter()
+        .position(|line| strategy.compare(line.trim(), pattern) == Ordering::Greater)
+        .map_or(lines.len(), |offset| region_start + offset);
+    lines.insert(position, pattern.to_string());
+}
+
+/// Comment heading a one-off group of patterns written with
+/// [`Placement::Group`]
+pub fn group_marker(heading: &str) -> String {
+    format!("# git-ignore group: {}", sanitize_pattern(heading))
+}
+
+/// Heading of the group a comment line opens, if it is a group marker
+pub(crate) fn group_marker_heading(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("# git-ignore group:")
+        .map(str::trim)
+}
+
+/// First line of a block of patterns managed as a named template
+pub fn template_begin_marker(name: &str) -> String {
+    format!("# BEGIN git-ignore template: {name}")
+}
+
+/// Last line of a block of patterns managed as a named template
+pub fn template_end_marker(name: &str) -> String {
+    format!("# END git-ignore template: {name}")
+}
+
+/// Name of the template a marker line opens or closes, if it is one
+pub(crate) fn template_marker_name<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.trim()
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix(" git-ignore template: "))
+        .map(str::trim)
+}
+
+/// The patterns inside every block for template `name` (matched
+/// case-insensitively), without markers, comments or blank lines
+pub fn template_block_patterns(file_path: &Path, name: &str) -> anyhow::Result<Vec<String>> {
+    let content = read_optional(file_path)?.unwrap_or_default();
+    let mut patterns = Vec::new();
+    let mut inside = false;
+    for line in content.lines() {
+        let matches_name = |prefix: &str| {
+            template_marker_name(line, prefix).is_some_and(|found| found.eq_ignore_ascii_case(name))
+        };
+        if matches_name("# BEGIN") {
+            inside = true;
+        } else if matches_name("# END") {
+            inside = false;
+        } else if let Some(pattern) = ignore_file::line_pattern(line).filter(|_| inside) {
+            patterns.push(pattern.to_string());
+        }
+    }
+    Ok(patterns)
+}
+
+/// Remove every block for template `name` (matched case-insensitively),
+/// including its marker lines and the blank line separating it from the
+/// surrounding content.
+///
+/// Returns `Ok(None)` when the template isn't present.
+fn strip_template_blocks(lines: &[String], name: &str) -> anyhow::Result<Option<Vec<String>>> {
+    let mut result: Vec<String> = Vec::with_capacity(lines.len());
+    let mut removed = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let is_begin = template_marker_name(&lines[i], "# BEGIN")
+            .is_some_and(|found| found.eq_ignore_ascii_case(name));
+        if !is_begin {
+            result.push(lines[i].clone());
+            i += 1;
+            continue;
+        }
+
+        let end = lines[i + 1..]
+            .iter()
+            .position(|line| {
+                template_marker_name(line, "# END")
+                    .is_some_and(|found| found.eq_ignore_ascii_case(name))
+            })
+            .map(|offset| i + 1 + offset)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Template block '{name}' starting on line {} has no end marker",
+                    i + 1
+                )
+            })?;
+        removed = true;
+
+        // Drop the padding after the block, or before it at the end of file,
+        // so exactly one separator survives between the remaining content.
+        let mut next = end + 1;
+        while next < lines.len() && lines[next].trim().is_empty() {
+            next += 1;
+        }
+        if next == lines.len() {
+            while result.last().is_some_and(|line| line.trim().is_empty()) {
+                result.pop();
+            }
+        }
+        i = next;
+    }
+
+    Ok(removed.then_some(result))
+}
+
+/// Patterns of an ignore file outside any block for template `name`, the
+/// ones re-applying the template must not repeat
+pub fn patterns_outside_template(file_path: &Path, name: &str) -> anyhow::Result<HashSet<String>> {
+    let content = read_optional(file_path)?.unwrap_or_default();
+    let (lines, newline) = split_lines(&content);
+    let outside = strip_template_blocks(&lines, name)?.unwrap_or(lines);
+    Ok(parse_ignore_patterns(&outside.join(newline)))
+}
+
+/// Remove an applied template block (markers, content and padding) from an
+/// ignore file, leaving everything else untouched.
+///
+/// Returns `false` if the file contains no block for the template.
+pub fn remove_template_from_ignore_file(file_path: &Path, name: &str) -> anyhow::Result<bool> {
+    remove_template_in(&mut FsStore, file_path, name)
+}
+
+/// [`remove_template_from_ignore_file`] on a file in `store`
+pub fn remove_template_in(
+    store: &mut dyn IgnoreStore,
+    file_path: &Path,
+    name: &str,
+) -> anyhow::Result<bool> {
+    let _lock = store.lock(file_path)?;
+    let Some(content) = store.read(file_path)? else {
+        return Ok(false);
+    };
+    let (lines, newline) = split_lines(&content);
+
+    let Some(remaining) = strip_template_blocks(&lines, name)? else {
+        return Ok(false);
+    };
+
+    let mut updated = remaining.join(newline);
+    if !remaining.is_empty() {
+        updated.push_str(newline);
+    }
+    store.write(file_path, &updated)?;
+    Ok(true)
+}
+
+/// Add `patterns` to an ignore file as the template block `name`,
+/// replacing any existing block of that name in place so re-applying a
+/// template updates it. The file is created if missing.
+///
+/// Returns `false` if the file already held exactly this block.
+pub fn add_template_to_ignore_file(
+    file_path: &Path,
+    name: &str,
+    patterns: &[String],
+) -> anyhow::Result<bool> {
+    add_template_in(&mut FsStore, file_path, name, patterns)
+}
+
+/// [`add_template_to_ignore_file`] on a file in `store`
+pub fn add_template_in(
+    store: &mut dyn IgnoreStore,
+    file_path: &Path,
+    name: &str,
+    patterns: &[String],
+) -> anyhow::Result<bool> {
+    let _lock = store.lock(file_path)?;
+    let content = store.read(file_path)?.unwrap_or_default();
+    ensure_no_conflict_markers(file_path, &content)?;
+    let (lines, newline) = split_lines(&content);
+
+    let mut block = vec![template_begin_marker(name)];
+    block.extend(patterns.iter().map(|pattern| sanitize_pattern(pattern)));
+    block.push(template_end_marker(name));
+
+    let begin = lines.iter().position(|line| {
+        template_marker_name(line, "# BEGIN").is_some_and(|found| found.eq_ignore_ascii_case(name))
+    });
+    let updated_lines = match begin {
+        Some(begin) => {
+            let end = lines[begin..]
+                .iter()
+                .position(|line| {
+                    template_marker_name(line, "# END")
+                        .is_some_and(|found| found.eq_ignore_ascii_case(name))
+                })
+                .map(|offset| begin + offset)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Template block '{name}' starting on line {} has no end marker",
+                        begin + 1
+                    )
+                })?;
+            if lines[begin..=end] == block[..] {
+                return Ok(false);
+            }
+            let mut updated = lines[..begin].to_vec();
+            updated.extend(block);
+            updated.extend_from_slice(&lines[end + 1..]);
+            updated
+        }
+        None => {
+            let mut updated = lines.clone();
+            while updated.last().is_some_and(|line| line.trim().is_empty()) {
+                updated.pop();
+            }
+            if !updated.is_empty() {
+                updated.push(String::new());
+            }
+            updated.extend(block);
+            updated
+        }
+    };
+
+    let mut updated = updated_lines.join(newline);
+    updated.push_str(newline);
+    store.write(file_path, &updated)?;
+    Ok(true)
+}
+
+/// A pattern line removed from an ignore file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RemovedPattern {
+    /// The line as it appeared in the file, trimmed
+    pub pattern: String,
+    /// 1-based line number the pattern occupied before removal
+    pub line_number: usize,
+}
+
+/// Sort an ignore file with [`sort::sort_content`](crate::sort::sort_content).
+/// Returns whether the file changed; a missing file is left alone.
+pub fn sort_ignore_file(
+    file_path: &Path,
+    strategy: SortStrategy,
+    section_order: &[String],
+) -> anyhow::Result<bool> {
+    let _span = debug_span!("sort_ignore_file", path = %file_path.display()).entered();
+    rewrite_ignore_file(file_path, "sorting", |content| {
+        sort::sort_content(content, strategy, section_order)
+    })
+}
+
+/// Whether an ignore file is already in the order [`sort_ignore_file`]
+/// would give it
+pub fn is_sorted(
+    file_path: &Path,
+    strategy: SortStrategy,
+    section_order: &[String],
+) -> anyhow::Result<bool> {
+    let content = read_optional(file_path)?.unwrap_or_default();
+    Ok(sort::sort_content(&content, strategy, section_order) == content)
+}
+
+/// Tidy an ignore file with [`format::format_content`](crate::format::format_content).
+/// Returns whether the file changed; a missing file is left alone.
+pub fn format_ignore_file(
+    file_path: &Path,
+    strategy: SortStrategy,
+    section_order: &[String],
+) -> anyhow::Result<bool> {
+    let _span = debug_span!("format_ignore_file", path = %file_path.display()).entered();
+    rewrite_ignore_file(file_path, "formatting", |content| {
+        format::format_content(content, strategy, section_order)
+    })
+}
+
+/// Whether an ignore file is already formatted the way
+/// [`format_ignore_file`] would format it
+pub fn is_formatted(
+    file_path: &Path,
+    strategy: SortStrategy,
+    section_order: &[String],
+) -> anyhow::Result<bool> {
+    let content = read_optional(file_path)?.unwrap_or_default();
+    Ok(format::format_content(&content, strategy, section_order) == content)
+}
+
+/// Replace an existing ignore file's content with `rewrite(content)`,
+/// failing if something else changes the file meanwhile. Returns whether
+/// the content changed.
+fn rewrite_ignore_file(
+    file_path: &Path,
+    action: &str,
+    rewrite: impl FnOnce(&str) -> String,
+) -> anyhow::Result<bool> {
+    let _lock = lock::lock(file_path)?;
+    let Some(content) = read_optional(file_path)? else {
+        return Ok(false);
+    };
+    ensure_no_conflict_markers(file_path, &content)?;
+    let rewritten = rewrite(&content);
+    if rewritten == content {
+        return Ok(false);
+    }
+    match replace_file_if_unchanged(file_path, Some(&content), &rewritten)? {
+        WriteOutcome::Written => Ok(true),
+        WriteOutcome::Changed(_) => bail!(
+            "Concurrent edit: {} changed while {action}; try again",
+            file_path.display()
+        ),
+    }
+}
+
+/// The patterns among `candidates` that are in the `protected` list, which
+/// cleanup must not delete without being forced. Patterns compare the way
+/// duplicates do, so protecting `.env` also protects `.env/`.
+pub fn find_protected(candidates: &[String], protected: &[String]) -> Vec<String> {
+    let protected: HashSet<String> = protected
+        .iter()
+        .map(|pattern| normalize_pattern_for_dedup(&sanitize_pattern(pattern)).to_string())
+        .collect();
+    let mut found: Vec<String> = Vec::new();
+    for candidate in candidates {
+        let candidate = sanitize_pattern(candidate);
+        if protected.contains(normalize_pattern_for_dedup(&candidate).as_ref())
+            && !found.contains(&candidate)
+        {
+            found.push(candidate);
+        }
+    }
+    found
+}
+
+/// Outcome of removing patterns from an ignore file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RemovalReport {
+    /// The file patterns were removed from
+    pub file: PathBuf,
+    /// Every removed line, in file order (duplicates included)
+    pub removed: Vec<RemovedPattern>,
+    /// Requested patterns that matched no line
+    pub not_found: Vec<String>,
+}
+
+/// Remove patterns from an ignore file.
+///
+/// Patterns match the same way duplicates are detected when adding, so
+/// removing `build/` also removes a `build` line. Every matching line is
+/// removed; comments and blank lines are left in place. The write uses the
+/// same concurrent-edit detection as [`add_patterns_with_placement`].
+pub fn remove_patterns_from_ignore_file(
+    file_path: &Path,
+    patterns: &[String],
+) -> anyhow::Result<RemovalReport> {
+    remove_patterns_observed(file_path, patterns, &mut ())
+}
+
+/// [`remove_patterns_from_ignore_file`], reporting removed lines, patterns
+/// that matched nothing and the commit of the file to `observer`
+pub fn remove_patterns_observed(
+    file_path: &Path,
+    patterns: &[String],
+    observer: &mut dyn Observer,
+) -> anyhow::Result<RemovalReport> {
+    remove_patterns_in(&mut FsStore, file_path, patterns, observer)
+}
+
+/// [`remove_patterns_observed`] on a file in `store`
+pub fn remove_patterns_in(
+    store: &mut dyn IgnoreStore,
+    file_path: &Path,
+    patterns: &[String],
+    observer: &mut dyn Observer,
+) -> anyhow::Result<RemovalReport> {
+    let report = remove_matching_lines(store, file_path, patterns)?;
+    for removed in &report.removed {
+        observer.line_removed(file_path, &removed.pattern, removed.line_number);
+    }
+    for pattern in &report.not_found {
+        observer.pattern_skipped(pattern, SkipReason::NotFound);
+    }
+    if !report.removed.is_empty() {
+        observer.file_committed(file_path);
+    }
+    Ok(report)
+}
+
+/// Compute the content of `file_path` after removing `patterns` from
+/// `content`, matching lines the way [`remove_patterns_from_ignore_file`]
+/// does
+pub fn remove_patterns_from_content(
+    file_path: &Path,
+    content: &str,
+    patterns: &[String],
+) -> (String, RemovalReport) {
+    let wanted: Vec<String> = patterns
+        .iter()
+        .map(|p| sanitize_pattern(p))
+        .filter(|p| !p.is_empty())
+        .collect();
+    let targets: HashSet<Cow<'_, str>> = wanted
+        .iter()
+        .map(|p| normalize_pattern_for_dedup(p))
+        .collect();
+
+    let mut file = IgnoreFile::parse(content);
+    let mut matched: HashSet<String> = HashSet::new();
+    let removed = file
+        .remove_where(|entry| {
+            let key = normalize_pattern_for_dedup(entry.text().trim());
+            if entry.kind() == EntryKind::Pattern && targets.contains(&key) {
+                matched.insert(key.to_string());
+                false
+            } else {
+                true
+            }
+        })
+        .into_iter()
+        .map(|entry| RemovedPattern {
+            pattern: entry.text().trim().to_string(),
+            line_number: entry.line_number().unwrap_or_default(),
+        })
+        .collect();
+
+    let not_found = wanted
+        .iter()
+        .filter(|p| !matched.contains(normalize_pattern_for_dedup(p).as_ref()))
+        .cloned()
+        .collect();
+    let report = RemovalReport {
+        file: file_path.to_path_buf(),
+        removed,
+        not_found,
+    };
+    (file.render(), report)
+}
+
+fn remove_matching_lines(
+    store: &mut dyn IgnoreStore,
+    file_path: &Path,
+    patterns: &[String],
+) -> anyhow::Result<RemovalReport> {
+    let _span = debug_span!("remove_patterns", path = %file_path.display(), count = patterns.len())
+        .entered();
+    let _lock = store.lock(file_path)?;
+    let mut base = store.read(file_path)?;
+
+    for attempt in 1..=MAX_MERGE_ATTEMPTS {
+        let Some(content) = base.as_deref() else {
+            return Ok(RemovalReport {
+                file: file_path.to_path_buf(),
+                removed: Vec::new(),
+                not_found: patterns.iter().map(|p| sanitize_pattern(p)).collect(),
+            });
+        };
+        ensure_no_conflict_markers(file_path, content)?;
+
+        let (updated, report) = remove_patterns_from_content(file_path, content, patterns);
+        if report.removed.is_empty() {
+            return Ok(report);
+        }
 
-    Ok(patterns_to_add)
+        match store.write_if_unchanged(file_path, base.as_deref(), &updated)? {
+            WriteOutcome::Written => {
+                info!(path = %file_path.display(), count = report.removed.len(), "patterns removed");
+                return Ok(report);
+            }
+            WriteOutcome::Changed(current) => {
+                warn!(path = %file_path.display(), attempt, "ignore file changed during update, re-merging");
+                base = current;
+            }
+        }
+    }
+
+    bail!(
+        "Concurrent edit: {} kept changing while removing patterns; try again",
+        file_path.display()
+    )
+}
+
+/// Outcome of [`move_patterns`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveReport {
+    /// Lines taken out of the source file, in file order
+    pub removed: Vec<RemovedPattern>,
+    /// Patterns written to the destination; a pattern it already had is
+    /// only removed from the source
+    pub added: Vec<String>,
+}
+
+/// Move `patterns` from one ignore file to another as a single change:
+/// either both files are updated or neither is. Every pattern must be in
+/// `from`; lines match as in [`remove_patterns_from_ignore_file`].
+pub fn move_patterns(
+    from: &Path,
+    to: &Path,
+    patterns: &[String],
+    placement: Placement,
+) -> anyhow::Result<MoveReport> {
+    let _span = debug_span!("move_patterns", from = %from.display(), to = %to.display()).entered();
+    if from == to {
+        bail!(Error::config("cannot move patterns within the same file"));
+    }
+    let mut transaction = Transaction::new();
+    let report = transaction.remove_patterns(from, patterns)?;
+    if !report.not_found.is_empty() {
+        bail!(
+            "Pattern not found in {}: {}",
+            from.display(),
+            report.not_found.join(", ")
+        );
+    }
+    if report.removed.is_empty() {
+        bail!("No patterns to move");
+    }
+    let mut moved: Vec<String> = Vec::new();
+    for removed in &report.removed {
+        if !moved.contains(&removed.pattern) {
+            moved.push(removed.pattern.clone());
+        }
+    }
+    let added = transaction.add_patterns(to, &moved, true, placement)?;
+    transaction.commit()?;
+    info!(from = %from.display(), to = %to.display(), count = moved.len(), "patterns moved");
+    Ok(MoveReport {
+        removed: report.removed,
+        added,
+    })
+}
+
+const CONFLICT_START: &str = "<<<<<<<";
+const CONFLICT_BASE: &str = "|||||||";
+const CONFLICT_SEPARATOR: &str = "=======";
+const CONFLICT_END: &str = ">>>>>>>";
+
+/// Whether `line` is the merge conflict marker `marker`, optionally followed
+/// by a label (`<<<<<<< HEAD`)
+fn is_conflict_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Line numbers (1-based) of merge conflict markers in ignore file content
+pub fn find_conflict_markers(content: &str) -> Vec<usize> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim_end_matches('\r');
+            [CONFLICT_START, CONFLICT_BASE, CONFLICT_END]
+                .iter()
+                .any(|marker| is_conflict_marker(line, marker))
+                || line == CONFLICT_SEPARATOR
+        })
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Fail if content still contains merge conflict markers, so patterns are
+/// never appended below an unresolved conflict
+pub(crate) fn ensure_no_conflict_markers(file_path: &Path, content: &str) -> anyhow::Result<()> {
+    if let Some(line) = find_conflict_markers(content).first() {
+        bail!(
+            "Merge conflict markers in {} (line {line}); resolve the conflict before adding patterns",
+            file_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Resolve every conflict block in `content` by keeping both sides: our
+/// lines, followed by their lines that we don't already have. The common
+/// ancestor section of diff3-style conflicts is dropped.
+pub fn resolve_conflicts_keep_both(content: &str) -> anyhow::Result<String> {
+    enum State {
+        Outside,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let (lines, newline) = split_lines(content);
+    let mut resolved: Vec<String> = Vec::new();
+    let mut ours: Vec<String> = Vec::new();
+    let mut theirs: Vec<String> = Vec::new();
+    let mut state = State::Outside;
+
+    for (index, line) in lines.into_iter().enumerate() {
+        state =
+            match state {
+                State::Outside if is_conflict_marker(&line, CONFLICT_START) => State::Ours,
+                State::Outside => {
+                    resolved.push(line);
+                    State::Outside
+                }
+                State::Ours if is_conflict_marker(&line, CONFLICT_BASE) => State::Base,
+                State::Ours | State::Base if line == CONFLICT_SEPARATOR => State::Theirs,
+                State::Ours => {
+                    ours.push(line);
+                    State::Ours
+                }
+                State::Base => State::Base,
+                State::Theirs if is_conflict_marker(&line, CONFLICT_END) => {
+                    let known: HashSet<String> = ours
+                        .iter()
+                        .map(|l| normalize_pattern_for_dedup(l.trim()).to_string())
+                        .collect();
+                    resolved.append(&mut ours);
+                    resolved.extend(theirs.drain(..).filter(|l| {
+                        !known.contains(normalize_pattern_for_dedup(l.trim()).as_ref())
+                    }));
+                    State::Outside
+                }
+                State::Theirs if is_conflict_marker(&line, CONFLICT_START) => {
+                    bail!("Nested conflict marker at line {}", index + 1)
+                }
+                State::Theirs => {
+                    theirs.push(line);
+                    State::Theirs
+                }
+            };
+    }
+
+    if !matches!(state, State::Outside) {
+        bail!("Unterminated merge conflict (missing '{CONFLICT_END}' marker)");
+    }
+
+    let mut updated = resolved.join(newline);
+    if !updated.is_empty() {
+        updated.push_str(newline);
+    }
+    Ok(updated)
+}
+
+/// Resolve merge conflicts in an ignore file with
+/// [`resolve_conflicts_keep_both`]. Returns `false` if there was nothing to
+/// resolve.
+pub fn resolve_conflicts_in_ignore_file(file_path: &Path) -> anyhow::Result<bool> {
+    let _lock = lock::lock(file_path)?;
+    let Some(content) = read_optional(file_path)? else {
+        return Ok(false);
+    };
+    if find_conflict_markers(&content).is_empty() {
+        return Ok(false);
+    }
+    let resolved = resolve_conflicts_keep_both(&content)
+        .with_context(|| format!("Failed to resolve conflicts in: {}", file_path.display()))?;
+    write_ignore_file(file_path, &resolved)?;
+    Ok(true)
+}
+
+/// How many times an addition is re-merged when the file keeps changing
+/// between reading and writing it
+const MAX_MERGE_ATTEMPTS: usize = 5;
+
+/// Add patterns to an ignore file, placing them according to `placement`.
+///
+/// The new content is computed from a snapshot of the file and written
+/// atomically. If the file changed on disk in the meantime (an editor save,
+/// another `git ignore`), the addition is re-planned against the new
+/// content rather than applied to the stale view. Only a true conflict, such
+/// as the other edit adding the negation of a pattern being added, is an
+/// error.
+///
+/// Returns the patterns that were actually added, in the order given.
+pub fn add_patterns_with_placement(
+    file_path: &Path,
+    new_patterns: &[String],
+    avoid_duplicates: bool,
+    placement: Placement,
+) -> anyhow::Result<Vec<String>> {
+    add_patterns_observed(
+        file_path,
+        new_patterns,
+        avoid_duplicates,
+        placement,
+        &mut (),
+    )
+}
+
+/// [`add_patterns_with_placement`], reporting skipped patterns, written
+/// lines and the commit of the file to `observer`
+pub fn add_patterns_observed(
+    file_path: &Path,
+    new_patterns: &[String],
+    avoid_duplicates: bool,
+    placement: Placement,
+    observer: &mut dyn Observer,
+) -> anyhow::Result<Vec<String>> {
+    add_patterns_in(
+        &mut FsStore,
+        file_path,
+        new_patterns,
+        avoid_duplicates,
+        placement,
+        observer,
+    )
+}
+
+/// [`add_patterns_observed`] on a file in `store`
+pub fn add_patterns_in(
+    store: &mut dyn IgnoreStore,
+    file_path: &Path,
+    new_patterns: &[String],
+    avoid_duplicates: bool,
+    placement: Placement,
+    observer: &mut dyn Observer,
+) -> anyhow::Result<Vec<String>> {
+    if new_patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let _span =
+        debug_span!("add_patterns", path = %file_path.display(), count = new_patterns.len())
+            .entered();
+    let _lock = store.lock(file_path)?;
+    let mut base = store.read(file_path)?;
+    let mut planned: Vec<String> = Vec::new();
+
+    for attempt in 1..=MAX_MERGE_ATTEMPTS {
+        let content = base.as_deref().unwrap_or("");
+        ensure_no_conflict_markers(file_path, content)?;
+        let existing = parse_ignore_patterns(content);
+        planned = plan_against(&existing, new_patterns, avoid_duplicates);
+        if planned.is_empty() {
+            report_skipped(new_patterns, &planned, observer);
+            return Ok(planned);
+        }
+
+        let updated = apply_placement(content, &planned, &placement);
+        match store.write_if_unchanged(file_path, base.as_deref(), &updated)? {
+            WriteOutcome::Written => {
+                info!(path = %file_path.display(), "patterns written");
+                report_skipped(new_patterns, &planned, observer);
+                for pattern in &planned {
+                    observer.line_written(file_path, pattern);
+                }
+                observer.file_committed(file_path);
+                return Ok(planned);
+            }
+            WriteOutcome::Changed(current) => {
+                warn!(path = %file_path.display(), attempt, "ignore file changed during update, re-merging");
+                check_concurrent_conflicts(
+                    file_path,
+                    &existing,
+                    current.as_deref().unwrap_or(""),
+                    &planned,
+                )?;
+                base = current;
+            }
+        }
+    }
+
+    bail!(
+        "Concurrent edit: {} kept changing while adding {}; try again",
+        file_path.display(),
+        planned.join(", ")
+    )
+}
+
+/// Tell `observer` which of `requested` did not make it into `planned`.
+/// `planned` holds the sanitized survivors in request order, so the first
+/// occurrence of each is the one that was kept.
+fn report_skipped(requested: &[String], planned: &[String], observer: &mut dyn Observer) {
+    let mut next = planned.iter().peekable();
+    for pattern in requested {
+        let sanitized = sanitize_pattern(pattern);
+        if sanitized.is_empty() {
+            observer.pattern_skipped(pattern, SkipReason::Empty);
+        } else if next.peek() == Some(&&sanitized) {
+            next.next();
+        } else {
+            observer.pattern_skipped(&sanitized, SkipReason::Duplicate);
+        }
+    }
+}
+
+/// Add patterns to ignore file content held in memory, as
+/// [`add_patterns_with_placement`] does for a file. Returns the new content
+/// and the patterns actually added. `label` names the content in errors.
+pub fn add_patterns_to_content(
+    label: &str,
+    content: &str,
+    new_patterns: &[String],
+    avoid_duplicates: bool,
+    placement: Placement,
+) -> anyhow::Result<(String, Vec<String>)> {
+    ensure_no_conflict_markers(Path::new(label), content)?;
+    let planned = plan_against(
+        &parse_ignore_patterns(content),
+        new_patterns,
+        avoid_duplicates,
+    );
+    if planned.is_empty() {
+        return Ok((content.to_string(), planned));
+    }
+    Ok((apply_placement(content, &planned, &placement), planned))
+}
+
+/// What adding patterns to an ignore file would do, worked out without
+/// writing anything
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddPreview {
+    /// The file that would change
+    pub file: PathBuf,
+    /// Patterns that would be added, sanitized, in the order given
+    pub added: Vec<String>,
+    /// Requested patterns that would be left out, and why
+    pub skipped: Vec<(String, SkipReason)>,
+    /// Whether the file doesn't exist yet and would be created
+    pub creates_file: bool,
+    /// The file's current content, empty if it doesn't exist
+    pub old_content: String,
+    /// The content the file would have afterwards
+    pub new_content: String,
+}
+
+impl AddPreview {
+    /// Whether the file would change at all
+    pub fn changes_file(&self) -> bool {
+        !self.added.is_empty()
+    }
+
+    /// The change as a unified diff (`/dev/null` as the old side of a new
+    /// file); empty if the file wouldn't change
+    pub fn diff(&self) -> String {
+        let label = self.file.display().to_string();
+        let old_label = if self.creates_file {
+            "/dev/null"
+        } else {
+            label.as_str()
+        };
+        unified_diff(old_label, &label, &self.old_content, &self.new_content)
+    }
+
+    /// Write the previewed content, returning the patterns added. Fails
+    /// without writing if the file changed since the preview was made, so
+    /// what gets written is exactly what was shown.
+    pub fn apply(&self) -> anyhow::Result<Vec<String>> {
+        if !self.changes_file() {
+            return Ok(Vec::new());
+        }
+        let expected = (!self.creates_file).then_some(self.old_content.as_str());
+        write_ignore_file_if_unchanged(&self.file, expected, &self.new_content)?;
+        Ok(self.added.clone())
+    }
+}
+
+/// Work out what [`add_patterns_with_placement`] would do to `file_path`
+/// with the same arguments, without touching the file
+pub fn preview_add_patterns(
+    file_path: &Path,
+    new_patterns: &[String],
+    avoid_duplicates: bool,
+    placement: Placement,
+) -> anyhow::Result<AddPreview> {
+    let current = read_optional(file_path)?;
+    let old_content = current.clone().unwrap_or_default();
+    let (new_content, added) = add_patterns_to_content(
+        &file_path.to_string_lossy(),
+        &old_content,
+        new_patterns,
+        avoid_duplicates,
+        placement,
+    )?;
+
+    let mut skipped = SkippedPatterns::default();
+    report_skipped(new_patterns, &added, &mut skipped);
+    Ok(AddPreview {
+        file: file_path.to_path_buf(),
+        creates_file: current.is_none() && !added.is_empty(),
+        added,
+        skipped: skipped.0,
+        old_content,
+        new_content,
+    })
+}
+
+/// Collects [`Observer::pattern_skipped`] events
+#[derive(Default)]
+struct SkippedPatterns(Vec<(String, SkipReason)>);
+
+impl Observer for SkippedPatterns {
+    fn pattern_skipped(&mut self, pattern: &str, reason: SkipReason) {
+        self.0.push((pattern.to_string(), reason));
+    }
+}
+
+/// Read a file's content, or `None` if it does not exist
+pub(crate) fn read_optional(file_path: &Path) -> anyhow::Result<Option<String>> {
+    match std::fs::read_to_string(file_path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::io("read", file_path, e).into()),
+    }
+}
+
+/// Compute the file content after adding `patterns` to `content`
+fn apply_placement(content: &str, patterns: &[String], placement: &Placement) -> String {
+    match placement {
+        Placement::Append => {
+            let mut updated = content.to_string();
+            if !updated.is_empty() {
+                updated.push('\n');
+            }
+            for pattern in patterns {
+                updated.push_str(pattern);
+                updated.push('\n');
+            }
+            updated
+        }
+        Placement::Affinity => {
+            let (mut lines, newline) = split_lines(content);
+            let mut unplaced = Vec::new();
+            for pattern in patterns {
+                match affinity::insertion_point(&lines, pattern) {
+                    Some(index) => lines.insert(index + 1, pattern.clone()),
+                    None => unplaced.push(pattern.clone()),
+                }
+            }
+            let mut updated = lines.join(newline);
+            if !lines.is_empty() {
+                updated.push_str(newline);
+            }
+            if unplaced.is_empty() {
+                updated
+            } else {
+                apply_placement(&updated, &unplaced, &Placement::Append)
+            }
+        }
+        Placement::Sorted(strategy) => {
+            let (mut lines, newline) = split_lines(content);
+            for pattern in patterns {
+                insert_sorted(&mut lines, pattern, *strategy);
+            }
+            let mut updated = lines.join(newline);
+            updated.push_str(newline);
+            updated
+        }
+        Placement::Group(heading) => {
+            let (mut lines, newline) = split_lines(content);
+            while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                lines.pop();
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(group_marker(heading));
+            lines.extend(patterns.iter().cloned());
+            let mut updated = lines.join(newline);
+            updated.push_str(newline);
+            updated
+        }
+    }
+}
+
+/// Atomically replace `file_path` with `content`, failing if the file no
+/// longer holds `expected` (`None` meaning it should not exist yet) because
+/// something else changed it in the meantime
+pub fn write_ignore_file_if_unchanged(
+    file_path: &Path,
+    expected: Option<&str>,
+    content: &str,
+) -> anyhow::Result<()> {
+    match FsStore.write_if_unchanged(file_path, expected, content)? {
+        WriteOutcome::Written => {
+            info!(path = %file_path.display(), "ignore file rewritten");
+            Ok(())
+        }
+        WriteOutcome::Changed(_) => bail!(store::concurrent_edit(file_path)),
+    }
+}
+
+/// Atomically replace `file_path` with `content`, unless its current content
+/// differs from `expected` (`None` meaning the file should not exist yet).
+///
+/// The new content is written to a temporary file (see [`write_temp_file`])
+/// and renamed over the target, so readers never see a half-written file.
+pub(crate) fn replace_file_if_unchanged(
+    file_path: &Path,
+    expected: Option<&str>,
+    content: &str,
+) -> anyhow::Result<WriteOutcome> {
+    let _lock = lock::lock(file_path)?;
+    let target = write_target(file_path)?;
+    let temp_path = write_temp_file(file_path, &target, content)?;
+
+    let result = (|| {
+        let current = read_optional(&target)?;
+        if current.as_deref() != expected {
+            return Ok(WriteOutcome::Changed(current));
+        }
+        if let Some(old) = expected.filter(|old| *old != content) {
+            backup::before_write(file_path, old)?;
+        }
+
+        std::fs::rename(&temp_path, &target).map_err(|e| Error::io("write to", target, e))?;
+        Ok(WriteOutcome::Written)
+    })();
+
+    if !matches!(result, Ok(WriteOutcome::Written)) {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// The file a write to `file_path` replaces, creating its directory first.
+/// Writes go through a symlink rather than replacing it.
+fn write_target(file_path: &Path) -> anyhow::Result<PathBuf> {
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
+    }
+    validate_file_path(file_path, None)?;
+
+    if file_path.is_symlink() {
+        paths::canonicalize(file_path)
+            .with_context(|| format!("Invalid file path: {}", file_path.display()))
+    } else {
+        Ok(file_path.to_path_buf())
+    }
+}
+
+/// Write `content` to a temporary file with the permissions `target` has,
+/// ready to be renamed over it. The file goes to `<git dir>/git-ignore/tmp`
+/// when `target` is in a work tree on the same filesystem as its git
+/// directory (see [`temp_dir`]), and next to `target` otherwise.
+fn write_temp_file(file_path: &Path, target: &Path, content: &str) -> anyhow::Result<PathBuf> {
+    let file_name = target
+        .file_name()
+        .with_context(|| format!("Invalid file path: {}", file_path.display()))?
+        .to_string_lossy();
+    // Unique per write, so threads replacing the same file don't share one
+    static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+    let unique = format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, atomic::Ordering::Relaxed)
+    );
+    let temp_path = match temp_dir(target) {
+        Some(dir) => dir.join(format!("{file_name}-{unique}.tmp")),
+        None => target.with_file_name(format!(".{file_name}.git-ignore-{unique}.tmp")),
+    };
+
+    let result = (|| {
+        std::fs::write(&temp_path, content).map_err(|e| Error::io("write to", &temp_path, e))?;
+        if let Ok(metadata) = std::fs::metadata(target) {
+            std::fs::set_permissions(&temp_path, metadata.permissions()).with_context(|| {
+                format!("Failed to write permissions to: {}", temp_path.display())
+            })?;
+        }
+        Ok(())
+    })();
+    match result {
+        Ok(()) => Ok(temp_path),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+/// `<git dir>/git-ignore/tmp` for a `target` in a work tree, created if
+/// needed, or `None` when there is none or it is on another filesystem than
+/// `target`, which a rename can't cross
+fn temp_dir(target: &Path) -> Option<PathBuf> {
+    let parent = paths::canonicalize(target.parent()?).ok()?;
+    let (private, _) = git::private_dir(&parent)?;
+    let dir = private.join("tmp");
+    std::fs::create_dir_all(&dir).ok()?;
+    same_filesystem(&dir, &parent).then_some(dir)
+}
+
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+/// Paths on one drive or share; a mounted folder in between is missed
+#[cfg(not(unix))]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    a.components().next() == b.components().next()
+}
+
+/// Writes to several ignore files that take effect together or not at all.
+///
+/// Each change is computed against the file as it is when staged (or as an
+/// earlier staged change left it), and nothing is written until
+/// [`commit`](Transaction::commit), which hands every change to the
+/// store's [`write_all_if_unchanged`](IgnoreStore::write_all_if_unchanged).
+/// On disk ([`FsStore`], the default) that writes every new file content to
+/// a temporary file first, checks that no file changed since it was staged,
+/// then renames the temporary files into place. If a rename fails, the
+/// files already replaced get their old content back.
+///
+/// ```no_run
+/// use git_ignore_tool::ignore::{Placement, Transaction};
+/// use std::path::Path;
+///
+/// let patterns = vec!["*.log".to_string(), "target/".to_string()];
+/// let mut transaction = Transaction::new();
+/// transaction.add_patterns(Path::new(".gitignore"), &patterns, true, Placement::Append)?;
+/// transaction.add_patterns(Path::new(".dockerignore"), &patterns, true, Placement::Append)?;
+/// transaction.commit()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Transaction<S = FsStore> {
+    store: S,
+    writes: Vec<StagedWrite>,
+}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Transaction::default()
+    }
+}
+
+impl<S: IgnoreStore> Transaction<S> {
+    /// A transaction on the files of `store`; pass `&mut store` to keep
+    /// using it afterwards
+    pub fn in_store(store: S) -> Transaction<S> {
+        Transaction {
+            store,
+            writes: Vec::new(),
+        }
+    }
+
+    /// Whether no file would change
+    pub fn is_empty(&self) -> bool {
+        self.writes
+            .iter()
+            .all(|write| write.original.as_deref() == Some(write.content.as_str()))
+    }
+
+    /// The files staged so far, in the order they were first staged
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.writes.iter().map(|write| write.path.as_path())
+    }
+
+    /// Stage adding `patterns` to `file_path` as
+    /// [`add_patterns_with_placement`] would, returning the patterns that
+    /// will be written
+    pub fn add_patterns(
+        &mut self,
+        file_path: &Path,
+        patterns: &[String],
+        avoid_duplicates: bool,
+        placement: Placement,
+    ) -> anyhow::Result<Vec<String>> {
+        let content = self.staged_content(file_path)?;
+        let (updated, added) = add_patterns_to_content(
+            &file_path.to_string_lossy(),
+            &content,
+            patterns,
+            avoid_duplicates,
+            placement,
+        )?;
+        if !added.is_empty() {
+            self.write(file_path, &updated)?;
+        }
+        Ok(added)
+    }
+
+    /// Stage removing the lines matching `patterns` from `file_path`, as
+    /// [`remove_patterns_from_ignore_file`] would
+    pub fn remove_patterns(
+        &mut self,
+        file_path: &Path,
+        patterns: &[String],
+    ) -> anyhow::Result<RemovalReport> {
+        let content = self.staged_content(file_path)?;
+        ensure_no_conflict_markers(file_path, &content)?;
+        let (updated, report) = remove_patterns_from_content(file_path, &content, patterns);
+        if !report.removed.is_empty() {
+            self.write(file_path, &updated)?;
+        }
+        Ok(report)
+    }
+
+    /// Stage replacing the whole content of `file_path`
+    pub fn write(&mut self, file_path: &Path, content: &str) -> anyhow::Result<()> {
+        match self.writes.iter_mut().find(|write| write.path == file_path) {
+            Some(write) => write.content = content.to_string(),
+            None => self.writes.push(StagedWrite {
+                path: file_path.to_path_buf(),
+                original: self.store.read(file_path)?,
+                content: content.to_string(),
+            }),
+        }
+        Ok(())
+    }
+
+    /// The content `file_path` will have once the staged writes are
+    /// committed
+    pub fn staged_content(&self, file_path: &Path) -> anyhow::Result<String> {
+        match self.writes.iter().find(|write| write.path == file_path) {
+            Some(write) => Ok(write.content.clone()),
+            None => Ok(self.store.read(file_path)?.unwrap_or_default()),
+        }
+    }
+
+    /// Write every staged change, or none of them (see the type
+    /// documentation)
+    pub fn commit(self) -> anyhow::Result<()> {
+        self.commit_observed(&mut ())
+    }
+
+    /// [`commit`](Transaction::commit), reporting
+    /// [`Observer::file_committed`] and [`Observer::progress`] for each file
+    /// once all of them are in place
+    pub fn commit_observed(mut self, observer: &mut dyn Observer) -> anyhow::Result<()> {
+        let writes: Vec<StagedWrite> = self
+            .writes
+            .into_iter()
+            .filter(|write| write.original.as_deref() != Some(write.content.as_str()))
+            .collect();
+        let _span = debug_span!("commit_transaction", files = writes.len()).entered();
+        self.store.write_all_if_unchanged(&writes)?;
+        info!(files = writes.len(), "transaction committed");
+        for (index, write) in writes.iter().enumerate() {
+            observer.file_committed(&write.path);
+            observer.progress(&write.path, index + 1, writes.len());
+        }
+        Ok(())
+    }
+}
+
+/// Replace every file of `writes` on disk, or none of them: see
+/// [`Transaction`]
+pub(crate) fn replace_files_if_unchanged(writes: &[StagedWrite]) -> anyhow::Result<()> {
+    let _locks = lock::lock_all(writes.iter().map(|write| write.path.as_path()))?;
+
+    let mut prepared: Vec<(&StagedWrite, PathBuf, PathBuf)> = Vec::new();
+    let remove_temps = |prepared: &[(&StagedWrite, PathBuf, PathBuf)]| {
+        for (_, _, temp_path) in prepared {
+            let _ = std::fs::remove_file(temp_path);
+        }
+    };
+    for write in writes {
+        let staged = write_target(&write.path).and_then(|target| {
+            let temp_path = write_temp_file(&write.path, &target, &write.content)?;
+            Ok((target, temp_path))
+        });
+        match staged {
+            Ok((target, temp_path)) => prepared.push((write, target, temp_path)),
+            Err(e) => {
+                remove_temps(&prepared);
+                return Err(e);
+            }
+        }
+    }
+
+    for (write, target, _) in &prepared {
+        let current = match read_optional(target) {
+            Ok(current) => current,
+            Err(e) => {
+                remove_temps(&prepared);
+                return Err(e);
+            }
+        };
+        if current != write.original {
+            remove_temps(&prepared);
+            bail!(store::concurrent_edit(&write.path));
+        }
+    }
+    for (write, _, _) in &prepared {
+        if let Some(old) = &write.original {
+            if let Err(e) = backup::before_write(&write.path, old) {
+                remove_temps(&prepared);
+                return Err(e);
+            }
+        }
+    }
+
+    for (index, (write, target, temp_path)) in prepared.iter().enumerate() {
+        if let Err(e) = std::fs::rename(temp_path, target) {
+            // Put back the files already replaced
+            let mut unrestored = Vec::new();
+            for (done, target, _) in &prepared[..index] {
+                let restored = match &done.original {
+                    Some(content) => std::fs::write(target, content),
+                    None => std::fs::remove_file(target),
+                };
+                if let Err(restore_error) = restored {
+                    unrestored.push((done.path.as_path(), restore_error.into()));
+                }
+            }
+            remove_temps(&prepared[index..]);
+            return Err(Error::io("write to", &write.path, e))
+                .context(store::rollback_outcome(&unrestored));
+        }
+    }
+    Ok(())
+}
+
+/// Fail if a concurrent edit contradicts the patterns being added: it added
+/// the negation of one of them (or, for an added negation, the pattern
+/// itself)
+fn check_concurrent_conflicts(
+    file_path: &Path,
+    before: &HashSet<String>,
+    current: &str,
+    planned: &[String],
+) -> anyhow::Result<()> {
+    let after = parse_ignore_patterns(current);
+    for pattern in planned {
+        let opposite = match pattern.strip_prefix('!') {
+            Some(positive) => positive.to_string(),
+            None => format!("!{pattern}"),
+        };
+        if after.contains(&opposite) && !before.contains(&opposite) {
+            bail!(
+                "Concurrent edit conflict: {} was changed to include '{opposite}', which contradicts '{pattern}'",
+                file_path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Add patterns to an ignore file, optionally avoiding duplicates
+pub fn add_patterns_to_ignore_file(
+    file_path: &Path,
+    new_patterns: &[String],
+    avoid_duplicates: bool,
+    _validation_level: PatternValidationLevel,
+) -> anyhow::Result<Vec<String>> {
+    if new_patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Skip validation - patterns should be pre-validated by caller
+    // The validation_level parameter is kept for API compatibility
+
+    add_patterns_with_placement(file_path, new_patterns, avoid_duplicates, Placement::Append)
+}
+
+/// Validate ignore patterns with the built-in checks
+pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
+    validate_ignore_patterns_with(patterns, &RuleSet::default())
 }
 
-/// Validate ignore patterns
-pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
+/// [`validate_ignore_patterns`], also running the application's `rules`
+/// after the built-in checks on each pattern
+pub fn validate_ignore_patterns_with(patterns: &[String], rules: &RuleSet) -> Vec<PatternIssue> {
+    let _span = debug_span!("validate_ignore_patterns", count = patterns.len()).entered();
     let mut issues = Vec::new();
 
     for original_pattern in patterns {
@@ -220,9 +1977,12 @@ pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
                 severity: PatternSeverity::Error,
                 message: "Pattern contains newline characters which will corrupt the ignore file"
                     .to_string(),
+                rule: PatternRule::LineBreak,
             });
         }
 
+        issues.extend(syntax_issue(&pattern));
+
         // Check for common issues
         if pattern.starts_with('/') && pattern.ends_with('/') && pattern.len() > 2 {
             issues.push(PatternIssue {
@@ -230,6 +1990,7 @@ pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
                 severity: PatternSeverity::Info,
                 message: "Pattern has leading and trailing slashes - might be too restrictive"
                     .to_string(),
+                rule: PatternRule::SlashWrapped,
             });
         }
 
@@ -238,6 +1999,7 @@ pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
                 pattern: pattern.clone(),
                 severity: PatternSeverity::Info,
                 message: "Pattern starts with './' which is redundant".to_string(),
+                rule: PatternRule::DotSlashPrefix,
             });
         }
 
@@ -247,6 +2009,17 @@ pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
                 severity: PatternSeverity::Warning,
                 message: "Pattern contains multiple '**' which may not work as expected"
                     .to_string(),
+                rule: PatternRule::MultipleDoubleStar,
+            });
+        }
+
+        if forward_slashes(&pattern).is_some() {
+            issues.push(PatternIssue {
+                pattern: pattern.clone(),
+                severity: PatternSeverity::Warning,
+                message: "Pattern uses '\\' as a path separator; git reads it as an escape"
+                    .to_string(),
+                rule: PatternRule::BackslashSeparator,
             });
         }
 
@@ -256,6 +2029,7 @@ pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
                 pattern: pattern.clone(),
                 severity: PatternSeverity::Warning,
                 message: "Pattern is very broad and may ignore more than intended".to_string(),
+                rule: PatternRule::BroadPattern,
             });
         }
 
@@ -268,13 +2042,83 @@ pub fn validate_ignore_patterns(patterns: &[String]) -> Vec<PatternIssue> {
                 pattern: pattern.clone(),
                 severity: PatternSeverity::Warning,
                 message: "Pattern might ignore important project files".to_string(),
+                rule: PatternRule::ImportantFile,
             });
         }
+
+        issues.extend(rules.check(&pattern));
+    }
+
+    // Patterns added together are written in order, so they can defeat
+    // each other's negations
+    let sanitized: Vec<String> = patterns.iter().map(|p| sanitize_pattern(p)).collect();
+    for (index, reason) in negation::ineffective_in_list(&sanitized) {
+        issues.push(PatternIssue {
+            pattern: sanitized[index].clone(),
+            severity: PatternSeverity::Warning,
+            message: format!("Negation has no effect: {reason}"),
+            rule: PatternRule::IneffectiveNegation,
+        });
     }
 
+    debug!(issues = issues.len(), "validation finished");
     issues
 }
 
+/// [`PatternRule::InvalidSyntax`] for `line` if its glob syntax can't
+/// match anything (see [`Pattern::compile`])
+pub(crate) fn syntax_issue(line: &str) -> Option<PatternIssue> {
+    let problem = pattern::syntax_problem(line)?;
+    Some(PatternIssue {
+        pattern: line.to_string(),
+        severity: PatternSeverity::Error,
+        message: format!("{problem}; git would never match this pattern"),
+        rule: PatternRule::InvalidSyntax,
+    })
+}
+
+/// `pattern` with every backslash that separates path components turned
+/// into `/`, or `None` if there is none. A backslash in front of a plain
+/// name character escapes nothing, so `build\output` was written as a
+/// Windows path; escapes such as `\*`, `\ ` and `\\` are kept.
+pub(crate) fn forward_slashes(pattern: &str) -> Option<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            // Escapes inside a bracket expression are part of the set
+            '[' => {
+                let len = pattern::class_length(&chars[i..]).unwrap_or(1);
+                out.extend(&chars[i..i + len]);
+                i += len;
+            }
+            '\\' => match chars.get(i + 1) {
+                Some(&next) if next.is_alphanumeric() || matches!(next, '.' | '_' | '-') => {
+                    out.push('/');
+                    changed = true;
+                    i += 1;
+                }
+                Some(&next) => {
+                    out.push('\\');
+                    out.push(next);
+                    i += 2;
+                }
+                None => {
+                    out.push('\\');
+                    i += 1;
+                }
+            },
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    changed.then_some(out)
+}
+
 /// Ensure the .git/info/exclude file exists and has proper structure
 pub fn ensure_info_exclude_exists(exclude_file_path: &Path) -> anyhow::Result<()> {
     if exclude_file_path.exists() {
@@ -283,8 +2127,7 @@ pub fn ensure_info_exclude_exists(exclude_file_path: &Path) -> anyhow::Result<()
 
     // Create the info directory if it doesn't exist
     if let Some(parent) = exclude_file_path.parent() {
-        std::fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        std::fs::create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
     }
 
     // Create the exclude file with default template
@@ -306,12 +2149,49 @@ pub fn ensure_info_exclude_exists(exclude_file_path: &Path) -> anyhow::Result<()
     Ok(())
 }
 
+/// Create the global gitignore file with a short explanatory header if it
+/// doesn't exist yet
+pub fn ensure_global_gitignore_exists(global_path: &Path) -> anyhow::Result<()> {
+    if global_path.exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = global_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| Error::io("create directory", parent, e))?;
+    }
+
+    let template = r#"# Global gitignore: patterns here apply to every repository on this machine.
+# Use it for editor, OS and tool files rather than project build output.
+# Lines that start with '#' are comments.
+"#;
+
+    std::fs::write(global_path, template).with_context(|| {
+        format!(
+            "Failed to initialize global gitignore: {}",
+            global_path.display()
+        )
+    })?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::store::MemoryStore;
     use std::{io::Write, path::PathBuf};
     use tempfile::{NamedTempFile, TempDir};
 
+    /// [`add_patterns_in`] without an observer
+    fn add(
+        store: &mut dyn IgnoreStore,
+        path: &Path,
+        patterns: &[&str],
+        placement: Placement,
+    ) -> anyhow::Result<Vec<String>> {
+        add_patterns_in(store, path, &lines(patterns), true, placement, &mut ())
+    }
+
     #[test]
     fn test_sanitize_pattern() {
         assert_eq!(sanitize_pattern("*.pyc"), "*.pyc");
@@ -319,6 +2199,8 @@ mod tests {
         assert_eq!(sanitize_pattern("*.pyc\n"), "*.pyc");
         assert_eq!(sanitize_pattern("*.pyc\r\n"), "*.pyc");
         assert_eq!(sanitize_pattern(""), "");
+        assert_eq!(sanitize_pattern("draft\\ "), "draft\\ ");
+        assert_eq!(sanitize_pattern("\\ draft "), "\\ draft");
     }
 
     #[test]
@@ -342,6 +2224,99 @@ mod tests {
         assert!(patterns.contains("__pycache__/"));
     }
 
+    #[test]
+    fn test_read_ignore_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_file = temp_dir.path().join("test_ignore");
+        std::fs::write(
+            &temp_file,
+            "*.log\n\n# Build output\n# (generated)\ntarget/\n*.o\n\n\
+             # BEGIN git-ignore template: Rust\n# Cargo\n\nCargo.lock\n\
+             # END git-ignore template: Rust\n*.o\n",
+        )
+        .unwrap();
+
+        let entries = read_ignore_entries(&temp_file).unwrap();
+        let summary: Vec<(usize, &str, Option<&str>)> = entries
+            .iter()
+            .map(|e| (e.line_number, e.pattern.as_str(), e.section.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "*.log", None),
+                (5, "target/", Some("Build output")),
+                (6, "*.o", Some("Build output")),
+                (11, "Cargo.lock", Some("Rust template")),
+                (13, "*.o", None),
+            ]
+        );
+        assert_eq!(entries[1].comments, ["# Build output", "# (generated)"]);
+        assert!(entries[2].comments.is_empty());
+        assert!(entries[4].comments.is_empty());
+        assert_eq!(entries[1].parsed.as_ref().map(|p| p.dir_only), Some(true));
+
+        let entries = parse_ignore_entries("  !keep.log  \n!\n");
+        assert_eq!(entries[0].raw, "  !keep.log  ");
+        assert_eq!(entries[0].pattern, "!keep.log");
+        assert!(entries[0].parsed.as_ref().unwrap().is_negation());
+        assert_eq!(entries[1].parsed, None);
+
+        // Only a `#` in the first column starts a comment
+        let entries = parse_ignore_entries(
+            "# notes
+  #notes
+",
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].pattern, "  #notes");
+        assert!(entries[0].parsed.is_some());
+        assert_eq!(
+            IgnoreDocument::parse(
+                "b
+  #notes
+"
+            )
+            .render("\n"),
+            "b\n  #notes\n"
+        );
+    }
+
+    #[test]
+    fn test_ignore_document() {
+        let content = "# Header\n\n# Build  \n  target/\n# objects\n*.o\n# end\n\n\n\
+             # BEGIN git-ignore template: Rust\nCargo.lock\n\n*.rs.bk\n\
+             # END git-ignore template: Rust\n# git-ignore group: Proto\nz.pb\na.pb\n";
+        let document = IgnoreDocument::parse(content);
+        assert_eq!(document.sections.len(), 4);
+        assert_eq!(document.sections[0].heading(), Some("Header"));
+        assert_eq!(
+            document.sections[1],
+            DocumentSection::Block {
+                heading: vec!["# Build".to_string()],
+                entries: vec![
+                    DocumentEntry {
+                        comments: Vec::new(),
+                        pattern: "target/".to_string(),
+                    },
+                    DocumentEntry {
+                        comments: vec!["# objects".to_string()],
+                        pattern: "*.o".to_string(),
+                    },
+                ],
+                trailing: vec!["# end".to_string()],
+            }
+        );
+        assert!(matches!(&document.sections[2], DocumentSection::Fixed(lines) if lines.len() == 5));
+        assert!(matches!(&document.sections[3], DocumentSection::Fixed(lines) if lines.len() == 3));
+        assert_eq!(
+            document.render("\n"),
+            "# Header\n\n# Build\ntarget/\n# objects\n*.o\n# end\n\n\
+             # BEGIN git-ignore template: Rust\nCargo.lock\n\n*.rs.bk\n\
+             # END git-ignore template: Rust\n\n# git-ignore group: Proto\nz.pb\na.pb\n"
+        );
+    }
+
     #[test]
     fn test_validate_ignore_patterns() {
         let patterns = vec!["*.pyc".to_string(), "build".to_string()];
@@ -352,6 +2327,49 @@ mod tests {
         let issues = validate_ignore_patterns(&patterns);
         assert_eq!(issues.len(), 1);
         assert_eq!(issues[0].severity, PatternSeverity::Error);
+
+        let issues = validate_ignore_patterns(&["build\\output".to_string()]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, PatternRule::BackslashSeparator);
+
+        let issues = validate_ignore_patterns(&["*.[ch".to_string()]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, PatternRule::InvalidSyntax);
+        assert_eq!(issues[0].severity, PatternSeverity::Error);
+
+        let issues = validate_ignore_patterns(&["!important.log".to_string(), "*.log".to_string()]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, PatternRule::IneffectiveNegation);
+        assert_eq!(issues[0].severity, PatternSeverity::Warning);
+        assert_eq!(
+            issues[0].message,
+            "Negation has no effect: '*.log' comes after it and ignores important.log again"
+        );
+        let issues = validate_ignore_patterns(&["build/".to_string(), "!build/keep".to_string()]);
+        assert_eq!(issues[0].pattern, "!build/keep");
+        assert!(
+            validate_ignore_patterns(&["*.log".to_string(), "!important.log".to_string()])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_forward_slashes() {
+        assert_eq!(
+            forward_slashes("build\\Debug\\x64").as_deref(),
+            Some("build/Debug/x64")
+        );
+        assert_eq!(forward_slashes("a\\.cache").as_deref(), Some("a/.cache"));
+        assert_eq!(forward_slashes("\\#notes"), None);
+        assert_eq!(forward_slashes("*.tmp\\ "), None);
+        assert_eq!(forward_slashes("a\\\\b"), None);
+        assert_eq!(forward_slashes("a\\*"), None);
+        // Escapes in a bracket expression are not separators
+        assert_eq!(forward_slashes("file[\\-_]name.txt"), None);
+        assert_eq!(
+            forward_slashes("bin\\x[\\.a]\\y").as_deref(),
+            Some("bin/x[\\.a]/y")
+        );
     }
 
     #[test]
@@ -373,49 +2391,808 @@ mod tests {
         assert_eq!(normalize_pattern_for_dedup("planning/"), "planning");
         assert_eq!(normalize_pattern_for_dedup("/"), "/");
         assert_eq!(normalize_pattern_for_dedup("/build/"), "/build");
+        assert_eq!(normalize_pattern_for_dedup("**/build/"), "build");
+        assert_eq!(
+            normalize_pattern_for_dedup("/src/**/**/*.rs"),
+            "src/**/*.rs"
+        );
+        assert_eq!(normalize_pattern_for_dedup("\\b\\uild"), "build");
+        assert_eq!(normalize_pattern_for_dedup("#comment"), "#comment");
     }
 
     #[test]
     fn test_add_patterns_skips_trailing_slash_duplicate() {
+        let path = Path::new(".gitignore");
+        let mut files = MemoryStore::new();
+
+        let added = add(&mut files, path, &["planning"], Placement::Append).unwrap();
+        assert_eq!(added, ["planning"]);
+
+        // "planning/" only differs from the already-present "planning" by a
+        // trailing slash, so it must be treated as a duplicate.
+        let added = add(&mut files, path, &["planning/"], Placement::Append).unwrap();
+        assert!(added.is_empty());
+        assert_eq!(files.get(path), Some("planning\n"));
+    }
+
+    #[test]
+    fn test_plan_patterns_to_add_does_not_write() {
         let temp_dir = TempDir::new().unwrap();
         let temp_file = temp_dir.path().join("test_ignore");
+        std::fs::write(&temp_file, "*.pyc\n").unwrap();
 
-        let added = add_patterns_to_ignore_file(
+        let planned = plan_patterns_to_add(
             &temp_file,
-            &["planning".to_string()],
+            &["*.pyc".to_string(), "build/".to_string()],
             true,
-            PatternValidationLevel::Warn,
         )
         .unwrap();
-        assert_eq!(added, vec!["planning".to_string()]);
+        assert_eq!(planned, vec!["build/".to_string()]);
+        assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "*.pyc\n");
+    }
 
-        // "planning/" only differs from the already-present "planning" by a
-        // trailing slash, so it must be treated as a duplicate.
-        let added = add_patterns_to_ignore_file(
-            &temp_file,
-            &["planning/".to_string()],
-            true,
-            PatternValidationLevel::Warn,
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_insert_sorted_within_last_section() {
+        let mut content = lines(&["# Build", "target/", "", "# Logs", "a.log", "c.log"]);
+        insert_sorted(&mut content, "b.log", SortStrategy::Lexicographic);
+        insert_sorted(&mut content, "z.log", SortStrategy::Lexicographic);
+        insert_sorted(&mut content, "0.log", SortStrategy::Lexicographic);
+        assert_eq!(
+            content,
+            lines(&[
+                "# Build", "target/", "", "# Logs", "0.log", "a.log", "b.log", "c.log", "z.log"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_insert_sorted_respects_negations() {
+        let mut content = lines(&["c.log", "*.log", "!keep.log", "d.tmp"]);
+        // Must not move in front of the negation it could override
+        insert_sorted(&mut content, "a.log", SortStrategy::Lexicographic);
+        assert_eq!(
+            content,
+            lines(&["c.log", "*.log", "!keep.log", "a.log", "d.tmp"])
+        );
+
+        // New negations stay after every plain pattern
+        insert_sorted(&mut content, "!also-keep.log", SortStrategy::Lexicographic);
+        assert_eq!(
+            content,
+            lines(&[
+                "c.log",
+                "*.log",
+                "!keep.log",
+                "a.log",
+                "d.tmp",
+                "!also-keep.log"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_add_patterns_sorted_placement() {
+        let path = Path::new(".gitignore");
+        let mut files = MemoryStore::new().with_file(path, "# Generated\nbuild/\ndist/\n");
+
+        let added = add(
+            &mut files,
+            path,
+            &["coverage/", "build/"],
+            Placement::Sorted(SortStrategy::Lexicographic),
         )
         .unwrap();
-        assert!(added.is_empty());
+        assert_eq!(added, ["coverage/"]);
+        assert_eq!(
+            files.get(path),
+            Some("# Generated\nbuild/\ncoverage/\ndist/\n")
+        );
+    }
 
-        let content = std::fs::read_to_string(&temp_file).unwrap();
-        assert_eq!(content.matches("planning").count(), 1);
+    #[test]
+    fn test_add_patterns_as_group() {
+        let path = Path::new(".gitignore");
+        let mut files = MemoryStore::new().with_file(path, "*.log\n\n");
+
+        let group = Placement::Group("Protobuf generated".to_string());
+        let added = add(&mut files, path, &["*.pb.go", "*.log", "*_pb2.py"], group).unwrap();
+        assert_eq!(added, ["*.pb.go", "*_pb2.py"]);
+
+        // Later sorted additions start a new section instead of joining it
+        let sorted = Placement::Sorted(SortStrategy::Lexicographic);
+        add(&mut files, path, &["a.out"], sorted.clone()).unwrap();
+        add(&mut files, path, &["core"], sorted).unwrap();
+        assert_eq!(
+            files.get(path),
+            Some("*.log\n\n# git-ignore group: Protobuf generated\n*.pb.go\n*_pb2.py\n\na.out\ncore\n")
+        );
+
+        let entries = store::read_entries(&files, path).unwrap();
+        assert_eq!(entries[1].section.as_deref(), Some("Protobuf generated"));
+        assert_eq!(entries[3].section, None);
+    }
+
+    #[test]
+    fn test_strip_template_blocks() {
+        let content = lines(&[
+            "*.log",
+            "",
+            "# BEGIN git-ignore template: Rust",
+            "target/",
+            "# END git-ignore template: Rust",
+            "",
+            "local/",
+        ]);
+        let stripped = strip_template_blocks(&content, "rust").unwrap().unwrap();
+        assert_eq!(stripped, lines(&["*.log", "", "local/"]));
+
+        // At the end of the file the separator before the block goes too
+        let content = lines(&[
+            "*.log",
+            "",
+            "# BEGIN git-ignore template: Rust",
+            "target/",
+            "# END git-ignore template: Rust",
+        ]);
+        let stripped = strip_template_blocks(&content, "Rust").unwrap().unwrap();
+        assert_eq!(stripped, lines(&["*.log"]));
+
+        assert!(strip_template_blocks(&content, "Python").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_add_template_to_ignore_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("info").join("exclude");
+        let rust = vec!["target/".to_string()];
+
+        assert!(add_template_to_ignore_file(&path, "Rust", &rust).unwrap());
+        std::fs::write(&path, std::fs::read_to_string(&path).unwrap() + "\n*.log\n").unwrap();
+        assert!(!add_template_to_ignore_file(&path, "Rust", &rust).unwrap());
+
+        // A changed template is replaced where it stands
+        let rust = vec!["target/".to_string(), "**/*.rs.bk".to_string()];
+        assert!(add_template_to_ignore_file(&path, "rust", &rust).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# BEGIN git-ignore template: rust\ntarget/\n**/*.rs.bk\n# END git-ignore template: rust\n\n*.log\n"
+        );
+    }
+
+    #[test]
+    fn test_template_in_store() {
+        let path = Path::new(".gitignore");
+        let mut store = MemoryStore::new().with_file(path, "*.log\n");
+        let rust = vec!["target/".to_string()];
+
+        assert!(add_template_in(&mut store, path, "Rust", &rust).unwrap());
+        assert_eq!(
+            store.get(path),
+            Some("*.log\n\n# BEGIN git-ignore template: Rust\ntarget/\n# END git-ignore template: Rust\n")
+        );
+        assert!(remove_template_in(&mut store, path, "rust").unwrap());
+        assert_eq!(store.get(path), Some("*.log\n"));
+        assert!(!remove_template_in(&mut store, Path::new("missing"), "Rust").unwrap());
+    }
+
+    #[test]
+    fn test_protected_patterns() {
+        let protected = vec![".env".to_string(), "*.key".to_string()];
+        let candidates = lines(&[".env/", "*.log", "*.key", ".env"]);
+        assert_eq!(
+            find_protected(&candidates, &protected),
+            lines(&[".env/", "*.key", ".env"])
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".gitignore");
+        std::fs::write(
+            &path,
+            "*.log\n# BEGIN git-ignore template: Secrets\n# keys\n*.key\n.env\n# END git-ignore template: Secrets\n",
+        )
+        .unwrap();
+        assert_eq!(
+            template_block_patterns(&path, "secrets").unwrap(),
+            lines(&["*.key", ".env"])
+        );
+    }
+
+    #[test]
+    fn test_strip_template_blocks_unterminated() {
+        let content = lines(&["# BEGIN git-ignore template: Rust", "target/"]);
+        assert!(strip_template_blocks(&content, "Rust").is_err());
     }
 
     #[test]
     fn test_add_patterns_skips_trailing_slash_duplicate_within_same_batch() {
+        let mut files = MemoryStore::new();
+        let added = add(
+            &mut files,
+            Path::new(".gitignore"),
+            &["planning", "planning/"],
+            Placement::Append,
+        )
+        .unwrap();
+        assert_eq!(added, ["planning"]);
+    }
+
+    #[test]
+    fn test_write_keeps_scratch_files_in_git_dir() {
         let temp_dir = TempDir::new().unwrap();
-        let temp_file = temp_dir.path().join("test_ignore");
+        std::fs::create_dir(temp_dir.path().join(".git")).unwrap();
+        let path = temp_dir.path().join(".gitignore");
+        std::fs::write(&path, "a\n").unwrap();
 
-        let added = add_patterns_to_ignore_file(
-            &temp_file,
-            &["planning".to_string(), "planning/".to_string()],
+        let outcome = replace_file_if_unchanged(&path, Some("a\n"), "a\nb\n").unwrap();
+        assert!(matches!(outcome, WriteOutcome::Written));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\n");
+        let mut names: Vec<String> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, [".git", ".gitignore"]);
+        let scratch = temp_dir.path().join(".git/git-ignore/tmp");
+        assert_eq!(std::fs::read_dir(scratch).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_write_if_unchanged_detects_concurrent_edit() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_file = temp_dir.path().join(".gitignore");
+        std::fs::write(&temp_file, "a\nb\n").unwrap();
+
+        // Our view is stale: another writer added `b` since we read the file
+        let outcome = replace_file_if_unchanged(&temp_file, Some("a\n"), "a\n\nc\n").unwrap();
+        match outcome {
+            WriteOutcome::Changed(current) => assert_eq!(current.as_deref(), Some("a\nb\n")),
+            WriteOutcome::Written => panic!("stale write was applied"),
+        }
+        assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "a\nb\n");
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        let outcome = replace_file_if_unchanged(&temp_file, Some("a\nb\n"), "a\nb\n\nc\n").unwrap();
+        assert!(matches!(outcome, WriteOutcome::Written));
+        assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "a\nb\n\nc\n");
+    }
+
+    /// A store where another writer edits the file between each read and
+    /// the write that follows it
+    struct RacingStore {
+        files: MemoryStore,
+        edits: Vec<String>,
+    }
+
+    impl IgnoreStore for RacingStore {
+        fn read(&self, path: &Path) -> anyhow::Result<Option<String>> {
+            self.files.read(path)
+        }
+
+        fn write(&mut self, path: &Path, content: &str) -> anyhow::Result<()> {
+            self.files.write(path, content)
+        }
+
+        fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+            self.files.remove(path)
+        }
+
+        fn write_if_unchanged(
+            &mut self,
+            path: &Path,
+            expected: Option<&str>,
+            content: &str,
+        ) -> anyhow::Result<WriteOutcome> {
+            if let Some(edit) = self.edits.pop() {
+                self.files.write(path, &edit)?;
+            }
+            self.files.write_if_unchanged(path, expected, content)
+        }
+    }
+
+    #[test]
+    fn test_add_patterns_remerges_concurrent_edit() {
+        let path = Path::new(".gitignore");
+        let mut racing = RacingStore {
+            files: MemoryStore::new().with_file(path, "a\n"),
+            edits: vec!["a\nb\n".to_string()],
+        };
+        let added = add(&mut racing, path, &["b", "c"], Placement::Append).unwrap();
+        // `b` arrived from the other writer, so only `c` is added after it
+        assert_eq!(added, ["c"]);
+        assert_eq!(racing.files.get(path), Some("a\nb\n\nc\n"));
+
+        // The other writer negated what is being added
+        racing.edits = vec!["a\nb\n\nc\n!d\n".to_string()];
+        let err = add(&mut racing, path, &["d"], Placement::Append).unwrap_err();
+        assert!(err.to_string().contains("contradicts 'd'"));
+
+        racing.edits = (0..MAX_MERGE_ATTEMPTS).map(|n| format!("x{n}\n")).collect();
+        let err = add(&mut racing, path, &["e"], Placement::Append).unwrap_err();
+        assert!(err.to_string().contains("kept changing"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_patterns_writes_through_symlink() {
+        let temp_dir = TempDir::new().unwrap();
+        let real_file = temp_dir.path().join("shared-ignore");
+        let link = temp_dir.path().join(".gitignore");
+        std::fs::write(&real_file, "a\n").unwrap();
+        std::os::unix::fs::symlink(&real_file, &link).unwrap();
+
+        add_patterns_to_ignore_file(
+            &link,
+            &["b".to_string()],
             true,
             PatternValidationLevel::Warn,
         )
         .unwrap();
-        assert_eq!(added, vec!["planning".to_string()]);
+        assert!(link.is_symlink());
+        assert_eq!(std::fs::read_to_string(&real_file).unwrap(), "a\n\nb\n");
+    }
+
+    #[test]
+    fn test_check_concurrent_conflicts() {
+        let path = Path::new(".gitignore");
+        let before = parse_ignore_patterns("a\n");
+        let planned = vec!["build/".to_string()];
+
+        assert!(check_concurrent_conflicts(path, &before, "a\nlogs/\n", &planned).is_ok());
+        let err = check_concurrent_conflicts(path, &before, "a\n!build/\n", &planned)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Concurrent edit conflict"));
+
+        // A negation that was already there is not a new conflict
+        let before = parse_ignore_patterns("!build/\n");
+        assert!(check_concurrent_conflicts(path, &before, "!build/\n", &planned).is_ok());
+    }
+
+    #[test]
+    fn test_find_conflict_markers() {
+        let content = "a\n<<<<<<< HEAD\nb\n=======\nc\n>>>>>>> feature\n";
+        assert_eq!(find_conflict_markers(content), vec![2, 4, 6]);
+        assert!(find_conflict_markers("a\n<<<<<<<<x\n# =======\n").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_conflicts_keep_both() {
+        let content = "a\n<<<<<<< HEAD\n*.log\ntarget/\n||||||| base\nold\n=======\ntarget\nnode_modules/\n>>>>>>> feature\nz\n";
+        assert_eq!(
+            resolve_conflicts_keep_both(content).unwrap(),
+            "a\n*.log\ntarget/\nnode_modules/\nz\n"
+        );
+        assert!(resolve_conflicts_keep_both("<<<<<<< HEAD\na\n").is_err());
+    }
+
+    #[test]
+    fn test_add_patterns_refuses_conflicted_file() {
+        let path = Path::new(".gitignore");
+        let content = "<<<<<<< HEAD\na\n=======\nb\n>>>>>>> other\n";
+        let mut files = MemoryStore::new().with_file(path, content);
+
+        let err = add(&mut files, path, &["c"], Placement::Append).unwrap_err();
+        assert!(err.to_string().contains("Merge conflict markers"));
+        assert_eq!(files.get(path), Some(content));
+    }
+
+    #[test]
+    fn test_ensure_global_gitignore_exists() {
+        let temp_dir = TempDir::new().unwrap();
+        let global_path = temp_dir.path().join("git").join("ignore");
+
+        ensure_global_gitignore_exists(&global_path).unwrap();
+        let content = std::fs::read_to_string(&global_path).unwrap();
+        assert!(content.starts_with("# Global gitignore"));
+        assert!(read_ignore_patterns(&global_path).unwrap().is_empty());
+
+        // An existing file is left alone
+        std::fs::write(&global_path, "*.swp\n").unwrap();
+        ensure_global_gitignore_exists(&global_path).unwrap();
+        assert_eq!(std::fs::read_to_string(&global_path).unwrap(), "*.swp\n");
+    }
+
+    #[test]
+    fn test_remove_patterns_from_ignore_file() {
+        let path = Path::new(".gitignore");
+        let mut files =
+            MemoryStore::new().with_file(path, "# build\nbuild\n*.log\n\n*.log\ntarget/\n");
+
+        let report = remove_patterns_in(
+            &mut files,
+            path,
+            &lines(&["*.log", "build/", "dist/"]),
+            &mut (),
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.removed,
+            vec![
+                RemovedPattern {
+                    pattern: "build".to_string(),
+                    line_number: 2
+                },
+                RemovedPattern {
+                    pattern: "*.log".to_string(),
+                    line_number: 3
+                },
+                RemovedPattern {
+                    pattern: "*.log".to_string(),
+                    line_number: 5
+                },
+            ]
+        );
+        assert_eq!(report.not_found, vec!["dist/".to_string()]);
+        assert_eq!(files.get(path), Some("# build\n\ntarget/\n"));
+    }
+
+    #[test]
+    fn test_remove_patterns_missing_file() {
+        let path = Path::new(".gitignore");
+        let mut files = MemoryStore::new();
+
+        let report = remove_patterns_in(&mut files, path, &lines(&["a"]), &mut ()).unwrap();
+        assert!(report.removed.is_empty());
+        assert_eq!(report.not_found, vec!["a".to_string()]);
+        assert_eq!(files.get(path), None);
+    }
+
+    #[test]
+    fn test_parse_pattern_input() {
+        assert_eq!(
+            parse_pattern_input(b"*.log\r\n\n  \nbuild/\n", false).unwrap(),
+            vec!["*.log".to_string(), "build/".to_string()]
+        );
+        assert_eq!(
+            parse_pattern_input(b"a b.txt\0#notes\0!keep\0trail \0*.tmp\0\0", true).unwrap(),
+            vec![
+                "a b.txt".to_string(),
+                "\\#notes".to_string(),
+                "\\!keep".to_string(),
+                "trail\\ ".to_string(),
+                "\\*.tmp".to_string()
+            ]
+        );
+        let err = parse_pattern_input(b"ok\0bad\nname\0", true).unwrap_err();
+        assert!(err.to_string().contains("Invalid input item 2"));
+        assert!(parse_pattern_input(b"\xff\0", true).is_err());
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Observer for Recorder {
+        fn pattern_skipped(&mut self, pattern: &str, reason: SkipReason) {
+            self.0.push(format!("skipped {pattern} {reason:?}"));
+        }
+
+        fn line_written(&mut self, _path: &Path, line: &str) {
+            self.0.push(format!("wrote {line}"));
+        }
+
+        fn line_removed(&mut self, _path: &Path, line: &str, line_number: usize) {
+            self.0.push(format!("removed {line} at {line_number}"));
+        }
+
+        fn file_committed(&mut self, _path: &Path) {
+            self.0.push("committed".to_string());
+        }
+
+        fn progress(&mut self, _item: &Path, done: usize, total: usize) {
+            self.0.push(format!("{done}/{total}"));
+        }
+    }
+
+    #[test]
+    fn test_observed_operations() {
+        let path = Path::new(".gitignore");
+        let mut files = MemoryStore::new().with_file(path, "*.log\n");
+
+        let mut recorder = Recorder::default();
+        let patterns = lines(&["*.log", "build/", " ", "build"]);
+        add_patterns_in(
+            &mut files,
+            path,
+            &patterns,
+            true,
+            Placement::Append,
+            &mut recorder,
+        )
+        .unwrap();
+        assert_eq!(
+            recorder.0,
+            [
+                "skipped *.log Duplicate",
+                "skipped   Empty",
+                "skipped build Duplicate",
+                "wrote build/",
+                "committed",
+            ]
+        );
+
+        // Nothing to write, nothing committed
+        let mut recorder = Recorder::default();
+        add_patterns_in(
+            &mut files,
+            path,
+            &patterns[..1],
+            true,
+            Placement::Append,
+            &mut recorder,
+        )
+        .unwrap();
+        assert_eq!(recorder.0, ["skipped *.log Duplicate"]);
+
+        let mut recorder = Recorder::default();
+        let remove = lines(&["build/", "dist/"]);
+        remove_patterns_in(&mut files, path, &remove, &mut recorder).unwrap();
+        assert_eq!(
+            recorder.0,
+            ["removed build/ at 3", "skipped dist/ NotFound", "committed"]
+        );
+    }
+
+    #[test]
+    fn test_move_patterns() {
+        let dir = TempDir::new().unwrap();
+        let exclude = dir.path().join("exclude");
+        let gitignore = dir.path().join(".gitignore");
+        std::fs::write(&exclude, "# mine\nbuild/\nnotes.txt\nbuild\n").unwrap();
+        std::fs::write(&gitignore, "*.log\n").unwrap();
+
+        let report = move_patterns(
+            &exclude,
+            &gitignore,
+            &["build".to_string()],
+            Placement::Append,
+        )
+        .unwrap();
+        assert_eq!(report.removed.len(), 2);
+        assert_eq!(report.added, vec!["build/"]);
+        assert_eq!(
+            std::fs::read_to_string(&exclude).unwrap(),
+            "# mine\nnotes.txt\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&gitignore).unwrap(),
+            "*.log\n\nbuild/\n"
+        );
+
+        // A pattern the source doesn't have leaves both files alone
+        let err = move_patterns(
+            &exclude,
+            &gitignore,
+            &["notes.txt".to_string(), "missing/".to_string()],
+            Placement::Append,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Pattern not found"));
+        assert_eq!(
+            std::fs::read_to_string(&exclude).unwrap(),
+            "# mine\nnotes.txt\n"
+        );
+
+        // Already in the destination: only removed from the source
+        std::fs::write(&exclude, "*.log\n").unwrap();
+        let report = move_patterns(
+            &exclude,
+            &gitignore,
+            &["*.log".to_string()],
+            Placement::Append,
+        )
+        .unwrap();
+        assert!(report.added.is_empty());
+        assert_eq!(std::fs::read_to_string(&exclude).unwrap(), "");
+        assert_eq!(
+            std::fs::read_to_string(&gitignore).unwrap(),
+            "*.log\n\nbuild/\n"
+        );
+    }
+
+    #[test]
+    fn test_edits_from_many_threads() {
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<crate::RepoContext>();
+        send_sync::<crate::IgnoreManager>();
+        send_sync::<Transaction>();
+        send_sync::<AddPreview>();
+        send_sync::<crate::store::MemoryStore>();
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".gitignore");
+        std::fs::write(&path, "shared.log\n").unwrap();
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for n in 0..20 {
+                        let pattern = format!("t{thread}-{n}.tmp");
+                        add_patterns_with_placement(
+                            &path,
+                            std::slice::from_ref(&pattern),
+                            true,
+                            Placement::Append,
+                        )
+                        .unwrap();
+                        if n % 2 == 0 {
+                            remove_patterns_from_ignore_file(&path, &[pattern]).unwrap();
+                        }
+                        // Everyone re-adds the same line; it must stay single
+                        add_patterns_with_placement(
+                            &path,
+                            &["shared.log".to_string()],
+                            true,
+                            Placement::Append,
+                        )
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let entries = read_ignore_entries(&path).unwrap();
+        let mut patterns: Vec<&str> = entries.iter().map(|e| e.pattern.as_str()).collect();
+        patterns.sort_unstable();
+        let mut expected: Vec<String> = (0..8)
+            .flat_map(|thread| {
+                (1..20)
+                    .step_by(2)
+                    .map(move |n| format!("t{thread}-{n}.tmp"))
+            })
+            .collect();
+        expected.push("shared.log".to_string());
+        expected.sort_unstable();
+        assert_eq!(patterns, expected);
+        // No temporary or lock file is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_transaction() {
+        let gitignore = Path::new(".gitignore");
+        let dockerignore = Path::new(".dockerignore");
+        let mut files = MemoryStore::new().with_file(gitignore, "*.log\n");
+        let patterns = lines(&["*.log", "target/"]);
+
+        let mut transaction = Transaction::in_store(&mut files);
+        let added = transaction
+            .add_patterns(gitignore, &patterns, true, Placement::Append)
+            .unwrap();
+        assert_eq!(added, ["target/"]);
+        transaction
+            .add_patterns(dockerignore, &patterns, true, Placement::Append)
+            .unwrap();
+        // A second change to a file builds on the first
+        transaction
+            .remove_patterns(gitignore, &lines(&["*.log"]))
+            .unwrap();
+        assert_eq!(
+            transaction.staged_content(gitignore).unwrap(),
+            "\ntarget/\n"
+        );
+        let mut recorder = Recorder::default();
+        transaction.commit_observed(&mut recorder).unwrap();
+        assert_eq!(recorder.0, ["committed", "1/2", "committed", "2/2"]);
+        assert_eq!(files.get(gitignore), Some("\ntarget/\n"));
+        assert_eq!(files.get(dockerignore), Some("*.log\ntarget/\n"));
+
+        // A file changed since staging: nothing is written
+        let writes = [
+            StagedWrite {
+                path: gitignore.to_path_buf(),
+                original: Some("\ntarget/\n".to_string()),
+                content: "a\n".to_string(),
+            },
+            StagedWrite {
+                path: dockerignore.to_path_buf(),
+                original: Some("*.log\n".to_string()),
+                content: "b\n".to_string(),
+            },
+        ];
+        let err = files.write_all_if_unchanged(&writes).unwrap_err();
+        assert!(err.to_string().contains("Concurrent edit conflict"));
+        assert_eq!(files.get(gitignore), Some("\ntarget/\n"));
+    }
+
+    #[test]
+    fn test_transaction_on_disk() {
+        let dir = TempDir::new().unwrap();
+        let gitignore = dir.path().join(".gitignore");
+        let dockerignore = dir.path().join(".dockerignore");
+        std::fs::write(&gitignore, "target/\n").unwrap();
+
+        // A file changed since staging: nothing is written
+        let mut transaction = Transaction::new();
+        transaction.write(&gitignore, "a\n").unwrap();
+        transaction.write(&dockerignore, "b\n").unwrap();
+        std::fs::write(&dockerignore, "edited\n").unwrap();
+        let err = transaction.commit().unwrap_err();
+        assert!(err.to_string().contains("Concurrent edit conflict"));
+        assert_eq!(std::fs::read_to_string(&gitignore).unwrap(), "target/\n");
+
+        // A file that can't be written: nothing is written either
+        let sub = dir.path().join("sub");
+        let mut transaction = Transaction::new();
+        transaction.write(&gitignore, "a\n").unwrap();
+        transaction.write(&sub.join(".gitignore"), "b\n").unwrap();
+        std::fs::write(&sub, "").unwrap();
+        assert!(transaction.commit().is_err());
+        assert_eq!(std::fs::read_to_string(&gitignore).unwrap(), "target/\n");
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_preview_add_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".gitignore");
+        let patterns: Vec<String> = ["*.log", "build/", "build", " "]
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+
+        let preview = preview_add_patterns(&path, &patterns, true, Placement::Append).unwrap();
+        assert!(preview.creates_file);
+        assert_eq!(preview.added, ["*.log", "build/"]);
+        assert_eq!(
+            preview.skipped,
+            [
+                ("build".to_string(), SkipReason::Duplicate),
+                (" ".to_string(), SkipReason::Empty)
+            ]
+        );
+        assert_eq!(preview.new_content, "*.log\nbuild/\n");
+        assert!(!path.exists());
+
+        std::fs::write(&path, "*.log\n").unwrap();
+        let preview = preview_add_patterns(&path, &patterns, true, Placement::Append).unwrap();
+        assert!(!preview.creates_file);
+        assert_eq!(preview.added, ["build/"]);
+        assert_eq!(preview.old_content, "*.log\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "*.log\n");
+
+        let preview = preview_add_patterns(&path, &patterns[..1], true, Placement::Append).unwrap();
+        assert!(!preview.changes_file());
+        assert_eq!(preview.new_content, preview.old_content);
+        assert_eq!(preview.diff(), "");
+    }
+
+    #[test]
+    fn test_apply_add_preview() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".gitignore");
+        let patterns = vec!["build/".to_string()];
+
+        let preview = preview_add_patterns(&path, &patterns, true, Placement::Append).unwrap();
+        let label = path.display();
+        assert_eq!(
+            preview.diff(),
+            format!("--- /dev/null\n+++ {label}\n@@ -0,0 +1 @@\n+build/\n")
+        );
+        assert_eq!(preview.apply().unwrap(), ["build/"]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "build/\n");
+
+        let preview =
+            preview_add_patterns(&path, &["*.log".to_string()], true, Placement::Append).unwrap();
+        assert_eq!(
+            preview.diff(),
+            format!("--- {label}\n+++ {label}\n@@ -1 +1,3 @@\n build/\n+\n+*.log\n")
+        );
+        // The file changed since the preview: nothing is written
+        std::fs::write(&path, "build/\ntarget/\n").unwrap();
+        assert!(preview.apply().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "build/\ntarget/\n");
     }
 }
@@ -0,0 +1,321 @@
+//! Line-by-line editing of an ignore file that keeps its formatting
+//!
+//! [`IgnoreFile`] holds every line exactly as written, its line ending
+//! included, so rendering a file that wasn't edited gives back the same
+//! bytes. Edits touch only the lines they name: comments, blank lines,
+//! indentation and mixed line endings elsewhere stay as they were. Unlike
+//! [`IgnoreDocument`](crate::ignore::IgnoreDocument), which regroups a file
+//! for `fmt`, nothing is normalized.
+
+use crate::{ignore, pattern::trim_unescaped_trailing_spaces};
+use std::{fmt, path::Path};
+
+/// What a line of an ignore file holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryKind {
+    /// Empty or only whitespace
+    Blank,
+    /// Starts with `#` in the first column; git reads an indented `#` as
+    /// part of a pattern
+    Comment,
+    Pattern,
+}
+
+/// One line of an [`IgnoreFile`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    text: String,
+    /// `\n`, `\r\n`, or empty for a last line without one
+    ending: String,
+    line_number: Option<usize>,
+}
+
+impl Entry {
+    /// The line as written, without its line ending
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// 1-based line number in the file as parsed; `None` for a line
+    /// inserted since
+    pub fn line_number(&self) -> Option<usize> {
+        self.line_number
+    }
+
+    pub fn kind(&self) -> EntryKind {
+        classify_line(&self.text)
+    }
+
+    /// The pattern on this line, without surrounding whitespace (an escaped
+    /// trailing space is kept), or `None` for a blank or comment line. An
+    /// indented `#` keeps its indentation, so it doesn't read as a comment.
+    pub fn pattern(&self) -> Option<&str> {
+        line_pattern(&self.text)
+    }
+}
+
+/// What `line`, without its line ending, holds. As in git, only a `#` in
+/// the first column starts a comment.
+pub fn classify_line(line: &str) -> EntryKind {
+    if line.trim().is_empty() {
+        EntryKind::Blank
+    } else if line.starts_with('#') {
+        EntryKind::Comment
+    } else {
+        EntryKind::Pattern
+    }
+}
+
+/// Whether `line` is a comment: `#` in the first column
+pub fn is_comment(line: &str) -> bool {
+    classify_line(line) == EntryKind::Comment
+}
+
+/// The pattern on `line` as [`Entry::pattern`] gives it, or `None` for a
+/// blank or comment line
+pub fn line_pattern(line: &str) -> Option<&str> {
+    if classify_line(line) != EntryKind::Pattern {
+        return None;
+    }
+    let trimmed = line.trim_start();
+    let text = if trimmed.starts_with('#') {
+        line
+    } else {
+        trimmed
+    };
+    Some(trim_unescaped_trailing_spaces(text))
+}
+
+/// An ignore file as an ordered list of lines (see the module
+/// documentation)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgnoreFile {
+    entries: Vec<Entry>,
+    /// Line ending for inserted lines: the file's own, `\r\n` if any line
+    /// uses it
+    newline: String,
+}
+
+impl IgnoreFile {
+    pub fn parse(content: &str) -> IgnoreFile {
+        let entries = content
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                let text = line.trim_end_matches('\n');
+                let text = text.strip_suffix('\r').unwrap_or(text);
+                Entry {
+                    text: text.to_string(),
+                    ending: line[text.len()..].to_string(),
+                    line_number: Some(index + 1),
+                }
+            })
+            .collect();
+        let newline = if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        IgnoreFile {
+            entries,
+            newline: newline.to_string(),
+        }
+    }
+
+    /// Read `path`; a missing file reads as an empty one
+    pub fn read(path: &Path) -> anyhow::Result<IgnoreFile> {
+        Ok(IgnoreFile::parse(
+            &ignore::read_optional(path)?.unwrap_or_default(),
+        ))
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Indices and text of the pattern lines, in file order
+    pub fn patterns(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((index, entry.pattern()?)))
+    }
+
+    /// Index of the first line holding `pattern`, compared the way
+    /// duplicates are detected when adding patterns
+    pub fn find(&self, pattern: &str) -> Option<usize> {
+        let wanted = ignore::normalize_pattern_for_dedup(pattern.trim());
+        self.patterns()
+            .find(|(_, text)| ignore::normalize_pattern_for_dedup(text) == wanted)
+            .map(|(index, _)| index)
+    }
+
+    /// Insert `text` as a new line before the line at `index`; an index
+    /// of [`len`](IgnoreFile::len) appends
+    ///
+    /// # Panics
+    ///
+    /// If `index` is greater than the number of lines
+    pub fn insert(&mut self, index: usize, text: &str) {
+        assert!(index <= self.entries.len(), "line index out of range");
+        let text = text.replace(['\n', '\r'], "");
+        if index == self.entries.len() {
+            // The old last line needs an ending now
+            if let Some(last) = self.entries.last_mut() {
+                if last.ending.is_empty() {
+                    last.ending = self.newline.clone();
+                }
+            }
+        }
+        self.entries.insert(
+            index,
+            Entry {
+                text,
+                ending: self.newline.clone(),
+                line_number: None,
+            },
+        );
+    }
+
+    /// Append `text` as a new last line
+    pub fn push(&mut self, text: &str) {
+        self.insert(self.entries.len(), text);
+    }
+
+    /// Remove the line at `index` and return it
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of range
+    pub fn remove(&mut self, index: usize) -> Entry {
+        let entry = self.entries.remove(index);
+        if index == self.entries.len() && entry.ending.is_empty() {
+            // Keep a file without a final line break that way
+            if let Some(last) = self.entries.last_mut() {
+                last.ending.clear();
+            }
+        }
+        entry
+    }
+
+    /// Keep only the lines for which `keep` returns `true`, returning the
+    /// others in file order
+    pub fn remove_where(&mut self, mut keep: impl FnMut(&Entry) -> bool) -> Vec<Entry> {
+        let mut removed = Vec::new();
+        for index in (0..self.entries.len()).rev() {
+            if !keep(&self.entries[index]) {
+                removed.push(self.remove(index));
+            }
+        }
+        removed.reverse();
+        removed
+    }
+
+    /// Replace the text of the line at `index`, keeping its line ending
+    /// and line number
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of range
+    pub fn set(&mut self, index: usize, text: &str) {
+        self.entries[index].text = text.replace(['\n', '\r'], "");
+    }
+
+    /// The file content: the original bytes for lines that weren't edited
+    pub fn render(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for IgnoreFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            f.write_str(&entry.text)?;
+            f.write_str(&entry.ending)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_is_exact() {
+        for content in [
+            "",
+            "*.log\n",
+            "# Build\r\ntarget/\r\n\r\n  *.tmp  \n!keep.tmp",
+            "\n\n#\n\\ \n",
+        ] {
+            assert_eq!(IgnoreFile::parse(content).render(), content);
+        }
+    }
+
+    #[test]
+    fn test_entries() {
+        let file = IgnoreFile::parse("# Build\n\n  target/ \nfoo\\ \n  #notes\n");
+        let kinds: Vec<EntryKind> = file.entries().iter().map(Entry::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                EntryKind::Comment,
+                EntryKind::Blank,
+                EntryKind::Pattern,
+                EntryKind::Pattern,
+                EntryKind::Pattern
+            ]
+        );
+        assert_eq!(
+            file.patterns().collect::<Vec<_>>(),
+            [(2, "target/"), (3, "foo\\ "), (4, "  #notes")]
+        );
+        assert_eq!(file.entries()[2].line_number(), Some(3));
+        assert_eq!(file.find("/target/"), None);
+        assert_eq!(file.find("target"), Some(2));
+    }
+
+    #[test]
+    fn test_edits_keep_other_lines() {
+        let mut file = IgnoreFile::parse("# Logs\r\n*.log   \r\n\r\n*.tmp");
+        file.insert(2, "*.out");
+        file.set(0, "# Logs and output");
+        assert_eq!(
+            file.render(),
+            "# Logs and output\r\n*.log   \r\n*.out\r\n\r\n*.tmp"
+        );
+        assert_eq!(file.entries()[2].line_number(), None);
+        assert_eq!(file.entries()[4].line_number(), Some(4));
+
+        file.push("build/");
+        assert_eq!(
+            file.render(),
+            "# Logs and output\r\n*.log   \r\n*.out\r\n\r\n*.tmp\r\nbuild/\r\n"
+        );
+
+        let removed = file.remove_where(|entry| entry.pattern() != Some("*.log"));
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].line_number(), Some(2));
+        file.remove(file.len() - 1);
+        assert_eq!(file.render(), "# Logs and output\r\n*.out\r\n\r\n*.tmp\r\n");
+    }
+
+    #[test]
+    fn test_remove_last_line_without_line_break() {
+        let mut file = IgnoreFile::parse("a\nb");
+        file.remove(1);
+        assert_eq!(file.render(), "a");
+    }
+}
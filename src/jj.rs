@@ -0,0 +1,115 @@
+//! Jujutsu (jj) workspace detection
+//!
+//! A jj repository that isn't colocated with git has no `.git` at its root:
+//! the workspace holds a `.jj` directory and the git objects live in a bare
+//! repository under `.jj/repo/store/git` (or wherever `store/git_target`
+//! points). jj still honours `.gitignore` files, the git repository's
+//! `info/exclude` and `core.excludesFile`, so git-ignore can edit them once
+//! git is told where the repository and the work tree are.
+//!
+//! Colocated repositories have a real `.git` next to `.jj` and need nothing
+//! special.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A jj workspace found above the current directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JjWorkspace {
+    /// Directory containing `.jj`, which git should use as the work tree
+    pub root: PathBuf,
+    /// The backing git repository, or `None` if the repository uses a
+    /// backend other than git
+    pub git_dir: Option<PathBuf>,
+}
+
+/// Find the jj workspace containing `start`. Stops at the first directory
+/// with a `.git`, where git's own discovery already works.
+pub fn find_workspace(start: &Path) -> Option<JjWorkspace> {
+    for dir in start.ancestors() {
+        if dir.join(".git").exists() {
+            return None;
+        }
+        let jj_dir = dir.join(".jj");
+        if jj_dir.is_dir() {
+            return Some(JjWorkspace {
+                root: dir.to_path_buf(),
+                git_dir: git_backend_dir(&jj_dir),
+            });
+        }
+    }
+    None
+}
+
+/// The git repository behind a `.jj` directory. Secondary workspaces
+/// (`jj workspace add`) replace `.jj/repo` with a file naming the shared
+/// repository directory.
+fn git_backend_dir(jj_dir: &Path) -> Option<PathBuf> {
+    let repo_link = jj_dir.join("repo");
+    let repo_dir = if repo_link.is_file() {
+        resolve_link(jj_dir, &fs::read_to_string(&repo_link).ok()?)
+    } else {
+        repo_link
+    };
+    let store = repo_dir.join("store");
+    let target = fs::read_to_string(store.join("git_target")).ok()?;
+    let git_dir = resolve_link(&store, &target);
+    git_dir.is_dir().then_some(git_dir)
+}
+
+/// Resolve a path stored in a jj metadata file relative to the directory
+/// holding the file
+fn resolve_link(base: &Path, contents: &str) -> PathBuf {
+    let target = Path::new(contents.trim_end_matches(['\n', '\r']));
+    if target.is_absolute() {
+        target.to_path_buf()
+    } else {
+        base.join(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_workspace() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        let store = root.join(".jj/repo/store");
+        fs::create_dir_all(store.join("git")).unwrap();
+        fs::write(store.join("git_target"), "git").unwrap();
+        fs::create_dir_all(root.join("src/deep")).unwrap();
+
+        let workspace = find_workspace(&root.join("src/deep")).unwrap();
+        assert_eq!(workspace.root, root);
+        assert_eq!(workspace.git_dir, Some(store.join("git")));
+
+        // A secondary workspace points at the shared repository
+        let secondary = root.join("src/deep/other");
+        fs::create_dir_all(secondary.join(".jj")).unwrap();
+        fs::write(
+            secondary.join(".jj/repo"),
+            root.join(".jj/repo").to_string_lossy().as_bytes(),
+        )
+        .unwrap();
+        let workspace = find_workspace(&secondary).unwrap();
+        assert_eq!(workspace.root, secondary);
+        assert_eq!(workspace.git_dir, Some(store.join("git")));
+
+        // Colocated repositories are found by git itself
+        fs::create_dir(root.join(".git")).unwrap();
+        assert_eq!(find_workspace(root), None);
+    }
+
+    #[test]
+    fn test_non_git_backend() {
+        let temp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join(".jj/repo/store")).unwrap();
+        let workspace = find_workspace(temp.path()).unwrap();
+        assert_eq!(workspace.git_dir, None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Minimal JSON serialization helpers for machine-readable output
+
+use std::fmt::Write;
+
+/// Quote and escape a string as a JSON string literal
+pub(crate) fn string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Encode already-encoded values as a JSON array
+pub(crate) fn array(items: impl IntoIterator<Item = String>) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+}
+
+/// Incrementally build a JSON object from already-encoded values
+#[derive(Debug, Default)]
+pub(crate) struct Object {
+    fields: Vec<(String, String)>,
+}
+
+impl Object {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field whose value is already valid JSON
+    pub(crate) fn raw(mut self, key: &str, value: String) -> Self {
+        self.fields.push((key.to_string(), value));
+        self
+    }
+
+    /// Add a string field
+    pub(crate) fn str(self, key: &str, value: &str) -> Self {
+        self.raw(key, string(value))
+    }
+
+    /// Add a numeric field
+    pub(crate) fn num(self, key: &str, value: usize) -> Self {
+        self.raw(key, value.to_string())
+    }
+
+    pub(crate) fn build(self) -> String {
+        let body: Vec<String> = self
+            .fields
+            .into_iter()
+            .map(|(key, value)| format!("{}:{}", string(&key), value))
+            .collect();
+        format!("{{{}}}", body.join(","))
+    }
+}
+
+/// Read the string field `key` of a top-level JSON object; fields of
+/// nested objects are ignored. Returns `None` if the field is missing or
+/// not a string.
+#[cfg(feature = "forge")]
+pub(crate) fn string_field(json: &str, key: &str) -> Option<String> {
+    let mut chars = json.chars().peekable();
+    let mut depth = 0usize;
+    let mut expect_key = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' => {
+                depth += 1;
+                expect_key = c == '{' && depth == 1;
+            }
+            '}' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 1 => expect_key = true,
+            '"' => {
+                let text = read_string(&mut chars)?;
+                if depth != 1 || !expect_key {
+                    continue;
+                }
+                expect_key = false;
+                if text != key {
+                    continue;
+                }
+                while chars.next_if(|c| c.is_whitespace() || *c == ':').is_some() {}
+                return match chars.next() {
+                    Some('"') => read_string(&mut chars),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Every string value of a field named `key`, in objects at any depth, in
+/// document order
+pub(crate) fn string_fields(json: &str, key: &str) -> Vec<String> {
+    let mut chars = json.chars().peekable();
+    // Whether each open container is an object rather than an array
+    let mut objects: Vec<bool> = Vec::new();
+    let mut expect_key = false;
+    let mut values = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' => {
+                objects.push(c == '{');
+                expect_key = c == '{';
+            }
+            '}' | ']' => {
+                objects.pop();
+                expect_key = false;
+            }
+            ',' => expect_key = objects.last() == Some(&true),
+            '"' => {
+                let Some(text) = read_string(&mut chars) else {
+                    break;
+                };
+                if !expect_key {
+                    continue;
+                }
+                expect_key = false;
+                if text != key {
+                    continue;
+                }
+                while chars.next_if(|c| c.is_whitespace() || *c == ':').is_some() {}
+                if chars.next_if_eq(&'"').is_some() {
+                    values.extend(read_string(&mut chars));
+                }
+            }
+            _ => {}
+        }
+    }
+    values
+}
+
+/// Decode the rest of a JSON string literal whose opening quote has been
+/// consumed
+fn read_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'b' => out.push('\u{8}'),
+                'f' => out.push('\u{c}'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16).ok()?;
+                    out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                escaped => out.push(escaped),
+            },
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_escaping() {
+        assert_eq!(string("plain"), "\"plain\"");
+        assert_eq!(string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(string("line\nbreak"), "\"line\\nbreak\"");
+        assert_eq!(string("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn test_object() {
+        let object = Object::new()
+            .str("name", "*.log")
+            .raw("fields", Object::new().str("path", "a\"b").build())
+            .build();
+        assert_eq!(object, r#"{"name":"*.log","fields":{"path":"a\"b"}}"#);
+
+        let list = array([string("a"), Object::new().num("n", 3).build()]);
+        assert_eq!(list, r#"["a",{"n":3}]"#);
+    }
+
+    #[test]
+    fn test_string_fields() {
+        let json = r#"{"sha": "x", "tree": [{"path": "Rust.gitignore", "mode": "100644"}, {"path":"Global/macOS.gitignore","tags":["path"]}], "path": "top"}"#;
+        assert_eq!(
+            string_fields(json, "path"),
+            ["Rust.gitignore", "Global/macOS.gitignore", "top"]
+        );
+        assert!(string_fields(json, "missing").is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "forge")]
+    fn test_string_field() {
+        let json = r#"{"user": {"html_url": "nested"}, "tags": ["a", "b"], "html_url" : "https://x/1", "n": 2, "q": "a\"b\u00e9"}"#;
+        assert_eq!(
+            string_field(json, "html_url").as_deref(),
+            Some("https://x/1")
+        );
+        assert_eq!(string_field(json, "q").as_deref(), Some("a\"bé"));
+        assert_eq!(string_field(json, "n"), None);
+        assert_eq!(string_field(json, "missing"), None);
+        assert_eq!(
+            string_field(r#"{"a": ["html_url", "x"]}"#, "html_url"),
+            None
+        );
+    }
+}
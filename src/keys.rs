@@ -0,0 +1,91 @@
+//! Key presses decoded from raw terminal input
+//!
+//! [`picker`](crate::picker) and [`tui`](crate::tui) both read the terminal
+//! in raw mode; [`decode_keys`] turns what was typed into [`Key`]s, which
+//! each of them maps to its own actions.
+
+/// A key press, decoded from raw terminal input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Tab,
+    /// Shift-Tab
+    BackTab,
+    Enter,
+    Backspace,
+    Delete,
+    Escape,
+    /// Ctrl-C
+    Interrupt,
+    Char(char),
+}
+
+/// Decode raw terminal input into keys. Unknown escape sequences and
+/// control characters are dropped.
+pub fn decode_keys(input: &[u8]) -> Vec<Key> {
+    let text = String::from_utf8_lossy(input);
+    let mut keys = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' if matches!(chars.peek(), Some('[') | Some('O')) => {
+                chars.next();
+                let mut sequence = String::new();
+                while let Some(&c) = chars.peek() {
+                    chars.next();
+                    if c.is_ascii_alphabetic() || c == '~' {
+                        sequence.push(c);
+                        break;
+                    }
+                    sequence.push(c);
+                }
+                match sequence.as_str() {
+                    "A" => Some(Key::Up),
+                    "B" => Some(Key::Down),
+                    "C" => Some(Key::Right),
+                    "D" => Some(Key::Left),
+                    "Z" => Some(Key::BackTab),
+                    "3~" => Some(Key::Delete),
+                    _ => None,
+                }
+            }
+            '\x1b' => Some(Key::Escape),
+            '\x03' => Some(Key::Interrupt),
+            '\t' => Some(Key::Tab),
+            '\r' | '\n' => Some(Key::Enter),
+            '\x7f' | '\x08' => Some(Key::Backspace),
+            c if c.is_control() => None,
+            c => Some(Key::Char(c)),
+        };
+        keys.extend(key);
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_keys() {
+        assert_eq!(
+            decode_keys(b"\x1b[A\x1b[3~a\x7f\x1b\t\x1b[Z\r\x03"),
+            vec![
+                Key::Up,
+                Key::Delete,
+                Key::Char('a'),
+                Key::Backspace,
+                Key::Escape,
+                Key::Tab,
+                Key::BackTab,
+                Key::Enter,
+                Key::Interrupt
+            ]
+        );
+        // Unknown sequences are dropped whole
+        assert_eq!(decode_keys(b"\x1b[15~x\x01"), vec![Key::Char('x')]);
+    }
+}
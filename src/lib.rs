@@ -15,21 +15,92 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
+pub mod affinity;
+pub mod attributes;
+pub mod audit;
+pub mod backend;
+pub mod backup;
+pub mod blame;
+pub mod commit;
+pub mod config;
+pub mod consolidate;
+pub mod decision;
+pub mod detect;
+pub mod diff;
+pub mod error;
+pub mod explain;
+#[cfg(feature = "forge")]
+pub mod forge;
+pub mod format;
 pub mod git;
+pub mod hook;
 pub mod ignore;
+pub mod ignore_file;
+pub mod jj;
+mod json;
+pub mod keys;
+pub mod lint;
+pub mod lock;
+pub mod manager;
+pub mod merge;
+pub mod negation;
+pub mod nested;
+pub mod observe;
+mod paths;
+pub mod pattern;
+pub mod picker;
+pub mod route;
+pub mod rules;
+pub mod sarif;
+pub mod sets;
+pub mod snapshot;
+pub mod sort;
+pub mod stats;
+pub mod store;
+pub mod subsume;
+pub mod templates;
+mod timestamp;
+pub mod tui;
+mod unified_diff;
+pub mod unignore;
+pub mod validation;
+pub mod walk;
+
+pub use decision::is_ignored;
+pub use error::Error;
+pub use git::RepoContext;
+pub use ignore::{AddPreview, MoveReport, RemovalReport, RemovedPattern, Transaction};
+pub use ignore_file::IgnoreFile;
+pub use manager::IgnoreManager;
+pub use observe::{Observer, SkipReason};
+pub use pattern::{pattern_from_path, Pattern};
+pub use templates::{apply_template, TemplateId};
 
 use anyhow::bail;
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
 
 /// Validate patterns for library usage (simpler than CLI validation)
 fn validate_patterns_for_library(
     patterns: &[String],
     validation_level: PatternValidationLevel,
+    rules: &validation::RuleSet,
 ) -> anyhow::Result<()> {
     if validation_level == PatternValidationLevel::None {
         return Ok(());
     }
 
-    let issues = ignore::validate_ignore_patterns(patterns);
+    let issues = ignore::validate_ignore_patterns_with(patterns, rules);
+    fail_on_issues(&issues, validation_level)
+}
+
+/// Fail with every issue that `validation_level` does not tolerate
+fn fail_on_issues(
+    issues: &[PatternIssue],
+    validation_level: PatternValidationLevel,
+) -> anyhow::Result<()> {
     let has_errors = issues.iter().any(|i| i.severity == PatternSeverity::Error);
     let has_warnings = issues
         .iter()
@@ -47,7 +118,7 @@ fn validate_patterns_for_library(
             .map(|issue| format!("{}: {}", issue.pattern, issue.message))
             .collect();
 
-        bail!("Pattern validation failed: {}", error_messages.join("; "));
+        bail!(Error::ValidationFailed(error_messages));
     }
 
     Ok(())
@@ -55,6 +126,8 @@ fn validate_patterns_for_library(
 
 /// Pattern validation severity levels
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum PatternSeverity {
     /// Informational message
     Info,
@@ -64,16 +137,177 @@ pub enum PatternSeverity {
     Error,
 }
 
+/// The check that produced a [`PatternIssue`] or lint finding.
+///
+/// Each rule has a stable [`id`](PatternRule::id) that reports and
+/// suppression lists can rely on across releases.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum PatternRule {
+    LineBreak,
+    SlashWrapped,
+    DotSlashPrefix,
+    MultipleDoubleStar,
+    BroadPattern,
+    ImportantFile,
+    ConflictMarker,
+    DuplicatePattern,
+    TrailingWhitespace,
+    NegatedAttributePattern,
+    MissingAttributes,
+    InvalidAttribute,
+    DirectoryAttributePattern,
+    NestedRepository,
+    BackslashSeparator,
+    InvalidSyntax,
+    IneffectiveNegation,
+    TrackedIgnored,
+    /// A rule the application supplied (see [`validation`]), by id
+    Custom(Cow<'static, str>),
+}
+
+impl PatternRule {
+    /// Every built-in rule, in id order
+    pub const ALL: [PatternRule; 18] = [
+        PatternRule::LineBreak,
+        PatternRule::SlashWrapped,
+        PatternRule::DotSlashPrefix,
+        PatternRule::MultipleDoubleStar,
+        PatternRule::BroadPattern,
+        PatternRule::ImportantFile,
+        PatternRule::ConflictMarker,
+        PatternRule::DuplicatePattern,
+        PatternRule::TrailingWhitespace,
+        PatternRule::NegatedAttributePattern,
+        PatternRule::MissingAttributes,
+        PatternRule::InvalidAttribute,
+        PatternRule::DirectoryAttributePattern,
+        PatternRule::NestedRepository,
+        PatternRule::BackslashSeparator,
+        PatternRule::InvalidSyntax,
+        PatternRule::IneffectiveNegation,
+        PatternRule::TrackedIgnored,
+    ];
+
+    /// Stable identifier, e.g. `GI004`
+    pub fn id(&self) -> &str {
+        match self {
+            PatternRule::LineBreak => "GI001",
+            PatternRule::SlashWrapped => "GI002",
+            PatternRule::DotSlashPrefix => "GI003",
+            PatternRule::MultipleDoubleStar => "GI004",
+            PatternRule::BroadPattern => "GI005",
+            PatternRule::ImportantFile => "GI006",
+            PatternRule::ConflictMarker => "GI007",
+            PatternRule::DuplicatePattern => "GI008",
+            PatternRule::TrailingWhitespace => "GI009",
+            PatternRule::NegatedAttributePattern => "GI010",
+            PatternRule::MissingAttributes => "GI011",
+            PatternRule::InvalidAttribute => "GI012",
+            PatternRule::DirectoryAttributePattern => "GI013",
+            PatternRule::NestedRepository => "GI014",
+            PatternRule::BackslashSeparator => "GI015",
+            PatternRule::InvalidSyntax => "GI016",
+            PatternRule::IneffectiveNegation => "GI017",
+            PatternRule::TrackedIgnored => "GI018",
+            PatternRule::Custom(id) => id,
+        }
+    }
+
+    /// Short kebab-case name; a custom rule's id, since only its
+    /// [`RuleSet`](validation::RuleSet) knows its name
+    pub fn name(&self) -> &str {
+        match self {
+            PatternRule::LineBreak => "line-break",
+            PatternRule::SlashWrapped => "slash-wrapped",
+            PatternRule::DotSlashPrefix => "dot-slash-prefix",
+            PatternRule::MultipleDoubleStar => "multiple-double-star",
+            PatternRule::BroadPattern => "broad-pattern",
+            PatternRule::ImportantFile => "important-file",
+            PatternRule::ConflictMarker => "conflict-marker",
+            PatternRule::DuplicatePattern => "duplicate-pattern",
+            PatternRule::TrailingWhitespace => "trailing-whitespace",
+            PatternRule::NegatedAttributePattern => "negated-attribute-pattern",
+            PatternRule::MissingAttributes => "missing-attributes",
+            PatternRule::InvalidAttribute => "invalid-attribute",
+            PatternRule::DirectoryAttributePattern => "directory-attribute-pattern",
+            PatternRule::NestedRepository => "nested-repository",
+            PatternRule::BackslashSeparator => "backslash-separator",
+            PatternRule::InvalidSyntax => "invalid-syntax",
+            PatternRule::IneffectiveNegation => "ineffective-negation",
+            PatternRule::TrackedIgnored => "tracked-ignored",
+            PatternRule::Custom(id) => id,
+        }
+    }
+
+    /// One-sentence description of what the rule checks
+    pub fn description(&self) -> &str {
+        match self {
+            PatternRule::LineBreak => "Patterns must not contain line breaks",
+            PatternRule::SlashWrapped => {
+                "Patterns with both a leading and a trailing slash only match one directory"
+            }
+            PatternRule::DotSlashPrefix => "A leading './' is redundant",
+            PatternRule::MultipleDoubleStar => {
+                "Several '**' in one pattern are hard to reason about"
+            }
+            PatternRule::BroadPattern => "Patterns such as '*' ignore almost everything",
+            PatternRule::ImportantFile => "Patterns that ignore files every project needs",
+            PatternRule::ConflictMarker => "Unresolved merge conflict markers",
+            PatternRule::DuplicatePattern => "The same pattern appears more than once in a file",
+            PatternRule::TrailingWhitespace => {
+                "Unescaped trailing spaces are silently dropped by git"
+            }
+            PatternRule::NegatedAttributePattern => {
+                "Negative patterns are not allowed in gitattributes files"
+            }
+            PatternRule::MissingAttributes => "Attributes lines need at least one attribute",
+            PatternRule::InvalidAttribute => "Attribute names and assignments must be well formed",
+            PatternRule::DirectoryAttributePattern => {
+                "Patterns ending in '/' never match files in gitattributes"
+            }
+            PatternRule::NestedRepository => {
+                "Patterns that ignore a nested git repository or submodule"
+            }
+            PatternRule::BackslashSeparator => {
+                "A backslash escapes the next character; git separates paths with '/'"
+            }
+            PatternRule::InvalidSyntax => {
+                "Patterns whose glob syntax git can't match anything with"
+            }
+            PatternRule::IneffectiveNegation => {
+                "Negations that a later pattern or an excluded parent directory defeats"
+            }
+            PatternRule::TrackedIgnored => {
+                "Patterns matching files that are already tracked, which git keeps tracking"
+            }
+            PatternRule::Custom(_) => "Rule supplied by the application",
+        }
+    }
+}
+
+impl std::fmt::Display for PatternRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
 /// A pattern validation issue
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PatternIssue {
     pub pattern: String,
     pub severity: PatternSeverity,
     pub message: String,
+    /// The check that raised the issue
+    pub rule: PatternRule,
 }
 
 /// Pattern validation level
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum PatternValidationLevel {
     /// Skip all validation
     None,
@@ -83,17 +317,57 @@ pub enum PatternValidationLevel {
     Strict,
 }
 
+/// Which ignore file an operation targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Scope {
+    /// The repository's `.gitignore`, shared with everyone
+    Repo,
+    /// The repository's `.git/info/exclude`, private to this clone
+    Local,
+    /// The user's global gitignore (`core.excludesFile`)
+    Global,
+    /// The machine-wide excludes file (`core.excludesFile` in the system
+    /// git config), read only while the global config doesn't set one
+    System,
+}
+
+impl std::str::FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "repo" | "gitignore" => Ok(Scope::Repo),
+            "local" | "exclude" => Ok(Scope::Local),
+            "global" => Ok(Scope::Global),
+            "system" => Ok(Scope::System),
+            other => bail!("Unknown scope '{other}' (expected repo, local, global or system)"),
+        }
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Scope::Repo => "repo",
+            Scope::Local => "local",
+            Scope::Global => "global",
+            Scope::System => "system",
+        })
+    }
+}
+
 /// Add patterns to repository .gitignore file
 pub fn add_patterns_to_gitignore(
     patterns: &[String],
     validation_level: PatternValidationLevel,
 ) -> anyhow::Result<Vec<String>> {
-    validate_patterns_for_library(patterns, validation_level)?;
-    let gitignore_path = git::get_gitignore_path()?;
-    ignore::add_patterns_to_ignore_file(
-        &gitignore_path,
+    validate_patterns_for_library(patterns, validation_level, &validation::RuleSet::default())?;
+    add_patterns_in(
+        RepoContext::current()?,
+        Scope::Repo,
         patterns,
-        true,
         PatternValidationLevel::None,
     )
 }
@@ -103,10 +377,30 @@ pub fn add_patterns_to_exclude(
     patterns: &[String],
     validation_level: PatternValidationLevel,
 ) -> anyhow::Result<Vec<String>> {
-    validate_patterns_for_library(patterns, validation_level)?;
-    let exclude_path = git::get_exclude_file_path()?;
-    ignore::ensure_info_exclude_exists(&exclude_path)?;
-    ignore::add_patterns_to_ignore_file(&exclude_path, patterns, true, PatternValidationLevel::None)
+    validate_patterns_for_library(patterns, validation_level, &validation::RuleSet::default())?;
+    add_patterns_in(
+        RepoContext::current()?,
+        Scope::Local,
+        patterns,
+        PatternValidationLevel::None,
+    )
+}
+
+/// Add patterns to the file for `scope` of `repo`, which needn't be the
+/// repository around the current directory. Behaves like
+/// [`add_patterns_to_gitignore`] and its siblings otherwise.
+pub fn add_patterns_in(
+    repo: &RepoContext,
+    scope: Scope,
+    patterns: &[String],
+    validation_level: PatternValidationLevel,
+) -> anyhow::Result<Vec<String>> {
+    validate_patterns_for_library(patterns, validation_level, &validation::RuleSet::default())?;
+    let path = scope_file_in(Some(repo), scope)?;
+    if scope == Scope::Local {
+        ignore::ensure_info_exclude_exists(&path)?;
+    }
+    ignore::add_patterns_to_ignore_file(&path, patterns, true, PatternValidationLevel::None)
 }
 
 /// Add patterns to global gitignore file
@@ -114,8 +408,242 @@ pub fn add_patterns_to_global(
     patterns: &[String],
     validation_level: PatternValidationLevel,
 ) -> anyhow::Result<Vec<String>> {
-    validate_patterns_for_library(patterns, validation_level)?;
-    let global_path = git::get_global_gitignore_path()
-        .ok_or_else(|| anyhow::anyhow!("No global gitignore file configured"))?;
+    validate_patterns_for_library(patterns, validation_level, &validation::RuleSet::default())?;
+    let global_path = existing_global_gitignore()?;
     ignore::add_patterns_to_ignore_file(&global_path, patterns, true, PatternValidationLevel::None)
 }
+
+/// The existing global gitignore, reporting a `core.excludesFile` value
+/// that cannot be expanded or names a missing file rather than treating it
+/// as unset
+fn existing_global_gitignore() -> anyhow::Result<PathBuf> {
+    if let Some(configured) = git::get_configured_global_excludes_file()? {
+        if !configured.exists() {
+            bail!(Error::NoGlobalGitignore(format!(
+                "at {}: core.excludesFile points to a file that does not exist",
+                configured.display()
+            )));
+        }
+    }
+    git::get_global_gitignore_path()
+        .ok_or_else(|| Error::NoGlobalGitignore("file configured".to_string()).into())
+}
+
+/// Set up the global gitignore: pick a location (or use `path`), point
+/// `core.excludesFile` at it and create the file with a header if needed.
+///
+/// Without `path`, an already-configured global gitignore is kept and
+/// otherwise git's default location (`$XDG_CONFIG_HOME/git/ignore` or
+/// `~/.config/git/ignore`) is used. `~` and environment variables in
+/// `path` are expanded as in `core.excludesFile`. A `path` other than the
+/// one `core.excludesFile` already names is refused with
+/// [`Error::Config`], since repointing the setting would drop the rules of
+/// the file in use. The setting is only written when it is unset, so a
+/// configured value keeps its own spelling. Returns the resolved absolute
+/// path.
+pub fn configure_global_gitignore(path: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let configured = git::get_configured_global_excludes_file()?;
+    let global_path = match path {
+        Some(path) => {
+            let path = git::current_dir()?.join(paths::expand_path(&path.to_string_lossy())?);
+            if let Some(configured) = configured
+                .as_ref()
+                .filter(|configured| !same_file(configured, &path))
+            {
+                bail!(Error::config(format!(
+                    "core.excludesFile already points to {}; not repointing it to {}",
+                    configured.display(),
+                    path.display()
+                )));
+            }
+            path
+        }
+        None => configured
+            .clone()
+            .or_else(git::get_global_gitignore_path)
+            .or_else(git::default_global_gitignore_path)
+            .ok_or_else(|| Error::NoGlobalGitignore("location (HOME is not set)".to_string()))?,
+    };
+
+    ignore::ensure_global_gitignore_exists(&global_path)?;
+    if configured.is_none() {
+        git::set_global_config_value("core.excludesFile", &global_path.to_string_lossy())?;
+    }
+    Ok(global_path)
+}
+
+/// Whether `a` and `b` name the same file, through symlinks and `..` when
+/// both exist
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (paths::canonicalize(a), paths::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Remove patterns from repository .gitignore file
+pub fn remove_patterns_from_gitignore(patterns: &[String]) -> anyhow::Result<RemovalReport> {
+    remove_patterns_in(RepoContext::current()?, Scope::Repo, patterns)
+}
+
+/// Remove patterns from local .git/info/exclude file
+pub fn remove_patterns_from_exclude(patterns: &[String]) -> anyhow::Result<RemovalReport> {
+    remove_patterns_in(RepoContext::current()?, Scope::Local, patterns)
+}
+
+/// Remove patterns from the file for `scope` of `repo`, like
+/// [`remove_patterns_from_gitignore`] and its siblings
+pub fn remove_patterns_in(
+    repo: &RepoContext,
+    scope: Scope,
+    patterns: &[String],
+) -> anyhow::Result<RemovalReport> {
+    let path = scope_file_in(Some(repo), scope)?;
+    ignore::remove_patterns_from_ignore_file(&path, patterns)
+}
+
+/// Remove patterns from global gitignore file
+pub fn remove_patterns_from_global(patterns: &[String]) -> anyhow::Result<RemovalReport> {
+    let global_path = existing_global_gitignore()?;
+    ignore::remove_patterns_from_ignore_file(&global_path, patterns)
+}
+
+/// Remove patterns from an arbitrary ignore file
+pub fn remove_patterns_from_file(
+    file_path: &Path,
+    patterns: &[String],
+) -> anyhow::Result<RemovalReport> {
+    ignore::remove_patterns_from_ignore_file(file_path, patterns)
+}
+
+/// The file a scope's patterns live in for `repo`, or for the repository
+/// around the current directory without one. The global and system files
+/// are the same whichever repository asks, and need none.
+fn scope_file_in(repo: Option<&RepoContext>, scope: Scope) -> anyhow::Result<PathBuf> {
+    let repo = || match repo {
+        Some(repo) => Ok(repo),
+        None => RepoContext::current(),
+    };
+    match scope {
+        Scope::Repo => repo()?.gitignore_path(),
+        Scope::Local => Ok(repo()?.exclude_file_path()),
+        Scope::Global => existing_global_gitignore(),
+        Scope::System => git::get_system_gitignore_path(),
+    }
+}
+
+/// Add patterns to the file for `scope`, reporting each step to `observer`.
+///
+/// Behaves like [`add_patterns_to_gitignore`], [`add_patterns_to_exclude`]
+/// and [`add_patterns_to_global`]. Every pattern is reported to
+/// [`Observer::pattern_validated`] before anything is written, unless
+/// `validation_level` is [`PatternValidationLevel::None`].
+pub fn add_patterns_observed(
+    scope: Scope,
+    patterns: &[String],
+    validation_level: PatternValidationLevel,
+    observer: &mut dyn Observer,
+) -> anyhow::Result<Vec<String>> {
+    add_observed(None, scope, patterns, validation_level, observer)
+}
+
+/// [`add_patterns_observed`] for the file of `scope` in `repo`
+pub fn add_patterns_observed_in(
+    repo: &RepoContext,
+    scope: Scope,
+    patterns: &[String],
+    validation_level: PatternValidationLevel,
+    observer: &mut dyn Observer,
+) -> anyhow::Result<Vec<String>> {
+    add_observed(Some(repo), scope, patterns, validation_level, observer)
+}
+
+fn add_observed(
+    repo: Option<&RepoContext>,
+    scope: Scope,
+    patterns: &[String],
+    validation_level: PatternValidationLevel,
+    observer: &mut dyn Observer,
+) -> anyhow::Result<Vec<String>> {
+    if validation_level != PatternValidationLevel::None {
+        let mut issues = Vec::new();
+        for pattern in patterns {
+            let found = ignore::validate_ignore_patterns(std::slice::from_ref(pattern));
+            observer.pattern_validated(pattern, &found);
+            issues.extend(found);
+        }
+        fail_on_issues(&issues, validation_level)?;
+    }
+
+    let path = scope_file_in(repo, scope)?;
+    observer.file_resolved(scope, &path);
+    if scope == Scope::Local {
+        ignore::ensure_info_exclude_exists(&path)?;
+    }
+    ignore::add_patterns_observed(&path, patterns, true, ignore::Placement::Append, observer)
+}
+
+/// Work out what adding `patterns` to the file for `scope` would do, without
+/// writing anything: the patterns that would be added after sanitizing and
+/// duplicate removal, the ones skipped, and the file content before and
+/// after. Validation fails as it would for the real addition.
+pub fn preview_add_patterns(
+    scope: Scope,
+    patterns: &[String],
+    validation_level: PatternValidationLevel,
+) -> anyhow::Result<AddPreview> {
+    preview_add(None, scope, patterns, validation_level)
+}
+
+/// [`preview_add_patterns`] for the file of `scope` in `repo`
+pub fn preview_add_patterns_in(
+    repo: &RepoContext,
+    scope: Scope,
+    patterns: &[String],
+    validation_level: PatternValidationLevel,
+) -> anyhow::Result<AddPreview> {
+    preview_add(Some(repo), scope, patterns, validation_level)
+}
+
+fn preview_add(
+    repo: Option<&RepoContext>,
+    scope: Scope,
+    patterns: &[String],
+    validation_level: PatternValidationLevel,
+) -> anyhow::Result<AddPreview> {
+    validate_patterns_for_library(patterns, validation_level, &validation::RuleSet::default())?;
+    let path = scope_file_in(repo, scope)?;
+    ignore::preview_add_patterns(&path, patterns, true, ignore::Placement::Append)
+}
+
+/// Remove patterns from the file for `scope`, reporting each step to
+/// `observer`. Behaves like [`remove_patterns_from_gitignore`] and its
+/// siblings.
+pub fn remove_patterns_observed(
+    scope: Scope,
+    patterns: &[String],
+    observer: &mut dyn Observer,
+) -> anyhow::Result<RemovalReport> {
+    remove_observed(None, scope, patterns, observer)
+}
+
+/// [`remove_patterns_observed`] for the file of `scope` in `repo`
+pub fn remove_patterns_observed_in(
+    repo: &RepoContext,
+    scope: Scope,
+    patterns: &[String],
+    observer: &mut dyn Observer,
+) -> anyhow::Result<RemovalReport> {
+    remove_observed(Some(repo), scope, patterns, observer)
+}
+
+fn remove_observed(
+    repo: Option<&RepoContext>,
+    scope: Scope,
+    patterns: &[String],
+    observer: &mut dyn Observer,
+) -> anyhow::Result<RemovalReport> {
+    let path = scope_file_in(repo, scope)?;
+    observer.file_resolved(scope, &path);
+    ignore::remove_patterns_observed(&path, patterns, observer)
+}
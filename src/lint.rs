@@ -0,0 +1,368 @@
+//! Line-by-line checks of existing ignore files
+//!
+//! Unlike [`validate_ignore_patterns`], which looks at patterns about to be
+//! added, linting reads whole files and reports every finding with the file
+//! and line it was found on, so editors and code scanning dashboards can
+//! point at it. Gitattributes files get the same checks with their own
+//! line syntax.
+//!
+//! [`validate_ignore_patterns`]: crate::ignore::validate_ignore_patterns
+
+use crate::{
+    attributes::{normalize_attribute_line, validate_attribute_lines},
+    ignore::{
+        ensure_no_conflict_markers, find_conflict_markers, forward_slashes, read_optional,
+        syntax_issue, validate_ignore_patterns_with, write_ignore_file_if_unchanged,
+    },
+    ignore_file,
+    pattern::{syntax_problem, trim_unescaped_trailing_spaces},
+    validation::RuleSet,
+    PatternIssue, PatternRule, PatternSeverity,
+};
+use anyhow::Context;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tracing::{debug_span, info};
+
+/// A problem found in an ignore file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LintFinding {
+    pub rule: PatternRule,
+    pub severity: PatternSeverity,
+    pub message: String,
+    /// File the finding is in
+    pub file: PathBuf,
+    /// 1-based line number in `file`
+    pub line: usize,
+    /// The offending line, without its line ending
+    pub text: String,
+    /// How to fix it, when there is an obvious way
+    pub suggestion: Option<String>,
+}
+
+/// The line syntax a linted file uses
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Ignore,
+    Attributes,
+}
+
+/// Lint one ignore file; a missing file has no findings
+pub fn lint_file(file: &Path) -> anyhow::Result<Vec<LintFinding>> {
+    lint_file_with(file, &RuleSet::default())
+}
+
+/// [`lint_file`], also running the application's `rules` on each pattern
+pub fn lint_file_with(file: &Path, rules: &RuleSet) -> anyhow::Result<Vec<LintFinding>> {
+    lint_path(file, Syntax::Ignore, rules)
+}
+
+/// Lint one gitattributes file; a missing file has no findings
+pub fn lint_attributes_file(file: &Path) -> anyhow::Result<Vec<LintFinding>> {
+    lint_path(file, Syntax::Attributes, &RuleSet::default())
+}
+
+fn lint_path(file: &Path, syntax: Syntax, rules: &RuleSet) -> anyhow::Result<Vec<LintFinding>> {
+    match std::fs::read(file) {
+        Ok(bytes) => Ok(lint_lines(
+            file,
+            &String::from_utf8_lossy(&bytes),
+            syntax,
+            rules,
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read: {}", file.display())),
+    }
+}
+
+/// Lint the content of an ignore file, attributing findings to `file`
+pub fn lint_content(file: &Path, content: &str) -> Vec<LintFinding> {
+    lint_content_with(file, content, &RuleSet::default())
+}
+
+/// [`lint_content`], also running the application's `rules` on each
+/// pattern
+pub fn lint_content_with(file: &Path, content: &str, rules: &RuleSet) -> Vec<LintFinding> {
+    lint_lines(file, content, Syntax::Ignore, rules)
+}
+
+/// Lint the content of a gitattributes file, attributing findings to `file`
+pub fn lint_attributes_content(file: &Path, content: &str) -> Vec<LintFinding> {
+    lint_lines(file, content, Syntax::Attributes, &RuleSet::default())
+}
+
+fn lint_lines(file: &Path, content: &str, syntax: Syntax, rules: &RuleSet) -> Vec<LintFinding> {
+    let conflict_lines = find_conflict_markers(content);
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut findings = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim_end_matches('\r');
+        let mut finding = |rule: PatternRule, severity: PatternSeverity, message: String| {
+            findings.push(LintFinding {
+                suggestion: suggestion(&rule, line),
+                rule,
+                severity,
+                message,
+                file: file.to_path_buf(),
+                line: line_number,
+                text: line.to_string(),
+            });
+        };
+
+        if conflict_lines.contains(&line_number) {
+            finding(
+                PatternRule::ConflictMarker,
+                PatternSeverity::Error,
+                "Merge conflict marker; resolve the conflict".to_string(),
+            );
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || ignore_file::is_comment(line) {
+            continue;
+        }
+
+        // Trailing spaces only matter where they could be part of a pattern
+        if syntax == Syntax::Ignore && has_unescaped_trailing_spaces(line) {
+            finding(
+                PatternRule::TrailingWhitespace,
+                PatternSeverity::Warning,
+                "Trailing spaces are ignored by git; escape them with '\\' if it is intended"
+                    .to_string(),
+            );
+        }
+
+        let key = match syntax {
+            Syntax::Ignore => trimmed.to_string(),
+            Syntax::Attributes => normalize_attribute_line(trimmed),
+        };
+        match first_seen.get(&key) {
+            Some(first) => finding(
+                PatternRule::DuplicatePattern,
+                PatternSeverity::Info,
+                format!("Duplicate of line {first}"),
+            ),
+            None => {
+                first_seen.insert(key, line_number);
+            }
+        }
+
+        let issues: Vec<PatternIssue> = match syntax {
+            Syntax::Ignore => {
+                // Validation sees the pattern trimmed as it would be added;
+                // the file keeps escaped trailing spaces
+                let mut issues = validate_ignore_patterns_with(&[line.to_string()], rules);
+                issues.retain(|issue| issue.rule != PatternRule::InvalidSyntax);
+                issues.extend(syntax_issue(line.trim_start()));
+                issues
+            }
+            Syntax::Attributes => validate_attribute_lines(&[line.to_string()]),
+        };
+        for issue in issues {
+            finding(issue.rule, issue.severity, issue.message);
+        }
+    }
+
+    findings
+}
+
+/// What to do about a finding of `rule` on `line`
+fn suggestion(rule: &PatternRule, line: &str) -> Option<String> {
+    if *rule == PatternRule::DuplicatePattern {
+        return Some("remove this line".to_string());
+    }
+    corrected_line(rule, line).map(|corrected| format!("write '{corrected}' instead"))
+}
+
+/// Rules [`fix_content`] corrects, in the order the fixes are applied
+pub const FIXABLE_RULES: [PatternRule; 4] = [
+    PatternRule::TrailingWhitespace,
+    PatternRule::DotSlashPrefix,
+    PatternRule::BackslashSeparator,
+    PatternRule::MultipleDoubleStar,
+];
+
+/// `line` with the problem `rule` reports corrected, where that can be
+/// done without changing what the line is meant to match
+fn corrected_line(rule: &PatternRule, line: &str) -> Option<String> {
+    let corrected = match rule {
+        PatternRule::TrailingWhitespace if has_unescaped_trailing_spaces(line) => {
+            trim_unescaped_trailing_spaces(line).to_string()
+        }
+        // `./build` matches nothing; the intent is the top-level `build`
+        PatternRule::DotSlashPrefix => format!("/{}", line.trim_start().strip_prefix("./")?),
+        PatternRule::BackslashSeparator => forward_slashes(line)?,
+        PatternRule::MultipleDoubleStar => {
+            let mut collapsed = line.to_string();
+            while collapsed.contains("**/**") {
+                collapsed = collapsed.replace("**/**", "**");
+            }
+            collapsed
+        }
+        _ => return None,
+    };
+    (corrected != line).then_some(corrected)
+}
+
+/// Apply every fix of [`FIXABLE_RULES`] to the pattern lines of ignore
+/// file content, returning the new content and the 1-based numbers of the
+/// lines that changed. Comments, blank lines and conflict markers are left
+/// alone, as are line endings, and a line keeps its text if the fixes would
+/// leave it with broken glob syntax.
+pub fn fix_content(content: &str) -> (String, Vec<usize>) {
+    let conflict_lines = find_conflict_markers(content);
+    let mut fixed = String::with_capacity(content.len());
+    let mut changed = Vec::new();
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let text = line.trim_end_matches(['\r', '\n']);
+        let ending = &line[text.len()..];
+        let trimmed = text.trim();
+        if trimmed.is_empty()
+            || ignore_file::is_comment(text)
+            || conflict_lines.contains(&(index + 1))
+        {
+            fixed.push_str(line);
+            continue;
+        }
+        let corrected = FIXABLE_RULES.iter().fold(text.to_string(), |text, rule| {
+            corrected_line(rule, &text).unwrap_or(text)
+        });
+        // A fix must never leave a line git can't match
+        let corrected = if syntax_problem(&corrected).is_some() {
+            text.to_string()
+        } else {
+            corrected
+        };
+        if corrected != text {
+            changed.push(index + 1);
+        }
+        fixed.push_str(&corrected);
+        fixed.push_str(ending);
+    }
+    (fixed, changed)
+}
+
+/// Fix an ignore file in place (see [`fix_content`]), returning the
+/// numbers of the lines that changed. A missing file has nothing to fix.
+pub fn fix_file(file: &Path) -> anyhow::Result<Vec<usize>> {
+    let _span = debug_span!("fix_file", file = %file.display()).entered();
+    let Some(content) = read_optional(file)? else {
+        return Ok(Vec::new());
+    };
+    ensure_no_conflict_markers(file, &content)?;
+    let (fixed, changed) = fix_content(&content);
+    if !changed.is_empty() {
+        write_ignore_file_if_unchanged(file, Some(&content), &fixed)?;
+        info!(file = %file.display(), count = changed.len(), "lint fixes applied");
+    }
+    Ok(changed)
+}
+
+/// Whether git would strip whitespace from the end of `line`
+fn has_unescaped_trailing_spaces(line: &str) -> bool {
+    trim_unescaped_trailing_spaces(line).len() < line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `line:id` of each finding
+    fn rules(content: &str) -> Vec<String> {
+        lint_content(Path::new(".gitignore"), content)
+            .iter()
+            .map(|finding| format!("{}:{}", finding.line, finding.rule.id()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_reports_line_numbers() {
+        assert_eq!(
+            rules("# comment\n*.log\n\n./build\n*.log\n*\n"),
+            vec!["4:GI003", "5:GI008", "6:GI005"]
+        );
+        assert!(rules("target/\n!keep.log\n").is_empty());
+    }
+
+    #[test]
+    fn test_lint_conflict_markers() {
+        let content = "a\n<<<<<<< HEAD\nb\n=======\nc\n>>>>>>> branch\n";
+        assert_eq!(rules(content), vec!["2:GI007", "4:GI007", "6:GI007"]);
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        assert!(has_unescaped_trailing_spaces("foo "));
+        assert!(has_unescaped_trailing_spaces("foo\\\\ "));
+        assert!(!has_unescaped_trailing_spaces("foo\\ "));
+        assert!(!has_unescaped_trailing_spaces("foo"));
+        assert_eq!(rules("*.o \r\n"), vec!["1:GI009"]);
+        // Git keeps trailing tabs
+        assert!(!has_unescaped_trailing_spaces("foo\t"));
+        assert!(rules("*.o\t\n").is_empty());
+    }
+
+    #[test]
+    fn test_lint_suggestions() {
+        let findings = lint_content(
+            Path::new(".gitignore"),
+            "./build\na/**/**/b\n*.o  \n*.o\n*\n",
+        );
+        let suggestions: Vec<(&str, Option<String>)> = findings
+            .iter()
+            .map(|finding| (finding.rule.id(), finding.suggestion.clone()))
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                ("GI003", Some("write '/build' instead".to_string())),
+                ("GI004", Some("write 'a/**/b' instead".to_string())),
+                ("GI009", Some("write '*.o' instead".to_string())),
+                ("GI008", Some("remove this line".to_string())),
+                ("GI005", None),
+            ]
+        );
+        // Double stars that aren't adjacent have no mechanical fix
+        assert_eq!(
+            corrected_line(&PatternRule::MultipleDoubleStar, "a/**/b/**"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fix_content() {
+        let content = "# ./notes \r\n./build\r\nbin\\Debug/**/**/*.pdb \r\n*.tmp\\ \r\n*.o\r\n";
+        let (fixed, changed) = fix_content(content);
+        assert_eq!(
+            fixed,
+            "# ./notes \r\n/build\r\nbin/Debug/**/*.pdb\r\n*.tmp\\ \r\n*.o\r\n"
+        );
+        assert_eq!(changed, vec![2, 3]);
+        assert!(rules(&fixed).is_empty());
+        assert_eq!(fix_content(&fixed).1, Vec::<usize>::new());
+        assert_eq!(rules("*.[ch\n*.tmp\\\n"), ["1:GI016", "2:GI016"]);
+
+        // Escapes in a bracket expression are not Windows separators
+        let content = "file[\\-_]name.txt\n";
+        assert_eq!(fix_content(content), (content.to_string(), Vec::new()));
+        assert!(rules(content).is_empty());
+    }
+
+    #[test]
+    fn test_lint_attributes() {
+        let findings: Vec<String> = lint_attributes_content(
+            Path::new(".gitattributes"),
+            "* text=auto\n*.png binary \n!*.md text\n*.png  binary\n*.sh\n",
+        )
+        .iter()
+        .map(|finding| format!("{}:{}", finding.line, finding.rule.id()))
+        .collect();
+        assert_eq!(findings, vec!["3:GI010", "4:GI008", "5:GI011"]);
+    }
+}
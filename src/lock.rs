@@ -0,0 +1,395 @@
+//! Advisory locks serializing edits of the same ignore file
+//!
+//! Every write in this crate holds the lock of the file it replaces, and
+//! the read-modify-write edits (adding and removing patterns, rewriting
+//! template blocks, sorting, formatting, resolving conflicts) hold it from
+//! the read to the write. Edits of one file from several threads therefore
+//! run one after the other instead of retrying against each other.
+//!
+//! Threads of one process wait on each other directly. Between processes
+//! the lock is a file created exclusively the way git takes its own
+//! `.lock` files: `<git dir>/git-ignore/locks/<path in work tree>.lock` for
+//! a file in a work tree, and `.<name>.git-ignore.lock` next to any other
+//! file. It holds the id of the process that took it, so a lock left by a
+//! process that died is broken rather than waited on. A lock held longer
+//! than [`LOCK_TIMEOUT`] by a running process fails the edit with an
+//! [`Error::Io`](crate::Error::Io) naming the lock file. The locks are
+//! advisory: other tools editing the file are still caught by the
+//! compare-and-swap every write does.
+//!
+//! A thread may take a lock it already holds; it is released when the
+//! outermost [`FileLock`] is dropped. Hosts can hold the lock around their
+//! own edits with [`lock`].
+
+use crate::{error::Error, git, paths};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Condvar, Mutex, MutexGuard},
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+
+/// How long to wait for another process to release a file
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause between attempts to take a lock file
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Files locked by threads of this process
+static HELD: Mutex<Vec<Held>> = Mutex::new(Vec::new());
+
+/// Signalled whenever an entry leaves [`HELD`]
+static RELEASED: Condvar = Condvar::new();
+
+struct Held {
+    key: PathBuf,
+    owner: ThreadId,
+    depth: usize,
+    /// The lock file created for other processes, if one could be
+    lock_file: Option<PathBuf>,
+}
+
+/// A held lock on one file, released on drop. It stays on the thread that
+/// took it.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as it is dropped"]
+pub struct FileLock {
+    key: PathBuf,
+    _not_send: PhantomData<*const ()>,
+}
+
+fn held() -> MutexGuard<'static, Vec<Held>> {
+    HELD.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Lock `path` for editing, waiting while another thread or process holds
+/// it. `path` need not exist yet.
+pub fn lock(path: &Path) -> anyhow::Result<FileLock> {
+    let key = lock_key(path);
+    let me = thread::current().id();
+    let mut entries = held();
+    loop {
+        match entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) if entry.owner == me => {
+                entry.depth += 1;
+                return Ok(FileLock::new(key));
+            }
+            Some(_) => {
+                entries = RELEASED
+                    .wait(entries)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            None => break,
+        }
+    }
+    // Claim the file in this process first, so other threads wait on the
+    // condition variable rather than polling the lock file
+    entries.push(Held {
+        key: key.clone(),
+        owner: me,
+        depth: 1,
+        lock_file: None,
+    });
+    drop(entries);
+
+    let guard = FileLock::new(key);
+    let lock_file = take_lock_file(&guard.key)?;
+    if let Some(entry) = held().iter_mut().find(|entry| entry.key == guard.key) {
+        entry.lock_file = lock_file;
+    }
+    Ok(guard)
+}
+
+/// Lock every file in `paths`, in a fixed order so that two callers locking
+/// overlapping sets can't deadlock
+pub fn lock_all<'a>(paths: impl IntoIterator<Item = &'a Path>) -> anyhow::Result<Vec<FileLock>> {
+    let mut keys: Vec<PathBuf> = paths.into_iter().map(lock_key).collect();
+    keys.sort();
+    keys.dedup();
+    keys.iter().map(|key| lock(key)).collect()
+}
+
+impl FileLock {
+    fn new(key: PathBuf) -> FileLock {
+        FileLock {
+            key,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// The file this lock is for, absolute with symlinks resolved
+    pub fn path(&self) -> &Path {
+        &self.key
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let mut entries = held();
+        let Some(index) = entries.iter().position(|entry| entry.key == self.key) else {
+            return;
+        };
+        entries[index].depth -= 1;
+        if entries[index].depth == 0 {
+            let entry = entries.remove(index);
+            if let Some(lock_file) = entry.lock_file {
+                let _ = fs::remove_file(lock_file);
+            }
+            RELEASED.notify_all();
+        }
+    }
+}
+
+/// The path a lock on `path` is kept under: absolute, with the parent
+/// directory and a symlinked file resolved, so every spelling of one file
+/// shares a lock
+fn lock_key(path: &Path) -> PathBuf {
+    if path.is_symlink() {
+        if let Ok(target) = paths::canonicalize(path) {
+            return target;
+        }
+    }
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        match git::current_dir() {
+            Ok(dir) => dir.join(path),
+            Err(_) => path.to_path_buf(),
+        }
+    };
+    match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => match paths::canonicalize(parent) {
+            Ok(parent) => parent.join(name),
+            Err(_) => absolute,
+        },
+        _ => absolute,
+    }
+}
+
+/// The lock file other processes look for when editing `key`: under the
+/// git directory for a file in a work tree (see [`git::private_dir`]), so
+/// it never shows up in `git status`, and next to the file otherwise
+fn lock_file_path(key: &Path) -> Option<PathBuf> {
+    let name = key.file_name()?.to_string_lossy();
+    let parent = key.parent()?;
+    Some(match git::private_dir(parent) {
+        Some((private, relative)) => private
+            .join("locks")
+            .join(relative)
+            .join(format!("{name}.lock")),
+        None => parent.join(format!(".{name}.git-ignore.lock")),
+    })
+}
+
+/// Create the lock file other processes look for, waiting up to
+/// [`LOCK_TIMEOUT`] while one exists and breaking it if the process that
+/// took it is gone. Returns `None` when the file can't be created for
+/// another reason, such as a missing or read-only directory; the edit then
+/// goes ahead and reports any problem with its own write.
+fn take_lock_file(key: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let Some(lock_file) = lock_file_path(key) else {
+        return Ok(None);
+    };
+    if let Some(dir) = lock_file.parent().filter(|dir| !dir.exists()) {
+        if key.parent().is_some_and(Path::exists) && fs::create_dir_all(dir).is_err() {
+            return Ok(None);
+        }
+    }
+    let started = Instant::now();
+    let mut waited = false;
+    loop {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_file)
+        {
+            Ok(mut file) => {
+                let _ = writeln!(file, "{}", std::process::id());
+                return Ok(Some(lock_file));
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if let Some(pid) = stale_owner(&lock_file) {
+                    warn!(path = %lock_file.display(), pid, "breaking stale lock");
+                    let _ = fs::remove_file(&lock_file);
+                    continue;
+                }
+                if started.elapsed() >= LOCK_TIMEOUT {
+                    return Err(Error::io(
+                        "lock",
+                        &lock_file,
+                        io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "another process is editing the file; \
+                             delete the lock file if none is",
+                        ),
+                    )
+                    .into());
+                }
+                if !waited {
+                    debug!(path = %lock_file.display(), "waiting for lock");
+                    waited = true;
+                }
+                thread::sleep(RETRY_INTERVAL);
+            }
+            Err(_) => return Ok(None),
+        }
+    }
+}
+
+/// The id of the process that took `lock_file`, if that process is no
+/// longer running. A lock naming this process is stale too: every lock it
+/// holds is tracked in [`HELD`], and the caller is about to take this one.
+fn stale_owner(lock_file: &Path) -> Option<u32> {
+    let pid: u32 = fs::read_to_string(lock_file).ok()?.trim().parse().ok()?;
+    (pid == std::process::id() || !process_running(pid)).then_some(pid)
+}
+
+/// Whether process `pid` is running on this machine. When that can't be
+/// told, the answer is yes, so a live lock is never broken.
+fn process_running(pid: u32) -> bool {
+    if cfg!(windows) {
+        let filter = format!("PID eq {pid}");
+        return match Command::new("tasklist")
+            .args(["/FI", &filter, "/NH"])
+            .output()
+        {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .any(|word| word == pid.to_string()),
+            _ => true,
+        };
+    }
+    if Path::new("/proc/self").exists() {
+        return Path::new("/proc").join(pid.to_string()).exists();
+    }
+    // `kill -0` also fails for another user's process; only "no such
+    // process" means it is gone
+    match Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+    {
+        Ok(output) => {
+            output.status.success()
+                || !String::from_utf8_lossy(&output.stderr)
+                    .to_lowercase()
+                    .contains("no such process")
+        }
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_lock_is_reentrant_and_exclusive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".gitignore");
+        let lock_file = dir.path().join("..gitignore.git-ignore.lock");
+
+        let outer = lock(&path).unwrap();
+        assert!(lock_file.exists());
+        let inner = lock(&dir.path().join(".").join(".gitignore")).unwrap();
+        assert_eq!(inner.path(), outer.path());
+        drop(inner);
+        assert!(lock_file.exists());
+
+        // Another thread waits until the outer lock is dropped
+        let entered = Arc::new(AtomicUsize::new(0));
+        let waiter = {
+            let path = path.clone();
+            let entered = Arc::clone(&entered);
+            thread::spawn(move || {
+                let _lock = lock(&path).unwrap();
+                entered.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(entered.load(Ordering::SeqCst), 0);
+        drop(outer);
+        waiter.join().unwrap();
+        assert_eq!(entered.load(Ordering::SeqCst), 1);
+        assert!(!lock_file.exists());
+    }
+
+    #[test]
+    fn test_lock_file_in_git_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = paths::canonicalize(dir.path()).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let lock_file = root.join(".git/git-ignore/locks/src/.gitignore.lock");
+
+        let guard = lock(&root.join("src/.gitignore")).unwrap();
+        assert!(lock_file.exists());
+        assert!(!root.join("src/..gitignore.git-ignore.lock").exists());
+        drop(guard);
+        assert!(!lock_file.exists());
+
+        // Files in the git directory keep their lock next to them
+        let exclude = root.join(".git/info/exclude");
+        std::fs::create_dir_all(exclude.parent().unwrap()).unwrap();
+        let guard = lock(&exclude).unwrap();
+        assert!(root.join(".git/info/.exclude.git-ignore.lock").exists());
+        drop(guard);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_lock_is_broken() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".gitignore");
+        let lock_file = dir.path().join("..gitignore.git-ignore.lock");
+
+        // Left behind by a process that has exited
+        let mut child = Command::new("true").spawn().unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+        std::fs::write(&lock_file, format!("{dead}\n")).unwrap();
+        let started = Instant::now();
+        let guard = lock(&path).unwrap();
+        assert!(started.elapsed() < LOCK_TIMEOUT);
+        assert_eq!(
+            std::fs::read_to_string(&lock_file).unwrap().trim(),
+            std::process::id().to_string()
+        );
+        drop(guard);
+
+        // Held by a running process
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        std::fs::write(&lock_file, format!("{}\n", child.id())).unwrap();
+        assert_eq!(stale_owner(&lock_file), None);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(stale_owner(&lock_file), Some(child.id()));
+    }
+
+    #[test]
+    fn test_lock_all_and_missing_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        let locks = lock_all([b.as_path(), a.as_path(), b.as_path()]).unwrap();
+        assert_eq!(locks.len(), 2);
+        assert!(locks[0].path() < locks[1].path());
+        drop(locks);
+
+        // Nothing to put a lock file in, but threads are still serialized
+        let missing = dir.path().join("missing").join(".gitignore");
+        let guard = lock(&missing).unwrap();
+        assert!(!dir.path().join("missing").exists());
+        drop(guard);
+    }
+}
@@ -1,12 +1,43 @@
 //! Main CLI module for git-ignore tool
 
-use clap::{Arg, ArgAction, Command};
-use git_ignore_tool::{git, ignore, PatternIssue, PatternSeverity, PatternValidationLevel};
+use anyhow::Context;
+use clap::{parser::ValueSource, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+#[cfg(feature = "forge")]
+use git_ignore_tool::forge::{self, PullRequestOptions};
+use git_ignore_tool::{
+    attributes, audit,
+    backup::{self, BackupPolicy},
+    blame,
+    commit::{self, CommitOptions},
+    config::Config,
+    consolidate, decision, detect,
+    diff::{self, Side},
+    explain, git, hook,
+    ignore::{self, Placement},
+    ignore_file::{classify_line, EntryKind},
+    keys, lint, merge,
+    negation::{self, NegationConflict, Resolution},
+    nested,
+    pattern::escape_literal,
+    picker::{decode_keys, Picker, PickerState},
+    route, rules, sarif, sets,
+    snapshot::Snapshot,
+    stats,
+    subsume::{self, Coverage},
+    templates,
+    tui::{self, ScopeFile},
+    unignore, Error, Pattern, PatternIssue, PatternSeverity, PatternValidationLevel, RepoContext,
+    Scope, SkipReason,
+};
 use std::{
-    env,
-    io::{self, Write},
-    process,
+    collections::VecDeque,
+    env, fs,
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    time::Duration,
 };
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
 
 /// Program version
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -17,37 +48,626 @@ const EXIT_VALIDATION_FAILED: i32 = 1;
 const EXIT_GIT_ERROR: i32 = 2;
 const EXIT_CONFIG_ERROR: i32 = 3;
 const EXIT_FILE_ERROR: i32 = 4;
+const EXIT_CHECK_FAILED: i32 = 5;
+const EXIT_TARGET_MISSING: i32 = 6;
 
 /// Create and configure the argument parser
 fn create_parser() -> Command {
-    Command::new("git-ignore")
+    let command = Command::new("git-ignore")
         .version(VERSION)
         .about("Add patterns to git ignore files")
         .after_help(
-            "Examples:\n  \
-            git-ignore '*.pyc' '__pycache__/'     # Add to .gitignore\n  \
+            "Without a subcommand, git-ignore adds the given patterns, like `add`.\n\n\
+            Examples:\n  \
+            git-ignore add '*.pyc' '__pycache__/' # Add to .gitignore\n  \
+            git-ignore '*.pyc' '__pycache__/'     # The same, without the subcommand\n  \
             git-ignore --local build/             # Add to .git/info/exclude\n  \
-            git-ignore --global '*.log'           # Add to global gitignore",
+            git-ignore --global '*.log'           # Add to global gitignore\n  \
+            git-ignore --auto-target .DS_Store    # Route to the best-fitting file\n  \
+            git-ignore remove '*.log'             # Remove a pattern from .gitignore\n  \
+            git-ignore template rust python       # Apply github/gitignore templates\n  \
+            git-ignore remove-template Rust       # Remove an applied template block\n  \
+            git-ignore template-dir .DS_Store     # Pre-ignore in every new clone\n  \
+            git-ignore prune-global --root ~/src  # Move shared editor/OS patterns to global\n  \
+            git-ignore --attributes '*.png binary' # Edit .gitattributes instead\n  \
+            git-ignore list                       # Show patterns grouped by section\n  \
+            git-ignore check build/ debug.log     # Tell whether paths are ignored, and why\n  \
+            git-ignore sort --strategy natural    # Sort each section, lib2 before lib10\n  \
+            git-ignore why '/build/**/*.o'        # Explain what a pattern matches\n  \
+            git-ignore snapshot -o ignore.snap    # Export all ignore state",
+        )
+        .subcommand_negates_reqs(true)
+        .subcommand(with_add_args(
+            Command::new("add").about("Add patterns to an ignore file (the default without a subcommand)"),
+        ))
+        .subcommand(
+            Command::new("template")
+                .about("Apply language and framework templates from github/gitignore")
+                .arg(
+                    Arg::new("names")
+                        .help("Template names, matched without regard to case (rust, python, macos, ...)")
+                        .value_name("NAME")
+                        .num_args(1..)
+                        .required_unless_present_any(["refresh", "list", "search"]),
+                )
+                .arg(
+                    Arg::new("list")
+                        .long("list")
+                        .help("List the bundled, cached and downloadable templates")
+                        .conflicts_with_all(["names", "refresh", "search"])
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("search")
+                        .long("search")
+                        .value_name("TERM")
+                        .help("List the templates whose name or path contains TERM")
+                        .conflicts_with_all(["names", "refresh"]),
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .long("refresh")
+                        .help("Download the current version of the named templates first, or of every bundled and cached one")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("remove-template")
+                .about("Remove an applied template block, including its markers")
+                .arg(
+                    Arg::new("name")
+                        .help("Name of the template to remove")
+                        .value_name("NAME")
+                        .required(true),
+                )
+                .arg(force_protected_arg()),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove patterns from an ignore file")
+                .arg(
+                    Arg::new("patterns")
+                        .help("Patterns to remove")
+                        .value_name("PATTERN")
+                        .required(true)
+                        .num_args(1..),
+                )
+                .arg(force_protected_arg()),
+        )
+        .subcommand(
+            Command::new("why")
+                .about("Explain in plain English what a pattern matches, or why a path is ignored")
+                .arg(
+                    Arg::new("pattern")
+                        .help("Pattern to explain, or the path to trace with --path")
+                        .value_name("PATTERN")
+                        .allow_hyphen_values(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Trace every rule that matches the path, in the order git weighs them")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Report whether paths are ignored, and by which pattern and file")
+                .arg(
+                    Arg::new("paths")
+                        .help("Paths to check; they needn't exist, and a trailing / checks a directory")
+                        .value_name("PATH")
+                        .num_args(1..)
+                        .allow_hyphen_values(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("no-index")
+                        .long("no-index")
+                        .help("Check the patterns alone, even for tracked files (which git never ignores)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("quiet")
+                        .short('q')
+                        .long("quiet")
+                        .help("Skip the per-path report; the exit code still tells")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("move")
+                .about("Move patterns from one ignore file to another in a single change")
+                .arg(
+                    Arg::new("patterns")
+                        .help("Patterns to move")
+                        .value_name("PATTERN")
+                        .num_args(1..)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_name("SCOPE")
+                        .help("File to take the patterns from: repo (gitignore), local (exclude) or global")
+                        .value_parser(clap::value_parser!(Scope))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("SCOPE")
+                        .help("File to put them in")
+                        .value_parser(clap::value_parser!(Scope))
+                        .required(true),
+                )
+                .arg(force_protected_arg()),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Merge the patterns of another gitignore-style file, skipping ones already covered")
+                .arg(
+                    Arg::new("file")
+                        .help("File to merge from")
+                        .value_name("FILE")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Show what would be merged without writing anything")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare the patterns of two ignore files: what only one has, what both have, and negations that fight")
+                .arg(
+                    Arg::new("files")
+                        .help("With one FILE, compare the scope's ignore file with it; with two, compare them")
+                        .value_name("FILE")
+                        .num_args(1..=2)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("unignore")
+                .about("Add the negations that re-include ignored paths, even inside ignored directories")
+                .arg(
+                    Arg::new("paths")
+                        .help("Paths to re-include; a trailing / marks a directory")
+                        .value_name("PATH")
+                        .num_args(1..)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Show the negations without writing them")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Report tracked files that the ignore rules match")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Report format (sarif for code scanning dashboards)")
+                        .value_parser(["table", "json", "sarif"])
+                        .default_value("table"),
+                ),
+        )
+        .subcommand(
+            Command::new("untrack")
+                .about("Stop tracking files the ignore rules match, keeping them on disk (git rm -r --cached)")
+                .arg(
+                    Arg::new("patterns")
+                        .help("Untrack files matching these patterns instead of the ignore files' rules")
+                        .value_name("PATTERN")
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Untrack without asking for confirmation")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only list the files that would be untracked")
+                        .conflicts_with("yes")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("tui")
+                .about("Edit the repository, local and global ignore files side by side"),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Create a first .gitignore from the templates of the languages in use")
+                .arg(
+                    Arg::new("merge")
+                        .long("merge")
+                        .help("Add to an existing .gitignore instead of refusing to touch it")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("suggest")
+                .about("Propose patterns for the build output and dependencies of the languages in use")
+                .arg(
+                    Arg::new("apply")
+                        .long("apply")
+                        .help("Add the suggested patterns instead of only listing them")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("Show patterns grouped by file and section")
+                .arg(
+                    Arg::new("color")
+                        .long("color")
+                        .value_name("WHEN")
+                        .help("Highlight pattern syntax")
+                        .value_parser(["auto", "always", "never"])
+                        .default_value("auto"),
+                )
+                .arg(
+                    Arg::new("line-numbers")
+                        .short('n')
+                        .long("line-numbers")
+                        .help("Prefix each pattern with its line number in the file")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("List the repository, local and global files together")
+                        .action(ArgAction::SetTrue),
+                ),
         )
+        .subcommand(
+            Command::new("sort")
+                .about("Sort the patterns of an ignore file within each section")
+                .arg(
+                    Arg::new("strategy")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .help("Sort order (config: ignore.sortStrategy)")
+                        .value_parser(["lexicographic", "natural", "directories-first", "extension"]),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Don't write anything; exit non-zero if the file isn't sorted")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("Tidy an ignore file: whitespace, duplicate patterns, merged and sorted sections")
+                .arg(
+                    Arg::new("strategy")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .help("Sort order within sections (config: ignore.sortStrategy)")
+                        .value_parser(["lexicographic", "natural", "directories-first", "extension"]),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Don't write anything; exit non-zero if the file isn't formatted")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("dedupe")
+                .about("Remove patterns that broader patterns in the same file already cover")
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Remove them without asking for confirmation")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only list the redundant patterns")
+                        .conflicts_with("yes")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(force_protected_arg()),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Check ignore files for problems, with file and line locations")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .help("File to lint instead of the scope's files"),
+                )
+                .arg(
+                    Arg::new("strict")
+                        .long("strict")
+                        .help("Fail on any finding, not only errors")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("fix")
+                        .long("fix")
+                        .help("Apply safe corrections first, then report what is left")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("format"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Report format (sarif for code scanning dashboards)")
+                        .value_parser(["text", "sarif"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            Command::new("blame")
+                .about("Show the commit and author that introduced each pattern of a tracked ignore file")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .help("Tracked ignore file to blame (default: the top-level .gitignore)"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Summarize ignore files: patterns per scope, files each pattern matches, unused patterns"),
+        )
+        .subcommand(
+            Command::new("prune-global")
+                .about("Find personal patterns repeated across repositories and move them to the global gitignore")
+                .arg(
+                    Arg::new("repositories")
+                        .help("Repositories to scan")
+                        .value_name("REPO")
+                        .required_unless_present("root")
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .value_name("DIR")
+                        .help("Scan every repository found under DIR")
+                        .conflicts_with("repositories"),
+                )
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("N")
+                        .help("How many directories below --root to look for repositories")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("3")
+                        .requires("root"),
+                )
+                .arg(
+                    Arg::new("min-share")
+                        .long("min-share")
+                        .value_name("PERCENT")
+                        .help("Share of the repositories a pattern must appear in")
+                        .value_parser(clap::value_parser!(u8).range(1..=100))
+                        .default_value("50"),
+                )
+                .arg(
+                    Arg::new("apply")
+                        .long("apply")
+                        .help("Add the patterns to the global gitignore and remove them from the repositories")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(force_protected_arg()),
+        )
+        .subcommand(
+            Command::new("template-dir")
+                .about("Add patterns to the exclude file new repositories start with (init.templateDir)")
+                .arg(
+                    Arg::new("patterns")
+                        .help("Patterns to add")
+                        .value_name("PATTERN")
+                        .required_unless_present("from")
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_name("FILE")
+                        .help("Add a whole template file as a managed block")
+                        .conflicts_with("patterns"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Block name for --from (default: the file name without its extension)")
+                        .requires("from"),
+                ),
+        )
+        .subcommand(
+            Command::new("snapshot")
+                .about("Export all ignore files and ignore.* settings to one file")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the snapshot to FILE instead of stdout"),
+                ),
+        )
+        .subcommand(
+            Command::new("restore")
+                .about("Restore ignore files and settings from a snapshot")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .help("Snapshot to restore, or - for stdin")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("skip-global")
+                        .long("skip-global")
+                        .help("Leave the global gitignore untouched")
+                        .action(ArgAction::SetTrue),
+                ),
+        );
+    with_add_args(command)
         .arg(
-            Arg::new("patterns")
-                .help("Patterns to add to ignore file")
-                .value_name("PATTERN")
-                .required(true)
-                .num_args(1..),
+            Arg::new("repo")
+                .long("repo")
+                .help("Add patterns to the repository .gitignore (overrides ignore.defaultTarget)")
+                .global(true)
+                .action(ArgAction::Count),
         )
         .arg(
             Arg::new("local")
                 .long("local")
                 .short('l')
+                .global(true)
                 .help("Add patterns to .git/info/exclude instead of .gitignore")
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("worktree")
+                .long("worktree")
+                .global(true)
+                .help(
+                    "With --local, use an exclude file private to this linked worktree; \
+                     points the worktree's core.excludesFile at it, which replaces the global \
+                     excludes file there",
+                )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("keep-excludes")
+                .long("keep-excludes")
+                .global(true)
+                .help("With --worktree, copy the excludes file it replaces into the worktree exclude file")
+                .requires("worktree")
                 .action(ArgAction::SetTrue),
         )
         .arg(
             Arg::new("global")
                 .long("global")
                 .short('g')
+                .global(true)
                 .help("Add patterns to global gitignore file")
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("system")
+                .long("system")
+                .global(true)
+                .help("Add patterns to the system-wide excludes file (core.excludesFile in the system git config)")
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("attributes")
+                .long("attributes")
+                .global(true)
+                .help("Edit the scope's gitattributes file instead of its ignore file")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("backup")
+                .long("backup")
+                .help("Save a timestamped copy of each file before changing it (config: ignore.backup)")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("backup-dir")
+                .long("backup-dir")
+                .value_name("DIR")
+                .help("Put backups under DIR instead of the git directory; implies --backup")
+                .global(true),
+        )
+        .arg(
+            Arg::new("backup-keep")
+                .long("backup-keep")
+                .value_name("N")
+                .help("Backups kept per file, 0 for all (default 5; config: ignore.backupKeep)")
+                .global(true)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("directory")
+                .short('C')
+                .long("directory")
+                .value_name("DIR")
+                .help("Run as if started in DIR; repeat to take each one relative to the last, as with git -C")
+                .global(true)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("git-timeout")
+                .long("git-timeout")
+                .value_name("SECONDS")
+                .help("Kill local git commands that run longer than this; pushes are exempt (default 30, 0 waits forever; env: GIT_IGNORE_TIMEOUT)")
+                .global(true)
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .help("Diagnostic log format on stderr (filter with RUST_LOG)")
+                .global(true)
+                .value_parser(["text", "json"]),
+        )
+}
+
+/// Arguments for adding patterns, shared by the `add` subcommand and the
+/// bare `git-ignore <pattern>` form that stands for it
+fn with_add_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("patterns")
+                .help("Patterns to add to ignore file (suffix @repo, @local, @global or @system to pick its file; @NAME adds a configured pattern set)")
+                .value_name("PATTERN")
+                .required_unless_present_any(["stdin", "interactive"])
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("interactive")
+                .short('i')
+                .long("interactive")
+                .help("Pick untracked files to ignore, by path, extension or directory")
+                .conflicts_with_all(["patterns", "stdin"])
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("stdin")
+                .long("stdin")
+                .help("Read patterns from standard input, one per line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("null")
+                .short('z')
+                .long("null")
+                .help("With --stdin, read NUL-separated file names, escaped as with --literal")
+                .requires("stdin")
+                .conflicts_with("attributes")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("literal")
+                .long("literal")
+                .help("Take the patterns as exact file names, escaping characters git reads as syntax")
+                .conflicts_with_all(["attributes", "interactive"])
                 .action(ArgAction::SetTrue),
         )
         .arg(
@@ -56,12 +676,136 @@ fn create_parser() -> Command {
                 .help("Skip pattern validation")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("untrack")
+                .long("untrack")
+                .help("Also remove tracked files the new patterns match from the index (they stay on disk)")
+                .conflicts_with_all(["attributes", "commit-mode"])
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("allow-duplicates")
                 .long("allow-duplicates")
                 .help("Allow duplicate patterns to be added")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("sorted")
+                .long("sorted")
+                .help("Insert patterns at their sorted position (config: ignore.sorted)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("affinity")
+                .long("affinity")
+                .help("Insert patterns next to the most similar existing ones (config: ignore.affinity)")
+                .conflicts_with("sorted")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("group")
+                .long("group")
+                .value_name("COMMENT")
+                .help("Write the patterns as one block under this comment, kept apart from later sorted additions")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .conflicts_with_all(["sorted", "affinity"]),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .help("Don't write anything; exit non-zero if the file would change")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Show the patterns that would be added and skipped without writing anything")
+                .conflicts_with_all(["check", "commit", "create-pr"])
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("auto-target")
+                .long("auto-target")
+                .help("Send each pattern to the file it fits best (global, local or repo)")
+                .conflicts_with_all(["repo", "local", "global", "system", "worktree", "attributes"])
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("commit")
+                .long("commit")
+                .help("Commit the .gitignore change to a branch without a work tree (for bare repos)")
+                .conflicts_with_all(["local", "global", "system", "worktree", "auto-target", "check", "attributes"])
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("create-pr")
+                .long("create-pr")
+                .help("Commit the .gitignore change to a new branch, push it and open a pull request")
+                .conflicts_with_all(["local", "global", "system", "worktree", "auto-target", "check", "attributes"])
+                .hide(!cfg!(feature = "forge"))
+                .action(ArgAction::SetTrue),
+        )
+        .group(
+            ArgGroup::new("commit-mode")
+                .args(["commit", "create-pr"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("branch")
+                .long("branch")
+                .value_name("BRANCH")
+                .help("With --commit, the branch to commit to (default: HEAD's branch); with --create-pr, the branch to push")
+                .requires("commit-mode"),
+        )
+        .arg(
+            Arg::new("author")
+                .long("author")
+                .value_name("NAME <EMAIL>")
+                .help("With --commit or --create-pr, author and committer of the commit")
+                .requires("commit-mode"),
+        )
+        .arg(
+            Arg::new("message")
+                .long("message")
+                .short('m')
+                .value_name("MESSAGE")
+                .help("With --commit or --create-pr, the commit message")
+                .requires("commit-mode"),
+        )
+        .arg(
+            Arg::new("remote")
+                .long("remote")
+                .value_name("REMOTE")
+                .help("With --create-pr, the remote to push to and open the pull request on")
+                .hide(!cfg!(feature = "forge"))
+                .default_value("origin")
+                .requires("create-pr"),
+        )
+        .arg(
+            Arg::new("base")
+                .long("base")
+                .value_name("BRANCH")
+                .help("With --create-pr, the branch to merge into (default: HEAD's branch)")
+                .hide(!cfg!(feature = "forge"))
+                .requires("create-pr"),
+        )
+        .arg(
+            Arg::new("no-create")
+                .long("no-create")
+                .help("Fail instead of creating the target file if it doesn't exist")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("create")
+                .long("create")
+                .value_name("PATH")
+                .help("With --global, create a global gitignore (at PATH, or git's default location) and point core.excludesFile at it if none is set up; a different configured file is never replaced")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("")
+                .requires("global")
+                .conflicts_with_all(["no-create", "check", "dry-run", "attributes"]),
+        )
 }
 
 /// Display validation issues to stderr
@@ -115,143 +859,3037 @@ fn has_blocking_issues(issues: &[PatternIssue]) -> bool {
     issues.iter().any(|i| i.severity == PatternSeverity::Error)
 }
 
-/// Get target file path based on arguments
-fn get_target_file(local: bool, global: bool) -> anyhow::Result<std::path::PathBuf> {
+/// Work out which file to target from the scope flags, falling back to the
+/// configured default target and then to the repository .gitignore
+fn resolve_scope(matches: &ArgMatches, config: &Config) -> anyhow::Result<Scope> {
+    let repo = matches.get_count("repo") > 0;
+    let local = matches.get_count("local") > 0;
+    let global = matches.get_count("global") > 0;
+    let system = matches.get_count("system") > 0;
+
     if local && global {
         anyhow::bail!("Cannot specify both --local and --global");
     }
+    if repo && (local || global) {
+        anyhow::bail!("Cannot combine --repo with --local or --global");
+    }
+    if system && (repo || local || global) {
+        anyhow::bail!("Cannot combine --system with --repo, --local or --global");
+    }
 
-    if global {
-        git::get_global_gitignore_path()
-            .ok_or_else(|| anyhow::anyhow!("No global gitignore configured. Run: git config --global core.excludesfile ~/.gitignore_global"))
+    Ok(if repo {
+        Scope::Repo
     } else if local {
-        Ok(git::get_exclude_file_path()?)
+        Scope::Local
+    } else if global {
+        Scope::Global
+    } else if system {
+        Scope::System
     } else {
-        Ok(git::get_gitignore_path()?)
+        config.default_target.unwrap_or(Scope::Repo)
+    })
+}
+
+/// Load the tool settings and install the backup policy they and the
+/// command line ask for
+fn load_config(matches: &ArgMatches) -> anyhow::Result<Config> {
+    let config = Config::load()?;
+    let dir = matches
+        .get_one::<String>("backup-dir")
+        .map(PathBuf::from)
+        .or_else(|| config.backup_dir.clone());
+    if matches.get_flag("backup") || matches.contains_id("backup-dir") || config.backup {
+        backup::set_policy(Some(BackupPolicy {
+            dir,
+            keep: matches
+                .get_one::<usize>("backup-keep")
+                .copied()
+                .unwrap_or(config.backup_keep),
+        }));
+    }
+    Ok(config)
+}
+
+/// `--force-protected`, for commands that may delete patterns
+fn force_protected_arg() -> Arg {
+    Arg::new("force-protected")
+        .long("force-protected")
+        .help("Also delete patterns listed in ignore.protected")
+        .action(ArgAction::SetTrue)
+}
+
+/// Refuse to delete protected patterns unless `--force-protected` is given
+fn guard_protected(
+    matches: &ArgMatches,
+    config: &Config,
+    patterns: &[String],
+) -> anyhow::Result<()> {
+    if matches.get_flag("force-protected") {
+        return Ok(());
+    }
+    let protected = ignore::find_protected(patterns, &config.protected);
+    if !protected.is_empty() {
+        anyhow::bail!(
+            "Protected pattern: refusing to remove {} (listed in ignore.protected; pass --force-protected to remove anyway)",
+            protected.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Get target file path for a scope
+fn get_target_file(matches: &ArgMatches, scope: Scope) -> anyhow::Result<std::path::PathBuf> {
+    let worktree = matches.get_flag("worktree");
+    if worktree && scope != Scope::Local {
+        anyhow::bail!("--worktree only applies to --local");
+    }
+
+    if matches.get_flag("attributes") {
+        if worktree {
+            anyhow::bail!("--worktree only applies to ignore files");
+        }
+        return match scope {
+            Scope::System => Err(Error::config("--system only applies to ignore files").into()),
+            Scope::Global => git::get_global_attributes_path(),
+            Scope::Local => Ok(git::get_info_attributes_path()?),
+            Scope::Repo => Ok(git::get_gitattributes_path()?),
+        };
+    }
+
+    match scope {
+        Scope::Global => {
+            // A configured file is the only one git reads, even before it exists
+            if let Some(configured) = git::get_configured_global_excludes_file()? {
+                if !configured.exists() {
+                    eprintln!(
+                        "Warning: core.excludesFile points to {}, which does not exist",
+                        configured.display()
+                    );
+                }
+                return Ok(configured);
+            }
+            git::get_global_gitignore_path().ok_or_else(|| {
+                Error::NoGlobalGitignore(
+                    "configured. Pass --create to set one up, or run: git config --global core.excludesfile ~/.gitignore_global"
+                        .to_string(),
+                )
+                .into()
+            })
+        }
+        Scope::System => {
+            let path = git::get_system_gitignore_path()?;
+            if let Some(global) = git::get_configured_global_excludes_file()? {
+                eprintln!(
+                    "Warning: the global core.excludesFile ({}) takes precedence, so git doesn't read {}",
+                    global.display(),
+                    path.display()
+                );
+            }
+            Ok(path)
+        }
+        Scope::Local if worktree => Ok(git::get_worktree_exclude_file_path()?),
+        Scope::Local => Ok(git::get_exclude_file_path()?),
+        Scope::Repo => Ok(git::get_gitignore_path()?),
     }
 }
 
+/// The global gitignore for `--create`: the usable one already set up, or
+/// a new one at `path` (git's default location when empty) that
+/// `core.excludesFile` is pointed at. A `path` other than the configured
+/// file is refused.
+fn create_global_gitignore(path: &str) -> anyhow::Result<PathBuf> {
+    if path.is_empty() {
+        if let Some(existing) = git::get_global_gitignore_path() {
+            return Ok(existing);
+        }
+    }
+    let path = (!path.is_empty()).then(|| Path::new(path));
+    let configured = git::get_configured_global_excludes_file()?;
+    let created = git_ignore_tool::configure_global_gitignore(path)?;
+    if configured.is_none() {
+        println!(
+            "Set core.excludesFile to {} in the global git config",
+            created.display()
+        );
+    }
+    Ok(created)
+}
+
 /// Get file description for user messages
-fn get_file_description(file_path: &std::path::Path, local: bool, global: bool) -> String {
-    if global {
-        format!("global gitignore ({})", file_path.display())
-    } else if local {
-        format!(".git/info/exclude ({})", file_path.display())
+fn get_file_description(file_path: &std::path::Path, scope: Scope) -> String {
+    if is_attributes_file(file_path, scope) {
+        return match scope {
+            Scope::Global | Scope::System => {
+                format!("global gitattributes ({})", file_path.display())
+            }
+            Scope::Local => format!(".git/info/attributes ({})", file_path.display()),
+            Scope::Repo => format!(".gitattributes ({})", file_path.display()),
+        };
+    }
+    match scope {
+        Scope::Global => format!("global gitignore ({})", file_path.display()),
+        Scope::System => format!("system gitignore ({})", file_path.display()),
+        Scope::Local if git::get_exclude_file_path().is_ok_and(|shared| shared != file_path) => {
+            format!("worktree exclude ({})", file_path.display())
+        }
+        Scope::Local => format!(".git/info/exclude ({})", file_path.display()),
+        Scope::Repo => format!(".gitignore ({})", file_path.display()),
+    }
+}
+
+/// Whether `file_path` is the gitattributes file of `scope` rather than
+/// its ignore file
+fn is_attributes_file(file_path: &std::path::Path, scope: Scope) -> bool {
+    match scope {
+        Scope::Global => git::get_global_attributes_path().is_ok_and(|path| path == file_path),
+        Scope::System => false,
+        Scope::Local => git::get_info_attributes_path().is_ok_and(|path| path == file_path),
+        Scope::Repo => file_path.file_name() == Some(std::ffi::OsStr::new(".gitattributes")),
+    }
+}
+
+/// Report what an add would change without writing; returns whether the
+/// file is already up to date
+fn check_target_file(
+    target_file: &std::path::Path,
+    patterns: &[String],
+    avoid_duplicates: bool,
+    scope: Scope,
+) -> anyhow::Result<bool> {
+    let file_description = get_file_description(target_file, scope);
+    let missing = ignore::plan_patterns_to_add(target_file, patterns, avoid_duplicates)?;
+
+    if missing.is_empty() {
+        println!("{file_description} is up to date");
+        return Ok(true);
+    }
+
+    println!(
+        "Would add {} to {}:",
+        pluralize_patterns(missing.len()),
+        file_description
+    );
+    for pattern in &missing {
+        println!("  {pattern}");
+    }
+    Ok(false)
+}
+
+/// Report what `--dry-run` found an addition would do
+fn print_add_preview(preview: &ignore::AddPreview, scope: Scope) {
+    let file_description = get_file_description(&preview.file, scope);
+    if preview.changes_file() {
+        println!(
+            "Would add {} to {}:",
+            pluralize_patterns(preview.added.len()),
+            file_description
+        );
+        for pattern in &preview.added {
+            println!("  {pattern}");
+        }
+        if preview.creates_file {
+            println!("(the file doesn't exist yet and would be created)");
+        }
     } else {
-        format!(".gitignore ({})", file_path.display())
+        println!("No new patterns would be added to {file_description}");
+    }
+    for (pattern, reason) in &preview.skipped {
+        let why = match reason {
+            SkipReason::Duplicate => "duplicate",
+            SkipReason::Empty => "empty",
+            SkipReason::NotFound => "not found",
+        };
+        println!("Would skip {pattern:?} ({why})");
     }
 }
 
-/// Main application logic
-fn run() -> anyhow::Result<()> {
-    let matches = create_parser().get_matches();
+/// Format a pattern count with the right noun, e.g. "1 pattern", "2 patterns"
+fn pluralize_patterns(count: usize) -> String {
+    if count == 1 {
+        format!("{count} pattern")
+    } else {
+        format!("{count} patterns")
+    }
+}
 
+/// Remove patterns from the target file
+fn run_remove(matches: &ArgMatches) -> anyhow::Result<()> {
     let patterns: Vec<String> = matches
         .get_many::<String>("patterns")
         .unwrap()
-        .cloned()
+        .map(|pattern| {
+            if matches.get_flag("attributes") {
+                attributes::normalize_attribute_line(pattern)
+            } else {
+                pattern.clone()
+            }
+        })
         .collect();
-    let local = matches.get_flag("local");
-    let global = matches.get_flag("global");
-    let no_validate = matches.get_flag("no-validate");
-    let allow_duplicates = matches.get_flag("allow-duplicates");
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
 
-    // Validate patterns first if not disabled
-    let validation_level = if no_validate {
-        PatternValidationLevel::None
+    guard_protected(matches, &config, &patterns)?;
+
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+    let report = ignore::remove_patterns_from_ignore_file(&target_file, &patterns)?;
+
+    if report.removed.is_empty() {
+        println!("No matching patterns in {file_description}");
     } else {
-        PatternValidationLevel::Warn
-    };
+        println!(
+            "Removed {} from {}:",
+            pluralize_patterns(report.removed.len()),
+            file_description
+        );
+        for removed in &report.removed {
+            println!("  {} (line {})", removed.pattern, removed.line_number);
+        }
+    }
+    for pattern in &report.not_found {
+        eprintln!("Not found: {pattern}");
+    }
+    Ok(())
+}
+
+/// Apply templates to the target file, each as its own block
+fn run_template(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "templates are ignore files; drop --attributes"
+        ));
+    }
+    if matches.get_flag("list") || matches.contains_id("search") {
+        return list_templates(matches.get_one::<String>("search"));
+    }
+    let names: Vec<String> = matches
+        .get_many::<String>("names")
+        .map(|names| names.cloned().collect())
+        .unwrap_or_default();
+
+    if matches.get_flag("refresh") {
+        let refreshed = templates::refresh(&names)?;
+        let cache = templates::cache_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|| "nowhere (no home directory)".to_string());
+        println!("Refreshed {} template(s) in {cache}", refreshed.len());
+        if names.is_empty() {
+            return Ok(());
+        }
+    }
+
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+    if scope == Scope::Local {
+        ignore::ensure_info_exclude_exists(&target_file)?;
+    }
+
+    for name in &names {
+        let template = templates::find_template(name)?;
+        let applied = templates::apply_to_file(&target_file, &template)?;
+        print_applied_template(&applied, &file_description);
+    }
+    Ok(())
+}
 
-    let issues = if validation_level != PatternValidationLevel::None {
-        ignore::validate_ignore_patterns(&patterns)
+/// Report what applying one template did to a file
+fn print_applied_template(applied: &templates::AppliedTemplate, file_description: &str) {
+    if applied.changed {
+        println!(
+            "Applied template '{}' to {file_description}: {}",
+            applied.name,
+            pluralize_patterns(applied.patterns.len())
+        );
     } else {
-        Vec::new()
-    };
+        println!(
+            "Template '{}' is already up to date in {file_description}",
+            applied.name
+        );
+    }
+    if !applied.skipped.is_empty() {
+        println!(
+            "  skipped {} already in the file: {}",
+            pluralize_patterns(applied.skipped.len()),
+            applied.skipped.join(", ")
+        );
+    }
+}
 
-    // Display validation issues
-    display_validation_issues(&issues);
+/// Write a first .gitignore from the templates of the detected languages,
+/// topped up with any suggested pattern they lack. A template that can't
+/// be had (offline, not bundled) leaves just the suggestions for its
+/// language.
+fn run_init(matches: &ArgMatches) -> anyhow::Result<()> {
+    if ["local", "global", "system"]
+        .iter()
+        .any(|flag| matches.get_count(flag) > 0)
+    {
+        anyhow::bail!("init writes the repository .gitignore; drop --local, --global and --system");
+    }
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "init writes an ignore file; drop --attributes"
+        ));
+    }
+    let config = load_config(matches)?;
+    let target_file = get_target_file(matches, Scope::Repo)?;
+    let file_description = get_file_description(&target_file, Scope::Repo);
 
-    // Check if we should continue
-    if has_blocking_issues(&issues) {
-        anyhow::bail!("Pattern validation failed with errors");
+    let existing = std::fs::read_to_string(&target_file).unwrap_or_default();
+    if !existing.trim().is_empty() && !matches.get_flag("merge") {
+        anyhow::bail!("{file_description} already exists; pass --merge to add the templates to it");
     }
 
-    // Determine target file
-    let target_file = get_target_file(local, global)?;
+    let languages = detect::detect_work_tree_languages()?;
+    if languages.is_empty() {
+        println!("No languages detected; {file_description} left as it is");
+        return Ok(());
+    }
 
-    // Ensure exclude file exists if targeting local
-    if local {
-        ignore::ensure_info_exclude_exists(&target_file)?;
+    for language in &languages {
+        let name = &language.template;
+        match templates::find_template(name) {
+            Ok(template) => {
+                let applied = templates::apply_to_file(&target_file, &template)?;
+                print_applied_template(&applied, &file_description);
+            }
+            Err(e) => eprintln!("Warning: skipping template '{name}': {e:#}"),
+        }
     }
 
-    // Add patterns to the target file (validation already done above)
-    let added_patterns = ignore::add_patterns_to_ignore_file(
+    let patterns: Vec<String> = detect::suggest_patterns(&languages)
+        .into_iter()
+        .map(|s| s.pattern)
+        .collect();
+    let added = ignore::add_patterns_with_placement(
         &target_file,
         &patterns,
-        !allow_duplicates,
-        PatternValidationLevel::None,
+        true,
+        config_placement(&config),
     )?;
+    if !added.is_empty() {
+        println!(
+            "Added {} to {}:",
+            pluralize_patterns(added.len()),
+            file_description
+        );
+        for pattern in &added {
+            println!("  {pattern}");
+        }
+    }
+    Ok(())
+}
 
-    // Report results
-    let file_description = get_file_description(&target_file, local, global);
-
-    if added_patterns.is_empty() {
-        println!("No new patterns added to {file_description} (all patterns already exist)");
+/// Print the available templates, or those matching `term`, with where
+/// each can come from. Without a network the local ones are still listed.
+fn list_templates(term: Option<&String>) -> anyhow::Result<()> {
+    let remote_paths = templates::remote_template_paths().unwrap_or_else(|e| {
+        eprintln!("Warning: showing bundled and cached templates only: {e}");
+        Vec::new()
+    });
+    let listings = templates::list_templates(&remote_paths)?;
+    let shown = match term {
+        Some(term) => templates::search(&listings, term),
+        None => listings.iter().collect(),
+    };
+    if shown.is_empty() {
+        if let Some(term) = term {
+            println!("No templates match '{term}'");
+        }
         return Ok(());
     }
 
-    // Report success with context
-    let pattern_word = if added_patterns.len() == 1 {
-        "pattern"
-    } else {
-        "patterns"
-    };
-    println!(
-        "Added {} {} to {}:",
-        added_patterns.len(),
-        pattern_word,
-        file_description
-    );
-    for pattern in &added_patterns {
-        println!("  {pattern}");
+    let width = shown
+        .iter()
+        .map(|listing| listing.name.len())
+        .max()
+        .unwrap_or(0);
+    for listing in shown {
+        let sources: Vec<String> = listing
+            .sources
+            .iter()
+            .map(|source| source.to_string())
+            .collect();
+        let location = match listing.path.rsplit_once('/') {
+            Some((dir, _)) => format!("  ({dir})"),
+            None => String::new(),
+        };
+        println!("{:<width$}  {}{location}", listing.name, sources.join(", "));
     }
+    Ok(())
+}
 
+/// Remove an applied template block from the target file
+fn run_remove_template(matches: &ArgMatches) -> anyhow::Result<()> {
+    let name = matches.get_one::<String>("name").unwrap();
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+    guard_protected(
+        matches,
+        &config,
+        &ignore::template_block_patterns(&target_file, name)?,
+    )?;
+
+    if !ignore::remove_template_from_ignore_file(&target_file, name)? {
+        anyhow::bail!("Template '{name}' not found in {file_description}");
+    }
+    println!("Removed template '{name}' from {file_description}");
     Ok(())
 }
 
-/// Main entry point
-fn main() {
-    let exit_code = match run() {
-        Ok(()) => EXIT_SUCCESS,
-        Err(e) => {
-            let error_str = e.to_string();
+/// Add patterns or a template to the exclude file of the template directory
+fn run_template_dir(matches: &ArgMatches) -> anyhow::Result<()> {
+    load_config(matches)?;
+    let exclude_file = git::get_template_exclude_file_path()?;
 
-            // Determine appropriate exit code based on error type
-            if error_str.contains("Pattern validation failed") {
-                EXIT_VALIDATION_FAILED
-            } else if error_str.contains("Not in a git repository")
-                || error_str.contains("Failed to find git directory")
-                || error_str.contains("Failed to find repository root")
-                || error_str.contains("Git not found in PATH")
-            {
-                eprintln!("Git error while determining target file: {e}");
-                EXIT_GIT_ERROR
-            } else if error_str.contains("No global gitignore")
-                || error_str.contains("Configuration error")
-            {
-                eprintln!("Configuration error: {e}");
-                EXIT_CONFIG_ERROR
-            } else if error_str.contains("Permission denied")
-                || error_str.contains("Failed to write")
-                || error_str.contains("Failed to read")
-                || error_str.contains("Failed to create")
+    let (patterns, template) = match matches.get_one::<String>("from") {
+        Some(from) => {
+            let content = std::fs::read_to_string(from)
+                .with_context(|| format!("Failed to read template: {from}"))?;
+            let name = match matches.get_one::<String>("name") {
+                Some(name) => name.clone(),
+                None => Path::new(from)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| from.clone()),
+            };
+            let lines: Vec<String> = content
+                .trim_matches(['\n', '\r'])
+                .lines()
+                .map(str::to_string)
+                .collect();
+            (lines, Some(name))
+        }
+        None => (
+            matches
+                .get_many::<String>("patterns")
+                .unwrap()
+                .cloned()
+                .collect(),
+            None,
+        ),
+    };
+
+    let checked: Vec<String> = patterns
+        .iter()
+        .filter(|line| classify_line(line) == EntryKind::Pattern)
+        .cloned()
+        .collect();
+    let issues = ignore::validate_ignore_patterns(&checked);
+    display_validation_issues(&issues);
+    if has_blocking_issues(&issues) {
+        anyhow::bail!(Error::ValidationFailed(Vec::new()));
+    }
+
+    ignore::ensure_info_exclude_exists(&exclude_file)?;
+    let description = format!("template exclude file ({})", exclude_file.display());
+    match template {
+        Some(name) => {
+            if ignore::add_template_to_ignore_file(&exclude_file, &name, &patterns)? {
+                println!("Applied template '{name}' to {description}");
+            } else {
+                println!("Template '{name}' is already up to date in {description}");
+                return Ok(());
+            }
+        }
+        None => {
+            let added = ignore::add_patterns_with_placement(
+                &exclude_file,
+                &patterns,
+                true,
+                Placement::Append,
+            )?;
+            if added.is_empty() {
+                println!("No new patterns added to {description} (all patterns already exist)");
+                return Ok(());
+            }
+            println!(
+                "Added {} to {description}:",
+                pluralize_patterns(added.len())
+            );
+            for pattern in &added {
+                println!("  {pattern}");
+            }
+        }
+    }
+    println!("Repositories created by git init or git clone from now on start with it; existing ones are unchanged.");
+    Ok(())
+}
+
+/// Report patterns repeated across repositories that belong in the global
+/// gitignore, and move them there with `--apply`
+fn run_prune_global(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!("--attributes doesn't apply to prune-global");
+    }
+    let config = load_config(matches)?;
+    let repositories: Vec<PathBuf> = match matches.get_one::<String>("root") {
+        Some(root) => consolidate::discover_repositories(
+            Path::new(root),
+            *matches.get_one::<usize>("depth").unwrap(),
+        )?,
+        None => matches
+            .get_many::<String>("repositories")
+            .unwrap()
+            .map(PathBuf::from)
+            .collect(),
+    };
+    let min_share = f64::from(*matches.get_one::<u8>("min-share").unwrap()) / 100.0;
+    let candidates = consolidate::find_candidates(&repositories, min_share, &config.auto_targets)?;
+
+    let scanned = repositories.len();
+    if candidates.is_empty() {
+        println!("No patterns to move to the global gitignore ({scanned} repositories scanned)");
+        return Ok(());
+    }
+    println!("Patterns to move to the global gitignore ({scanned} repositories scanned):");
+    let width = candidates
+        .iter()
+        .map(|candidate| candidate.pattern.len())
+        .max()
+        .unwrap_or(0);
+    for candidate in &candidates {
+        println!(
+            "  {:<width$}  in {} of {scanned} ({})",
+            candidate.pattern,
+            candidate.repositories.len(),
+            candidate.reason
+        );
+    }
+    if !matches.get_flag("apply") {
+        println!("Run again with --apply to move them.");
+        return Ok(());
+    }
+
+    let removals: Vec<(PathBuf, Vec<String>)> = repositories
+        .iter()
+        .map(|repository| {
+            let patterns = candidates
+                .iter()
+                .filter(|candidate| candidate.repositories.contains(repository))
+                .map(|candidate| candidate.pattern.clone())
+                .collect();
+            (repository.clone(), patterns)
+        })
+        .collect();
+    // Check every repository before anything is changed
+    if !matches.get_flag("force-protected") {
+        for (repository, patterns) in &removals {
+            let protected = git::get_config_values_in(repository, "ignore.protected")?;
+            let protected = ignore::find_protected(patterns, &protected);
+            if !protected.is_empty() {
+                anyhow::bail!(
+                    "Protected pattern: refusing to remove {} from {} (listed in ignore.protected; pass --force-protected to remove anyway)",
+                    protected.join(", "),
+                    repository.display()
+                );
+            }
+        }
+    }
+
+    let global_file = get_target_file(matches, Scope::Global)?;
+    let patterns: Vec<String> = candidates
+        .iter()
+        .map(|candidate| candidate.pattern.clone())
+        .collect();
+    // The global file and every repository change together or not at all
+    let mut transaction = ignore::Transaction::new();
+    let added =
+        transaction.add_patterns(&global_file, &patterns, true, config_placement(&config))?;
+    let mut removed = Vec::new();
+    for (repository, patterns) in removals {
+        if patterns.is_empty() {
+            continue;
+        }
+        let gitignore = repository.join(".gitignore");
+        let report = transaction.remove_patterns(&gitignore, &patterns)?;
+        removed.push((gitignore, report.removed.len()));
+    }
+    transaction.commit()?;
+
+    println!(
+        "Added {} to {}",
+        pluralize_patterns(added.len()),
+        get_file_description(&global_file, Scope::Global)
+    );
+    for (gitignore, count) in removed {
+        println!(
+            "Removed {} from {}",
+            pluralize_patterns(count),
+            gitignore.display()
+        );
+    }
+    Ok(())
+}
+
+/// Export the full ignore state
+fn run_snapshot(matches: &ArgMatches) -> anyhow::Result<()> {
+    let snapshot = Snapshot::capture()?;
+    match matches.get_one::<String>("output") {
+        Some(output) => {
+            std::fs::write(output, snapshot.to_string())
+                .with_context(|| format!("Failed to write snapshot: {output}"))?;
+            println!(
+                "Saved {} ignore file(s) and {} setting(s) to {output}",
+                snapshot.files.len(),
+                snapshot.config.len()
+            );
+        }
+        None => print!("{snapshot}"),
+    }
+    Ok(())
+}
+
+/// Restore ignore files and settings from a snapshot
+fn run_restore(matches: &ArgMatches) -> anyhow::Result<()> {
+    load_config(matches)?;
+    let file = matches.get_one::<String>("file").unwrap();
+    let text = if file == "-" {
+        io::read_to_string(io::stdin()).context("Failed to read snapshot from stdin")?
+    } else {
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read snapshot: {file}"))?
+    };
+
+    let snapshot = Snapshot::parse(&text)?;
+    let written = snapshot.restore(!matches.get_flag("skip-global"))?;
+    for path in &written {
+        println!("Restored {}", path.display());
+    }
+    for (key, value) in &snapshot.config {
+        println!("Set {key} = {value}");
+    }
+    Ok(())
+}
+
+/// Explain a pattern in words, with examples
+fn run_why(matches: &ArgMatches) -> anyhow::Result<()> {
+    let text = matches.get_one::<String>("pattern").unwrap();
+    if matches.get_flag("path") {
+        return print_decision_chain(text);
+    }
+    let pattern = Pattern::parse(text)?;
+    let explanation = explain::explain(&pattern);
+
+    println!("Pattern: {}", explanation.pattern);
+    for note in &explanation.notes {
+        println!("  - {note}");
+    }
+    if !explanation.matches.is_empty() {
+        println!("\nMatches, for example:");
+        for path in &explanation.matches {
+            println!("  {path}");
+        }
+    }
+    if !explanation.non_matches.is_empty() {
+        println!("\nDoes not match:");
+        for path in &explanation.non_matches {
+            println!("  {path}");
+        }
+    }
+    if pattern.negated && !explanation.matches.is_empty() {
+        println!("\n(Matching paths are re-included, not ignored.)");
+    }
+    Ok(())
+}
+
+/// Print every rule that matches `path` or its parent directories, then
+/// which one decides
+fn print_decision_chain(path: &str) -> anyhow::Result<()> {
+    let chain = decision::decision_chain(path)?;
+    let repo_root = git::get_repo_root()?;
+    let location = |step: &decision::ChainStep| {
+        let source = step
+            .rule
+            .source
+            .strip_prefix(&repo_root)
+            .unwrap_or(&step.rule.source);
+        format!("{}:{}", source.display(), step.rule.line_number)
+    };
+    let display = if chain.is_dir {
+        format!("{}/", chain.path)
+    } else {
+        chain.path.clone()
+    };
+
+    match chain.decided_by() {
+        None => println!("{display}: not ignored, no pattern matches"),
+        Some(step) if step.matched != chain.path => println!(
+            "{display}: ignored, because its directory {}/ is excluded by {} ({})",
+            step.matched,
+            step.rule.pattern,
+            location(step)
+        ),
+        Some(step) if step.rule.pattern.negated => println!(
+            "{display}: not ignored, re-included by {} ({})",
+            step.rule.pattern,
+            location(step)
+        ),
+        Some(step) => println!(
+            "{display}: ignored by {} ({})",
+            step.rule.pattern,
+            location(step)
+        ),
+    }
+    for (index, step) in chain.steps.iter().enumerate() {
+        let verb = if step.rule.pattern.negated {
+            "re-includes"
+        } else {
+            "ignores"
+        };
+        let target = if step.is_dir {
+            format!("{}/", step.matched)
+        } else {
+            step.matched.clone()
+        };
+        let decides = if chain.deciding == Some(index) {
+            " (decides)"
+        } else {
+            ""
+        };
+        println!(
+            "  {}. {}: {} {verb} {target}{decides}",
+            index + 1,
+            location(step),
+            step.rule.pattern
+        );
+    }
+    if chain.is_ignored() && git::list_tracked_files()?.contains(&chain.path) {
+        println!("The file is tracked, so git doesn't ignore it; see `git ignore untrack`");
+    }
+    Ok(())
+}
+
+/// Report the pattern deciding each path. Fails with a check failure unless
+/// every path is ignored, so scripts can test a single path by exit code.
+fn run_check(matches: &ArgMatches) -> anyhow::Result<()> {
+    let paths: Vec<String> = matches
+        .get_many::<String>("paths")
+        .unwrap()
+        .cloned()
+        .collect();
+    let repo_root = git::get_repo_root()?;
+    let checks = git::check_ignore(&paths, matches.get_flag("no-index"))?;
+
+    if !matches.get_flag("quiet") {
+        for check in &checks {
+            let Some(matched) = &check.matched else {
+                println!("{}: not ignored", check.path);
+                continue;
+            };
+            let source = matched
+                .source
+                .strip_prefix(&repo_root)
+                .unwrap_or(&matched.source);
+            let location = format!(
+                "{}:{}, {}",
+                source.display(),
+                matched.line_number,
+                matched.scope
+            );
+            if check.is_ignored() {
+                println!(
+                    "{}: ignored by {} ({location})",
+                    check.path, matched.pattern
+                );
+            } else {
+                println!(
+                    "{}: not ignored, re-included by {} ({location})",
+                    check.path, matched.pattern
+                );
+            }
+        }
+    }
+
+    let not_ignored = checks.iter().filter(|check| !check.is_ignored()).count();
+    if not_ignored > 0 {
+        anyhow::bail!(Error::check_failed(format!(
+            "{not_ignored} of {} path(s) not ignored",
+            checks.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Propose patterns for the languages detected in the repository, leaving
+/// out those the target file already has; `--apply` adds them
+fn run_suggest(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "suggestions are ignore patterns; drop --attributes"
+        ));
+    }
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+
+    let suggestions = detect::suggest()?;
+    if suggestions.is_empty() {
+        println!("No suggestions: no language with build output to ignore was detected");
+        return Ok(());
+    }
+    let patterns: Vec<String> = suggestions.iter().map(|s| s.pattern.clone()).collect();
+    let missing = ignore::plan_patterns_to_add(&target_file, &patterns, true)?;
+    if missing.is_empty() {
+        println!("No suggestions: {file_description} already has every suggested pattern");
+        return Ok(());
+    }
+    let suggestions: Vec<&detect::Suggestion> = suggestions
+        .iter()
+        .filter(|s| missing.contains(&s.pattern))
+        .collect();
+
+    if !matches.get_flag("apply") {
+        let width = suggestions
+            .iter()
+            .map(|s| s.pattern.len())
+            .max()
+            .unwrap_or(0);
+        println!("Suggested for {file_description}:");
+        for suggestion in &suggestions {
+            println!("  {:<width$}  {}", suggestion.pattern, suggestion.reason);
+        }
+        println!("Run git-ignore suggest --apply to add them");
+        return Ok(());
+    }
+
+    if scope == Scope::Local {
+        ignore::ensure_info_exclude_exists(&target_file)?;
+    }
+    let added = ignore::add_patterns_with_placement(
+        &target_file,
+        &missing,
+        true,
+        config_placement(&config),
+    )?;
+    println!(
+        "Added {} to {}:",
+        pluralize_patterns(added.len()),
+        file_description
+    );
+    for pattern in &added {
+        println!("  {pattern}");
+    }
+    Ok(())
+}
+
+/// ANSI styling for terminal output, disabled when not writing to a terminal
+#[derive(Debug, Clone, Copy)]
+struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    fn new(when: &str) -> Self {
+        let enabled = match when {
+            "always" => true,
+            "never" => false,
+            _ => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        };
+        Palette { enabled }
+    }
+
+    fn paint(self, text: &str, code: &str) -> String {
+        if self.enabled && !text.is_empty() {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+const STYLE_HEADER: &str = "1";
+const STYLE_SECTION: &str = "36";
+const STYLE_NEGATION: &str = "1;31";
+const STYLE_DIRECTORY: &str = "34";
+const STYLE_GLOB: &str = "33";
+
+/// Highlight a pattern: negations, directory-only patterns and wildcards
+fn highlight_pattern(pattern: &str, palette: Palette) -> String {
+    let (negation, rest) = match pattern.strip_prefix('!') {
+        Some(rest) => ("!", rest),
+        None => ("", pattern),
+    };
+    let base_style = if rest.ends_with('/') {
+        STYLE_DIRECTORY
+    } else {
+        ""
+    };
+
+    let mut out = palette.paint(negation, STYLE_NEGATION);
+    let mut plain = String::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                plain.push(c);
+                if let Some(escaped) = chars.next() {
+                    plain.push(escaped);
+                }
+            }
+            '*' | '?' | '[' => {
+                out.push_str(&paint_plain(&plain, base_style, palette));
+                plain.clear();
+                let mut glob = c.to_string();
+                if c == '[' {
+                    for next in chars.by_ref() {
+                        glob.push(next);
+                        if next == ']' {
+                            break;
+                        }
+                    }
+                }
+                out.push_str(&palette.paint(&glob, STYLE_GLOB));
+            }
+            _ => plain.push(c),
+        }
+    }
+    out.push_str(&paint_plain(&plain, base_style, palette));
+    out
+}
+
+fn paint_plain(text: &str, style: &str, palette: Palette) -> String {
+    if style.is_empty() {
+        text.to_string()
+    } else {
+        palette.paint(text, style)
+    }
+}
+
+/// Describe an ignore file for list output
+fn describe_source(path: &Path, scope: Scope) -> String {
+    if scope == Scope::Repo {
+        if let Ok(root) = git::get_repo_root() {
+            if let Ok(relative) = path.strip_prefix(&root) {
+                if relative != Path::new(".gitignore") {
+                    return format!("{} ({})", relative.display(), path.display());
+                }
+            }
+        }
+    }
+    get_file_description(path, scope)
+}
+
+/// Print the patterns of one ignore file, grouped by section, optionally
+/// with the line each one is on
+fn print_ignore_file(
+    path: &Path,
+    scope: Scope,
+    palette: Palette,
+    line_numbers: bool,
+) -> anyhow::Result<()> {
+    let entries = ignore::read_ignore_entries(path)?;
+    let description = describe_source(path, scope);
+
+    if entries.is_empty() {
+        println!("{}: no patterns", palette.paint(&description, STYLE_HEADER));
+        return Ok(());
+    }
+    println!(
+        "{}: {}",
+        palette.paint(&description, STYLE_HEADER),
+        pluralize_patterns(entries.len())
+    );
+
+    let width = entries
+        .iter()
+        .map(|entry| entry.line_number.to_string().len())
+        .max()
+        .unwrap_or(1);
+
+    // Group by section in order of first appearance
+    let mut groups: Vec<(Option<&str>, Vec<&ignore::IgnoreEntry>)> = Vec::new();
+    for entry in &entries {
+        let section = entry.section.as_deref();
+        match groups.iter_mut().find(|(name, _)| *name == section) {
+            Some((_, members)) => members.push(entry),
+            None => groups.push((section, vec![entry])),
+        }
+    }
+
+    for (section, members) in groups {
+        let heading = match section {
+            Some(name) => format!("# {name}"),
+            None => "(no section)".to_string(),
+        };
+        println!(
+            "  {} ({})",
+            palette.paint(&heading, STYLE_SECTION),
+            pluralize_patterns(members.len())
+        );
+        for entry in members {
+            let pattern = highlight_pattern(&entry.pattern, palette);
+            if line_numbers {
+                println!("    {:>width$}: {pattern}", entry.line_number);
+            } else {
+                println!("    {pattern}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The ignore files of a scope: every `.gitignore` in the work tree for
+/// `--repo`, otherwise the single target file. With `--attributes`, the
+/// gitattributes files instead.
+fn scope_files(matches: &ArgMatches, scope: Scope) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    match scope {
+        Scope::Repo if matches.get_flag("attributes") => {
+            let root_gitattributes = git::get_gitattributes_path()?;
+            let mut files = git::find_gitattributes_files()?;
+            if !files.contains(&root_gitattributes) {
+                files.insert(0, root_gitattributes);
+            }
+            Ok(files)
+        }
+        Scope::Repo => {
+            let root_gitignore = git::get_gitignore_path()?;
+            let mut files = git::find_gitignore_files()?;
+            if !files.contains(&root_gitignore) {
+                files.insert(0, root_gitignore);
+            }
+            Ok(files)
+        }
+        _ => Ok(vec![get_target_file(matches, scope)?]),
+    }
+}
+
+/// List the patterns of the selected scope, or of every scope with `--all`
+fn run_list(matches: &ArgMatches) -> anyhow::Result<()> {
+    let config = load_config(matches)?;
+    let palette = Palette::new(matches.get_one::<String>("color").unwrap());
+    let line_numbers = matches.get_flag("line-numbers");
+
+    let scopes = if matches.get_flag("all") {
+        if ["repo", "local", "global", "system"]
+            .iter()
+            .any(|flag| matches.get_count(flag) > 0)
+        {
+            anyhow::bail!(
+                "--all already lists every scope; drop --repo, --local, --global and --system"
+            );
+        }
+        let mut scopes = vec![Scope::Repo, Scope::Local, Scope::Global];
+        if !matches.get_flag("attributes") && git::get_configured_system_excludes_file()?.is_some()
+        {
+            scopes.push(Scope::System);
+        }
+        scopes
+    } else {
+        vec![resolve_scope(matches, &config)?]
+    };
+
+    let mut first = true;
+    for scope in scopes {
+        let files = match scope_files(matches, scope) {
+            Ok(files) => files,
+            // A missing global file shouldn't hide the other scopes
+            Err(e)
+                if scope == Scope::Global
+                    && matches!(e.downcast_ref::<Error>(), Some(Error::NoGlobalGitignore(_))) =>
+            {
+                let kind = if matches.get_flag("attributes") {
+                    "gitattributes"
+                } else {
+                    "gitignore"
+                };
+                if !first {
+                    println!();
+                }
+                println!(
+                    "{}: not configured",
+                    palette.paint(&format!("global {kind}"), STYLE_HEADER)
+                );
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for file in &files {
+            if !first {
+                println!();
+            }
+            first = false;
+            print_ignore_file(file, scope, palette, line_numbers)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sort the target file of the selected scope
+fn run_sort(matches: &ArgMatches) -> anyhow::Result<()> {
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+    let strategy = match matches.get_one::<String>("strategy") {
+        Some(strategy) => strategy.parse()?,
+        None => config.sort_strategy,
+    };
+
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+    let changed = if matches.get_flag("check") {
+        ignore::is_sorted(&target_file, strategy, &config.section_order).map(|sorted| !sorted)?
+    } else {
+        ignore::sort_ignore_file(&target_file, strategy, &config.section_order)?
+    };
+
+    match (changed, matches.get_flag("check")) {
+        (false, _) => println!("{file_description} is already sorted ({strategy})"),
+        (true, false) => println!("Sorted {file_description} ({strategy})"),
+        (true, true) => {
+            anyhow::bail!(Error::check_failed(format!(
+                "{file_description} is not sorted ({strategy})"
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Format the target file
+fn run_fmt(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config("fmt formats ignore files; drop --attributes"));
+    }
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+    let strategy = match matches.get_one::<String>("strategy") {
+        Some(strategy) => strategy.parse()?,
+        None => config.sort_strategy,
+    };
+
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+    let check = matches.get_flag("check");
+    let changed = if check {
+        !ignore::is_formatted(&target_file, strategy, &config.section_order)?
+    } else {
+        ignore::format_ignore_file(&target_file, strategy, &config.section_order)?
+    };
+
+    match (changed, check) {
+        (false, _) => println!("{file_description} is already formatted"),
+        (true, false) => println!("Formatted {file_description}"),
+        (true, true) => anyhow::bail!(Error::check_failed(format!(
+            "{file_description} is not formatted"
+        ))),
+    }
+    Ok(())
+}
+
+/// Remove the patterns of the target file that broader ones cover, after
+/// confirmation
+fn run_dedupe(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "dedupe works on ignore files; drop --attributes"
+        ));
+    }
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+    let content = if target_file.exists() {
+        fs::read_to_string(&target_file)
+            .with_context(|| format!("Failed to read: {}", target_file.display()))?
+    } else {
+        String::new()
+    };
+
+    let redundant = subsume::find_redundant(&content);
+    if redundant.is_empty() {
+        println!("No redundant patterns in {file_description}");
+        return Ok(());
+    }
+    let dry_run = matches.get_flag("dry-run");
+    println!("Redundant patterns in {file_description}:");
+    for found in &redundant {
+        println!(
+            "  {} (line {}) is covered by {} (line {})",
+            found.pattern, found.line_number, found.covered_by, found.covered_by_line
+        );
+    }
+    if dry_run {
+        return Ok(());
+    }
+    let patterns: Vec<String> = redundant
+        .iter()
+        .map(|found| found.pattern.clone())
+        .collect();
+    guard_protected(matches, &config, &patterns)?;
+
+    if !matches.get_flag("yes") {
+        if !io::stdin().is_terminal() {
+            anyhow::bail!(Error::config(
+                "not a terminal; pass --yes to remove without confirmation"
+            ));
+        }
+        eprint!("Remove them? [y/N] ");
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Nothing removed");
+            return Ok(());
+        }
+    }
+
+    let lines: Vec<usize> = redundant.iter().map(|found| found.line_number).collect();
+    ignore::write_ignore_file_if_unchanged(
+        &target_file,
+        Some(&content),
+        &subsume::without_lines(&content, &lines),
+    )?;
+    println!(
+        "Removed {} from {file_description}",
+        pluralize_patterns(redundant.len())
+    );
+    Ok(())
+}
+
+/// Lint the ignore files of the selected scope
+fn run_lint(matches: &ArgMatches) -> anyhow::Result<()> {
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+
+    // An explicit FILE needs no repository; its findings keep the path as given
+    let (files, repo_root) = match matches.get_one::<String>("file").map(PathBuf::from) {
+        Some(file) if !file.is_file() => anyhow::bail!("File not found: {}", file.display()),
+        Some(file) => (vec![file], None),
+        None => (scope_files(matches, scope)?, Some(git::get_repo_root()?)),
+    };
+    let display = |file: &Path| -> PathBuf {
+        repo_root
+            .as_deref()
+            .and_then(|root| file.strip_prefix(root).ok())
+            .unwrap_or(file)
+            .to_path_buf()
+    };
+    if matches.get_flag("fix") {
+        if matches.get_flag("attributes") {
+            anyhow::bail!(Error::config(
+                "--fix works on ignore files; drop --attributes"
+            ));
+        }
+        for file in &files {
+            let changed = lint::fix_file(file)?;
+            if !changed.is_empty() {
+                println!(
+                    "Fixed {} in {}",
+                    if changed.len() == 1 {
+                        "1 line".to_string()
+                    } else {
+                        format!("{} lines", changed.len())
+                    },
+                    display(file).display()
+                );
+            }
+        }
+    }
+    let mut findings = Vec::new();
+    for file in files {
+        if matches.get_flag("attributes") {
+            findings.extend(lint::lint_attributes_file(&file)?);
+        } else {
+            findings.extend(lint::lint_file(&file)?);
+        }
+    }
+
+    if matches.get_one::<String>("format").map(String::as_str) == Some("sarif") {
+        let root = match repo_root.clone() {
+            Some(root) => root,
+            None => std::env::current_dir().context("Failed to read the current directory")?,
+        };
+        println!("{}", sarif::to_sarif(&findings, &root));
+    } else {
+        for finding in &findings {
+            let file = display(&finding.file);
+            let severity = match finding.severity {
+                PatternSeverity::Error => "error",
+                PatternSeverity::Warning => "warning",
+                PatternSeverity::Info => "info",
+            };
+            println!(
+                "{}:{}: {severity}[{}]: {}",
+                file.display(),
+                finding.line,
+                finding.rule.id(),
+                finding.message
+            );
+            if let Some(suggestion) = &finding.suggestion {
+                println!("  help: {suggestion}");
+            }
+        }
+    }
+
+    if matches.get_flag("strict") && !findings.is_empty() {
+        anyhow::bail!(Error::check_failed(format!(
+            "{} finding(s) in ignore files",
+            findings.len()
+        )));
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == PatternSeverity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!(Error::check_failed(format!(
+            "{errors} error(s) in ignore files"
+        )));
+    }
+    Ok(())
+}
+
+/// Print the commit, date and author behind each pattern of a tracked
+/// ignore file
+fn run_blame(matches: &ArgMatches) -> anyhow::Result<()> {
+    if ["local", "global", "system"]
+        .iter()
+        .any(|flag| matches.get_count(flag) > 0)
+    {
+        anyhow::bail!(Error::config("only files tracked by git have a history; blame works on .gitignore and .gitattributes files"));
+    }
+    let file = match matches.get_one::<String>("file") {
+        Some(file) => PathBuf::from(file),
+        None if matches.get_flag("attributes") => git::get_gitattributes_path()?,
+        None => git::get_gitignore_path()?,
+    };
+    let lines = blame::blame_patterns(&file)?;
+    let number_width = lines
+        .iter()
+        .map(|line| line.line_number.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let author_width = lines
+        .iter()
+        .map(|line| line.author.chars().count())
+        .max()
+        .unwrap_or(0);
+    for line in &lines {
+        println!(
+            "{:>number_width$}  {}  {}  {:<author_width$}  {}",
+            line.line_number,
+            line.short_commit(),
+            line.date(),
+            line.author,
+            line.text.trim()
+        );
+    }
+    Ok(())
+}
+
+/// Summarize the ignore files of every scope, or of the selected one
+fn run_stats(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "stats works on ignore files; drop --attributes"
+        ));
+    }
+    let scopes = if ["repo", "local", "global", "system"]
+        .iter()
+        .any(|flag| matches.get_count(flag) > 0)
+    {
+        let config = load_config(matches)?;
+        vec![resolve_scope(matches, &config)?]
+    } else {
+        vec![Scope::Repo, Scope::Local, Scope::Global, Scope::System]
+    };
+    let repo_root = git::get_repo_root()?;
+    let files: Vec<_> = stats::collect()?
+        .into_iter()
+        .filter(|file| scopes.contains(&file.scope))
+        .collect();
+    let name = |path: &Path| {
+        path.strip_prefix(&repo_root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+
+    for &scope in &scopes {
+        let in_scope: Vec<_> = files.iter().filter(|file| file.scope == scope).collect();
+        let patterns: usize = in_scope.iter().map(|file| file.patterns.len()).sum();
+        match in_scope.len() {
+            0 => println!("{scope}: no ignore file"),
+            1 => println!("{scope}: {} in 1 file", pluralize_patterns(patterns)),
+            count => println!("{scope}: {} in {count} files", pluralize_patterns(patterns)),
+        }
+    }
+
+    let now = std::time::SystemTime::now();
+    for file in &files {
+        let age = file
+            .modified
+            .map(|modified| {
+                format!(
+                    ", modified {}",
+                    stats::format_age(now.duration_since(modified).unwrap_or_default())
+                )
+            })
+            .unwrap_or_default();
+        println!();
+        println!(
+            "{}: {}, {} bytes{age}",
+            name(&file.path),
+            pluralize_patterns(file.patterns.len()),
+            file.size
+        );
+        let width = file
+            .patterns
+            .iter()
+            .map(|usage| usage.matches.to_string().len())
+            .max()
+            .unwrap_or(1);
+        for usage in &file.patterns {
+            println!("  {:>width$}  {}", usage.matches, usage.rule.pattern);
+        }
+    }
+
+    let unused: Vec<_> = files.iter().flat_map(|file| file.unused()).collect();
+    if !unused.is_empty() {
+        println!();
+        println!("Unused patterns ({}):", unused.len());
+        for usage in unused {
+            println!(
+                "  {}:{}: {}",
+                name(&usage.rule.source),
+                usage.rule.line_number,
+                usage.rule.pattern
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Fail if options that only make sense when adding were given before the
+/// `name` subcommand. Scope flags and other global options may come before
+/// any subcommand, as with git itself.
+fn reject_add_args(matches: &ArgMatches, name: &str) -> anyhow::Result<()> {
+    let parser = create_parser();
+    for arg in parser.get_arguments().filter(|arg| !arg.is_global_set()) {
+        let id = arg.get_id().as_str();
+        if matches.value_source(id) == Some(ValueSource::CommandLine) {
+            let flag = arg
+                .get_long()
+                .map(|long| format!("--{long}"))
+                .unwrap_or_else(|| format!("<{}>", id.to_uppercase()));
+            if name == "add" {
+                anyhow::bail!("{flag} goes after the add subcommand");
+            }
+            anyhow::bail!("{flag} only applies to add, not to {name}");
+        }
+    }
+    Ok(())
+}
+
+/// Send diagnostics to stderr, filtered by `RUST_LOG`. Without `RUST_LOG`
+/// they stay off unless `--log-format` asks for them, which shows `info`
+/// and above. Closing a span logs how long it took.
+fn init_logging(format: Option<&str>) {
+    let filter = match env::var("RUST_LOG") {
+        Ok(spec) => EnvFilter::new(spec),
+        Err(_) if format.is_some() => EnvFilter::new("info"),
+        Err(_) => return,
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal());
+    if format == Some("json") {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
+}
+
+/// Act in `dir` as `git -C` does, before anything looks at the repository:
+/// relative paths, repository discovery and git commands all start there.
+/// Repeated `-C` options arrive joined, each relative to the one before.
+fn change_directory(dir: &Path) -> anyhow::Result<()> {
+    env::set_current_dir(dir).map_err(|e| Error::io("change to", dir, e))?;
+    Ok(())
+}
+
+/// Main application logic
+fn run() -> anyhow::Result<()> {
+    let matches = create_parser().get_matches();
+
+    init_logging(matches.get_one::<String>("log-format").map(String::as_str));
+    if let Some(&seconds) = matches.get_one::<u64>("git-timeout") {
+        git::set_git_timeout((seconds > 0).then(|| Duration::from_secs(seconds)));
+    }
+
+    if let Some(dirs) = matches.get_many::<String>("directory") {
+        change_directory(&dirs.collect::<PathBuf>())?;
+    }
+
+    if let Some((name, _)) = matches.subcommand() {
+        reject_add_args(&matches, name)?;
+    }
+    match matches.subcommand() {
+        Some(("add", sub_matches)) => run_add(sub_matches),
+        Some(("remove", sub_matches)) => run_remove(sub_matches),
+        Some(("template", sub_matches)) => run_template(sub_matches),
+        Some(("remove-template", sub_matches)) => run_remove_template(sub_matches),
+        Some(("list", sub_matches)) => run_list(sub_matches),
+        Some(("why", sub_matches)) => run_why(sub_matches),
+        Some(("check", sub_matches)) => run_check(sub_matches),
+        Some(("suggest", sub_matches)) => run_suggest(sub_matches),
+        Some(("init", sub_matches)) => run_init(sub_matches),
+        Some(("tui", sub_matches)) => run_tui(sub_matches),
+        Some(("untrack", sub_matches)) => run_untrack(sub_matches),
+        Some(("audit", sub_matches)) => run_audit(sub_matches),
+        Some(("unignore", sub_matches)) => run_unignore(sub_matches),
+        Some(("move", sub_matches)) => run_move(sub_matches),
+        Some(("merge", sub_matches)) => run_merge(sub_matches),
+        Some(("diff", sub_matches)) => run_diff(sub_matches),
+        Some(("sort", sub_matches)) => run_sort(sub_matches),
+        Some(("fmt", sub_matches)) => run_fmt(sub_matches),
+        Some(("dedupe", sub_matches)) => run_dedupe(sub_matches),
+        Some(("lint", sub_matches)) => run_lint(sub_matches),
+        Some(("stats", sub_matches)) => run_stats(sub_matches),
+        Some(("blame", sub_matches)) => run_blame(sub_matches),
+        Some(("prune-global", sub_matches)) => run_prune_global(sub_matches),
+        Some(("template-dir", sub_matches)) => run_template_dir(sub_matches),
+        Some(("snapshot", sub_matches)) => run_snapshot(sub_matches),
+        Some(("restore", sub_matches)) => run_restore(sub_matches),
+        _ => run_add(&matches),
+    }
+}
+
+/// If the target file contains merge conflict markers and we are talking to
+/// a terminal, offer to resolve them by keeping both sides' unique patterns.
+/// Otherwise the add fails with a message pointing at the conflict.
+fn offer_conflict_resolution(target_file: &Path, scope: Scope) -> anyhow::Result<()> {
+    let Ok(content) = std::fs::read_to_string(target_file) else {
+        return Ok(());
+    };
+    let markers = ignore::find_conflict_markers(&content);
+    let Some(first_line) = markers.first() else {
+        return Ok(());
+    };
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Ok(());
+    }
+
+    let file_description = get_file_description(target_file, scope);
+    eprint!(
+        "{file_description} contains merge conflict markers (line {first_line}).\n\
+         Resolve by keeping both sides' unique patterns? [y/N] "
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    if matches!(answer.trim(), "y" | "Y" | "yes") {
+        ignore::resolve_conflicts_in_ignore_file(target_file)?;
+        eprintln!("Resolved merge conflict in {file_description}");
+    }
+    Ok(())
+}
+
+/// Look for patterns that would fight existing negations in the target file.
+/// On a terminal each conflict is resolved interactively; otherwise it is
+/// reported as a warning and the pattern is added as given.
+///
+/// Returns the patterns still to add normally, and those already written
+/// by a resolution.
+fn handle_negation_conflicts(
+    target_file: &Path,
+    scope: Scope,
+    patterns: Vec<String>,
+    avoid_duplicates: bool,
+) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+    let planned = ignore::plan_patterns_to_add(target_file, &patterns, avoid_duplicates)?;
+    let content = std::fs::read_to_string(target_file).unwrap_or_default();
+    let conflicts = negation::find_negation_conflicts(&content, &planned);
+    if conflicts.is_empty() {
+        return Ok((patterns, Vec::new()));
+    }
+
+    let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+    if !interactive {
+        for conflict in &conflicts {
+            eprintln!("Warning: {conflict}");
+        }
+        return Ok((patterns, Vec::new()));
+    }
+
+    let file_description = get_file_description(target_file, scope);
+    let mut remaining = patterns;
+    let mut written = Vec::new();
+    let mut queue: VecDeque<String> = conflicts
+        .into_iter()
+        .map(|conflict| conflict.pattern)
+        .collect();
+    while let Some(pattern) = queue.pop_front() {
+        // Earlier fixes may have moved lines, so look again
+        let current = std::fs::read_to_string(target_file).unwrap_or_default();
+        let Some(conflict) =
+            negation::find_negation_conflicts(&current, std::slice::from_ref(&pattern)).pop()
+        else {
+            continue;
+        };
+        let Some(position) = remaining
+            .iter()
+            .position(|candidate| candidate.trim() == conflict.pattern)
+        else {
+            continue;
+        };
+
+        match ask_negation_choice(&conflict)? {
+            NegationChoice::Fix(resolution) => {
+                let updated = negation::resolve(&current, &conflict, resolution);
+                ignore::write_ignore_file(target_file, &updated)?;
+                written.push(remaining.remove(position));
+                eprintln!("Updated {file_description}");
+            }
+            NegationChoice::Adjust(replacement) => {
+                let issues = ignore::validate_ignore_patterns(std::slice::from_ref(&replacement));
+                display_validation_issues(&issues);
+                if has_blocking_issues(&issues) {
+                    anyhow::bail!(Error::ValidationFailed(Vec::new()));
+                }
+                queue.push_front(replacement.clone());
+                remaining[position] = replacement;
+            }
+            NegationChoice::Proceed => {}
+            NegationChoice::Skip => {
+                remaining.remove(position);
+            }
+        }
+    }
+    Ok((remaining, written))
+}
+
+/// Warn about patterns an existing line already covers, and note existing
+/// lines a new pattern makes redundant
+fn report_overlaps(
+    target_file: &Path,
+    patterns: &[String],
+    avoid_duplicates: bool,
+) -> anyhow::Result<()> {
+    let planned = ignore::plan_patterns_to_add(target_file, patterns, avoid_duplicates)?;
+    let content = std::fs::read_to_string(target_file).unwrap_or_default();
+    for overlap in subsume::find_redundant_patterns(&planned, &content) {
+        match overlap.coverage {
+            Coverage::AlreadyCovered => eprintln!("Warning: {overlap}"),
+            Coverage::CoversExisting => {
+                eprintln!("Note: {overlap}; 'git ignore dedupe' removes it")
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Warn about new negations that a higher-precedence ignore file defeats,
+/// such as a `.gitignore` rule overriding a negation in
+/// `.git/info/exclude`. Outside a repository there is nothing to check.
+fn report_overridden_negations(scope: Scope, patterns: &[String]) {
+    let Ok(repo) = RepoContext::current() else {
+        return;
+    };
+    let Ok(sources) = rules::effective_ignore_rules(repo, Path::new("")) else {
+        return;
+    };
+    for conflict in negation::overridden_negations(scope, &sources, patterns) {
+        eprintln!("Warning: {conflict}");
+    }
+}
+
+/// Warn about new patterns that match tracked files, which they won't
+/// ignore. Outside a repository there is nothing to check.
+fn report_tracked_matches(patterns: &[String]) {
+    let Ok(matches) = git::tracked_by_pattern(patterns) else {
+        return;
+    };
+    for (pattern, files) in matches {
+        eprintln!(
+            "Warning: '{pattern}' matches {} tracked file(s), which will not become ignored; pass --untrack to remove them from the index",
+            files.len()
+        );
+    }
+}
+
+/// Remove the tracked files `patterns` match from the index
+fn untrack_matching(patterns: &[String]) -> anyhow::Result<()> {
+    let mut files: Vec<String> = git::tracked_by_pattern(patterns)?
+        .into_iter()
+        .flat_map(|(_, files)| files)
+        .collect();
+    files.sort();
+    files.dedup();
+    if files.is_empty() {
+        return Ok(());
+    }
+    git::untrack_files(&files)?;
+    println!(
+        "Untracked {} file(s); commit to record the removal",
+        files.len()
+    );
+    Ok(())
+}
+
+/// What to do about a pattern that fights a negation
+enum NegationChoice {
+    Fix(Resolution),
+    Adjust(String),
+    Proceed,
+    Skip,
+}
+
+/// Show a negation conflict with its fixes and read the user's choice.
+/// Proceeding is the default, also when stdin is closed.
+fn ask_negation_choice(conflict: &NegationConflict) -> anyhow::Result<NegationChoice> {
+    eprintln!("{conflict}");
+    let resolutions = conflict.resolutions();
+    for (i, resolution) in resolutions.iter().enumerate() {
+        let label = match resolution {
+            Resolution::Reorder => "Reorder",
+            Resolution::Compensate => "Compensate",
+        };
+        eprintln!("  {}) {label}: {}", i + 1, conflict.describe(*resolution));
+    }
+    let adjust = resolutions.len() + 1;
+    eprintln!("  {adjust}) Adjust: enter a different pattern");
+    eprintln!(
+        "  {}) Proceed: add '{}' anyway",
+        adjust + 1,
+        conflict.pattern
+    );
+    eprintln!("  {}) Skip: leave '{}' out", adjust + 2, conflict.pattern);
+
+    loop {
+        eprint!("Choice [{}]: ", adjust + 1);
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(NegationChoice::Proceed);
+        }
+        match answer.trim().parse::<usize>() {
+            _ if answer.trim().is_empty() => return Ok(NegationChoice::Proceed),
+            Ok(choice) if (1..adjust).contains(&choice) => {
+                return Ok(NegationChoice::Fix(resolutions[choice - 1]))
+            }
+            Ok(choice) if choice == adjust => {
+                eprint!("Pattern to add instead of '{}': ", conflict.pattern);
+                io::stderr().flush()?;
+                let mut replacement = String::new();
+                io::stdin().read_line(&mut replacement)?;
+                return Ok(match replacement.trim() {
+                    "" => NegationChoice::Skip,
+                    text => NegationChoice::Adjust(text.to_string()),
+                });
+            }
+            Ok(choice) if choice == adjust + 1 => return Ok(NegationChoice::Proceed),
+            Ok(choice) if choice == adjust + 2 => return Ok(NegationChoice::Skip),
+            _ => eprintln!("Please enter a number from 1 to {}", adjust + 2),
+        }
+    }
+}
+
+/// What to do with the excludes file a worktree's `core.excludesFile`
+/// would replace
+enum DisplacedExcludes {
+    /// Copy its patterns into the worktree exclude file
+    Copy,
+    /// Stop applying it in this worktree
+    Replace,
+    /// Leave the worktree alone and add nothing
+    Cancel,
+}
+
+/// Point this worktree's `core.excludesFile` at its private exclude file.
+///
+/// The setting replaces the excludes file git read until now, usually the
+/// user's global one, for this worktree only. Its patterns are copied into
+/// the private file with `--keep-excludes`; otherwise the user is asked,
+/// and without a terminal to ask on the change is refused. Returns `false`
+/// if the user cancelled.
+fn enable_worktree_exclude(matches: &ArgMatches, exclude_file: &Path) -> anyhow::Result<bool> {
+    let previous = git::get_excludes_file()?.filter(|previous| previous != exclude_file);
+    if let Some(previous) = &previous {
+        let choice = if matches.get_flag("keep-excludes") {
+            DisplacedExcludes::Copy
+        } else {
+            ask_displaced_excludes(previous)?
+        };
+        match choice {
+            DisplacedExcludes::Copy => {
+                let patterns: Vec<String> = ignore::read_ignore_entries(previous)?
+                    .into_iter()
+                    .filter(|entry| entry.parsed.is_some())
+                    .map(|entry| entry.pattern)
+                    .collect();
+                let heading = format!("from {}", previous.display());
+                let copied = ignore::add_patterns_with_placement(
+                    exclude_file,
+                    &patterns,
+                    true,
+                    Placement::Group(heading),
+                )?;
+                println!(
+                    "Copied {} pattern(s) from {} into the worktree exclude file",
+                    copied.len(),
+                    previous.display()
+                );
+            }
+            DisplacedExcludes::Replace => {}
+            DisplacedExcludes::Cancel => return Ok(false),
+        }
+    }
+
+    if git::enable_worktree_excludes_file(exclude_file)? {
+        println!(
+            "Set core.excludesFile for this worktree to {}",
+            exclude_file.display()
+        );
+    }
+    Ok(true)
+}
+
+/// Ask whether to copy, replace or keep the excludes file that `--worktree`
+/// would stop git reading in this worktree
+fn ask_displaced_excludes(previous: &Path) -> anyhow::Result<DisplacedExcludes> {
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!(Error::config(format!(
+            "--worktree sets core.excludesFile for this worktree, so {} would no longer apply \
+             in it; pass --keep-excludes to copy its patterns into the worktree exclude file",
+            previous.display()
+        )));
+    }
+
+    eprint!(
+        "--worktree sets core.excludesFile for this worktree, so {} would no longer apply in it.\n\
+         [c]opy its patterns into the worktree exclude file, [r]eplace it anyway, or [N] cancel? ",
+        previous.display()
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(match answer.trim() {
+        "c" | "C" | "copy" => DisplacedExcludes::Copy,
+        "r" | "R" | "replace" => DisplacedExcludes::Replace,
+        _ => DisplacedExcludes::Cancel,
+    })
+}
+
+/// Where new patterns go: a `--group` block, sorted, next to similar
+/// patterns, or appended; flags win over config
+fn placement(matches: &ArgMatches, config: &Config) -> Placement {
+    if let Some(heading) = matches.get_one::<String>("group") {
+        Placement::Group(heading.clone())
+    } else if matches.get_flag("sorted") {
+        Placement::Sorted(config.sort_strategy)
+    } else if matches.get_flag("affinity") {
+        Placement::Affinity
+    } else {
+        config_placement(config)
+    }
+}
+
+/// Where the settings place added patterns when the command line doesn't say
+fn config_placement(config: &Config) -> Placement {
+    if config.sorted {
+        Placement::Sorted(config.sort_strategy)
+    } else if config.affinity {
+        Placement::Affinity
+    } else {
+        Placement::Append
+    }
+}
+
+/// Let the user pick untracked files on the terminal and return the
+/// patterns chosen for them
+fn pick_untracked() -> anyhow::Result<Vec<String>> {
+    let untracked = git::list_untracked_status()?;
+    if untracked.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!(Error::config("--interactive needs a terminal"));
+    }
+
+    let mut tty = fs::OpenOptions::new()
+        .read(true)
+        .open("/dev/tty")
+        .context(Error::config("--interactive needs a terminal"))?;
+    let _raw = RawMode::enable()?;
+    let mut picker = Picker::new(&untracked);
+    let height = terminal_size().0.saturating_sub(3).max(1);
+    let mut stderr = io::stderr();
+    // Draw on the alternate screen so the list doesn't linger afterwards
+    write!(stderr, "\x1b[?1049h")?;
+    let state = loop {
+        write!(stderr, "\x1b[H\x1b[2J")?;
+        for line in picker.render(height) {
+            write!(stderr, "{line}\r\n")?;
+        }
+        stderr.flush()?;
+
+        let mut input = [0u8; 16];
+        let read = tty.read(&mut input)?;
+        if read == 0 {
+            break PickerState::Cancelled;
+        }
+        let mut state = PickerState::Open;
+        for key in decode_keys(&input[..read]) {
+            state = picker.handle(key);
+            if state != PickerState::Open {
+                break;
+            }
+        }
+        if state != PickerState::Open {
+            break state;
+        }
+    };
+    write!(stderr, "\x1b[?1049l")?;
+    stderr.flush()?;
+
+    Ok(match state {
+        PickerState::Confirmed => picker.patterns(),
+        _ => Vec::new(),
+    })
+}
+
+/// Size of the terminal as rows and columns, 24 by 80 if it can't be told
+fn terminal_size() -> (usize, usize) {
+    let size = std::process::Command::new("stty")
+        .arg("size")
+        .stdin(fs::File::open("/dev/tty").map_or_else(|_| Stdio::null(), Stdio::from))
+        .output()
+        .ok()
+        .and_then(|output| {
+            let text = String::from_utf8_lossy(&output.stdout).into_owned();
+            let mut numbers = text.split_whitespace().map(|n| n.parse::<usize>().ok());
+            Some((numbers.next()??, numbers.next()??))
+        });
+    match size {
+        Some((rows, columns)) if rows > 0 && columns > 0 => (rows, columns),
+        _ => (24, 80),
+    }
+}
+
+/// Fail up front where [`RawMode`] can't work: it drives the terminal with
+/// `stty` and `/dev/tty`, which Windows doesn't have
+fn require_unix_terminal(mode: &str) -> anyhow::Result<()> {
+    if cfg!(windows) {
+        anyhow::bail!(Error::config(format!("{mode} is not supported on Windows")));
+    }
+    Ok(())
+}
+
+/// The terminal in unbuffered, unechoed mode, restored when dropped
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> anyhow::Result<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1", "time", "0"])?;
+        Ok(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Run `stty` on the controlling terminal
+fn stty(args: &[&str]) -> anyhow::Result<String> {
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(fs::File::open("/dev/tty")?)
+        .output()
+        .context(Error::config("--interactive needs stty"))?;
+    if !output.status.success() {
+        anyhow::bail!(Error::config(format!(
+            "stty failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Move patterns between the ignore files of two scopes
+fn run_move(matches: &ArgMatches) -> anyhow::Result<()> {
+    if ["repo", "local", "global", "system"]
+        .iter()
+        .any(|flag| matches.get_count(flag) > 0)
+    {
+        anyhow::bail!(
+            "move picks its files with --from and --to; drop --repo, --local, --global and --system"
+        );
+    }
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "move works on ignore files; drop --attributes"
+        ));
+    }
+    let patterns: Vec<String> = matches
+        .get_many::<String>("patterns")
+        .unwrap()
+        .cloned()
+        .collect();
+    let from = *matches.get_one::<Scope>("from").unwrap();
+    let to = *matches.get_one::<Scope>("to").unwrap();
+    let config = load_config(matches)?;
+    guard_protected(matches, &config, &patterns)?;
+
+    let from_file = get_target_file(matches, from)?;
+    let to_file = get_target_file(matches, to)?;
+    if to == Scope::Local {
+        ignore::ensure_info_exclude_exists(&to_file)?;
+    }
+    let report = ignore::move_patterns(&from_file, &to_file, &patterns, config_placement(&config))?;
+
+    let from_description = get_file_description(&from_file, from);
+    let to_description = get_file_description(&to_file, to);
+    println!(
+        "Moved {} from {from_description} to {to_description}:",
+        pluralize_patterns(report.removed.len())
+    );
+    for removed in &report.removed {
+        println!("  {} (line {})", removed.pattern, removed.line_number);
+    }
+    let present: Vec<&str> = report
+        .removed
+        .iter()
+        .map(|removed| removed.pattern.as_str())
+        .filter(|pattern| !report.added.iter().any(|added| added == pattern))
+        .collect();
+    if !present.is_empty() {
+        println!("  {} already had {}", to_description, present.join(", "));
+    }
+    Ok(())
+}
+
+/// Merge another ignore file into the target file
+fn run_merge(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "merge works on ignore files; drop --attributes"
+        ));
+    }
+    let source = PathBuf::from(matches.get_one::<String>("file").unwrap());
+    let dry_run = matches.get_flag("dry-run");
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+
+    let target_file = get_target_file(matches, scope)?;
+    if scope == Scope::Local && !dry_run {
+        ignore::ensure_info_exclude_exists(&target_file)?;
+    }
+    let file_description = get_file_description(&target_file, scope);
+    let report = merge::merge_file(&target_file, &source, dry_run)?;
+
+    if report.added.is_empty() {
+        println!(
+            "{file_description} already has every pattern in {}",
+            source.display()
+        );
+    } else {
+        println!(
+            "{} {} from {} into {file_description}:",
+            if dry_run { "Would merge" } else { "Merged" },
+            pluralize_patterns(report.added.len()),
+            source.display()
+        );
+        for pattern in &report.added {
+            println!("  {pattern}");
+        }
+    }
+    for (pattern, existing) in &report.skipped {
+        if pattern == existing {
+            println!("Skipped {pattern} (already present)");
+        } else {
+            println!("Skipped {pattern} (same as {existing})");
+        }
+    }
+    Ok(())
+}
+
+/// Compare two ignore sources pattern by pattern
+fn run_diff(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "diff compares ignore files; drop --attributes"
+        ));
+    }
+    let files: Vec<PathBuf> = matches
+        .get_many::<String>("files")
+        .unwrap()
+        .map(PathBuf::from)
+        .collect();
+    for file in &files {
+        if !file.is_file() {
+            anyhow::bail!("File not found: {}", file.display());
+        }
+    }
+    let (left, left_description, right) = match files.as_slice() {
+        [left, right] => {
+            if ["repo", "local", "global", "system"]
+                .iter()
+                .any(|flag| matches.get_count(flag) > 0)
             {
-                eprintln!("File system error: {e}");
-                EXIT_FILE_ERROR
+                anyhow::bail!("Give either a scope and one file, or two files, not both");
+            }
+            (left.clone(), left.display().to_string(), right.clone())
+        }
+        [right] => {
+            let config = load_config(matches)?;
+            let scope = resolve_scope(matches, &config)?;
+            let left = get_target_file(matches, scope)?;
+            let description = get_file_description(&left, scope);
+            (left, description, right.clone())
+        }
+        _ => unreachable!("clap takes one or two files"),
+    };
+    let right_description = right.display().to_string();
+    let diff = diff::diff_files(&left, &right)?;
+
+    let describe = |side: Side| match side {
+        Side::Left => left_description.as_str(),
+        Side::Right => right_description.as_str(),
+    };
+    for (side, entries) in [
+        (Side::Left, &diff.only_left),
+        (Side::Right, &diff.only_right),
+    ] {
+        if !entries.is_empty() {
+            println!("Only in {} ({}):", describe(side), entries.len());
+            for entry in entries {
+                println!("  {} (line {})", entry.pattern, entry.line_number);
+            }
+        }
+    }
+    if !diff.common.is_empty() {
+        println!("In both ({}):", diff.common.len());
+        for (left, right) in &diff.common {
+            if left.pattern == right.pattern {
+                println!(
+                    "  {} (lines {} and {})",
+                    left.pattern, left.line_number, right.line_number
+                );
             } else {
-                eprintln!("Error: {e}");
-                EXIT_FILE_ERROR
+                println!(
+                    "  {} (line {}) = {} (line {})",
+                    left.pattern, left.line_number, right.pattern, right.line_number
+                );
+            }
+        }
+    }
+    if !diff.conflicts.is_empty() {
+        println!("Conflicting negations ({}):", diff.conflicts.len());
+        for conflict in &diff.conflicts {
+            let other = match conflict.negation_side {
+                Side::Left => Side::Right,
+                Side::Right => Side::Left,
+            };
+            println!(
+                "  {} ({}:{}) re-includes {}, which {} ({}:{}) ignores",
+                conflict.negation.pattern,
+                describe(conflict.negation_side),
+                conflict.negation.line_number,
+                conflict.example,
+                conflict.pattern.pattern,
+                describe(other),
+                conflict.pattern.line_number
+            );
+        }
+    }
+    if diff.is_empty() {
+        println!("{left_description} and {right_description} have the same patterns");
+    }
+    Ok(())
+}
+
+/// Re-include each path with the chain of negations it needs, checking
+/// afterwards that git no longer ignores it
+fn run_unignore(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config(
+            "unignore writes ignore patterns; drop --attributes"
+        ));
+    }
+    let config = load_config(matches)?;
+    let scope = resolve_scope(matches, &config)?;
+    let target_file = get_target_file(matches, scope)?;
+    let file_description = get_file_description(&target_file, scope);
+    let dry_run = matches.get_flag("dry-run");
+    if scope == Scope::Local && !dry_run {
+        ignore::ensure_info_exclude_exists(&target_file)?;
+    }
+
+    for path in matches.get_many::<String>("paths").unwrap() {
+        let added = if dry_run {
+            unignore::plan(path)?.map(|(chain, matched)| {
+                println!(
+                    "{path} is ignored by {} ({}:{})",
+                    matched.pattern,
+                    matched.source.display(),
+                    matched.line_number
+                );
+                println!("Would add to {file_description}:");
+                chain
+            })
+        } else {
+            let added = unignore::unignore(path, &target_file)?;
+            if added.is_some() {
+                println!("Unignored {path} in {file_description}:");
+            }
+            added
+        };
+        match added {
+            Some(patterns) => {
+                for pattern in &patterns {
+                    println!("  {pattern}");
+                }
+            }
+            None => println!("{path} is not ignored"),
+        }
+    }
+    Ok(())
+}
+
+/// List tracked files the ignore rules match, failing the check if there
+/// are any
+fn run_audit(matches: &ArgMatches) -> anyhow::Result<()> {
+    let repo_root = git::get_repo_root()?;
+    let findings = audit::tracked_ignored()?;
+
+    let format = matches.get_one::<String>("format").map(String::as_str);
+    if format == Some("json") {
+        println!("{}", audit::to_json(&findings, &repo_root));
+    } else if format == Some("sarif") {
+        println!("{}", audit::to_sarif(&findings, &repo_root));
+    } else if findings.is_empty() {
+        println!("No tracked files match the ignore rules");
+    } else {
+        let rows: Vec<[String; 4]> = findings
+            .iter()
+            .map(|finding| {
+                let source = finding
+                    .rule
+                    .source
+                    .strip_prefix(&repo_root)
+                    .unwrap_or(&finding.rule.source);
+                [
+                    finding.path.clone(),
+                    finding.rule.pattern.clone(),
+                    format!("{}:{}", source.display(), finding.rule.line_number),
+                    finding.rule.scope.to_string(),
+                ]
+            })
+            .collect();
+        let header = ["PATH", "PATTERN", "SOURCE", "SCOPE"].map(String::from);
+        let mut widths = [0; 3];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in std::iter::once(&header).chain(&rows) {
+            println!(
+                "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2]
+            );
+        }
+    }
+
+    if !findings.is_empty() {
+        anyhow::bail!(Error::check_failed(format!("{} tracked file(s) match the ignore rules (git ignore untrack removes them from the index)",
+            findings.len())));
+    }
+    Ok(())
+}
+
+/// Remove tracked files that the ignore rules (or the given patterns) match
+/// from the index, after showing them and asking
+fn run_untrack(matches: &ArgMatches) -> anyhow::Result<()> {
+    let patterns: Vec<String> = matches
+        .get_many::<String>("patterns")
+        .map(|patterns| patterns.cloned().collect())
+        .unwrap_or_default();
+    let files = git::list_tracked_ignored(&patterns)?;
+    if files.is_empty() {
+        println!("No tracked files match the ignore rules");
+        return Ok(());
+    }
+
+    let dry_run = matches.get_flag("dry-run");
+    println!(
+        "{} {} file(s):",
+        if dry_run {
+            "Would stop tracking"
+        } else {
+            "Stop tracking"
+        },
+        files.len()
+    );
+    for file in &files {
+        println!("  {file}");
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    if !matches.get_flag("yes") {
+        if !io::stdin().is_terminal() {
+            anyhow::bail!(Error::config(
+                "not a terminal; pass --yes to untrack without confirmation"
+            ));
+        }
+        eprint!("Remove them from the index? The files stay on disk. [y/N] ");
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Nothing untracked");
+            return Ok(());
+        }
+    }
+
+    git::untrack_files(&files)?;
+    println!(
+        "Untracked {} file(s); commit to record the removal",
+        files.len()
+    );
+    Ok(())
+}
+
+/// Edit the three ignore files side by side until the user saves or
+/// discards the changes
+fn run_tui(matches: &ArgMatches) -> anyhow::Result<()> {
+    require_unix_terminal("tui")?;
+    if matches.get_flag("attributes") {
+        anyhow::bail!(Error::config("tui edits ignore files; drop --attributes"));
+    }
+    load_config(matches)?;
+    let files = vec![
+        ScopeFile::load(Scope::Repo, Some(get_target_file(matches, Scope::Repo)?))?,
+        ScopeFile::load(Scope::Local, Some(get_target_file(matches, Scope::Local)?))?,
+        ScopeFile::load(Scope::Global, get_target_file(matches, Scope::Global).ok())?,
+    ];
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!(Error::config("tui needs a terminal"));
+    }
+    let mut tty = fs::OpenOptions::new()
+        .read(true)
+        .open("/dev/tty")
+        .context(Error::config("tui needs a terminal"))?;
+    let mut editor = tui::Editor::new(files, git::list_work_tree_files()?);
+
+    let outcome = {
+        let _raw = RawMode::enable()?;
+        let mut stderr = io::stderr();
+        write!(stderr, "\x1b[?1049h")?;
+        let outcome = loop {
+            let (rows, columns) = terminal_size();
+            write!(stderr, "\x1b[H\x1b[2J")?;
+            write!(stderr, "{}", editor.render(columns, rows).join("\r\n"))?;
+            stderr.flush()?;
+
+            let mut input = [0u8; 64];
+            let read = tty.read(&mut input)?;
+            if read == 0 {
+                break tui::Outcome::Discard;
+            }
+            let outcome = keys::decode_keys(&input[..read])
+                .into_iter()
+                .map(|key| editor.handle(key))
+                .find(|outcome| *outcome != tui::Outcome::Continue);
+            if let Some(outcome) = outcome {
+                break outcome;
+            }
+        };
+        write!(stderr, "\x1b[?1049l")?;
+        stderr.flush()?;
+        outcome
+    };
+
+    let modified: Vec<&ScopeFile> = editor.files.iter().filter(|f| f.is_modified()).collect();
+    if modified.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+    if outcome == tui::Outcome::Discard {
+        println!("Discarded changes to {} file(s)", modified.len());
+        return Ok(());
+    }
+    editor.save()?;
+    for file in modified {
+        if let Some(path) = &file.path {
+            println!("Saved {}", get_file_description(path, file.scope));
+        }
+    }
+    Ok(())
+}
+
+/// Add patterns to the selected ignore file
+fn run_add(matches: &ArgMatches) -> anyhow::Result<()> {
+    if matches.get_flag("interactive") {
+        require_unix_terminal("--interactive")?;
+    }
+    let config = load_config(matches)?;
+    let mut targeted = command_line_targets(matches);
+    if !matches.get_flag("literal") {
+        targeted = expand_sets(targeted, &config)?;
+    }
+    if matches.get_flag("literal") {
+        for (_, pattern) in &mut targeted {
+            *pattern = escape_literal(pattern);
+        }
+    }
+    if matches.get_flag("stdin") {
+        let mut input = Vec::new();
+        io::stdin()
+            .read_to_end(&mut input)
+            .context("Failed to read patterns from stdin")?;
+        // Piped items are taken without @target suffixes; NUL-separated ones
+        // come back already escaped as file names
+        let null = matches.get_flag("null");
+        let escape = matches.get_flag("literal") && !null;
+        targeted.extend(
+            ignore::parse_pattern_input(&input, null)?
+                .into_iter()
+                .map(|pattern| {
+                    let pattern = if escape {
+                        escape_literal(&pattern)
+                    } else {
+                        pattern
+                    };
+                    (None, pattern)
+                }),
+        );
+    }
+    if matches.get_flag("interactive") {
+        let picked = pick_untracked()?;
+        if picked.is_empty() {
+            println!("Nothing selected");
+            return Ok(());
+        }
+        targeted.extend(picked.into_iter().map(|pattern| (None, pattern)));
+    }
+    let no_validate = matches.get_flag("no-validate");
+
+    // Validate patterns first if not disabled
+    let validation_level = if no_validate {
+        PatternValidationLevel::None
+    } else {
+        PatternValidationLevel::Warn
+    };
+
+    // Attribute lines are compared with their whitespace collapsed
+    let attributes_mode = matches.get_flag("attributes");
+    if attributes_mode {
+        for (_, pattern) in &mut targeted {
+            *pattern = attributes::normalize_attribute_line(pattern);
+        }
+    }
+    let patterns: Vec<String> = targeted
+        .iter()
+        .map(|(_, pattern)| pattern.clone())
+        .collect();
+
+    let issues = if validation_level == PatternValidationLevel::None {
+        Vec::new()
+    } else if attributes_mode {
+        attributes::validate_attribute_lines(&patterns)
+    } else {
+        let mut issues = ignore::validate_ignore_patterns(&patterns);
+        // Best effort: outside a repository there is nothing to find
+        if let Ok(repositories) = git::find_nested_repositories() {
+            issues.extend(nested::check_patterns(&patterns, &repositories));
+        }
+        issues
+    };
+
+    // Display validation issues
+    display_validation_issues(&issues);
+
+    // Check if we should continue
+    if has_blocking_issues(&issues) {
+        anyhow::bail!(Error::ValidationFailed(Vec::new()));
+    }
+
+    if matches.get_flag("commit") || matches.get_flag("create-pr") {
+        return run_commit(matches, &config, targeted);
+    }
+
+    let interleaved = scopes_interleaved(matches);
+    let fallback = if interleaved || matches.get_flag("auto-target") {
+        config.default_target.unwrap_or(Scope::Repo)
+    } else {
+        resolve_scope(matches, &config)?
+    };
+
+    let mut groups: Vec<(Scope, Vec<String>)> = Vec::new();
+    let mut automatic = Vec::new();
+    for (target, pattern) in targeted {
+        match target {
+            Some(scope) => push_to_group(&mut groups, scope, pattern),
+            None if matches.get_flag("auto-target") => automatic.push(pattern),
+            None => push_to_group(&mut groups, fallback, pattern),
+        }
+    }
+    for (scope, patterns) in auto_target_groups(&automatic, &config) {
+        for pattern in patterns {
+            push_to_group(&mut groups, scope, pattern);
+        }
+    }
+
+    let mut up_to_date = true;
+    for (scope, patterns) in groups {
+        up_to_date &= add_to_scope(matches, &config, scope, patterns)?;
+    }
+    if !up_to_date {
+        anyhow::bail!(Error::check_failed("ignore files are missing patterns"));
+    }
+    Ok(())
+}
+
+/// Add patterns to `.gitignore` on a branch by writing a commit
+fn run_commit(
+    matches: &ArgMatches,
+    config: &Config,
+    targeted: Vec<(Option<Scope>, String)>,
+) -> anyhow::Result<()> {
+    if targeted
+        .iter()
+        .any(|(target, _)| target.is_some_and(|scope| scope != Scope::Repo))
+    {
+        anyhow::bail!(Error::config("--commit only edits .gitignore"));
+    }
+    let patterns: Vec<String> = targeted.into_iter().map(|(_, pattern)| pattern).collect();
+    let patterns = run_validator_hook(
+        matches,
+        config,
+        Path::new(".gitignore"),
+        Scope::Repo,
+        patterns,
+    )?;
+
+    let options = CommitOptions {
+        branch: matches.get_one::<String>("branch").cloned(),
+        start_point: None,
+        author: matches.get_one::<String>("author").cloned(),
+        message: matches.get_one::<String>("message").cloned(),
+        avoid_duplicates: !matches.get_flag("allow-duplicates"),
+        placement: placement(matches, config),
+    };
+    if matches.get_flag("create-pr") {
+        return run_create_pr(matches, config, &patterns, options);
+    }
+    let outcome = commit::commit_patterns(&patterns, &options)?;
+
+    match &outcome.commit {
+        None => println!(
+            "No new patterns added to .gitignore on {} (all patterns already exist)",
+            outcome.reference
+        ),
+        Some(commit) => {
+            println!(
+                "Committed {} to .gitignore on {} ({commit}):",
+                pluralize_patterns(outcome.added.len()),
+                outcome.reference
+            );
+            for pattern in &outcome.added {
+                println!("  {pattern}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Push a commit adding `patterns` and open a pull request for it
+#[cfg(feature = "forge")]
+fn run_create_pr(
+    matches: &ArgMatches,
+    config: &Config,
+    patterns: &[String],
+    commit: CommitOptions,
+) -> anyhow::Result<()> {
+    let options = PullRequestOptions {
+        remote: matches.get_one::<String>("remote").unwrap().clone(),
+        base: matches.get_one::<String>("base").cloned(),
+        commit,
+    };
+    match forge::create_pull_request(patterns, &options, &config.auto_targets)? {
+        None => println!("No new patterns to propose (all patterns already exist)"),
+        Some(request) => {
+            println!(
+                "Opened {} {} adding {}:",
+                request.forge.request_noun(),
+                request.url,
+                pluralize_patterns(request.added.len())
+            );
+            for pattern in &request.added {
+                println!("  {pattern}");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "forge"))]
+fn run_create_pr(
+    _matches: &ArgMatches,
+    _config: &Config,
+    _patterns: &[String],
+    _commit: CommitOptions,
+) -> anyhow::Result<()> {
+    anyhow::bail!(Error::config(
+        "--create-pr needs git-ignore built with the forge feature"
+    ))
+}
+
+/// Command-line patterns with the scope each was explicitly given, either
+/// by a `pattern@target` suffix or, when scope flags are interleaved with
+/// patterns, by the closest scope flag before it
+fn command_line_targets(matches: &ArgMatches) -> Vec<(Option<Scope>, String)> {
+    let (Some(indices), Some(values)) = (
+        matches.indices_of("patterns"),
+        matches.get_many::<String>("patterns"),
+    ) else {
+        return Vec::new();
+    };
+    let flags = scope_flag_indices(matches);
+    let interleaved = scopes_interleaved(matches);
+
+    indices
+        .zip(values)
+        .map(|(index, value)| {
+            let (pattern, suffix) = route::split_target_suffix(value);
+            let flag = flags
+                .iter()
+                .filter(|(flag_index, _)| interleaved && *flag_index < index)
+                .max_by_key(|(flag_index, _)| *flag_index)
+                .map(|(_, scope)| *scope);
+            (suffix.or(flag), pattern.to_string())
+        })
+        .collect()
+}
+
+/// Replace each `@name` with the patterns of that set, keeping the target
+/// the reference had
+fn expand_sets(
+    targeted: Vec<(Option<Scope>, String)>,
+    config: &Config,
+) -> anyhow::Result<Vec<(Option<Scope>, String)>> {
+    let mut expanded = Vec::with_capacity(targeted.len());
+    for (target, pattern) in targeted {
+        let patterns = sets::expand(std::slice::from_ref(&pattern), &config.sets)?;
+        expanded.extend(patterns.into_iter().map(|pattern| (target, pattern)));
+    }
+    Ok(expanded)
+}
+
+/// Positions of every `--repo`, `--local`, `--global` and `--system` on the
+/// command line
+fn scope_flag_indices(matches: &ArgMatches) -> Vec<(usize, Scope)> {
+    let mut flags = Vec::new();
+    for (id, scope) in [
+        ("repo", Scope::Repo),
+        ("local", Scope::Local),
+        ("global", Scope::Global),
+        ("system", Scope::System),
+    ] {
+        if matches.get_count(id) > 0 {
+            if let Some(indices) = matches.indices_of(id) {
+                flags.extend(indices.map(|index| (index, scope)));
+            }
+        }
+    }
+    flags
+}
+
+/// Whether scope flags are mixed in among the patterns (`a --local b`,
+/// `--local a --global b`), in which case each flag applies to the
+/// patterns after it. When the flags form one block before or after all
+/// patterns (`--local a b`, `a b --local`), the single resolved scope
+/// applies to every pattern, as it always has.
+fn scopes_interleaved(matches: &ArgMatches) -> bool {
+    let flags: Vec<usize> = scope_flag_indices(matches)
+        .iter()
+        .map(|(index, _)| *index)
+        .collect();
+    let (Some(&first), Some(&last)) = (flags.iter().min(), flags.iter().max()) else {
+        return false;
+    };
+    let patterns: Vec<usize> = matches
+        .indices_of("patterns")
+        .map(|indices| indices.collect())
+        .unwrap_or_default();
+    let between = patterns.iter().any(|&index| first < index && index < last);
+    let around =
+        patterns.iter().any(|&index| index < first) && patterns.iter().any(|&index| index > first);
+    between || around
+}
+
+/// Append `pattern` to the group for `scope`, creating it if needed, so
+/// groups keep the order in which scopes first appear
+fn push_to_group(groups: &mut Vec<(Scope, Vec<String>)>, scope: Scope, pattern: String) {
+    match groups
+        .iter_mut()
+        .find(|(group_scope, _)| *group_scope == scope)
+    {
+        Some((_, members)) => members.push(pattern),
+        None => groups.push((scope, vec![pattern])),
+    }
+}
+
+/// Route each pattern to its recommended scope, printing where each one
+/// goes and why
+fn auto_target_groups(patterns: &[String], config: &Config) -> Vec<(Scope, Vec<String>)> {
+    let mut groups = Vec::new();
+    if patterns.is_empty() {
+        return groups;
+    }
+    println!("Routing patterns:");
+    for pattern in patterns {
+        let route = route::route_pattern(pattern, &config.auto_targets);
+        println!("  {pattern} -> {} ({})", route.scope, route.reason);
+        push_to_group(&mut groups, route.scope, route.pattern);
+    }
+    groups
+}
+
+/// Pass `patterns` through the `ignore.validator` hook, if one is
+/// configured. Patterns the hook rewrote are validated again, as typed
+/// ones are, so a hook can't smuggle in a pattern that would be refused.
+fn run_validator_hook(
+    matches: &ArgMatches,
+    config: &Config,
+    target_file: &Path,
+    scope: Scope,
+    patterns: Vec<String>,
+) -> anyhow::Result<Vec<String>> {
+    let Some(command) = &config.validator else {
+        return Ok(patterns);
+    };
+    let rewritten = hook::run_validator(command, target_file, scope, &patterns)?;
+    if rewritten == patterns || matches.get_flag("no-validate") {
+        return Ok(rewritten);
+    }
+    let issues = if matches.get_flag("attributes") {
+        attributes::validate_attribute_lines(&rewritten)
+    } else {
+        ignore::validate_ignore_patterns(&rewritten)
+    };
+    display_validation_issues(&issues);
+    if has_blocking_issues(&issues) {
+        anyhow::bail!(Error::ValidationFailed(Vec::new()));
+    }
+    Ok(rewritten)
+}
+
+/// Add validated patterns to the ignore file of one scope. In `--check`
+/// mode nothing is written and the result says whether the file is up to
+/// date.
+fn add_to_scope(
+    matches: &ArgMatches,
+    config: &Config,
+    scope: Scope,
+    patterns: Vec<String>,
+) -> anyhow::Result<bool> {
+    let allow_duplicates = matches.get_flag("allow-duplicates");
+    let placement = placement(matches, config);
+
+    // Determine target file
+    let target_file = match matches.get_one::<String>("create") {
+        Some(path) if scope == Scope::Global => create_global_gitignore(path)?,
+        _ => get_target_file(matches, scope)?,
+    };
+
+    // Let an organization-specific validator veto or rewrite the patterns
+    let patterns = run_validator_hook(matches, config, &target_file, scope, patterns)?;
+
+    if matches.get_flag("check") {
+        return check_target_file(&target_file, &patterns, !allow_duplicates, scope);
+    }
+    if matches.get_flag("dry-run") {
+        let preview =
+            ignore::preview_add_patterns(&target_file, &patterns, !allow_duplicates, placement)?;
+        print_add_preview(&preview, scope);
+        return Ok(true);
+    }
+
+    // Creating a new ignore file can be reserved for a reviewed change
+    if (matches.get_flag("no-create") || config.no_create) && !target_file.exists() {
+        anyhow::bail!(Error::TargetMissing(format!(
+            "{} (not created because of --no-create)",
+            get_file_description(&target_file, scope)
+        )));
+    }
+
+    // Offer to finish a half-resolved merge rather than failing outright
+    offer_conflict_resolution(&target_file, scope)?;
+
+    let attributes_mode = matches.get_flag("attributes");
+
+    // Ensure exclude file exists if targeting local
+    if scope == Scope::Local && !attributes_mode {
+        ignore::ensure_info_exclude_exists(&target_file)?;
+        if matches.get_flag("worktree") && !enable_worktree_exclude(matches, &target_file)? {
+            println!("Nothing added");
+            return Ok(true);
+        }
+    }
+
+    // Catch additions that would undo an existing exception
+    let (patterns, mut added_patterns) = if matches.get_flag("no-validate") || attributes_mode {
+        (patterns, Vec::new())
+    } else {
+        handle_negation_conflicts(&target_file, scope, patterns, !allow_duplicates)?
+    };
+
+    // Point out additions that overlap what the file already ignores, and
+    // negations another ignore file overrides
+    if !matches.get_flag("no-validate") && !attributes_mode {
+        report_overlaps(&target_file, &patterns, !allow_duplicates)?;
+        report_overridden_negations(scope, &patterns);
+    }
+
+    // Git keeps tracking files whatever the ignore files say
+    let untrack = matches.get_flag("untrack");
+    if !untrack && !matches.get_flag("no-validate") && !attributes_mode {
+        report_tracked_matches(&patterns);
+    }
+
+    // Add patterns to the target file (validation already done above)
+    added_patterns.extend(ignore::add_patterns_with_placement(
+        &target_file,
+        &patterns,
+        !allow_duplicates,
+        placement,
+    )?);
+
+    // Report results
+    let file_description = get_file_description(&target_file, scope);
+
+    if added_patterns.is_empty() {
+        println!("No new patterns added to {file_description} (all patterns already exist)");
+        if untrack {
+            untrack_matching(&patterns)?;
+        }
+        return Ok(true);
+    }
+
+    // Report success with context
+    println!(
+        "Added {} to {}:",
+        pluralize_patterns(added_patterns.len()),
+        file_description
+    );
+    for pattern in &added_patterns {
+        println!("  {pattern}");
+    }
+
+    if untrack {
+        untrack_matching(&patterns)?;
+    }
+
+    Ok(true)
+}
+
+/// Whether the message of `e` is that of the [`Error`] in it, rather than
+/// context added around it
+fn reads_as_itself(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<Error>()
+        .is_some_and(|error| error.to_string() == e.to_string())
+}
+
+/// Main entry point
+fn main() {
+    let exit_code = match run() {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            // Errors name their category; only context added around one
+            // needs the label in front
+            let labelled = |label: &str| {
+                if reads_as_itself(&e) {
+                    eprintln!("{e}");
+                } else {
+                    eprintln!("{label}: {e}");
+                }
+            };
+            // Determine appropriate exit code based on error type
+            match e.downcast_ref::<Error>() {
+                // Issues found by the built-in checks were shown as
+                // they were found; only the other failures carry messages
+                Some(Error::ValidationFailed(messages)) => {
+                    for message in messages {
+                        eprintln!("Error: {message}");
+                    }
+                    EXIT_VALIDATION_FAILED
+                }
+                Some(Error::CheckFailed(_)) => {
+                    eprintln!("{e}");
+                    EXIT_CHECK_FAILED
+                }
+                Some(Error::TargetMissing(_)) => {
+                    labelled("Error");
+                    EXIT_TARGET_MISSING
+                }
+                // Whatever was waiting on git, the timeout is what to fix
+                Some(timeout @ Error::GitTimeout { .. }) => {
+                    eprintln!("{timeout}");
+                    EXIT_GIT_ERROR
+                }
+                Some(
+                    Error::GitNotFound(_)
+                    | Error::NotARepository(_)
+                    | Error::NoWorkTree(_)
+                    | Error::BareRepository(_)
+                    | Error::GitFailed(_),
+                ) => {
+                    labelled("Git error");
+                    EXIT_GIT_ERROR
+                }
+                Some(Error::Config(_) | Error::NoGlobalGitignore(_)) => {
+                    labelled("Configuration error");
+                    EXIT_CONFIG_ERROR
+                }
+                // Error::Io names its path and cause
+                Some(Error::Io { .. }) if reads_as_itself(&e) => {
+                    eprintln!("{e}");
+                    EXIT_FILE_ERROR
+                }
+                // I/O failures given only context
+                _ if e.chain().any(|cause| cause.is::<io::Error>()) => {
+                    let denied = e.chain().any(|cause| {
+                        cause
+                            .downcast_ref::<io::Error>()
+                            .is_some_and(|error| error.kind() == io::ErrorKind::PermissionDenied)
+                    });
+                    if denied {
+                        // Typical of the system gitignore, which root owns
+                        eprintln!("File system error: {e}: permission denied");
+                    } else {
+                        eprintln!("File system error: {e}");
+                    }
+                    EXIT_FILE_ERROR
+                }
+                _ => {
+                    eprintln!("Error: {e}");
+                    EXIT_FILE_ERROR
+                }
             }
         }
     };
@@ -0,0 +1,226 @@
+//! One value carrying the options of an ignore file edit
+//!
+//! [`IgnoreManager`] collects the repository, scope, validation level and
+//! rules, duplicate handling and placement that the free functions take as
+//! separate arguments, then adds, previews or removes patterns with them.
+//!
+//! ```no_run
+//! use git_ignore_tool::{IgnoreManager, PatternValidationLevel, Scope};
+//!
+//! let added = IgnoreManager::for_repo("/path/to/repo")
+//!     .scope(Scope::Local)
+//!     .validation(PatternValidationLevel::Strict)
+//!     .dedupe(true)
+//!     .add(&["*.log".to_string()])?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::{
+    ignore::{self, IgnoreEntry, Placement},
+    scope_file_in, sets, validate_patterns_for_library,
+    validation::RuleSet,
+    AddPreview, PatternValidationLevel, RemovalReport, RepoContext, Scope,
+};
+use std::path::PathBuf;
+
+/// Options for editing one ignore file; see the module documentation.
+///
+/// Defaults: the repository around the current directory, [`Scope::Repo`],
+/// [`PatternValidationLevel::Warn`], duplicates skipped and new patterns
+/// appended. Nothing is resolved until an operation runs, so a manager can
+/// be built before the repository exists.
+#[derive(Debug, Clone)]
+pub struct IgnoreManager {
+    /// Directory inside the repository; `None` for the current one
+    repo: Option<PathBuf>,
+    scope: Scope,
+    validation: PatternValidationLevel,
+    dedupe: bool,
+    placement: Placement,
+    rules: RuleSet,
+}
+
+impl Default for IgnoreManager {
+    fn default() -> IgnoreManager {
+        IgnoreManager {
+            repo: None,
+            scope: Scope::Repo,
+            validation: PatternValidationLevel::Warn,
+            dedupe: true,
+            placement: Placement::Append,
+            rules: RuleSet::default(),
+        }
+    }
+}
+
+impl IgnoreManager {
+    /// Edit the repository around the current directory
+    pub fn new() -> IgnoreManager {
+        IgnoreManager::default()
+    }
+
+    /// Edit the repository containing `path`
+    pub fn for_repo(path: impl Into<PathBuf>) -> IgnoreManager {
+        IgnoreManager {
+            repo: Some(path.into()),
+            ..IgnoreManager::default()
+        }
+    }
+
+    pub fn scope(mut self, scope: Scope) -> IgnoreManager {
+        self.scope = scope;
+        self
+    }
+
+    pub fn validation(mut self, level: PatternValidationLevel) -> IgnoreManager {
+        self.validation = level;
+        self
+    }
+
+    /// Whether to skip patterns the file already has or that repeat
+    /// earlier in the batch
+    pub fn dedupe(mut self, dedupe: bool) -> IgnoreManager {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Where added patterns go in the file
+    pub fn placement(mut self, placement: Placement) -> IgnoreManager {
+        self.placement = placement;
+        self
+    }
+
+    /// Application rules to run alongside the built-in checks when
+    /// patterns are added or previewed
+    pub fn rules(mut self, rules: RuleSet) -> IgnoreManager {
+        self.rules = rules;
+        self
+    }
+
+    /// The file the scope resolves to. The global and system files are
+    /// found without looking for a repository.
+    pub fn path(&self) -> anyhow::Result<PathBuf> {
+        match &self.repo {
+            Some(dir) if matches!(self.scope, Scope::Repo | Scope::Local) => {
+                scope_file_in(Some(&RepoContext::discover(dir)?), self.scope)
+            }
+            _ => scope_file_in(None, self.scope),
+        }
+    }
+
+    /// Validate `patterns` and add the ones that aren't skipped, returning
+    /// those written. `@name` stands for a pattern set from git config (see
+    /// [`sets`](crate::sets)).
+    pub fn add(&self, patterns: &[String]) -> anyhow::Result<Vec<String>> {
+        let patterns = self.expand(patterns)?;
+        validate_patterns_for_library(&patterns, self.validation.clone(), &self.rules)?;
+        let path = self.path()?;
+        if self.scope == Scope::Local {
+            ignore::ensure_info_exclude_exists(&path)?;
+        }
+        ignore::add_patterns_with_placement(&path, &patterns, self.dedupe, self.placement.clone())
+    }
+
+    /// What [`add`](IgnoreManager::add) would do, without writing anything
+    pub fn preview(&self, patterns: &[String]) -> anyhow::Result<AddPreview> {
+        let patterns = self.expand(patterns)?;
+        validate_patterns_for_library(&patterns, self.validation.clone(), &self.rules)?;
+        ignore::preview_add_patterns(
+            &self.path()?,
+            &patterns,
+            self.dedupe,
+            self.placement.clone(),
+        )
+    }
+
+    /// `patterns` with set references replaced, reading the sets only if
+    /// there is a reference
+    fn expand(&self, patterns: &[String]) -> anyhow::Result<Vec<String>> {
+        if !patterns
+            .iter()
+            .any(|pattern| sets::set_reference(pattern).is_some())
+        {
+            return Ok(patterns.to_vec());
+        }
+        let defined = match &self.repo {
+            Some(dir) => sets::load_in(&RepoContext::discover(dir)?)?,
+            None => sets::load()?,
+        };
+        sets::expand(patterns, &defined)
+    }
+
+    /// Remove every line matching one of `patterns`
+    pub fn remove(&self, patterns: &[String]) -> anyhow::Result<RemovalReport> {
+        ignore::remove_patterns_from_ignore_file(&self.path()?, patterns)
+    }
+
+    /// The patterns in the file, in order
+    pub fn entries(&self) -> anyhow::Result<Vec<IgnoreEntry>> {
+        ignore::read_ignore_entries(&self.path()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_manager_edits_the_chosen_repository() {
+        let dir = tempfile::TempDir::new().unwrap();
+        Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "# Logs\n*.log\n").unwrap();
+        let patterns = |list: &[&str]| -> Vec<String> {
+            list.iter().map(|pattern| pattern.to_string()).collect()
+        };
+
+        let manager = IgnoreManager::for_repo(dir.path());
+        assert_eq!(
+            manager.add(&patterns(&["*.log/", "build/"])).unwrap(),
+            ["build/"]
+        );
+        assert_eq!(
+            manager
+                .clone()
+                .dedupe(false)
+                .preview(&patterns(&["*.log"]))
+                .unwrap()
+                .added,
+            ["*.log"]
+        );
+        assert!(manager
+            .clone()
+            .validation(PatternValidationLevel::Strict)
+            .add(&patterns(&["build\\output"]))
+            .is_err());
+        let no_keys = RuleSet::new().with_fn("TEST001", "No keys", |pattern| {
+            pattern
+                .ends_with(".pem")
+                .then(|| (crate::PatternSeverity::Error, "Keys".to_string()))
+        });
+        assert!(manager
+            .clone()
+            .rules(no_keys)
+            .add(&patterns(&["*.pem"]))
+            .is_err());
+
+        let local = manager.clone().scope(Scope::Local);
+        assert_eq!(local.add(&patterns(&["*.tmp"])).unwrap(), ["*.tmp"]);
+        let exclude = std::fs::read_to_string(local.path().unwrap()).unwrap();
+        assert!(exclude.ends_with("*.tmp\n"));
+
+        let report = manager.remove(&patterns(&["*.log"])).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(".gitignore")).unwrap(),
+            "# Logs\n\nbuild/\n"
+        );
+        let entries = manager.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].line_number, 3);
+    }
+}
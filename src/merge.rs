@@ -0,0 +1,258 @@
+//! Merge another gitignore-style file into an ignore file
+//!
+//! Patterns are compared by what they mean rather than how they are
+//! spelled: `build` and `build/` count as the same pattern (as they do for
+//! duplicate detection when adding), and so do `/doc/api` and `doc/api`,
+//! or `**/tmp` and `tmp`, which anchor the same way. The incoming file's
+//! blocks are appended with the comments that head them, minus the
+//! patterns the target already has; a block left without patterns is
+//! dropped along with its comments.
+
+use crate::{
+    ignore, ignore_file,
+    pattern::{Pattern, Segment},
+    Error,
+};
+use anyhow::{bail, Context};
+use std::{collections::HashMap, path::Path};
+use tracing::{debug_span, info};
+
+/// Outcome of a merge
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergeReport {
+    /// Patterns from the incoming file written to the target, in order
+    pub added: Vec<String>,
+    /// Incoming patterns left out, each with the equivalent pattern that
+    /// was already there
+    pub skipped: Vec<(String, String)>,
+}
+
+/// A form of `pattern` shared by every spelling that matches the same
+/// paths: the trailing `/` is dropped, anchoring is written as a leading
+/// `/` whether or not the original had one, and a leading `**/` in front
+/// of a single name is dropped. Lines that don't parse compare as written.
+pub fn equivalence_key(pattern: &str) -> String {
+    let Ok(parsed) = Pattern::parse(pattern) else {
+        return pattern.trim().to_string();
+    };
+    let mut segments = parsed.segments.as_slice();
+    let mut anchored = parsed.anchored;
+    if let [Segment::DoubleStar, name] = segments {
+        if *name != Segment::DoubleStar {
+            segments = &segments[1..];
+            anchored = false;
+        }
+    }
+
+    let mut key = String::new();
+    if parsed.negated {
+        key.push('!');
+    }
+    if anchored {
+        key.push('/');
+    }
+    let names: Vec<String> = segments.iter().map(Segment::to_string).collect();
+    key.push_str(&names.join("/"));
+    key
+}
+
+/// Append the blocks of `incoming` to `target`, leaving out patterns that
+/// `target` (or an earlier incoming line) already has. Comments heading a
+/// block stay with it, comments inside a block stay with the patterns
+/// below them, and a comment-only block is kept unless `target` already
+/// has all of its lines.
+pub fn merge_content(target: &str, incoming: &str) -> (String, MergeReport) {
+    let newline = if target.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut seen: HashMap<String, String> = HashMap::new();
+    for line in target.lines().map(str::trim) {
+        if !line.is_empty() && !line.starts_with('#') {
+            seen.entry(equivalence_key(line))
+                .or_insert_with(|| line.to_string());
+        }
+    }
+    let target_lines: Vec<&str> = target.lines().map(str::trim).collect();
+
+    let mut report = MergeReport::default();
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+    for block in split_blocks(incoming) {
+        // Runs of comments followed by the patterns they describe
+        let mut runs: Vec<(Vec<&str>, Vec<&str>)> = Vec::new();
+        for &line in &block {
+            let is_comment = ignore_file::is_comment(line);
+            match runs.last_mut() {
+                Some((comments, patterns)) if patterns.is_empty() && is_comment => {
+                    comments.push(line)
+                }
+                Some((_, patterns)) if !is_comment => patterns.push(line),
+                _ if is_comment => runs.push((vec![line], Vec::new())),
+                _ => runs.push((Vec::new(), vec![line])),
+            }
+        }
+
+        let has_patterns = runs.iter().any(|(_, patterns)| !patterns.is_empty());
+        if !has_patterns {
+            let known = runs
+                .iter()
+                .flat_map(|(comments, _)| comments)
+                .all(|line| target_lines.contains(&line.trim()));
+            if !known {
+                blocks.push(block.iter().map(|line| line.to_string()).collect());
+            }
+            continue;
+        }
+
+        let mut kept_runs: Vec<(Vec<&str>, Vec<&str>)> = Vec::new();
+        for (comments, patterns) in &runs {
+            let mut kept = Vec::new();
+            for &line in patterns {
+                let pattern = line.trim();
+                match seen.get(&equivalence_key(pattern)) {
+                    Some(existing) => report.skipped.push((pattern.to_string(), existing.clone())),
+                    None => {
+                        seen.insert(equivalence_key(pattern), pattern.to_string());
+                        report.added.push(pattern.to_string());
+                        kept.push(line);
+                    }
+                }
+            }
+            kept_runs.push((comments.clone(), kept));
+        }
+        if kept_runs.iter().all(|(_, kept)| kept.is_empty()) {
+            continue;
+        }
+        let mut lines = Vec::new();
+        for (index, (comments, kept)) in kept_runs.iter().enumerate() {
+            // The block's heading stays as long as anything in the block does
+            if index == 0 || !kept.is_empty() {
+                lines.extend(comments.iter().map(|line| line.to_string()));
+            }
+            lines.extend(kept.iter().map(|line| line.to_string()));
+        }
+        blocks.push(lines);
+    }
+
+    if blocks.is_empty() {
+        return (target.to_string(), report);
+    }
+    let mut lines: Vec<String> = target.lines().map(str::to_string).collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    for block in blocks {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(block);
+    }
+    let mut merged = lines.join(newline);
+    merged.push_str(newline);
+    (merged, report)
+}
+
+/// Blocks of consecutive non-blank lines, trailing whitespace aside
+fn split_blocks(content: &str) -> Vec<Vec<&str>> {
+    let mut blocks: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            if blocks.last().is_some_and(|block| !block.is_empty()) {
+                blocks.push(Vec::new());
+            }
+        } else {
+            blocks.last_mut().unwrap().push(line);
+        }
+    }
+    blocks.retain(|block| !block.is_empty());
+    blocks
+}
+
+/// Merge the ignore patterns of `source` into `target` (see
+/// [`merge_content`]). With `dry_run` nothing is written.
+pub fn merge_file(target: &Path, source: &Path, dry_run: bool) -> anyhow::Result<MergeReport> {
+    let _span =
+        debug_span!("merge_file", target = %target.display(), source = %source.display()).entered();
+    if target == source {
+        bail!(Error::config("cannot merge a file into itself"));
+    }
+    let incoming = std::fs::read_to_string(source)
+        .with_context(|| format!("Failed to read: {}", source.display()))?;
+    let original = ignore::read_optional(target)?;
+    let content = original.as_deref().unwrap_or_default();
+    ignore::ensure_no_conflict_markers(source, &incoming)?;
+    ignore::ensure_no_conflict_markers(target, content)?;
+
+    let (merged, report) = merge_content(content, &incoming);
+    if !dry_run && merged != content {
+        ignore::write_ignore_file_if_unchanged(target, original.as_deref(), &merged)?;
+        info!(target = %target.display(), count = report.added.len(), "ignore files merged");
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equivalence_key() {
+        assert_eq!(equivalence_key("build/"), equivalence_key("build"));
+        assert_eq!(equivalence_key("/doc/api"), equivalence_key("doc/api"));
+        assert_eq!(equivalence_key("**/tmp/"), equivalence_key("tmp"));
+        assert_eq!(equivalence_key("/**/tmp"), equivalence_key("tmp"));
+        assert_ne!(equivalence_key("/build"), equivalence_key("build"));
+        assert_ne!(equivalence_key("!keep.log"), equivalence_key("keep.log"));
+        assert_ne!(equivalence_key("**/a/b"), equivalence_key("a/b"));
+    }
+
+    #[test]
+    fn test_merge_content() {
+        let target = "# Build\nbuild/\n/doc/api\n";
+        let incoming = "\
+# Generated by the editor
+
+# Output
+build
+doc/api/
+*.o
+
+# Logs
+*.log
+# Kept
+!keep.log
+
+# Already here
+**/build
+";
+        let (merged, report) = merge_content(target, incoming);
+        assert_eq!(
+            merged,
+            "# Build\nbuild/\n/doc/api\n\n# Generated by the editor\n\n# Output\n*.o\n\n# Logs\n*.log\n# Kept\n!keep.log\n"
+        );
+        assert_eq!(report.added, vec!["*.o", "*.log", "!keep.log"]);
+        assert_eq!(
+            report.skipped,
+            vec![
+                ("build".to_string(), "build/".to_string()),
+                ("doc/api/".to_string(), "/doc/api".to_string()),
+                ("**/build".to_string(), "build/".to_string()),
+            ]
+        );
+
+        // Merging again changes nothing
+        let (again, report) = merge_content(&merged, incoming);
+        assert_eq!(again, merged);
+        assert!(report.added.is_empty());
+    }
+
+    #[test]
+    fn test_merge_drops_comments_of_skipped_runs() {
+        let (merged, _) = merge_content("*.log\n", "# Logs\n*.log\n# Temp\n*.tmp\n");
+        assert_eq!(merged, "*.log\n\n# Logs\n# Temp\n*.tmp\n");
+        let (merged, _) = merge_content("*.log\n", "# Temp\n*.tmp\n# Logs\n*.log\n");
+        assert_eq!(merged, "*.log\n\n# Temp\n*.tmp\n");
+    }
+}
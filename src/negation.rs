@@ -0,0 +1,537 @@
+//! Detect new patterns that fight existing negations
+//!
+//! In an ignore file the last matching pattern wins, and a negation cannot
+//! re-include a path whose parent directory is excluded. An addition can
+//! therefore quietly undo a deliberate exception in two ways: a pattern
+//! appended after `!keep.log` re-ignores `keep.log`, and a new `!build/keep`
+//! does nothing while `build/` excludes the whole directory.
+//! [`find_negation_conflicts`] reports both, with an example path, and
+//! [`resolve`] rewrites the file content for the chosen fix. A new negation
+//! can also lose to another ignore file: `.gitignore` takes precedence over
+//! `.git/info/exclude`, which [`overridden_negations`] checks.
+
+use crate::{
+    decision,
+    explain::sample_paths,
+    ignore_file,
+    pattern::{Pattern, Segment},
+    rules::RuleSource,
+    Scope,
+};
+use std::{fmt, path::PathBuf};
+
+/// How a new pattern and existing lines fight
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConflictKind {
+    /// The new pattern would come after negations and re-ignore paths they
+    /// re-include
+    OverridesNegation,
+    /// The new negation can't re-include anything inside `directory`,
+    /// which an existing pattern excludes
+    ParentExcluded { directory: String },
+    /// The new negation can't re-include what a rule of the
+    /// higher-precedence ignore file `source` ignores
+    Overridden { source: PathBuf },
+}
+
+/// A new pattern that would defeat, or be defeated by, existing lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NegationConflict {
+    /// The pattern being added
+    pub pattern: String,
+    pub kind: ConflictKind,
+    /// The existing lines involved, as (1-based line number, text), in
+    /// file order; for [`ConflictKind::Overridden`] the line is in the
+    /// other file
+    pub lines: Vec<(usize, String)>,
+    /// A path affected by the conflict (directories end with `/`)
+    pub example: String,
+}
+
+/// A fix that edits the file; adjusting the new pattern, adding it anyway
+/// or skipping it are left to the caller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Resolution {
+    /// Insert the new pattern before the first conflicting negation, so the
+    /// negations still win
+    Reorder,
+    /// Append the new pattern and repeat the negations after it; for a
+    /// negation under an excluded directory, ignore the directory's
+    /// contents instead of the directory and re-include the directories
+    /// leading to the path
+    Compensate,
+}
+
+impl NegationConflict {
+    /// The fixes that apply to this conflict
+    pub fn resolutions(&self) -> Vec<Resolution> {
+        match &self.kind {
+            ConflictKind::OverridesNegation => vec![Resolution::Reorder, Resolution::Compensate],
+            ConflictKind::ParentExcluded { .. } => {
+                if compensation_chain(self).is_some() {
+                    vec![Resolution::Compensate]
+                } else {
+                    Vec::new()
+                }
+            }
+            ConflictKind::Overridden { .. } => Vec::new(),
+        }
+    }
+
+    /// One-line description of what `resolution` does to the file
+    pub fn describe(&self, resolution: Resolution) -> String {
+        let quoted: Vec<String> = self
+            .lines
+            .iter()
+            .map(|(_, text)| format!("'{text}'"))
+            .collect();
+        match (resolution, &self.kind) {
+            (Resolution::Reorder, _) => format!(
+                "put '{}' before {} (line {}) so the exception still holds",
+                self.pattern, quoted[0], self.lines[0].0
+            ),
+            (Resolution::Compensate, ConflictKind::OverridesNegation) => format!(
+                "add '{}', then repeat {} after it",
+                self.pattern,
+                quoted.join(", ")
+            ),
+            (Resolution::Compensate, ConflictKind::ParentExcluded { .. }) => {
+                match compensation_chain(self) {
+                    Some((replacement, chain)) => format!(
+                        "change {} (line {}) to '{replacement}' and add {}",
+                        quoted[0],
+                        self.lines[0].0,
+                        chain
+                            .iter()
+                            .map(|line| format!("'{line}'"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    None => "not available".to_string(),
+                }
+            }
+            (_, ConflictKind::Overridden { .. }) => "not available".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for NegationConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (line, text) = &self.lines[0];
+        match &self.kind {
+            ConflictKind::OverridesNegation => write!(
+                f,
+                "'{}' would re-ignore {}, which '{text}' (line {line}) re-includes",
+                self.pattern, self.example
+            ),
+            ConflictKind::ParentExcluded { directory } => write!(
+                f,
+                "'{}' cannot re-include {}: its parent directory {directory}/ is excluded by '{text}' (line {line})",
+                self.pattern, self.example
+            ),
+            ConflictKind::Overridden { source } => write!(
+                f,
+                "'{}' cannot re-include {}: '{text}' in {} (line {line}) takes precedence",
+                self.pattern,
+                self.example,
+                source.display()
+            ),
+        }
+    }
+}
+
+/// Find the patterns in `new_patterns` that, appended to `content`, would
+/// fight its existing negations. At most one conflict is reported per new
+/// pattern.
+pub fn find_negation_conflicts(content: &str, new_patterns: &[String]) -> Vec<NegationConflict> {
+    let existing: Vec<(usize, String, Pattern)> = content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            if ignore_file::is_comment(line) {
+                return None;
+            }
+            let text = line.trim();
+            Pattern::parse(line)
+                .ok()
+                .map(|pattern| (i + 1, text.to_string(), pattern))
+        })
+        .collect();
+
+    new_patterns
+        .iter()
+        .filter_map(|new| {
+            let pattern = Pattern::parse(new).ok()?;
+            if pattern.negated {
+                parent_excluded(new, &pattern, &existing)
+            } else {
+                overrides_negation(new, &pattern, &existing)
+            }
+        })
+        .collect()
+}
+
+/// The new negations in `new_patterns` that can't take effect in an ignore
+/// file of `scope`, because a rule of a higher-precedence file ignores
+/// what they re-include. `sources` are the rule sources as
+/// [`effective_ignore_rules`](crate::rules::effective_ignore_rules) lists
+/// them for the repository root; those of a later scope (the global file,
+/// then `info/exclude`, then `.gitignore`) are weighed against each
+/// negation. At most one conflict is reported per negation.
+pub fn overridden_negations(
+    scope: Scope,
+    sources: &[RuleSource],
+    new_patterns: &[String],
+) -> Vec<NegationConflict> {
+    let precedence = |scope: Scope| match scope {
+        Scope::System | Scope::Global => 0,
+        Scope::Local => 1,
+        Scope::Repo => 2,
+    };
+    let higher: Vec<RuleSource> = sources
+        .iter()
+        .filter(|source| precedence(source.scope) > precedence(scope))
+        .cloned()
+        .collect();
+    if higher.is_empty() {
+        return Vec::new();
+    }
+
+    new_patterns
+        .iter()
+        .filter_map(|new| {
+            let pattern = Pattern::parse(new).ok().filter(|p| p.negated)?;
+            sample_paths(&pattern)
+                .into_iter()
+                .find_map(|(path, is_dir)| {
+                    let chain = decision::decision_chain_from(&higher, &path, is_dir);
+                    if !chain.is_ignored() {
+                        return None;
+                    }
+                    let rule = &chain.decided_by()?.rule;
+                    Some(NegationConflict {
+                        pattern: new.trim().to_string(),
+                        kind: ConflictKind::Overridden {
+                            source: rule.source.clone(),
+                        },
+                        lines: vec![(rule.line_number, rule.pattern.to_string())],
+                        example: display_path(&path, is_dir),
+                    })
+                })
+        })
+        .collect()
+}
+
+/// Negations among `patterns`, written to a file together and in this
+/// order, that the others leave without effect: a later pattern ignores
+/// again what the negation re-includes, or an earlier one excludes its
+/// parent directory. Returns the index of each such negation with the
+/// reason.
+pub(crate) fn ineffective_in_list(patterns: &[String]) -> Vec<(usize, String)> {
+    let parsed: Vec<(usize, String, Pattern)> = patterns
+        .iter()
+        .enumerate()
+        .filter_map(|(i, text)| {
+            Pattern::parse(text)
+                .ok()
+                .map(|pattern| (i, text.trim().to_string(), pattern))
+        })
+        .collect();
+
+    let mut found = Vec::new();
+    for (position, (index, text, negation)) in parsed.iter().enumerate() {
+        if !negation.negated {
+            continue;
+        }
+        let own = [(*index, text.clone(), negation.clone())];
+        let overridden = parsed[position + 1..]
+            .iter()
+            .filter(|(_, _, later)| !later.negated)
+            .find_map(|(_, later_text, later)| {
+                overrides_negation(later_text, later, &own)
+                    .map(|conflict| (later_text, conflict.example))
+            });
+        if let Some((later_text, example)) = overridden {
+            found.push((
+                *index,
+                format!("'{later_text}' comes after it and ignores {example} again"),
+            ));
+        } else if let Some(conflict) = parent_excluded(text, negation, &parsed[..position]) {
+            if let ConflictKind::ParentExcluded { directory } = conflict.kind {
+                found.push((
+                    *index,
+                    format!(
+                        "'{}' excludes its parent directory {directory}/, so {} can't be re-included",
+                        conflict.lines[0].1, conflict.example
+                    ),
+                ));
+            }
+        }
+    }
+    found
+}
+
+/// Negations a new positive pattern would override once appended
+fn overrides_negation(
+    new: &str,
+    pattern: &Pattern,
+    existing: &[(usize, String, Pattern)],
+) -> Option<NegationConflict> {
+    let new_samples = sample_paths(pattern);
+    let mut lines = Vec::new();
+    let mut example = None;
+    for (line, text, negation) in existing.iter().filter(|(_, _, p)| p.negated) {
+        let overlap = sample_paths(negation)
+            .into_iter()
+            .find(|(path, is_dir)| pattern.matches(path, *is_dir))
+            .or_else(|| {
+                new_samples
+                    .iter()
+                    .find(|(path, is_dir)| negation.matches(path, *is_dir))
+                    .cloned()
+            });
+        if let Some(found) = overlap {
+            example.get_or_insert(found);
+            lines.push((*line, text.clone()));
+        }
+    }
+    let (path, is_dir) = example?;
+    Some(NegationConflict {
+        pattern: new.trim().to_string(),
+        kind: ConflictKind::OverridesNegation,
+        lines,
+        example: display_path(&path, is_dir),
+    })
+}
+
+/// An existing pattern excluding a parent directory of what a new,
+/// anchored negation re-includes
+fn parent_excluded(
+    new: &str,
+    pattern: &Pattern,
+    existing: &[(usize, String, Pattern)],
+) -> Option<NegationConflict> {
+    // Unanchored negations apply at every depth; only flag paths the
+    // pattern itself names
+    if !pattern.anchored {
+        return None;
+    }
+    for (path, is_dir) in sample_paths(pattern) {
+        let components: Vec<&str> = path.split('/').collect();
+        for depth in 1..components.len() {
+            let directory = components[..depth].join("/");
+            // The decision for the directory is the last pattern matching it
+            let excluded_by = existing
+                .iter()
+                .rev()
+                .find(|(_, _, p)| p.matches(&directory, true));
+            if let Some((line, text, excluder)) = excluded_by {
+                if !excluder.negated {
+                    return Some(NegationConflict {
+                        pattern: new.trim().to_string(),
+                        kind: ConflictKind::ParentExcluded { directory },
+                        lines: vec![(*line, text.clone())],
+                        example: display_path(&path, is_dir),
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// For a [`ConflictKind::ParentExcluded`] conflict whose negation names its
+/// directories literally: the replacement for the excluding line, and the
+/// lines to append before the new negation
+fn compensation_chain(conflict: &NegationConflict) -> Option<(String, Vec<String>)> {
+    let ConflictKind::ParentExcluded { directory } = &conflict.kind else {
+        return None;
+    };
+    let negation = Pattern::parse(&conflict.pattern).ok()?;
+    let (_, parents) = negation.segments.split_last()?;
+    if !parents
+        .iter()
+        .all(|segment| matches!(segment, Segment::Literal(_)))
+    {
+        return None;
+    }
+
+    let excluder_text = &conflict.lines[0].1;
+    let excluder = Pattern::parse(excluder_text).ok()?;
+    let base = excluder_text.trim_end_matches('/');
+    let replacement = if excluder.anchored {
+        format!("{base}/*")
+    } else {
+        format!("**/{base}/*")
+    };
+
+    // Re-include each directory between the excluded one and the path,
+    // ignoring its other contents
+    let example = conflict.example.trim_end_matches('/');
+    let components: Vec<&str> = example.split('/').collect();
+    let start = directory.split('/').count();
+    let mut chain = Vec::new();
+    for depth in start + 1..components.len() {
+        let intermediate = components[..depth].join("/");
+        chain.push(format!("!/{intermediate}/"));
+        chain.push(format!("/{intermediate}/*"));
+    }
+    Some((replacement, chain))
+}
+
+/// Content with the conflicting pattern added according to `resolution`
+pub fn resolve(content: &str, conflict: &NegationConflict, resolution: Resolution) -> String {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    match (resolution, &conflict.kind) {
+        // Nothing in this file can fix it
+        (_, ConflictKind::Overridden { .. }) => lines.push(conflict.pattern.clone()),
+        (Resolution::Reorder, _) => {
+            let index = conflict.lines[0].0 - 1;
+            lines.insert(index.min(lines.len()), conflict.pattern.clone());
+        }
+        (Resolution::Compensate, ConflictKind::OverridesNegation) => {
+            lines.push(conflict.pattern.clone());
+            lines.extend(conflict.lines.iter().map(|(_, text)| text.clone()));
+        }
+        (Resolution::Compensate, ConflictKind::ParentExcluded { .. }) => {
+            if let Some((replacement, chain)) = compensation_chain(conflict) {
+                if let Some(line) = lines.get_mut(conflict.lines[0].0 - 1) {
+                    *line = replacement;
+                }
+                lines.extend(chain);
+            }
+            lines.push(conflict.pattern.clone());
+        }
+    }
+
+    let mut updated = lines.join(newline);
+    updated.push_str(newline);
+    updated
+}
+
+fn display_path(path: &str, is_dir: bool) -> String {
+    if is_dir {
+        format!("{path}/")
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflicts(content: &str, new: &[&str]) -> Vec<NegationConflict> {
+        let new: Vec<String> = new.iter().map(|p| p.to_string()).collect();
+        find_negation_conflicts(content, &new)
+    }
+
+    #[test]
+    fn test_new_pattern_overrides_negation() {
+        let content = "*.log\n!important.log\nbuild/\n";
+        let found = conflicts(content, &["important.*", "dist/"]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, ConflictKind::OverridesNegation);
+        assert_eq!(found[0].lines, vec![(2, "!important.log".to_string())]);
+        assert_eq!(found[0].example, "important.log");
+        assert_eq!(
+            found[0].to_string(),
+            "'important.*' would re-ignore important.log, which '!important.log' (line 2) re-includes"
+        );
+
+        assert_eq!(
+            resolve(content, &found[0], Resolution::Reorder),
+            "*.log\nimportant.*\n!important.log\nbuild/\n"
+        );
+        assert_eq!(
+            resolve(content, &found[0], Resolution::Compensate),
+            "*.log\n!important.log\nbuild/\nimportant.*\n!important.log\n"
+        );
+    }
+
+    #[test]
+    fn test_negation_under_excluded_directory() {
+        let content = "build/\n*.o\n";
+        let found = conflicts(content, &["!build/gen/keep.txt", "!*.c"]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].kind,
+            ConflictKind::ParentExcluded {
+                directory: "build".to_string()
+            }
+        );
+        assert_eq!(found[0].resolutions(), vec![Resolution::Compensate]);
+        assert_eq!(
+            resolve(content, &found[0], Resolution::Compensate),
+            "**/build/*\n*.o\n!/build/gen/\n/build/gen/*\n!build/gen/keep.txt\n"
+        );
+
+        // Re-including the directory first is fine
+        assert!(conflicts("build/\n!build/\n", &["!/build/keep.txt"]).is_empty());
+        // Wildcard directories can't be re-included line by line
+        let found = conflicts("/out/\n", &["!/out/*/keep"]);
+        assert!(found[0].resolutions().is_empty());
+    }
+
+    #[test]
+    fn test_ineffective_in_list() {
+        let list = |patterns: &[&str]| {
+            let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+            ineffective_in_list(&patterns)
+        };
+        assert_eq!(
+            list(&["!important.log", "build/", "*.log"]),
+            vec![(
+                0,
+                "'*.log' comes after it and ignores important.log again".to_string()
+            )]
+        );
+        assert_eq!(
+            list(&["build/", "!build/gen/keep"]),
+            vec![(
+                1,
+                "'build/' excludes its parent directory build/, so build/gen/keep can't be re-included"
+                    .to_string()
+            )]
+        );
+        assert!(list(&["*.log", "!important.log", "build/"]).is_empty());
+    }
+
+    #[test]
+    fn test_negation_overridden_by_other_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let exclude = dir.path().join("exclude");
+        std::fs::write(&exclude, "*.tmp\n").unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+        let sources =
+            crate::rules::effective_rules_in(dir.path(), &[(Scope::Local, exclude)], "").unwrap();
+        let new = vec!["!important.log".to_string(), "!keep.tmp".to_string()];
+
+        let found = overridden_negations(Scope::Local, &sources, &new);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pattern, "!important.log");
+        assert_eq!(
+            found[0].kind,
+            ConflictKind::Overridden {
+                source: dir.path().join(".gitignore")
+            }
+        );
+        assert_eq!(found[0].lines, vec![(1, "*.log".to_string())]);
+        assert!(found[0].resolutions().is_empty());
+        assert!(found[0]
+            .to_string()
+            .starts_with("'!important.log' cannot re-include important.log: '*.log' in "));
+
+        // Nothing takes precedence over the repository's own file
+        assert!(overridden_negations(Scope::Repo, &sources, &new).is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+//! Git repositories inside the work tree
+//!
+//! A directory that is itself a git repository — a vendored checkout, an
+//! accidental clone inside another — is never part of the outer
+//! repository's history, whether or not it is ignored. Ignoring it only
+//! hides it from `git status` and stops `git add .` from recording it as an
+//! embedded repository; collaborators still won't get its content. Ignoring
+//! a registered submodule does nothing at all, since ignore rules don't
+//! apply to tracked paths. New patterns that match such directories are
+//! reported so the user can pick the handling they actually want.
+
+use crate::{Pattern, PatternIssue, PatternRule, PatternSeverity};
+
+/// How a nested repository relates to the outer one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NestedKind {
+    /// An untracked directory with its own `.git`
+    Embedded,
+    /// A gitlink in the index (a submodule)
+    Submodule,
+}
+
+/// A repository found inside the work tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NestedRepository {
+    /// `/`-separated path relative to the top of the work tree
+    pub path: String,
+    pub kind: NestedKind,
+}
+
+/// A [`PatternRule::NestedRepository`] warning for every pattern in
+/// `patterns` that would ignore one of `repositories` or a directory
+/// containing it. Patterns are taken relative to the top of the work tree.
+pub fn check_patterns(patterns: &[String], repositories: &[NestedRepository]) -> Vec<PatternIssue> {
+    let mut issues = Vec::new();
+    for text in patterns {
+        let Ok(pattern) = Pattern::parse(text.trim()) else {
+            continue;
+        };
+        if pattern.negated {
+            continue;
+        }
+        for repository in repositories {
+            if !ignores_directory(&pattern, &repository.path) {
+                continue;
+            }
+            let path = &repository.path;
+            let message = match repository.kind {
+                NestedKind::Embedded => format!(
+                    "Matches {path}, a separate git repository: ignoring it hides it from git status, but its files still won't reach anyone who clones this repository; to share it, make it a submodule (git submodule add <url> {path})"
+                ),
+                NestedKind::Submodule => format!(
+                    "Matches {path}, a submodule of this repository: ignore rules don't apply to tracked paths, so this has no effect (to stop using it: git submodule deinit {path} && git rm {path})"
+                ),
+            };
+            issues.push(PatternIssue {
+                pattern: text.clone(),
+                severity: PatternSeverity::Warning,
+                message,
+                rule: PatternRule::NestedRepository,
+            });
+        }
+    }
+    issues
+}
+
+/// Whether `pattern` matches the directory `path` or one of its parents
+fn ignores_directory(pattern: &Pattern, path: &str) -> bool {
+    let path = path.trim_matches('/');
+    path.match_indices('/')
+        .map(|(index, _)| &path[..index])
+        .chain(std::iter::once(path))
+        .any(|directory| pattern.matches(directory, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repositories() -> Vec<NestedRepository> {
+        vec![
+            NestedRepository {
+                path: "vendor/lib".to_string(),
+                kind: NestedKind::Embedded,
+            },
+            NestedRepository {
+                path: "modules/core".to_string(),
+                kind: NestedKind::Submodule,
+            },
+        ]
+    }
+
+    fn flagged(pattern: &str) -> Vec<String> {
+        check_patterns(&[pattern.to_string()], &repositories())
+            .into_iter()
+            .map(|issue| issue.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_check_patterns() {
+        assert!(flagged("vendor/lib/")[0].contains("separate git repository"));
+        assert!(flagged("lib")[0].contains("git submodule add <url> vendor/lib"));
+        assert_eq!(flagged("/vendor").len(), 1);
+        assert!(flagged("modules/*")[0].contains("has no effect"));
+        assert!(flagged("*.log").is_empty());
+        assert!(flagged("vendor/lib/build/").is_empty());
+        assert!(flagged("!vendor/").is_empty());
+        assert!(flagged("/lib").is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+//! Fine-grained events from library operations
+//!
+//! GUI and editor hosts that embed the library can follow an operation step
+//! by step — which file a scope resolved to, what validation said about each
+//! pattern, which patterns were skipped and why, every line written or
+//! removed, and when the file was replaced on disk — by passing an
+//! [`Observer`] to [`add_patterns_observed`](crate::add_patterns_observed),
+//! [`remove_patterns_observed`](crate::remove_patterns_observed) or the
+//! file-level functions in [`ignore`](crate::ignore). Every method has an
+//! empty default, so an observer only implements what it shows; `()` is the
+//! observer that ignores everything.
+//!
+//! Bulk operations that work through many repositories or files, such as
+//! [`find_candidates_observed`](crate::consolidate::find_candidates_observed)
+//! and [`Transaction::commit_observed`](crate::ignore::Transaction::commit_observed),
+//! also report [`progress`](Observer::progress) after each one, so a host
+//! can draw a progress bar.
+//!
+//! Events are reported once the outcome is certain: lines are reported
+//! after the file was written, never for an attempt that was retried or
+//! failed.
+
+use crate::{PatternIssue, Scope};
+use std::path::Path;
+
+/// Why a requested pattern did not change the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SkipReason {
+    /// The file, or an earlier pattern in the same request, already has it
+    Duplicate,
+    /// Nothing was left after trimming whitespace and line breaks
+    Empty,
+    /// A pattern to remove matched no line
+    NotFound,
+}
+
+/// Receives events from observed operations
+#[allow(unused_variables)]
+pub trait Observer {
+    /// The file a scope refers to was determined
+    fn file_resolved(&mut self, scope: Scope, path: &Path) {}
+
+    /// A pattern was validated; `issues` is empty when nothing was found
+    fn pattern_validated(&mut self, pattern: &str, issues: &[PatternIssue]) {}
+
+    /// A pattern left the file unchanged
+    fn pattern_skipped(&mut self, pattern: &str, reason: SkipReason) {}
+
+    /// A line was added to `path`
+    fn line_written(&mut self, path: &Path, line: &str) {}
+
+    /// A line was removed from `path`; `line_number` is 1-based and refers
+    /// to the file before the removal
+    fn line_removed(&mut self, path: &Path, line: &str, line_number: usize) {}
+
+    /// The new content of `path` replaced the old one on disk
+    fn file_committed(&mut self, path: &Path) {}
+
+    /// A bulk operation finished with `item`, a repository or file, the
+    /// `done`th of `total`
+    fn progress(&mut self, item: &Path, done: usize, total: usize) {}
+}
+
+impl Observer for () {}
@@ -0,0 +1,353 @@
+//! Path canonicalization that behaves the same on every platform
+//!
+//! On Windows, `std::fs::canonicalize` returns verbatim paths
+//! (`\\?\C:\repo`, `\\?\UNC\server\share\repo`). They are valid but compare
+//! unequal to the ordinary spelling git reports, and are hard to read in
+//! error messages. [`canonicalize`] resolves symlinks and junctions like the
+//! standard function and then drops the verbatim prefix whenever the plain
+//! form means the same path.
+//!
+//! [`expand_path`] resolves `~` and environment variables in paths read
+//! from git config. On Windows it also translates the MSYS/Cygwin spellings
+//! Git Bash users write (`/c/Users/me/...`, `/cygdrive/c/...`, `/etc/...`)
+//! into native paths, which a native program would otherwise take as
+//! relative to the current drive.
+
+use crate::Error;
+use std::{io, path::Path, path::PathBuf};
+
+/// Longest path the plain (non-verbatim) Win32 form is guaranteed to handle
+const MAX_PLAIN_PATH: usize = 260;
+
+/// Canonicalize `path`, preferring the plain form over the verbatim one
+pub(crate) fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let resolved = path.canonicalize()?;
+    if cfg!(windows) {
+        if let Some(plain) = resolved.to_str().and_then(simplify_verbatim) {
+            return Ok(PathBuf::from(plain));
+        }
+    }
+    Ok(resolved)
+}
+
+/// Convert a verbatim Windows path to its plain equivalent, if it has one:
+/// `\\?\C:\dir` becomes `C:\dir` and `\\?\UNC\server\share\dir` becomes
+/// `\\server\share\dir`. Returns `None` for other paths and for verbatim
+/// paths whose plain spelling would be misinterpreted (too long, reserved
+/// device names, trailing dots or spaces).
+fn simplify_verbatim(path: &str) -> Option<String> {
+    let rest = path.strip_prefix(r"\\?\")?;
+
+    let (plain, components) = if let Some(unc) = rest.strip_prefix(r"UNC\") {
+        (format!(r"\\{unc}"), unc)
+    } else {
+        let bytes = rest.as_bytes();
+        let is_disk = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+        if !is_disk || (bytes.len() > 2 && bytes[2] != b'\\') {
+            return None;
+        }
+        (rest.to_string(), rest.get(3..).unwrap_or(""))
+    };
+
+    if plain.len() >= MAX_PLAIN_PATH || plain.contains('/') {
+        return None;
+    }
+    if components
+        .split('\\')
+        .filter(|c| !c.is_empty())
+        .any(|c| !is_plain_component(c))
+    {
+        return None;
+    }
+    Some(plain)
+}
+
+/// Whether a path component keeps its meaning outside a verbatim path
+fn is_plain_component(component: &str) -> bool {
+    if component == "." || component == ".." || component.ends_with(['.', ' ']) {
+        return false;
+    }
+    let stem = component
+        .split('.')
+        .next()
+        .unwrap_or(component)
+        .trim_end()
+        .to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit());
+    !reserved
+}
+
+/// Expand a leading `~` and environment variable references in a
+/// configured path.
+///
+/// Rules, kept deliberately small:
+/// - `~` or `~/...` at the start expands to `$HOME` (`%USERPROFILE%` on
+///   Windows when `HOME` is unset);
+/// - `$NAME` and `${NAME}` expand to the variable's value, where `NAME` is
+///   letters, digits and underscores not starting with a digit;
+/// - a `$` not followed by a valid name is kept as is;
+/// - nothing else is interpreted: no defaults, nesting or command
+///   substitution.
+///
+/// Referencing a variable that is unset or empty is an error, so a broken
+/// setup is reported instead of silently producing a relative path.
+pub(crate) fn expand_path(value: &str) -> anyhow::Result<PathBuf> {
+    let expanded = expand_path_with(value, |name| std::env::var(name).ok())?;
+    Ok(native_path(expanded))
+}
+
+/// The user's home directory: `$HOME`, else `%USERPROFILE%` on Windows
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").filter(|home| !home.is_empty());
+    let home = if cfg!(windows) {
+        home.or_else(|| std::env::var_os("USERPROFILE").filter(|home| !home.is_empty()))
+    } else {
+        home
+    };
+    home.map(|home| native_path(PathBuf::from(home)))
+}
+
+/// On Windows, translate an MSYS-style absolute path to a native one: drive
+/// mounts directly, other roots (`/etc/gitignore`, inside the Git for
+/// Windows installation) with `cygpath` when it is available. Other paths,
+/// and every path elsewhere, are returned unchanged.
+pub(crate) fn native_path(path: PathBuf) -> PathBuf {
+    if !cfg!(windows) {
+        return path;
+    }
+    let Some(text) = path.to_str() else {
+        return path;
+    };
+    if let Some(native) = msys_drive_path(text) {
+        return PathBuf::from(native);
+    }
+    if text.starts_with('/') && !text.starts_with("//") {
+        if let Some(native) = cygpath(text) {
+            return PathBuf::from(native);
+        }
+    }
+    path
+}
+
+/// Translate an MSYS or Cygwin drive path to its Windows spelling:
+/// `/c/Users/me` and `/cygdrive/c/Users/me` become `C:\Users\me`.
+/// Returns `None` for anything else.
+fn msys_drive_path(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("/cygdrive").unwrap_or(path);
+    let rest = rest.strip_prefix('/')?;
+    let mut chars = rest.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let tail = chars.as_str();
+    if !(tail.is_empty() || tail.starts_with('/')) {
+        return None;
+    }
+    Some(format!(
+        "{}:\\{}",
+        drive.to_ascii_uppercase(),
+        tail.trim_start_matches('/').replace('/', "\\")
+    ))
+}
+
+/// Ask Git for Windows' `cygpath` for the native form of an MSYS path
+fn cygpath(path: &str) -> Option<String> {
+    let output = std::process::Command::new("cygpath")
+        .args(["-w", path])
+        .output()
+        .ok()?;
+    let native = String::from_utf8(output.stdout).ok()?;
+    let native = native.trim_end_matches(['\r', '\n']);
+    (output.status.success() && !native.is_empty()).then(|| native.to_string())
+}
+
+fn expand_path_with(
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<PathBuf> {
+    let lookup_nonempty = |name: &str| -> anyhow::Result<String> {
+        match lookup(name).filter(|v| !v.is_empty()) {
+            Some(v) => Ok(v),
+            None => {
+                anyhow::bail!(Error::config(format!(
+                    "{value} refers to ${name}, which is not set"
+                )))
+            }
+        }
+    };
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        let home = lookup("HOME")
+            .filter(|v| !v.is_empty())
+            .or_else(|| lookup("USERPROFILE").filter(|v| !v.is_empty()));
+        match home {
+            Some(home) => out.push_str(&home),
+            None => {
+                anyhow::bail!(Error::config(format!(
+                    "cannot expand ~ in {value}: HOME is not set"
+                )))
+            }
+        }
+        rest = &rest[1..];
+    }
+
+    while let Some(index) = rest.find('$') {
+        out.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) if is_var_name(&braced[..end]) => (&braced[..end], end + 2),
+                _ => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if is_var_name(&after[..end]) {
+                (&after[..end], end)
+            } else {
+                ("", 0)
+            }
+        };
+
+        if name.is_empty() {
+            out.push('$');
+            rest = after;
+        } else {
+            out.push_str(&lookup_nonempty(name)?);
+            rest = &after[consumed..];
+        }
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
+
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(value: &str) -> anyhow::Result<String> {
+        let env = |name: &str| match name {
+            "HOME" => Some("/home/dev".to_string()),
+            "XDG_CONFIG_HOME" => Some("/home/dev/.config".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        expand_path_with(value, env).map(|p| p.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn test_expand_path() {
+        assert_eq!(expand("~/.gitignore").unwrap(), "/home/dev/.gitignore");
+        assert_eq!(
+            expand("$XDG_CONFIG_HOME/git/ignore").unwrap(),
+            "/home/dev/.config/git/ignore"
+        );
+        assert_eq!(
+            expand("${HOME}/dotfiles/gitignore").unwrap(),
+            "/home/dev/dotfiles/gitignore"
+        );
+        assert_eq!(expand("/etc/gitignore").unwrap(), "/etc/gitignore");
+        assert_eq!(expand("/a/$/b$").unwrap(), "/a/$/b$");
+        assert_eq!(expand("/a/${not valid}").unwrap(), "/a/${not valid}");
+        assert_eq!(expand("/a/~/b").unwrap(), "/a/~/b");
+    }
+
+    #[test]
+    fn test_expand_path_unset_variable() {
+        let err = expand("$MISSING/ignore").unwrap_err().to_string();
+        assert!(err.contains("$MISSING"));
+        assert!(expand("${EMPTY}/ignore").is_err());
+    }
+
+    #[test]
+    fn test_msys_drive_path() {
+        assert_eq!(
+            msys_drive_path("/c/Users/me/.gitignore_global").as_deref(),
+            Some(r"C:\Users\me\.gitignore_global")
+        );
+        assert_eq!(
+            msys_drive_path("/cygdrive/d/work/ignore").as_deref(),
+            Some(r"D:\work\ignore")
+        );
+        assert_eq!(msys_drive_path("/c").as_deref(), Some(r"C:\"));
+        assert_eq!(msys_drive_path("/etc/gitignore"), None);
+        assert_eq!(msys_drive_path(r"C:\Users\me"), None);
+        assert_eq!(msys_drive_path("relative/c/x"), None);
+    }
+
+    #[test]
+    fn test_simplify_disk_paths() {
+        assert_eq!(
+            simplify_verbatim(r"\\?\C:\Users\dev\repo").as_deref(),
+            Some(r"C:\Users\dev\repo")
+        );
+        assert_eq!(simplify_verbatim(r"\\?\D:\").as_deref(), Some(r"D:\"));
+        assert_eq!(simplify_verbatim(r"C:\already\plain"), None);
+        assert_eq!(simplify_verbatim(r"\\?\Volume{1234}\repo"), None);
+    }
+
+    #[test]
+    fn test_simplify_unc_paths() {
+        assert_eq!(
+            simplify_verbatim(r"\\?\UNC\server\share\repo\.gitignore").as_deref(),
+            Some(r"\\server\share\repo\.gitignore")
+        );
+        assert_eq!(simplify_verbatim(r"\\server\share\repo"), None);
+    }
+
+    #[test]
+    fn test_keep_verbatim_when_plain_form_differs() {
+        assert_eq!(simplify_verbatim(r"\\?\C:\repo\nul"), None);
+        assert_eq!(simplify_verbatim(r"\\?\C:\repo\com1.txt"), None);
+        assert_eq!(simplify_verbatim(r"\\?\C:\repo\trailing."), None);
+        assert_eq!(simplify_verbatim(r"\\?\C:\repo\trailing "), None);
+        assert_eq!(simplify_verbatim(r"\\?\C:\repo\a/b"), None);
+        let long = format!(r"\\?\C:\{}", "a".repeat(MAX_PLAIN_PATH));
+        assert_eq!(simplify_verbatim(&long), None);
+        assert!(simplify_verbatim(r"\\?\C:\repo\console\compile").is_some());
+    }
+
+    #[test]
+    fn test_canonicalize_matches_std_outside_windows() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let resolved = canonicalize(temp_dir.path()).unwrap();
+        assert!(resolved.is_absolute());
+        if !cfg!(windows) {
+            assert_eq!(resolved, temp_dir.path().canonicalize().unwrap());
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_canonicalize_through_junction() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let real = temp_dir.path().join("real");
+        let junction = temp_dir.path().join("junction");
+        std::fs::create_dir(&real).unwrap();
+        let status = std::process::Command::new("cmd")
+            .args(["/C", "mklink", "/J"])
+            .arg(&junction)
+            .arg(&real)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let via_junction = canonicalize(&junction.join(".")).unwrap();
+        let direct = canonicalize(&real).unwrap();
+        assert_eq!(via_junction, direct);
+        assert!(!via_junction.to_string_lossy().starts_with(r"\\?\"));
+    }
+}
@@ -0,0 +1,914 @@
+//! Parsed representation of a single gitignore pattern
+
+use crate::paths;
+use anyhow::bail;
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+/// One `/`-separated component of a pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Segment {
+    /// A `**` component, matching zero or more directories
+    DoubleStar,
+    /// A component without wildcards, stored unescaped
+    Literal(String),
+    /// A component containing `*`, `?` or `[...]`, stored as written
+    Glob(String),
+}
+
+impl Segment {
+    fn parse(text: &str) -> Segment {
+        if text == "**" {
+            return Segment::DoubleStar;
+        }
+
+        let mut literal = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        literal.push(escaped);
+                    }
+                }
+                '*' | '?' | '[' => return Segment::Glob(text.to_string()),
+                _ => literal.push(c),
+            }
+        }
+        Segment::Literal(literal)
+    }
+
+    /// Whether this segment contains any wildcard syntax
+    pub fn is_wildcard(&self) -> bool {
+        !matches!(self, Segment::Literal(_))
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::DoubleStar => f.write_str("**"),
+            Segment::Glob(text) => f.write_str(text),
+            Segment::Literal(text) => {
+                let last = text.chars().count().saturating_sub(1);
+                for (i, c) in text.chars().enumerate() {
+                    // Trailing spaces are stripped by git unless escaped
+                    if needs_escape(c) || (i == last && c == ' ') {
+                        write!(f, "\\{c}")?;
+                    } else {
+                        write!(f, "{c}")?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A gitignore pattern split into its syntactic components.
+///
+/// Parsing follows gitignore(5): a leading `!` negates the pattern, a
+/// trailing `/` restricts it to directories, and a slash at the start or in
+/// the middle anchors it to the directory containing the ignore file.
+/// Converting back with `Display` yields the pattern text, so parsing and
+/// printing a pattern round-trips.
+///
+/// ```
+/// use git_ignore_tool::Pattern;
+///
+/// let pattern = Pattern::parse("!/build/**/*.o").unwrap();
+/// assert!(pattern.negated);
+/// assert!(pattern.anchored);
+/// assert!(!pattern.dir_only);
+/// assert_eq!(pattern.segments.len(), 3);
+/// assert_eq!(pattern.to_string(), "!/build/**/*.o");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pattern {
+    /// Pattern starts with `!` and re-includes matching paths
+    pub negated: bool,
+    /// Pattern ends with `/` and only matches directories
+    pub dir_only: bool,
+    /// Pattern is matched relative to the ignore file's directory rather
+    /// than against the name of every path component
+    pub anchored: bool,
+    /// Path components of the pattern, in order
+    pub segments: Vec<Segment>,
+    /// Whether the anchor was written as a leading `/`
+    leading_slash: bool,
+}
+
+impl Pattern {
+    /// Parse a single line of gitignore syntax.
+    ///
+    /// Fails for lines that aren't patterns: blank lines, comments and a
+    /// lone `!`.
+    pub fn parse(line: &str) -> anyhow::Result<Pattern> {
+        let text = trim_unescaped_trailing_spaces(line.trim_end_matches(['\r', '\n']));
+
+        if text.trim().is_empty() {
+            bail!("Empty line is not a pattern");
+        }
+        if text.starts_with('#') {
+            bail!("Comment line is not a pattern: {text}");
+        }
+
+        let (negated, rest) = match text.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        if rest.is_empty() {
+            bail!("Negation without a pattern: {text}");
+        }
+
+        let (leading_slash, rest) = match rest.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let (dir_only, rest) = match rest.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+
+        let segments: Vec<Segment> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split('/').map(Segment::parse).collect()
+        };
+        let anchored = leading_slash || segments.len() > 1;
+
+        Ok(Pattern {
+            negated,
+            dir_only,
+            anchored,
+            segments,
+            leading_slash,
+        })
+    }
+
+    /// [`parse`](Pattern::parse) `line`, also rejecting glob syntax that
+    /// can't match anything: an unterminated `[...]` bracket expression, an
+    /// unknown `[:class:]` name or a trailing backslash that escapes
+    /// nothing. Git accepts such a line without complaint and the pattern
+    /// silently never matches.
+    ///
+    /// ```
+    /// use git_ignore_tool::Pattern;
+    ///
+    /// assert!(Pattern::compile("*.[ch]").is_ok());
+    /// assert!(Pattern::compile("*.[ch").is_err());
+    /// assert!(Pattern::compile("[[:word:]]").is_err());
+    /// ```
+    pub fn compile(line: &str) -> anyhow::Result<Pattern> {
+        let pattern = Pattern::parse(line)?;
+        if let Some(problem) = syntax_problem(line.trim_end_matches(['\r', '\n'])) {
+            bail!("{problem}");
+        }
+        Ok(pattern)
+    }
+
+    /// Whether the pattern re-includes paths instead of excluding them
+    pub fn is_negation(&self) -> bool {
+        self.negated
+    }
+
+    /// The simplest spelling of the same pattern: repeated `**` components
+    /// collapse into one, a leading `**/` (with or without `/` in front)
+    /// before a single remaining component becomes an unanchored pattern,
+    /// and a leading `/` is dropped where a later slash anchors the pattern
+    /// anyway.
+    ///
+    /// ```
+    /// use git_ignore_tool::Pattern;
+    ///
+    /// let pattern: Pattern = "/**/**/node_modules/".parse().unwrap();
+    /// assert_eq!(pattern.normalize().to_string(), "node_modules/");
+    /// let pattern: Pattern = "/src/**/**/*.rs".parse().unwrap();
+    /// assert_eq!(pattern.normalize().to_string(), "src/**/*.rs");
+    /// ```
+    pub fn normalize(&self) -> Pattern {
+        let mut segments: Vec<Segment> = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            if *segment == Segment::DoubleStar && segments.last() == Some(&Segment::DoubleStar) {
+                continue;
+            }
+            segments.push(segment.clone());
+        }
+        if segments.len() == 2 && segments[0] == Segment::DoubleStar {
+            segments.remove(0);
+            return Pattern {
+                anchored: false,
+                segments,
+                leading_slash: false,
+                ..self.clone()
+            };
+        }
+        let anchored = self.leading_slash || segments.len() > 1;
+        Pattern {
+            anchored,
+            leading_slash: self.leading_slash && segments.len() <= 1,
+            segments,
+            ..self.clone()
+        }
+    }
+
+    /// Whether any segment contains wildcard syntax
+    pub fn has_wildcards(&self) -> bool {
+        self.segments.iter().any(Segment::is_wildcard)
+    }
+
+    /// Whether the pattern matches `path`, a `/`-separated path relative to
+    /// the directory of the ignore file the pattern came from.
+    ///
+    /// `is_dir` tells whether `path` is a directory, which directory-only
+    /// patterns require. Negation is not applied here: a negated pattern
+    /// matches the paths it re-includes. Paths inside a matched directory
+    /// are not matched by this function; callers that walk a tree stop at
+    /// the excluded directory the way git does.
+    ///
+    /// ```
+    /// use git_ignore_tool::Pattern;
+    ///
+    /// let pattern = Pattern::parse("docs/**/*.md").unwrap();
+    /// assert!(pattern.matches("docs/api/index.md", false));
+    /// assert!(!pattern.matches("src/docs/index.md", false));
+    /// ```
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let path = path.trim_matches('/');
+        if path.is_empty() || self.segments.is_empty() {
+            return false;
+        }
+
+        if !self.anchored {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            return self.segments[0].matches_component(name);
+        }
+
+        let components: Vec<&str> = path.split('/').collect();
+        match_segments(&self.segments, &components)
+    }
+}
+
+impl Segment {
+    /// Whether this (non-`**`) segment matches a single path component
+    pub(crate) fn matches_component(&self, component: &str) -> bool {
+        match self {
+            Segment::DoubleStar => true,
+            Segment::Literal(text) => text == component,
+            Segment::Glob(glob) => {
+                let glob: Vec<char> = glob.chars().collect();
+                let text: Vec<char> = component.chars().collect();
+                wildmatch(&glob, &text)
+            }
+        }
+    }
+}
+
+/// Match pattern segments against path components, with `**` matching any
+/// number of directories (at least one when it ends the pattern, so that
+/// `dir/**` matches the contents of `dir` but not `dir` itself)
+fn match_segments(segments: &[Segment], components: &[&str]) -> bool {
+    // `matched[i][j]`: whether `segments[i..]` match `components[j..]`,
+    // filled in from the end so that a run of `**` can't backtrack
+    // exponentially
+    let (n, m) = (segments.len(), components.len());
+    let mut matched = vec![vec![false; m + 1]; n + 1];
+    matched[n][m] = true;
+    for i in (0..n).rev() {
+        for j in (0..=m).rev() {
+            matched[i][j] = match &segments[i] {
+                Segment::DoubleStar => {
+                    let min = usize::from(i + 1 == n);
+                    (j + min..=m).any(|k| matched[i + 1][k])
+                }
+                segment => {
+                    j < m && matched[i + 1][j + 1] && segment.matches_component(components[j])
+                }
+            };
+        }
+    }
+    matched[0][0]
+}
+
+/// Match a single path component against glob syntax: `*`, `?`, `[...]`
+/// classes (with `!`/`^` negation, ranges and `[:class:]` names) and
+/// backslash escapes
+fn wildmatch(glob: &[char], text: &[char]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    // Position to resume from after the most recent `*`: (glob, text)
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match glob.get(g) {
+            Some('*') => {
+                while glob.get(g) == Some(&'*') {
+                    g += 1;
+                }
+                backtrack = Some((g, t));
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match match_class(&glob[g..], text[t]) {
+                Some((true, len)) => Some(len),
+                _ => None,
+            },
+            Some('\\') if g + 1 < glob.len() => (glob[g + 1] == text[t]).then_some(2),
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+
+        match step {
+            Some(len) => {
+                g += len;
+                t += 1;
+            }
+            None => match backtrack {
+                Some((star_g, star_t)) => {
+                    g = star_g;
+                    t = star_t + 1;
+                    backtrack = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+/// Match `c` against the bracket expression at the start of `glob`.
+/// Returns whether it matched and the length of the expression, or `None`
+/// if the expression is unterminated.
+pub(crate) fn match_class(glob: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(glob.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let current = *glob.get(i)?;
+        if current == ']' && !first {
+            break;
+        }
+        first = false;
+
+        if current == '[' && glob.get(i + 1) == Some(&':') {
+            let rest: String = glob[i + 2..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                matched |= match_named_class(&rest[..end], c);
+                i += 2 + rest[..end].chars().count() + 2;
+                continue;
+            }
+        }
+
+        let (low, len) = match current {
+            '\\' => (*glob.get(i + 1)?, 2),
+            other => (other, 1),
+        };
+        i += len;
+
+        if glob.get(i) == Some(&'-') && glob.get(i + 1).is_some_and(|&next| next != ']') {
+            let (high, len) = match glob[i + 1] {
+                '\\' => (*glob.get(i + 2)?, 2),
+                other => (other, 1),
+            };
+            i += 1 + len;
+            matched |= low <= c && c <= high;
+        } else {
+            matched |= low == c;
+        }
+    }
+
+    Some((matched != negated, i + 1))
+}
+
+/// Names git accepts in a `[:name:]` character class
+const CLASS_NAMES: [&str; 12] = [
+    "alnum", "alpha", "blank", "cntrl", "digit", "graph", "lower", "print", "punct", "space",
+    "upper", "xdigit",
+];
+
+/// What makes the glob syntax of pattern `line` unable to match, if
+/// anything (see [`Pattern::compile`])
+pub(crate) fn syntax_problem(line: &str) -> Option<String> {
+    let chars: Vec<char> = trim_unescaped_trailing_spaces(line).chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 == chars.len() => {
+                return Some("Trailing backslash escapes nothing".to_string());
+            }
+            '\\' => i += 2,
+            '[' => match class_length(&chars[i..]) {
+                Ok(len) => i += len,
+                Err(problem) => return Some(problem),
+            },
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Length of the bracket expression at the start of `glob`, parsed as
+/// [`match_class`] does, or what is wrong with it
+pub(crate) fn class_length(glob: &[char]) -> Result<usize, String> {
+    // A '/' ends the path component, and with it the expression
+    let end = glob.iter().position(|&c| c == '/').unwrap_or(glob.len());
+    let unterminated = || {
+        format!(
+            "Unterminated bracket expression '{}'",
+            glob[..end].iter().collect::<String>()
+        )
+    };
+    let mut i = 1;
+    if matches!(glob.get(i), Some('!') | Some('^')) {
+        i += 1;
+    }
+    let mut first = true;
+    loop {
+        let current = *glob[..end].get(i).ok_or_else(unterminated)?;
+        if current == ']' && !first {
+            return Ok(i + 1);
+        }
+        first = false;
+
+        if current == '[' && glob.get(i + 1) == Some(&':') {
+            let rest: String = glob[i + 2..end].iter().collect();
+            if let Some(close) = rest.find(":]") {
+                let name = &rest[..close];
+                if !CLASS_NAMES.contains(&name) {
+                    return Err(format!("Unknown character class '[:{name}:]'"));
+                }
+                i += 2 + name.chars().count() + 2;
+                continue;
+            }
+        }
+        i += if current == '\\' { 2 } else { 1 };
+    }
+}
+
+fn match_named_class(name: &str, c: char) -> bool {
+    match name {
+        "alnum" => c.is_ascii_alphanumeric(),
+        "alpha" => c.is_ascii_alphabetic(),
+        "blank" => c == ' ' || c == '\t',
+        "cntrl" => c.is_ascii_control(),
+        "digit" => c.is_ascii_digit(),
+        "graph" => c.is_ascii_graphic(),
+        "lower" => c.is_ascii_lowercase(),
+        "print" => c.is_ascii_graphic() || c == ' ',
+        "punct" => c.is_ascii_punctuation(),
+        "space" => c.is_ascii_whitespace(),
+        "upper" => c.is_ascii_uppercase(),
+        "xdigit" => c.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            f.write_str("!")?;
+        } else if let Some(Segment::Literal(first)) = self.segments.first() {
+            // A literal leading '#' or '!' would otherwise read back as a
+            // comment or a negation
+            if (first.starts_with('#') || first.starts_with('!')) && !self.leading_slash {
+                f.write_str("\\")?;
+            }
+        }
+        if self.leading_slash || (self.anchored && self.segments.len() <= 1) {
+            f.write_str("/")?;
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            write!(f, "{segment}")?;
+        }
+        if self.dir_only && !self.segments.is_empty() {
+            f.write_str("/")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> anyhow::Result<Pattern> {
+        Pattern::parse(line)
+    }
+}
+
+/// The pattern that ignores exactly `path` and nothing else, for the
+/// `.gitignore` at `repo_root`.
+///
+/// `path` may be absolute or relative to the current directory, and needn't
+/// exist. The pattern is relative to the root and anchored with a leading
+/// `/`, so `Makefile` doesn't also match `src/Makefile`; a directory (one
+/// that exists, or a path ending in a separator) gets a trailing `/`.
+/// Characters git would read as syntax are escaped: `*`, `?`, `[` and `\`
+/// anywhere, and a trailing space, so the pattern matches the name as
+/// written.
+///
+/// ```no_run
+/// use git_ignore_tool::pattern_from_path;
+/// use std::path::Path;
+///
+/// let root = Path::new("/work/repo");
+/// let pattern = pattern_from_path(root, Path::new("/work/repo/notes [draft].txt"))?;
+/// assert_eq!(pattern, "/notes \\[draft].txt");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn pattern_from_path(repo_root: &Path, path: &Path) -> anyhow::Result<String> {
+    let is_dir = path.is_dir() || path.as_os_str().to_string_lossy().ends_with(['/', '\\']);
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        crate::git::current_dir()?.join(path)
+    };
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+    }
+    // Compare real locations, so symlinked spellings of the root still match
+    let root = paths::canonicalize(repo_root).unwrap_or_else(|_| repo_root.to_path_buf());
+    let resolved = match (resolved.parent(), resolved.file_name()) {
+        (Some(parent), Some(name)) => match paths::canonicalize(parent) {
+            Ok(parent) => parent.join(name),
+            Err(_) => resolved,
+        },
+        _ => resolved,
+    };
+    let Ok(relative) = resolved.strip_prefix(&root) else {
+        bail!(
+            "{} is outside the repository ({})",
+            path.display(),
+            root.display()
+        );
+    };
+
+    let mut segments = Vec::new();
+    for component in relative.components() {
+        let Some(name) = component.as_os_str().to_str() else {
+            bail!("{} is not valid UTF-8", path.display());
+        };
+        segments.push(Segment::Literal(name.to_string()));
+    }
+    if segments.is_empty() {
+        bail!("The repository root can't be ignored");
+    }
+    Ok(Pattern {
+        negated: false,
+        dir_only: is_dir,
+        anchored: true,
+        segments,
+        leading_slash: true,
+    }
+    .to_string())
+}
+
+/// `name` escaped so that, as a pattern, it matches a file of exactly that
+/// name (or path, as `/` is kept as the separator). Git reads `*`, `?`, `[`,
+/// `]` and `\` as syntax anywhere, a leading `#` or `!` as a comment or a
+/// negation, and drops trailing spaces; each of those gets a backslash, as
+/// do leading spaces, which this crate would otherwise trim.
+///
+/// ```
+/// use git_ignore_tool::pattern::escape_literal;
+///
+/// assert_eq!(escape_literal("#notes[1].txt"), "\\#notes\\[1\\].txt");
+/// assert_eq!(escape_literal("draft "), "draft\\ ");
+/// ```
+pub fn escape_literal(name: &str) -> String {
+    let is_edge_space = |c: char| c == ' ' || c == '\t';
+    let leading = name.len() - name.trim_start_matches(is_edge_space).len();
+    let trailing = name.trim_end_matches(is_edge_space).len().max(leading);
+
+    let mut escaped = String::with_capacity(name.len() + 2);
+    if name.starts_with(['#', '!']) {
+        escaped.push('\\');
+    }
+    for (i, c) in name.char_indices() {
+        if needs_escape(c) || i < leading || i >= trailing {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Whether `c` is glob syntax wherever it appears in a pattern, so a
+/// literal needs a backslash before it
+fn needs_escape(c: char) -> bool {
+    matches!(c, '*' | '?' | '[' | ']' | '\\')
+}
+
+/// Strip trailing spaces unless they are escaped with a backslash. Like
+/// git, tabs are kept: only spaces are trimmed.
+pub(crate) fn trim_unescaped_trailing_spaces(text: &str) -> &str {
+    let trimmed = text.trim_end_matches(' ');
+    if trimmed.len() < text.len() && trimmed.ends_with('\\') {
+        let backslashes = trimmed.chars().rev().take_while(|&c| c == '\\').count();
+        if backslashes % 2 == 1 {
+            // Keep the escaped space
+            return &text[..trimmed.len() + 1];
+        }
+    }
+    trimmed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_components() {
+        let pattern = Pattern::parse("*.pyc").unwrap();
+        assert!(!pattern.negated);
+        assert!(!pattern.dir_only);
+        assert!(!pattern.anchored);
+        assert_eq!(pattern.segments, vec![Segment::Glob("*.pyc".to_string())]);
+
+        let pattern = Pattern::parse("!build/").unwrap();
+        assert!(pattern.negated);
+        assert!(pattern.dir_only);
+        assert!(!pattern.anchored);
+        assert_eq!(
+            pattern.segments,
+            vec![Segment::Literal("build".to_string())]
+        );
+
+        let pattern = Pattern::parse("docs/**/generated").unwrap();
+        assert!(pattern.anchored);
+        assert_eq!(
+            pattern.segments,
+            vec![
+                Segment::Literal("docs".to_string()),
+                Segment::DoubleStar,
+                Segment::Literal("generated".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_escapes() {
+        let pattern = Pattern::parse("\\#notes").unwrap();
+        assert_eq!(
+            pattern.segments,
+            vec![Segment::Literal("#notes".to_string())]
+        );
+
+        let pattern = Pattern::parse("\\!important").unwrap();
+        assert!(!pattern.negated);
+
+        let pattern = Pattern::parse("file\\ ").unwrap();
+        assert_eq!(
+            pattern.segments,
+            vec![Segment::Literal("file ".to_string())]
+        );
+
+        let pattern = Pattern::parse("trailing   ").unwrap();
+        assert_eq!(
+            pattern.segments,
+            vec![Segment::Literal("trailing".to_string())]
+        );
+
+        // Git trims only spaces; a trailing tab is part of the name
+        let pattern = Pattern::parse("tab\t").unwrap();
+        assert_eq!(
+            pattern.segments,
+            vec![Segment::Literal("tab\t".to_string())]
+        );
+        assert_eq!(trim_unescaped_trailing_spaces("tab\t  "), "tab\t");
+    }
+
+    #[test]
+    fn test_parse_rejects_non_patterns() {
+        assert!(Pattern::parse("").is_err());
+        assert!(Pattern::parse("   ").is_err());
+        assert!(Pattern::parse("# comment").is_err());
+        assert!(Pattern::parse("!").is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for text in [
+            "*.pyc",
+            "build/",
+            "/target",
+            "/target/",
+            "!keep.log",
+            "!/docs/**/*.md",
+            "**/node_modules/",
+            "a/b/c",
+            "src/*.rs",
+            "\\#notes",
+            "\\!important",
+            "file\\ ",
+            "literal\\*star",
+            "notes\\[1\\].txt",
+            "[Bb]uild/",
+            "/",
+        ] {
+            let pattern = Pattern::parse(text).unwrap();
+            assert_eq!(pattern.to_string(), text, "round trip of {text:?}");
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        for (text, expected) in [
+            ("*.pyc", "*.pyc"),
+            ("/target/", "/target/"),
+            ("**/node_modules/", "node_modules/"),
+            ("/**/node_modules", "node_modules"),
+            ("!**/**/keep.log", "!keep.log"),
+            ("/src/*.rs", "src/*.rs"),
+            ("docs/**/**/**/*.md", "docs/**/*.md"),
+            ("**/a/b", "**/a/b"),
+            ("\\b\\uild", "build"),
+            ("/", "/"),
+        ] {
+            let pattern: Pattern = text.parse().unwrap();
+            let normalized = pattern.normalize();
+            assert_eq!(normalized.to_string(), expected, "normalizing {text:?}");
+            assert_eq!(normalized.normalize(), normalized);
+            for path in [
+                "target",
+                "a/target",
+                "node_modules",
+                "x/node_modules",
+                "src/a.rs",
+            ] {
+                assert_eq!(
+                    normalized.matches(path, true),
+                    pattern.matches(path, true),
+                    "{text:?} against {path:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_negation_and_from_str() {
+        assert!("!keep.log".parse::<Pattern>().unwrap().is_negation());
+        assert!(!"\\!keep.log".parse::<Pattern>().unwrap().is_negation());
+        assert!("# comment".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn test_pattern_from_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/gen")).unwrap();
+        let pattern = |path: &Path| pattern_from_path(root, path).unwrap();
+
+        assert_eq!(pattern(&root.join("Makefile")), "/Makefile");
+        assert_eq!(pattern(&root.join("src/gen")), "/src/gen/");
+        assert_eq!(pattern(&root.join("src/../out/")), "/out/");
+        assert_eq!(
+            pattern(&root.join("a*b?[c] d\\e ")),
+            "/a\\*b\\?\\[c\\] d\\\\e\\ "
+        );
+        assert_eq!(pattern(&root.join("#notes")), "/#notes");
+        assert_eq!(pattern(&root.join("!important")), "/!important");
+
+        // The pattern names exactly that path again
+        let written = pattern(&root.join("src/gen/[x].txt"));
+        let parsed = Pattern::parse(&written).unwrap();
+        assert!(parsed.matches("src/gen/[x].txt", false));
+        assert!(!parsed.matches("src/gen/x.txt", false));
+
+        assert!(pattern_from_path(root, root).is_err());
+        assert!(pattern_from_path(root, &root.join("..").join("elsewhere")).is_err());
+    }
+
+    #[test]
+    fn test_escape_literal() {
+        assert_eq!(escape_literal("plain.txt"), "plain.txt");
+        assert_eq!(escape_literal("a*b?c\\d"), "a\\*b\\?c\\\\d");
+        assert_eq!(escape_literal("!important"), "\\!important");
+        assert_eq!(escape_literal(" two  "), "\\ two\\ \\ ");
+        assert_eq!(escape_literal("logs/#1/"), "logs/#1/");
+
+        // Each escaped name matches itself and nothing else
+        for name in ["#notes", "!keep", "[x].txt", "a]b", "star*", "space "] {
+            let pattern = Pattern::parse(&escape_literal(name)).unwrap();
+            assert!(!pattern.negated, "{name}");
+            assert!(pattern.matches(name, false), "{name}");
+            assert!(!pattern.segments[0].is_wildcard(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_compile_syntax_errors() {
+        for valid in [
+            "*.[ch]",
+            "[]x]",
+            "[!a-z]/",
+            "[[:digit:]]*",
+            "a\\]",
+            "\\[x",
+            "x\\ ",
+        ] {
+            assert!(Pattern::compile(valid).is_ok(), "{valid}");
+        }
+        let problem = |line: &str| syntax_problem(line).unwrap_or_default();
+        assert_eq!(problem("*.[ch"), "Unterminated bracket expression '[ch'");
+        assert_eq!(problem("[]"), "Unterminated bracket expression '[]'");
+        assert_eq!(
+            problem("src/[ab/c]"),
+            "Unterminated bracket expression '[ab'"
+        );
+        assert_eq!(problem("[[:word:]]"), "Unknown character class '[:word:]'");
+        assert_eq!(problem("build\\"), "Trailing backslash escapes nothing");
+        assert_eq!(problem("build\\\\"), "");
+        assert!(Pattern::compile("# comment").is_err());
+    }
+
+    #[test]
+    fn test_matches_unanchored_basename() {
+        let pattern = Pattern::parse("*.log").unwrap();
+        assert!(pattern.matches("debug.log", false));
+        assert!(pattern.matches("logs/debug.log", false));
+        assert!(!pattern.matches("debug.log.txt", false));
+
+        let pattern = Pattern::parse("build/").unwrap();
+        assert!(pattern.matches("build", true));
+        assert!(pattern.matches("src/build", true));
+        assert!(!pattern.matches("build", false));
+    }
+
+    #[test]
+    fn test_matches_anchored() {
+        let pattern = Pattern::parse("/target").unwrap();
+        assert!(pattern.matches("target", true));
+        assert!(!pattern.matches("crates/target", true));
+
+        let pattern = Pattern::parse("doc/frotz").unwrap();
+        assert!(pattern.matches("doc/frotz", false));
+        assert!(!pattern.matches("a/doc/frotz", false));
+
+        let pattern = Pattern::parse("src/*.rs").unwrap();
+        assert!(pattern.matches("src/main.rs", false));
+        assert!(!pattern.matches("src/bin/main.rs", false));
+    }
+
+    #[test]
+    fn test_matches_double_star() {
+        let pattern = Pattern::parse("**/foo").unwrap();
+        assert!(pattern.matches("foo", false));
+        assert!(pattern.matches("a/b/foo", false));
+
+        let pattern = Pattern::parse("abc/**").unwrap();
+        assert!(pattern.matches("abc/x", false));
+        assert!(pattern.matches("abc/x/y", false));
+        assert!(!pattern.matches("abc", true));
+
+        let pattern = Pattern::parse("a/**/b").unwrap();
+        assert!(pattern.matches("a/b", false));
+        assert!(pattern.matches("a/x/y/b", false));
+        assert!(!pattern.matches("a/x/c", false));
+    }
+
+    #[test]
+    fn test_wildmatch_syntax() {
+        let glob = |g: &str, t: &str| {
+            let g: Vec<char> = g.chars().collect();
+            let t: Vec<char> = t.chars().collect();
+            wildmatch(&g, &t)
+        };
+        assert!(glob("*", ""));
+        assert!(glob("a*b*c", "aXbYbc"));
+        assert!(!glob("a*b", "aXc"));
+        assert!(glob("?.txt", "a.txt"));
+        assert!(!glob("?.txt", "ab.txt"));
+        assert!(glob("[Bb]uild", "Build"));
+        assert!(glob("[!a-c]x", "dx"));
+        assert!(!glob("[^a-c]x", "bx"));
+        assert!(glob("[]]", "]"));
+        assert!(glob("file[0-9][[:alpha:]]", "file7z"));
+        assert!(glob("\\*literal", "*literal"));
+        assert!(!glob("\\*literal", "xliteral"));
+        assert!(!glob("[abc", "a"));
+    }
+}
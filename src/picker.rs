@@ -0,0 +1,329 @@
+//! Keyboard-driven selection of untracked files for `--interactive`
+//!
+//! [`Picker`] holds the list and reacts to [`Key`]s; the caller owns the
+//! terminal, feeds it the keys [`decode_keys`] finds in what was typed and
+//! draws the lines [`Picker::render`] returns. Each entry can become an
+//! ignore pattern for its exact path, its extension or its directory.
+
+use crate::{
+    keys::{self, Key as RawKey},
+    pattern::escape_literal,
+};
+use std::fmt;
+
+/// What a pattern made from an untracked file matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PatternKind {
+    /// Just this file or directory (`/src/out.txt`)
+    Path,
+    /// Every file with its extension (`*.txt`)
+    Extension,
+    /// The directory it lies in (`/src/`)
+    Directory,
+}
+
+impl fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PatternKind::Path => "path",
+            PatternKind::Extension => "extension",
+            PatternKind::Directory => "directory",
+        })
+    }
+}
+
+/// The patterns an untracked entry can become, exact path first. `path`
+/// is relative to the repository root, with a trailing `/` for a
+/// directory; kinds that make no sense for it (the extension of
+/// `Makefile`, the directory of a top-level file) are left out.
+pub fn candidate_patterns(path: &str) -> Vec<(PatternKind, String)> {
+    let mut candidates = vec![(PatternKind::Path, format!("/{}", escape_literal(path)))];
+    let is_dir = path.ends_with('/');
+    let trimmed = path.trim_end_matches('/');
+    let (parent, name) = match trimmed.rsplit_once('/') {
+        Some((parent, name)) => (Some(parent), name),
+        None => (None, trimmed),
+    };
+
+    if !is_dir {
+        if let Some((stem, extension)) = name.rsplit_once('.') {
+            if !stem.is_empty() && !extension.is_empty() {
+                candidates.push((
+                    PatternKind::Extension,
+                    format!("*.{}", escape_literal(extension)),
+                ));
+            }
+        }
+    }
+    if let Some(parent) = parent {
+        candidates.push((
+            PatternKind::Directory,
+            format!("/{}/", escape_literal(parent)),
+        ));
+    }
+    candidates
+}
+
+/// A key press the picker understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    /// Select or deselect the current entry
+    Toggle,
+    /// Select everything, or nothing if everything is selected
+    ToggleAll,
+    NextKind,
+    PreviousKind,
+    Confirm,
+    Cancel,
+}
+
+/// Decode raw terminal input: arrow keys or `j`/`k` move, space toggles,
+/// `a` toggles all, tab or left/right change the pattern kind, enter
+/// confirms, and `q`, escape or Ctrl-C cancel. Anything else is dropped.
+pub fn decode_keys(input: &[u8]) -> Vec<Key> {
+    keys::decode_keys(input)
+        .into_iter()
+        .filter_map(|key| match key {
+            RawKey::Up | RawKey::Char('k') => Some(Key::Up),
+            RawKey::Down | RawKey::Char('j') => Some(Key::Down),
+            RawKey::Char(' ') => Some(Key::Toggle),
+            RawKey::Char('a') => Some(Key::ToggleAll),
+            RawKey::Right | RawKey::Tab | RawKey::Char('l') => Some(Key::NextKind),
+            RawKey::Left | RawKey::BackTab | RawKey::Char('h') => Some(Key::PreviousKind),
+            RawKey::Enter => Some(Key::Confirm),
+            RawKey::Escape | RawKey::Interrupt | RawKey::Char('q') => Some(Key::Cancel),
+            _ => None,
+        })
+        .collect()
+}
+
+/// One untracked entry in the picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerEntry {
+    pub path: String,
+    pub selected: bool,
+    /// Patterns the entry can become, from [`candidate_patterns`]
+    pub candidates: Vec<(PatternKind, String)>,
+    /// Index into `candidates` of the chosen one
+    pub choice: usize,
+}
+
+impl PickerEntry {
+    /// The kind and pattern currently chosen for the entry
+    pub fn chosen(&self) -> &(PatternKind, String) {
+        &self.candidates[self.choice]
+    }
+}
+
+/// Where a key left the picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerState {
+    Open,
+    Confirmed,
+    Cancelled,
+}
+
+/// Multi-select list of untracked entries
+#[derive(Debug, Clone)]
+pub struct Picker {
+    pub entries: Vec<PickerEntry>,
+    pub cursor: usize,
+}
+
+impl Picker {
+    pub fn new(paths: &[String]) -> Self {
+        Picker {
+            entries: paths
+                .iter()
+                .map(|path| PickerEntry {
+                    path: path.clone(),
+                    selected: false,
+                    candidates: candidate_patterns(path),
+                    choice: 0,
+                })
+                .collect(),
+            cursor: 0,
+        }
+    }
+
+    /// React to one key
+    pub fn handle(&mut self, key: Key) -> PickerState {
+        let count = self.entries.len();
+        match key {
+            Key::Up if count > 0 => self.cursor = (self.cursor + count - 1) % count,
+            Key::Down if count > 0 => self.cursor = (self.cursor + 1) % count,
+            Key::Toggle => {
+                if let Some(entry) = self.entries.get_mut(self.cursor) {
+                    entry.selected = !entry.selected;
+                }
+            }
+            Key::ToggleAll => {
+                let select = !self.entries.iter().all(|entry| entry.selected);
+                for entry in &mut self.entries {
+                    entry.selected = select;
+                }
+            }
+            Key::NextKind | Key::PreviousKind => {
+                if let Some(entry) = self.entries.get_mut(self.cursor) {
+                    let kinds = entry.candidates.len();
+                    entry.choice = if key == Key::NextKind {
+                        (entry.choice + 1) % kinds
+                    } else {
+                        (entry.choice + kinds - 1) % kinds
+                    };
+                    // Choosing a pattern for an entry implies wanting it
+                    entry.selected = true;
+                }
+            }
+            Key::Confirm => return PickerState::Confirmed,
+            Key::Cancel => return PickerState::Cancelled,
+            Key::Up | Key::Down => {}
+        }
+        PickerState::Open
+    }
+
+    /// Patterns of the selected entries, in list order and each once
+    pub fn patterns(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = Vec::new();
+        for entry in self.entries.iter().filter(|entry| entry.selected) {
+            let pattern = &entry.chosen().1;
+            if !patterns.contains(pattern) {
+                patterns.push(pattern.clone());
+            }
+        }
+        patterns
+    }
+
+    /// The lines to draw: a key summary, then one line per entry with the
+    /// cursor, the selection mark, the path and the chosen pattern. At most
+    /// `height` entries are shown, scrolled to keep the cursor in view.
+    pub fn render(&self, height: usize) -> Vec<String> {
+        let mut lines = vec![
+            "Select untracked files to ignore: up/down move, space selects, a selects all,"
+                .to_string(),
+            "tab or left/right picks path, extension or directory, enter adds, q cancels"
+                .to_string(),
+        ];
+        let height = height.max(1);
+        let start = self.cursor.saturating_sub(height - 1);
+        let width = self
+            .entries
+            .iter()
+            .map(|entry| entry.path.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (index, entry) in self.entries.iter().enumerate().skip(start).take(height) {
+            let (kind, pattern) = entry.chosen();
+            lines.push(format!(
+                "{} [{}] {:<width$}  {:<9}  {pattern}",
+                if index == self.cursor { '>' } else { ' ' },
+                if entry.selected { 'x' } else { ' ' },
+                entry.path,
+                kind.to_string(),
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_patterns() {
+        assert_eq!(
+            candidate_patterns("src/gen/out.txt"),
+            vec![
+                (PatternKind::Path, "/src/gen/out.txt".to_string()),
+                (PatternKind::Extension, "*.txt".to_string()),
+                (PatternKind::Directory, "/src/gen/".to_string()),
+            ]
+        );
+        assert_eq!(
+            candidate_patterns("Makefile"),
+            vec![(PatternKind::Path, "/Makefile".to_string())]
+        );
+        assert_eq!(
+            candidate_patterns(".env"),
+            vec![(PatternKind::Path, "/.env".to_string())]
+        );
+        assert_eq!(
+            candidate_patterns("web/dist/"),
+            vec![
+                (PatternKind::Path, "/web/dist/".to_string()),
+                (PatternKind::Directory, "/web/".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_keys() {
+        assert_eq!(
+            decode_keys(b"\x1b[B \x1b[Ck\tq\r"),
+            vec![
+                Key::Down,
+                Key::Toggle,
+                Key::NextKind,
+                Key::Up,
+                Key::NextKind,
+                Key::Cancel,
+                Key::Confirm
+            ]
+        );
+        assert_eq!(decode_keys(b"\x1b"), vec![Key::Cancel]);
+        assert_eq!(
+            decode_keys(b"\x1b[Z\x1b[3~\x03"),
+            vec![Key::PreviousKind, Key::Cancel]
+        );
+        assert_eq!(decode_keys(b"xyz"), vec![]);
+    }
+
+    #[test]
+    fn test_picker_selection() {
+        let mut picker = Picker::new(&[
+            "debug.log".to_string(),
+            "trace.log".to_string(),
+            "build/".to_string(),
+        ]);
+        for key in decode_keys(b"\tj\t") {
+            assert_eq!(picker.handle(key), PickerState::Open);
+        }
+        // Both logs chose the extension, so it's listed once
+        assert_eq!(picker.patterns(), vec!["*.log"]);
+
+        for key in decode_keys(b"k ") {
+            picker.handle(key);
+        }
+        assert_eq!(picker.patterns(), vec!["*.log"]);
+        assert_eq!(picker.cursor, 0);
+        picker.handle(Key::Toggle);
+        picker.handle(Key::Up);
+        picker.handle(Key::Toggle);
+        assert_eq!(picker.patterns(), vec!["*.log", "/build/"]);
+        assert_eq!(picker.handle(Key::Confirm), PickerState::Confirmed);
+
+        // Everything is selected, so toggling all clears the selection
+        picker.handle(Key::ToggleAll);
+        assert!(picker.patterns().is_empty());
+        picker.handle(Key::ToggleAll);
+        assert_eq!(picker.patterns(), vec!["*.log", "/build/"]);
+    }
+
+    #[test]
+    fn test_render_scrolls_to_cursor() {
+        let paths: Vec<String> = (0..5).map(|i| format!("f{i}.txt")).collect();
+        let mut picker = Picker::new(&paths);
+        for _ in 0..4 {
+            picker.handle(Key::Down);
+        }
+        picker.handle(Key::Toggle);
+        let lines = picker.render(2);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], "  [ ] f3.txt  path       /f3.txt");
+        assert_eq!(lines[3], "> [x] f4.txt  path       /f4.txt");
+    }
+}
@@ -0,0 +1,209 @@
+//! Choose the most appropriate ignore file for a pattern
+//!
+//! Editor and operating system clutter follows a person from project to
+//! project, so it belongs in the global gitignore. Personal scratch files
+//! are specific to one clone and belong in `.git/info/exclude`. Everything
+//! else is assumed to be a project artifact and goes in `.gitignore`, where
+//! it is shared with everyone.
+//!
+//! The built-in lists can be extended per user or repository with the
+//! multi-valued `ignore.autoGlobal`, `ignore.autoLocal` and
+//! `ignore.autoRepo` settings, whose entries take precedence.
+
+use crate::{Pattern, Scope};
+
+/// Files created by operating systems and file managers
+const OS_FILES: &[&str] = &[
+    ".DS_Store",
+    "._*",
+    ".AppleDouble",
+    ".LSOverride",
+    ".Spotlight-V100",
+    ".Trashes",
+    ".fseventsd",
+    "Thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+    "$RECYCLE.BIN",
+    ".directory",
+    ".Trash-*",
+];
+
+/// Files created by editors and IDEs
+const EDITOR_FILES: &[&str] = &[
+    ".idea",
+    ".vscode",
+    ".fleet",
+    ".history",
+    "*.iml",
+    "*.swp",
+    "*.swo",
+    ".*.sw?",
+    "*~",
+    r"\#*#",
+    ".#*",
+    ".netrwhist",
+    "*.sublime-workspace",
+    "*.code-workspace",
+];
+
+/// Names that usually mean personal, machine-specific scratch space
+const SCRATCH_FILES: &[&str] = &[
+    "scratch*",
+    "sandbox",
+    "playground",
+    "notes*",
+    "todo*",
+    "*.local",
+    ".env.local",
+    "my-*",
+    "personal*",
+    "wip",
+];
+
+/// Where a pattern should go and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Route {
+    pub pattern: String,
+    pub scope: Scope,
+    /// Short explanation for the user
+    pub reason: String,
+}
+
+/// Pick the ignore file `pattern` belongs in.
+///
+/// `overrides` pairs a scope with a gitignore-style pattern (from
+/// `ignore.autoGlobal` and friends); the first override whose pattern
+/// matches the new pattern's final path component decides. Negations stay
+/// in `.gitignore` alongside the rules they usually re-include from.
+pub fn route_pattern(pattern: &str, overrides: &[(Scope, String)]) -> Route {
+    let route = |scope: Scope, reason: String| Route {
+        pattern: pattern.to_string(),
+        scope,
+        reason,
+    };
+
+    let trimmed = pattern.trim();
+    if trimmed.starts_with('!') {
+        return route(
+            Scope::Repo,
+            "negation, kept with the shared rules it re-includes from".to_string(),
+        );
+    }
+
+    let name = trimmed
+        .trim_end_matches('/')
+        .rsplit('/')
+        .find(|component| !component.is_empty() && *component != "**")
+        .unwrap_or(trimmed);
+
+    for (scope, entry) in overrides {
+        if name_matches(entry, name) {
+            let key = match scope {
+                // Routing never picks the system file
+                Scope::Global | Scope::System => "ignore.autoGlobal",
+                Scope::Local => "ignore.autoLocal",
+                Scope::Repo => "ignore.autoRepo",
+            };
+            return route(*scope, format!("matches {key} entry '{entry}'"));
+        }
+    }
+
+    if OS_FILES.iter().any(|entry| name_matches(entry, name)) {
+        return route(Scope::Global, "operating system file".to_string());
+    }
+    if EDITOR_FILES.iter().any(|entry| name_matches(entry, name)) {
+        return route(Scope::Global, "editor or IDE file".to_string());
+    }
+    if SCRATCH_FILES.iter().any(|entry| name_matches(entry, name)) {
+        return route(Scope::Local, "personal scratch file".to_string());
+    }
+    route(
+        Scope::Repo,
+        "project file, shared with the team".to_string(),
+    )
+}
+
+/// Split an explicit target off a command-line pattern: `*.log@local`
+/// becomes `*.log` for [`Scope::Local`]. Only `@repo`, `@local`,
+/// `@global` and `@system` are recognised, so other patterns containing
+/// `@` (such as `icon@2x.png`) are left alone.
+pub fn split_target_suffix(pattern: &str) -> (&str, Option<Scope>) {
+    for (suffix, scope) in [
+        ("@repo", Scope::Repo),
+        ("@local", Scope::Local),
+        ("@global", Scope::Global),
+        ("@system", Scope::System),
+    ] {
+        if let Some(rest) = pattern.strip_suffix(suffix).filter(|rest| !rest.is_empty()) {
+            return (rest, Some(scope));
+        }
+    }
+    (pattern, None)
+}
+
+/// Whether the gitignore pattern `entry` matches `name`, which may itself
+/// contain wildcards (`*.swp` matches `*.swp`)
+fn name_matches(entry: &str, name: &str) -> bool {
+    Pattern::parse(entry).is_ok_and(|entry| entry.matches(name, false) || entry.matches(name, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(pattern: &str) -> Scope {
+        route_pattern(pattern, &[]).scope
+    }
+
+    #[test]
+    fn test_builtin_routes() {
+        assert_eq!(scope(".DS_Store"), Scope::Global);
+        assert_eq!(scope("**/.DS_Store"), Scope::Global);
+        assert_eq!(scope(".idea/"), Scope::Global);
+        assert_eq!(scope("*.swp"), Scope::Global);
+        assert_eq!(scope(".*.swp"), Scope::Global);
+        assert_eq!(scope("scratch/"), Scope::Local);
+        assert_eq!(scope("/notes.md"), Scope::Local);
+        assert_eq!(scope(".env.local"), Scope::Local);
+        assert_eq!(scope("target/"), Scope::Repo);
+        assert_eq!(scope("*.log"), Scope::Repo);
+        assert_eq!(scope("!.vscode/"), Scope::Repo);
+    }
+
+    #[test]
+    fn test_split_target_suffix() {
+        assert_eq!(
+            split_target_suffix("*.log@local"),
+            ("*.log", Some(Scope::Local))
+        );
+        assert_eq!(
+            split_target_suffix(".DS_Store@global"),
+            (".DS_Store", Some(Scope::Global))
+        );
+        assert_eq!(
+            split_target_suffix("dist/@repo"),
+            ("dist/", Some(Scope::Repo))
+        );
+        assert_eq!(
+            split_target_suffix("*.bak@system"),
+            ("*.bak", Some(Scope::System))
+        );
+        assert_eq!(split_target_suffix("icon@2x.png"), ("icon@2x.png", None));
+        assert_eq!(split_target_suffix("@local"), ("@local", None));
+    }
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let overrides = vec![
+            (Scope::Repo, ".vscode".to_string()),
+            (Scope::Local, "*.log".to_string()),
+        ];
+        let route = route_pattern(".vscode/", &overrides);
+        assert_eq!(route.scope, Scope::Repo);
+        assert_eq!(route.reason, "matches ignore.autoRepo entry '.vscode'");
+        assert_eq!(route_pattern("debug.log", &overrides).scope, Scope::Local);
+        assert_eq!(route_pattern(".DS_Store", &overrides).scope, Scope::Global);
+    }
+}
@@ -0,0 +1,282 @@
+//! Ignore rules loaded from ignore files, with git's precedence
+//!
+//! A [`Rule`] is one pattern together with where it came from. Rules are
+//! grouped into levels: the global excludes file, `.git/info/exclude`, and
+//! one level per `.gitignore`, each deeper directory overriding its parent.
+//! Within a level the last matching rule wins.
+
+use crate::{git, paths, Pattern, RepoContext, Scope};
+use anyhow::{bail, Context};
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+/// A single pattern and its origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    pub pattern: Pattern,
+    /// Ignore file the pattern was read from
+    pub source: PathBuf,
+    /// 1-based line number in `source`
+    pub line_number: usize,
+    /// Directory the pattern is relative to, as a `/`-separated path from
+    /// the repository root (empty for the root, `info/exclude` and the
+    /// global file)
+    pub base: String,
+}
+
+impl Rule {
+    /// Whether this rule's pattern matches `path` (relative to the
+    /// repository root). Negation is not applied.
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        let relative = if self.base.is_empty() {
+            Some(path)
+        } else {
+            path.strip_prefix(self.base.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+        };
+        relative.is_some_and(|relative| self.pattern.matches(relative, is_dir))
+    }
+}
+
+/// Read the rules of one ignore file; a missing file has no rules
+pub fn read_rules(source: &Path, base: &str) -> anyhow::Result<Vec<Rule>> {
+    let content = match std::fs::read(source) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read: {}", source.display()));
+        }
+    };
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            Pattern::parse(line).ok().map(|pattern| Rule {
+                pattern,
+                source: source.to_path_buf(),
+                line_number: index + 1,
+                base: base.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// One ignore file that applies to a directory, with its rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleSource {
+    pub scope: Scope,
+    pub path: PathBuf,
+    /// Directory the rules are relative to, as in [`Rule::base`]
+    pub base: String,
+    /// The file's rules, in file order
+    pub rules: Vec<Rule>,
+}
+
+/// Every ignore file that applies to paths in `dir` of `repo`, from lowest
+/// to highest precedence: the global excludes file, `info/exclude`, then
+/// each `.gitignore` from the root down to `dir` itself. Files that don't
+/// exist are left out. `dir` is absolute inside the work tree or relative
+/// to its root.
+///
+/// To decide a path, take the last matching rule of the last source with
+/// a match, checking parent directories first; see
+/// [`decision`](crate::decision).
+pub fn effective_ignore_rules(repo: &RepoContext, dir: &Path) -> anyhow::Result<Vec<RuleSource>> {
+    let root = repo.root()?;
+    let relative = if dir.is_absolute() {
+        let dir = paths::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let root = paths::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        match dir.strip_prefix(&root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => bail!(
+                "{} is outside the repository ({})",
+                dir.display(),
+                root.display()
+            ),
+        }
+    } else {
+        dir.to_path_buf()
+    };
+    let relative: Vec<String> = relative
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    let mut base_files = Vec::new();
+    if let Some(global) = repo.excludes_file()? {
+        base_files.push((git::excludes_file_scope(&global), global));
+    }
+    base_files.push((Scope::Local, repo.exclude_file_path()));
+    effective_rules_in(root, &base_files, &relative.join("/"))
+}
+
+/// [`effective_ignore_rules`] for `dir` (`/`-separated, relative to `root`,
+/// empty for the root), with the given repository-wide ignore files listed
+/// from lowest to highest precedence
+pub fn effective_rules_in(
+    root: &Path,
+    base_files: &[(Scope, PathBuf)],
+    dir: &str,
+) -> anyhow::Result<Vec<RuleSource>> {
+    let mut sources = Vec::new();
+    for (scope, path) in base_files {
+        if path.is_file() {
+            sources.push(RuleSource {
+                scope: *scope,
+                rules: read_rules(path, "")?,
+                path: path.clone(),
+                base: String::new(),
+            });
+        }
+    }
+
+    let components: Vec<&str> = dir.split('/').filter(|part| !part.is_empty()).collect();
+    for depth in 0..=components.len() {
+        let base = components[..depth].join("/");
+        let path = root.join(&base).join(".gitignore");
+        if path.is_file() {
+            sources.push(RuleSource {
+                scope: Scope::Repo,
+                rules: read_rules(&path, &base)?,
+                path,
+                base,
+            });
+        }
+    }
+    Ok(sources)
+}
+
+/// Stack of rule levels, cheap to clone and extend per directory
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RuleStack(Option<Arc<Level>>);
+
+#[derive(Debug)]
+struct Level {
+    rules: Vec<Rule>,
+    parent: RuleStack,
+}
+
+impl RuleStack {
+    /// A new stack with `rules` as its highest-precedence level
+    pub(crate) fn push(&self, rules: Vec<Rule>) -> RuleStack {
+        if rules.is_empty() {
+            return self.clone();
+        }
+        RuleStack(Some(Arc::new(Level {
+            rules,
+            parent: self.clone(),
+        })))
+    }
+
+    /// The rule that decides whether `path` is ignored: the last matching
+    /// rule of the highest-precedence level with a match. The rule may be a
+    /// negation, meaning the path is explicitly not ignored.
+    pub(crate) fn decide(&self, path: &str, is_dir: bool) -> Option<&Rule> {
+        let mut level = self.0.as_deref();
+        while let Some(current) = level {
+            if let Some(rule) = current
+                .rules
+                .iter()
+                .rev()
+                .find(|rule| rule.matches(path, is_dir))
+            {
+                return Some(rule);
+            }
+            level = current.parent.0.as_deref();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(base: &str, patterns: &[&str]) -> Vec<Rule> {
+        patterns
+            .iter()
+            .enumerate()
+            .map(|(index, text)| Rule {
+                pattern: Pattern::parse(text).unwrap(),
+                source: PathBuf::from(".gitignore"),
+                line_number: index + 1,
+                base: base.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_base_directory() {
+        let rule = &rules("docs", &["/build"])[0];
+        assert!(rule.matches("docs/build", true));
+        assert!(!rule.matches("build", true));
+        assert!(!rule.matches("docsbuild", true));
+    }
+
+    #[test]
+    fn test_decide_precedence() {
+        let stack = RuleStack::default()
+            .push(rules("", &["*.log", "!keep.log"]))
+            .push(rules("sub", &["keep.log"]));
+
+        let decision = |path: &str| stack.decide(path, false).map(|r| r.pattern.to_string());
+        assert_eq!(decision("a.log").as_deref(), Some("*.log"));
+        assert_eq!(decision("keep.log").as_deref(), Some("!keep.log"));
+        // The deeper .gitignore overrides the root's negation
+        assert_eq!(decision("sub/keep.log").as_deref(), Some("keep.log"));
+        assert_eq!(decision("main.rs"), None);
+    }
+
+    #[test]
+    fn test_effective_rules_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("src/deep")).unwrap();
+        std::fs::create_dir_all(root.join("other")).unwrap();
+        std::fs::write(root.join("exclude"), "*.tmp\n").unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(root.join("src/.gitignore"), "*.o\n!keep.o\n").unwrap();
+        std::fs::write(root.join("other/.gitignore"), "*.bin\n").unwrap();
+        let base_files = [
+            (Scope::Global, root.join("missing-global")),
+            (Scope::Local, root.join("exclude")),
+        ];
+
+        let sources = effective_rules_in(root, &base_files, "src/deep").unwrap();
+        let summary: Vec<(Scope, &str, usize)> = sources
+            .iter()
+            .map(|source| (source.scope, source.base.as_str(), source.rules.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Scope::Local, "", 1),
+                (Scope::Repo, "", 1),
+                (Scope::Repo, "src", 2)
+            ]
+        );
+        assert_eq!(sources[2].rules[1].base, "src");
+        assert_eq!(effective_rules_in(root, &[], "").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_read_rules_skips_non_patterns() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".gitignore");
+        std::fs::write(&path, "# comment\n\n*.o\n!keep.o\n").unwrap();
+
+        let rules = read_rules(&path, "").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].line_number, 3);
+        assert_eq!(rules[1].pattern.to_string(), "!keep.o");
+        assert!(read_rules(&temp_dir.path().join("missing"), "")
+            .unwrap()
+            .is_empty());
+    }
+}
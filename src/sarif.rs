@@ -0,0 +1,234 @@
+//! SARIF 2.1.0 reports for lint findings
+//!
+//! [SARIF](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+//! is the format GitHub code scanning and most security dashboards ingest.
+//! Every [`PatternRule`] is listed in the report under its stable id,
+//! together with the application's rules, and each finding points at its
+//! file and line.
+
+use crate::{json, lint::LintFinding, validation::RuleSet, PatternRule, PatternSeverity};
+use std::{borrow::Cow, path::Path};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/andrewleech/git-ignore";
+
+/// Render `findings` as a SARIF log.
+///
+/// Files under `root` get paths relative to it (resolved against the
+/// `%SRCROOT%` base, as code scanning expects), as do relative paths, which
+/// are taken to be relative to `root` already; others get `file://` URIs.
+pub fn to_sarif(findings: &[LintFinding], root: &Path) -> String {
+    to_sarif_with(findings, root, &RuleSet::default())
+}
+
+/// [`to_sarif`], describing the application's `rules` alongside the
+/// built-in ones. Custom findings from rules not in the set are listed
+/// under their id alone.
+pub fn to_sarif_with(findings: &[LintFinding], root: &Path, rule_set: &RuleSet) -> String {
+    let mut descriptors: Vec<(PatternRule, String, String)> = PatternRule::ALL
+        .iter()
+        .map(|rule| {
+            (
+                rule.clone(),
+                rule.name().to_string(),
+                rule.description().to_string(),
+            )
+        })
+        .collect();
+    for rule in rule_set.iter() {
+        descriptors.push((
+            PatternRule::Custom(Cow::Owned(rule.id().to_string())),
+            rule.name().to_string(),
+            rule.description().to_string(),
+        ));
+    }
+    for finding in findings {
+        if !descriptors.iter().any(|(rule, ..)| *rule == finding.rule) {
+            descriptors.push((
+                finding.rule.clone(),
+                finding.rule.name().to_string(),
+                finding.rule.description().to_string(),
+            ));
+        }
+    }
+    let all_rules: Vec<PatternRule> = descriptors.iter().map(|(rule, ..)| rule.clone()).collect();
+
+    let rules = json::array(descriptors.iter().map(|(rule, name, description)| {
+        json::Object::new()
+            .str("id", rule.id())
+            .str("name", name)
+            .raw(
+                "shortDescription",
+                json::Object::new().str("text", description).build(),
+            )
+            .build()
+    }));
+
+    let driver = json::Object::new()
+        .str("name", "git-ignore")
+        .str("version", env!("CARGO_PKG_VERSION"))
+        .str("informationUri", INFORMATION_URI)
+        .raw("rules", rules)
+        .build();
+
+    let results = json::array(
+        findings
+            .iter()
+            .map(|finding| result(finding, &all_rules, root)),
+    );
+
+    let run = json::Object::new()
+        .raw("tool", json::Object::new().raw("driver", driver).build())
+        .raw("results", results)
+        .build();
+
+    json::Object::new()
+        .str("$schema", SCHEMA)
+        .str("version", "2.1.0")
+        .raw("runs", json::array([run]))
+        .build()
+}
+
+fn result(finding: &LintFinding, rules: &[PatternRule], root: &Path) -> String {
+    let rule_index = rules
+        .iter()
+        .position(|rule| *rule == finding.rule)
+        .unwrap_or_default();
+
+    let relative = if finding.file.is_relative() {
+        Ok(finding.file.as_path())
+    } else {
+        finding.file.strip_prefix(root)
+    };
+    let artifact = match relative {
+        Ok(relative) => json::Object::new()
+            .str("uri", &encode_uri_path(&relative.to_string_lossy()))
+            .str("uriBaseId", "%SRCROOT%"),
+        Err(_) => json::Object::new().str("uri", &file_uri(&finding.file)),
+    };
+    let location = json::Object::new().raw(
+        "physicalLocation",
+        json::Object::new()
+            .raw("artifactLocation", artifact.build())
+            .raw(
+                "region",
+                json::Object::new().num("startLine", finding.line).build(),
+            )
+            .build(),
+    );
+
+    json::Object::new()
+        .str("ruleId", finding.rule.id())
+        .num("ruleIndex", rule_index)
+        .str("level", level(&finding.severity))
+        .raw(
+            "message",
+            json::Object::new().str("text", &finding.message).build(),
+        )
+        .raw("locations", json::array([location.build()]))
+        .build()
+}
+
+fn level(severity: &PatternSeverity) -> &'static str {
+    match severity {
+        PatternSeverity::Error => "error",
+        PatternSeverity::Warning => "warning",
+        PatternSeverity::Info => "note",
+    }
+}
+
+fn file_uri(path: &Path) -> String {
+    let path = encode_uri_path(&path.to_string_lossy());
+    if path.starts_with('/') {
+        format!("file://{path}")
+    } else {
+        // Windows drive paths: file:///C:/...
+        format!("file:///{path}")
+    }
+}
+
+/// Percent-encode a path for use in a URI, using `/` as the separator
+fn encode_uri_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.replace('\\', "/").bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                out.push(byte as char)
+            }
+            other => out.push_str(&format!("%{other:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn finding(file: &str, line: usize, rule: PatternRule) -> LintFinding {
+        LintFinding {
+            rule,
+            severity: PatternSeverity::Warning,
+            message: "Pattern is \"broad\"".to_string(),
+            file: PathBuf::from(file),
+            line,
+            text: "*".to_string(),
+            suggestion: None,
+        }
+    }
+
+    #[test]
+    fn test_sarif_result_locations() {
+        let report = to_sarif(
+            &[
+                finding(
+                    "/repo/docs/my notes/.gitignore",
+                    3,
+                    PatternRule::BroadPattern,
+                ),
+                finding("/home/dev/.config/git/ignore", 1, PatternRule::BroadPattern),
+                finding("vendor/ignore-list", 2, PatternRule::BroadPattern),
+            ],
+            Path::new("/repo"),
+        );
+        assert!(report.starts_with(
+            r#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","runs":[{"#
+        ));
+        assert!(report.contains(
+            r#"{"ruleId":"GI005","ruleIndex":4,"level":"warning","message":{"text":"Pattern is \"broad\""},"locations":[{"physicalLocation":{"artifactLocation":{"uri":"docs/my%20notes/.gitignore","uriBaseId":"%SRCROOT%"},"region":{"startLine":3}}}]}"#
+        ));
+        assert!(report.contains(r#""uri":"file:///home/dev/.config/git/ignore""#));
+        assert!(report.contains(r#""uri":"vendor/ignore-list","uriBaseId":"%SRCROOT%""#));
+    }
+
+    #[test]
+    fn test_sarif_lists_every_rule() {
+        let report = to_sarif(&[], Path::new("/repo"));
+        for rule in PatternRule::ALL {
+            assert!(report.contains(&format!(r#""id":"{}","name":"{}""#, rule.id(), rule.name())));
+        }
+        assert!(report.contains(r#""results":[]"#));
+    }
+
+    #[test]
+    fn test_sarif_lists_application_rules() {
+        let rules = RuleSet::new().with_fn("ORG001", "No keys", |_| None);
+        let custom = PatternRule::Custom(Cow::Borrowed("ORG001"));
+        let other = PatternRule::Custom(Cow::Borrowed("ORG002"));
+        let report = to_sarif_with(
+            &[
+                finding("/repo/.gitignore", 1, custom),
+                finding("/repo/.gitignore", 2, other),
+            ],
+            Path::new("/repo"),
+            &rules,
+        );
+        let first = PatternRule::ALL.len();
+        assert!(report
+            .contains(r#""id":"ORG001","name":"ORG001","shortDescription":{"text":"No keys"}"#));
+        assert!(report.contains(r#""id":"ORG002","name":"ORG002""#));
+        assert!(report.contains(&format!(r#""ruleId":"ORG001","ruleIndex":{first}"#)));
+        assert!(report.contains(&format!(r#""ruleId":"ORG002","ruleIndex":{}"#, first + 1)));
+    }
+}
@@ -0,0 +1,144 @@
+//! Named pattern sets defined in git config
+//!
+//! A set is a list of patterns under a name, kept with the other settings
+//! in git config so it can be shared per user or per repository:
+//!
+//! ```text
+//! [ignore "python"]
+//!     pattern = __pycache__/
+//!     pattern = *.pyc
+//!     pattern = .venv/
+//! ```
+//!
+//! (`git config --global --add ignore.python.pattern '*.pyc'` adds one.)
+//! Wherever patterns are added, `@python` then stands for the set's
+//! patterns. [`expand`] does the substitution, so the CLI and
+//! [`IgnoreManager`](crate::IgnoreManager) treat a reference alike. A
+//! pattern meant to start with `@` is written `\@name`.
+
+use crate::{git, Error, RepoContext};
+use anyhow::bail;
+
+/// Config keys holding set patterns, `ignore.<name>.pattern`
+const SET_KEYS: &str = r"^ignore\..+\.pattern$";
+
+/// A named list of patterns
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatternSet {
+    /// Name as written in the config subsection; references match it
+    /// exactly
+    pub name: String,
+    /// The set's patterns, in config order
+    pub patterns: Vec<String>,
+}
+
+/// The sets defined in the effective git config of the current directory
+pub fn load() -> anyhow::Result<Vec<PatternSet>> {
+    Ok(collect(git::get_config_entries(SET_KEYS)?))
+}
+
+/// The sets defined in the config `repo` sees
+pub fn load_in(repo: &RepoContext) -> anyhow::Result<Vec<PatternSet>> {
+    Ok(collect(repo.config_entries(SET_KEYS)?))
+}
+
+/// Group `ignore.<name>.pattern` entries by name, in order of first
+/// appearance
+fn collect(entries: Vec<(String, String)>) -> Vec<PatternSet> {
+    let mut sets: Vec<PatternSet> = Vec::new();
+    for (key, value) in entries {
+        let Some(name) = key
+            .strip_prefix("ignore.")
+            .and_then(|rest| rest.strip_suffix(".pattern"))
+        else {
+            continue;
+        };
+        if value.trim().is_empty() {
+            continue;
+        }
+        match sets.iter_mut().find(|set| set.name == name) {
+            Some(set) => set.patterns.push(value),
+            None => sets.push(PatternSet {
+                name: name.to_string(),
+                patterns: vec![value],
+            }),
+        }
+    }
+    sets
+}
+
+/// The set name `pattern` refers to, if it has the form `@name`
+pub fn set_reference(pattern: &str) -> Option<&str> {
+    let name = pattern.trim().strip_prefix('@')?;
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then_some(name)
+}
+
+/// `patterns` with every `@name` replaced by the patterns of that set.
+/// Fails for a reference to a set that isn't defined.
+///
+/// ```
+/// use git_ignore_tool::sets::{expand, PatternSet};
+///
+/// let sets = [PatternSet {
+///     name: "python".to_string(),
+///     patterns: vec!["__pycache__/".to_string(), "*.pyc".to_string()],
+/// }];
+/// let expanded = expand(&["@python".to_string(), "dist/".to_string()], &sets)?;
+/// assert_eq!(expanded, ["__pycache__/", "*.pyc", "dist/"]);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn expand(patterns: &[String], sets: &[PatternSet]) -> anyhow::Result<Vec<String>> {
+    let mut expanded = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        let Some(name) = set_reference(pattern) else {
+            expanded.push(pattern.clone());
+            continue;
+        };
+        let Some(set) = sets.iter().find(|set| set.name == name) else {
+            bail!(Error::config(format!(
+                "unknown pattern set '@{name}'; define it with `git config --add ignore.{name}.pattern PATTERN`, or write '\\@{name}' for a pattern starting with '@'"
+            )));
+        };
+        expanded.extend(set.patterns.iter().cloned());
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_and_expand() {
+        let entries = [
+            ("ignore.sorted", "true"),
+            ("ignore.python.pattern", "__pycache__/"),
+            ("ignore.Node.pattern", "node_modules/"),
+            ("ignore.python.pattern", "*.pyc"),
+            ("ignore.python.pattern", ""),
+        ];
+        let sets = collect(
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].name, "python");
+        assert_eq!(sets[0].patterns, ["__pycache__/", "*.pyc"]);
+        assert_eq!(sets[1].name, "Node");
+
+        let patterns = |list: &[&str]| list.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            expand(&patterns(&["@Node", "\\@types", "@types/", "a@b"]), &sets).unwrap(),
+            ["node_modules/", "\\@types", "@types/", "a@b"]
+        );
+        let error = expand(&patterns(&["@node"]), &sets).unwrap_err();
+        assert!(error.to_string().contains("unknown pattern set '@node'"));
+    }
+}
@@ -0,0 +1,372 @@
+//! Export and restore the complete ignore state of a repository
+//!
+//! A snapshot is a single text document holding every `.gitignore` in the
+//! work tree, `.git/info/exclude`, the global ignore file and the
+//! repository's `ignore.*` settings:
+//!
+//! ```text
+//! git-ignore snapshot 1
+//! created 2024-05-01T12:00:00.000Z
+//! config ignore.sorted true
+//! file repo 14 .gitignore
+//! target/
+//! *.log
+//!
+//! file local 0 info/exclude
+//!
+//! ```
+//!
+//! Each `file` header gives the scope, the content length in bytes and the
+//! path (relative to the repository root for `repo` files). The content
+//! follows verbatim, terminated by one extra newline, so files round-trip
+//! byte for byte.
+
+use crate::{git, ignore, timestamp::utc_timestamp, Error, Scope};
+use anyhow::{bail, Context};
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+const HEADER: &str = "git-ignore snapshot 1";
+
+/// Config keys a snapshot carries, besides the `ignore.<name>.pattern` sets.
+/// Anything else under `ignore.` stays behind: the validator hook is a
+/// shell command, and restoring it from a shared snapshot would run someone
+/// else's code on the next `git ignore`; the forge settings decide where the
+/// forge token is sent, and the backup directory where copies of files are
+/// written. Settings added later stay behind until they are listed here.
+const RESTORED_CONFIG_KEYS: &[&str] = &[
+    "ignore.sorted",
+    "ignore.affinity",
+    "ignore.sortStrategy",
+    "ignore.sectionOrder",
+    "ignore.defaultTarget",
+    "ignore.noCreate",
+    "ignore.autoGlobal",
+    "ignore.autoLocal",
+    "ignore.autoRepo",
+    "ignore.protected",
+    "ignore.backup",
+    "ignore.backupKeep",
+];
+
+/// One ignore file captured in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotFile {
+    pub scope: Scope,
+    /// Path relative to the repository root for `repo` files; a descriptive
+    /// label for the other scopes
+    pub path: String,
+    pub content: String,
+}
+
+/// Full ignore state of a repository and its user
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// RFC 3339 time the snapshot was taken
+    pub created: Option<String>,
+    /// Repository-local `ignore.*` settings
+    pub config: Vec<(String, String)>,
+    pub files: Vec<SnapshotFile>,
+}
+
+impl Snapshot {
+    /// Capture the ignore state of the current repository
+    pub fn capture() -> anyhow::Result<Snapshot> {
+        let repo_root = git::get_repo_root()?;
+        let mut files = Vec::new();
+
+        for path in git::find_gitignore_files()? {
+            let relative = path.strip_prefix(&repo_root).unwrap_or(&path);
+            files.push(SnapshotFile {
+                scope: Scope::Repo,
+                path: relative.to_string_lossy().replace('\\', "/"),
+                content: read_file(&path)?,
+            });
+        }
+
+        let exclude_path = git::get_exclude_file_path()?;
+        if exclude_path.exists() {
+            files.push(SnapshotFile {
+                scope: Scope::Local,
+                path: "info/exclude".to_string(),
+                content: read_file(&exclude_path)?,
+            });
+        }
+
+        if let Some(global_path) = git::get_global_gitignore_path() {
+            files.push(SnapshotFile {
+                scope: Scope::Global,
+                path: global_path.to_string_lossy().into_owned(),
+                content: read_file(&global_path)?,
+            });
+        }
+
+        let config = git::get_local_config_entries(r"^ignore\.")?
+            .into_iter()
+            .filter(|(key, _)| check_config_key(key).is_ok())
+            .collect();
+
+        Ok(Snapshot {
+            created: Some(utc_timestamp(SystemTime::now())),
+            config,
+            files,
+        })
+    }
+
+    /// Parse a snapshot previously produced by formatting a [`Snapshot`]
+    pub fn parse(text: &str) -> anyhow::Result<Snapshot> {
+        let mut rest = text;
+        let next_line = |rest: &mut &str| -> Option<String> {
+            if rest.is_empty() {
+                return None;
+            }
+            let (line, tail) = rest.split_once('\n').unwrap_or((rest, ""));
+            *rest = tail;
+            Some(line.trim_end_matches('\r').to_string())
+        };
+
+        if next_line(&mut rest).as_deref() != Some(HEADER) {
+            bail!("Not a git-ignore snapshot (expected '{HEADER}' header)");
+        }
+
+        let mut snapshot = Snapshot::default();
+        while let Some(line) = next_line(&mut rest) {
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, fields) = line.split_once(' ').unwrap_or((&line, ""));
+            match kind {
+                "created" => snapshot.created = Some(fields.to_string()),
+                "config" => {
+                    let (key, value) = fields.split_once(' ').unwrap_or((fields, ""));
+                    check_config_key(key)?;
+                    snapshot.config.push((key.to_string(), value.to_string()));
+                }
+                "file" => {
+                    let mut parts = fields.splitn(3, ' ');
+                    let (Some(scope), Some(len), Some(path)) =
+                        (parts.next(), parts.next(), parts.next())
+                    else {
+                        bail!("Malformed snapshot entry: {line}");
+                    };
+                    let scope: Scope = scope.parse()?;
+                    let len: usize = len
+                        .parse()
+                        .with_context(|| format!("Malformed snapshot entry: {line}"))?;
+                    let content = rest
+                        .get(..len)
+                        .with_context(|| format!("Truncated snapshot content for {path}"))?;
+                    rest = rest[len..].strip_prefix('\n').unwrap_or(&rest[len..]);
+                    snapshot.files.push(SnapshotFile {
+                        scope,
+                        path: path.to_string(),
+                        content: content.to_string(),
+                    });
+                }
+                _ => bail!("Unknown snapshot entry: {line}"),
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Write the snapshot back into the current repository, returning the
+    /// files that were written.
+    ///
+    /// Files in the snapshot replace their counterparts; ignore files that
+    /// are not part of the snapshot are left alone. The global file goes to
+    /// this machine's global ignore location and is only restored when
+    /// `include_global` is set.
+    pub fn restore(&self, include_global: bool) -> anyhow::Result<Vec<PathBuf>> {
+        // Settings can't be built by hand to get past the check in `parse`
+        for (key, _) in &self.config {
+            check_config_key(key)?;
+        }
+
+        let repo_root = git::get_repo_root()?;
+        let mut written = Vec::new();
+
+        for file in &self.files {
+            let target = match file.scope {
+                Scope::Repo => repo_root.join(safe_relative_path(&file.path)?),
+                Scope::Local => git::get_exclude_file_path()?,
+                Scope::Global if include_global => git::get_global_gitignore_path()
+                    .or_else(git::default_global_gitignore_path)
+                    .context(Error::NoGlobalGitignore(
+                        "location (HOME is not set)".to_string(),
+                    ))?,
+                // The system file belongs to the machine, not the snapshot
+                Scope::Global | Scope::System => continue,
+            };
+            ignore::write_ignore_file(&target, &file.content)?;
+            written.push(target);
+        }
+
+        for (key, value) in &self.config {
+            git::set_local_config_value(key, value)?;
+        }
+
+        Ok(written)
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        if let Some(created) = &self.created {
+            writeln!(f, "created {created}")?;
+        }
+        for (key, value) in &self.config {
+            writeln!(f, "config {key} {value}")?;
+        }
+        for file in &self.files {
+            writeln!(
+                f,
+                "file {} {} {}",
+                file.scope,
+                file.content.len(),
+                file.path
+            )?;
+            writeln!(f, "{}", file.content)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_file(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read: {}", path.display()))
+}
+
+/// Reject config keys a snapshot may not carry: anything outside
+/// [`RESTORED_CONFIG_KEYS`] and the named pattern sets. Git compares section
+/// and key names without regard to case, but not subsection names.
+fn check_config_key(key: &str) -> anyhow::Result<()> {
+    let is_pattern_set = key.split_once('.').zip(key.rsplit_once('.')).is_some_and(
+        |((section, _), (subsection, name))| {
+            section.eq_ignore_ascii_case("ignore")
+                && subsection.len() > section.len()
+                && name.eq_ignore_ascii_case("pattern")
+        },
+    );
+    let allowed = is_pattern_set
+        || RESTORED_CONFIG_KEYS
+            .iter()
+            .any(|restored| restored.eq_ignore_ascii_case(key));
+    if !allowed {
+        bail!(Error::config(format!(
+            "refusing to restore config key '{key}' from a snapshot"
+        )));
+    }
+    Ok(())
+}
+
+/// Reject snapshot paths that would escape the repository
+fn safe_relative_path(path: &str) -> anyhow::Result<&Path> {
+    let relative = Path::new(path);
+    let is_safe = relative
+        .file_name()
+        .is_some_and(|name| name == ".gitignore")
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !is_safe {
+        bail!("Refusing to restore unsafe snapshot path: {path}");
+    }
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Snapshot {
+        Snapshot {
+            created: Some("2024-05-01T12:00:00.000Z".to_string()),
+            config: vec![("ignore.sorted".to_string(), "true".to_string())],
+            files: vec![
+                SnapshotFile {
+                    scope: Scope::Repo,
+                    path: ".gitignore".to_string(),
+                    content: "target/\n*.log\n".to_string(),
+                },
+                SnapshotFile {
+                    scope: Scope::Repo,
+                    path: "docs/my dir/.gitignore".to_string(),
+                    content: "no trailing newline".to_string(),
+                },
+                SnapshotFile {
+                    scope: Scope::Local,
+                    path: "info/exclude".to_string(),
+                    content: String::new(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let snapshot = sample();
+        let text = snapshot.to_string();
+        assert!(text.starts_with("git-ignore snapshot 1\ncreated "));
+        assert!(text.contains("file repo 14 .gitignore\ntarget/\n*.log\n\n"));
+        assert_eq!(Snapshot::parse(&text).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_content_that_looks_like_headers() {
+        let mut snapshot = sample();
+        snapshot.files[0].content = "file repo 3 x\nconfig a b\n".to_string();
+        assert_eq!(Snapshot::parse(&snapshot.to_string()).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_parse_errors() {
+        assert!(Snapshot::parse("not a snapshot\n").is_err());
+        assert!(
+            Snapshot::parse("git-ignore snapshot 1\nfile repo 99 .gitignore\nshort\n").is_err()
+        );
+        assert!(Snapshot::parse("git-ignore snapshot 1\nbogus entry\n").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_config_keys() {
+        for key in [
+            "core.hooksPath",
+            "core.fsmonitor",
+            "ignore.validator",
+            "IGNORE.Validator",
+            "ignore.forgeapiurl",
+            "ignore.backupDir",
+            "ignore.forge",
+            "ignore.pattern",
+            "ignore.unknownSetting",
+        ] {
+            let text = format!("git-ignore snapshot 1\nconfig {key} /tmp/evil\n");
+            assert!(Snapshot::parse(&text).is_err(), "{key} accepted");
+        }
+        assert!(
+            Snapshot::parse("git-ignore snapshot 1\nconfig ignore.python.pattern *.pyc\n").is_ok()
+        );
+        assert!(Snapshot::parse("git-ignore snapshot 1\nconfig IGNORE.Sorted true\n").is_ok());
+
+        let mut snapshot = sample();
+        snapshot
+            .config
+            .push(("core.hooksPath".to_string(), "/tmp/evil".to_string()));
+        let error = snapshot.restore(false).unwrap_err();
+        assert!(error.to_string().contains("core.hooksPath"));
+    }
+
+    #[test]
+    fn test_safe_relative_path() {
+        assert!(safe_relative_path(".gitignore").is_ok());
+        assert!(safe_relative_path("src/.gitignore").is_ok());
+        assert!(safe_relative_path("../.gitignore").is_err());
+        assert!(safe_relative_path("/etc/.gitignore").is_err());
+        assert!(safe_relative_path("src/main.rs").is_err());
+    }
+}
@@ -0,0 +1,364 @@
+//! Sort orders for ignore file patterns
+//!
+//! Teams disagree about what a tidy ignore file looks like, so the order is
+//! a [`SortStrategy`] chosen per run (`sort --strategy`) or per repository
+//! (`ignore.sortStrategy`), used both by the `sort` command and by sorted
+//! insertion. [`sort_content`] additionally arranges whole sections in a
+//! configured order (`ignore.sectionOrder`).
+//!
+//! Sorting never changes what a file ignores: only runs of consecutive
+//! patterns of the same kind (all plain or all negations) are reordered,
+//! comments stay with the lines after them, and template blocks and
+//! `--group` blocks keep the order they were written in.
+
+use crate::{
+    ignore::{group_marker_heading, template_marker_name},
+    ignore_file,
+};
+use anyhow::bail;
+use std::cmp::Ordering;
+
+/// How patterns are ordered within a section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SortStrategy {
+    /// Plain string order (`lib10` before `lib2`)
+    #[default]
+    Lexicographic,
+    /// Runs of digits compare as numbers (`lib2` before `lib10`)
+    Natural,
+    /// Directory patterns (ending in `/`) before file patterns, then
+    /// natural order
+    DirectoriesFirst,
+    /// Grouped by file extension, patterns without one first, then natural
+    /// order
+    Extension,
+}
+
+impl SortStrategy {
+    /// Compare two patterns under this strategy
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            SortStrategy::Lexicographic => a.cmp(b),
+            SortStrategy::Natural => natural_cmp(a, b),
+            SortStrategy::DirectoriesFirst => (!a.ends_with('/'))
+                .cmp(&!b.ends_with('/'))
+                .then_with(|| natural_cmp(a, b)),
+            SortStrategy::Extension => extension(a)
+                .cmp(&extension(b))
+                .then_with(|| natural_cmp(a, b)),
+        }
+    }
+}
+
+impl std::str::FromStr for SortStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "lexicographic" | "plain" => Ok(SortStrategy::Lexicographic),
+            "natural" | "version" => Ok(SortStrategy::Natural),
+            "directories-first" | "dirs-first" => Ok(SortStrategy::DirectoriesFirst),
+            "extension" => Ok(SortStrategy::Extension),
+            other => bail!(
+                "Unknown sort strategy '{other}' (expected lexicographic, natural, directories-first or extension)"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for SortStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SortStrategy::Lexicographic => "lexicographic",
+            SortStrategy::Natural => "natural",
+            SortStrategy::DirectoriesFirst => "directories-first",
+            SortStrategy::Extension => "extension",
+        })
+    }
+}
+
+/// Compare with runs of ASCII digits taken as numbers; strings that only
+/// differ in leading zeros fall back to plain order
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    while !a_rest.is_empty() && !b_rest.is_empty() {
+        let (a_chunk, a_next) = split_chunk(a_rest);
+        let (b_chunk, b_next) = split_chunk(b_rest);
+        let both_numbers = a_chunk.starts_with(|c: char| c.is_ascii_digit())
+            && b_chunk.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if both_numbers {
+            let a_number = a_chunk.trim_start_matches('0');
+            let b_number = b_chunk.trim_start_matches('0');
+            a_number
+                .len()
+                .cmp(&b_number.len())
+                .then_with(|| a_number.cmp(b_number))
+        } else {
+            a_chunk.cmp(b_chunk)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        (a_rest, b_rest) = (a_next, b_next);
+    }
+    a_rest.len().cmp(&b_rest.len()).then_with(|| a.cmp(b))
+}
+
+/// Split off the leading run of digits or of non-digits
+fn split_chunk(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Extension of the last path component of a file pattern (`*.log` ->
+/// `log`); directories and dotfiles such as `.env` have none
+pub(crate) fn extension(pattern: &str) -> Option<&str> {
+    if pattern.ends_with('/') {
+        return None;
+    }
+    let name = pattern.rsplit('/').next().unwrap_or(pattern);
+    match name.rfind('.') {
+        Some(0) | None => None,
+        Some(dot) => Some(&name[dot + 1..]),
+    }
+}
+
+/// A block of non-blank lines, with the blank lines that preceded it
+struct Section {
+    blank_before: Vec<String>,
+    lines: Vec<String>,
+}
+
+impl Section {
+    /// The heading `list` shows for this section
+    fn heading(&self) -> Option<String> {
+        let first = self.lines.first()?.trim();
+        if let Some(name) = template_marker_name(first, "# BEGIN") {
+            return Some(format!("{name} template"));
+        }
+        let comment = first.strip_prefix('#')?;
+        let heading = group_marker_heading(first).unwrap_or(comment.trim());
+        (!heading.is_empty()).then(|| heading.to_string())
+    }
+
+    /// Whether the section's lines must keep their written order
+    fn is_fixed(&self) -> bool {
+        self.lines.first().is_some_and(|line| {
+            template_marker_name(line, "# BEGIN").is_some() || group_marker_heading(line).is_some()
+        })
+    }
+}
+
+/// Split content into sections; a template block is one section even if it
+/// contains blank lines
+fn split_sections(content: &str) -> (Vec<Section>, Vec<String>) {
+    let mut sections = Vec::new();
+    let mut blank = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut in_template = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if template_marker_name(trimmed, "# BEGIN").is_some() {
+            if !current.is_empty() {
+                sections.push(Section {
+                    blank_before: std::mem::take(&mut blank),
+                    lines: std::mem::take(&mut current),
+                });
+            }
+            in_template = true;
+        } else if template_marker_name(trimmed, "# END").is_some() {
+            in_template = false;
+            current.push(line.to_string());
+            sections.push(Section {
+                blank_before: std::mem::take(&mut blank),
+                lines: std::mem::take(&mut current),
+            });
+            continue;
+        } else if trimmed.is_empty() && !in_template {
+            if !current.is_empty() {
+                sections.push(Section {
+                    blank_before: std::mem::take(&mut blank),
+                    lines: std::mem::take(&mut current),
+                });
+            }
+            blank.push(line.to_string());
+            continue;
+        }
+        current.push(line.to_string());
+    }
+    if !current.is_empty() {
+        sections.push(Section {
+            blank_before: std::mem::take(&mut blank),
+            lines: current,
+        });
+    }
+    (sections, blank)
+}
+
+/// Sort each run of consecutive plain patterns, and each run of
+/// consecutive negations, in place
+fn sort_runs(lines: &mut [String], strategy: SortStrategy) {
+    let kind = |line: &str| {
+        if ignore_file::is_comment(line) {
+            None
+        } else {
+            Some(line.trim_start().starts_with('!'))
+        }
+    };
+    let mut start = 0;
+    while start < lines.len() {
+        let Some(negated) = kind(&lines[start]) else {
+            start += 1;
+            continue;
+        };
+        let end = lines[start..]
+            .iter()
+            .position(|line| kind(line) != Some(negated))
+            .map_or(lines.len(), |offset| start + offset);
+        lines[start..end].sort_by(|a, b| strategy.compare(a.trim(), b.trim()));
+        start = end;
+    }
+}
+
+/// Sort ignore file content: patterns within each section by `strategy`,
+/// and sections whose heading appears in `section_order` (compared
+/// case-insensitively) first, in that order, followed by the others in
+/// their current order. A header of only comments stays at the top.
+/// Sections are only reordered when the file has no negations, since moving
+/// a section across a negation could change what it re-includes.
+pub fn sort_content(content: &str, strategy: SortStrategy, section_order: &[String]) -> String {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let (mut sections, trailing_blank) = split_sections(content);
+    for section in &mut sections {
+        if !section.is_fixed() {
+            sort_runs(&mut section.lines, strategy);
+        }
+    }
+
+    let has_negations = content
+        .lines()
+        .any(|line| line.trim_start().starts_with('!'));
+    if !section_order.is_empty() && !has_negations {
+        let rank = |section: &Section| {
+            let heading = section.heading()?;
+            section_order
+                .iter()
+                .position(|name| name.trim().eq_ignore_ascii_case(&heading))
+        };
+        // A leading block of only comments is the file's header
+        let header = sections.first().is_some_and(|first| {
+            !first.is_fixed() && first.lines.iter().all(|line| ignore_file::is_comment(line))
+        });
+        let start = usize::from(header);
+        let mut moved: Vec<(Option<usize>, Vec<String>)> = sections[start..]
+            .iter_mut()
+            .map(|section| (rank(section), std::mem::take(&mut section.lines)))
+            .collect();
+        // Listed sections first; stable, so the rest keep their order
+        moved.sort_by_key(|(rank, _)| rank.unwrap_or(usize::MAX));
+        for (section, (_, lines)) in sections[start..].iter_mut().zip(moved) {
+            section.lines = lines;
+        }
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    for section in sections {
+        lines.extend(section.blank_before);
+        lines.extend(section.lines);
+    }
+    lines.extend(trailing_blank);
+    let mut sorted = lines.join(newline);
+    if content.ends_with('\n') {
+        sorted.push_str(newline);
+    }
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(strategy: SortStrategy, patterns: &[&str]) -> Vec<String> {
+        let mut lines: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        sort_runs(&mut lines, strategy);
+        lines
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_names_match_config_values() {
+        use serde::de::{value, Deserialize, IntoDeserializer};
+        for strategy in [
+            SortStrategy::Lexicographic,
+            SortStrategy::Natural,
+            SortStrategy::DirectoriesFirst,
+            SortStrategy::Extension,
+        ] {
+            let name = strategy.to_string();
+            let deserializer: value::StrDeserializer<'_, value::Error> =
+                name.as_str().into_deserializer();
+            assert_eq!(SortStrategy::deserialize(deserializer).unwrap(), strategy);
+        }
+    }
+
+    #[test]
+    fn test_strategies() {
+        let patterns = ["lib10/", "*.log", "lib2/", "debug.log", "*.o", ".env"];
+        assert_eq!(
+            sorted(SortStrategy::Lexicographic, &patterns),
+            ["*.log", "*.o", ".env", "debug.log", "lib10/", "lib2/"]
+        );
+        assert_eq!(
+            sorted(SortStrategy::Natural, &patterns),
+            ["*.log", "*.o", ".env", "debug.log", "lib2/", "lib10/"]
+        );
+        assert_eq!(
+            sorted(SortStrategy::DirectoriesFirst, &patterns),
+            ["lib2/", "lib10/", "*.log", "*.o", ".env", "debug.log"]
+        );
+        assert_eq!(
+            sorted(SortStrategy::Extension, &patterns),
+            [".env", "lib2/", "lib10/", "*.log", "debug.log", "*.o"]
+        );
+        assert_eq!(natural_cmp("v1.02", "v1.2"), Ordering::Less);
+        assert_eq!(
+            "dirs-first".parse::<SortStrategy>().unwrap(),
+            SortStrategy::DirectoriesFirst
+        );
+    }
+
+    #[test]
+    fn test_sort_content_keeps_structure() {
+        let content = "# Build\ntarget/\nbuild/\n\n*.log\n!keep.log\n!a.log\nz.log\n\n# git-ignore group: Proto\nz.pb\na.pb\n";
+        assert_eq!(
+            sort_content(content, SortStrategy::Lexicographic, &[]),
+            "# Build\nbuild/\ntarget/\n\n*.log\n!a.log\n!keep.log\nz.log\n\n# git-ignore group: Proto\nz.pb\na.pb\n"
+        );
+    }
+
+    #[test]
+    fn test_section_order() {
+        let content = "# OS\n.DS_Store\n\n# BEGIN git-ignore template: Rust\ntarget/\n\n*.rs.bk\n# END git-ignore template: Rust\n\n# Editors\n*.swp\n";
+        let order = vec!["editors".to_string(), "Rust template".to_string()];
+        assert_eq!(
+            sort_content(content, SortStrategy::Lexicographic, &order),
+            "# Editors\n*.swp\n\n# BEGIN git-ignore template: Rust\ntarget/\n\n*.rs.bk\n# END git-ignore template: Rust\n\n# OS\n.DS_Store\n"
+        );
+        // Sections stay put when a negation could be affected
+        let negated = format!("{content}!keep.swp\n");
+        assert_eq!(
+            sort_content(&negated, SortStrategy::Lexicographic, &order),
+            negated
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! How much each ignore file and pattern is actually used
+//!
+//! [`collect`] reads the `.gitignore` files of the work tree, the
+//! repository's `info/exclude` and the global excludes file, then walks
+//! the work tree once, counting for every pattern the files it matches
+//! either directly or through a parent directory. Tracked files count as
+//! well, and a negation counts the files it matches, not the ones it
+//! re-includes. A pattern with no matches at all is a candidate for
+//! removal.
+
+use crate::{
+    git,
+    rules::{self, Rule},
+    RepoContext, Scope,
+};
+use anyhow::Context;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tracing::debug_span;
+
+/// One pattern and the number of work tree files it matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatternUsage {
+    pub rule: Rule,
+    pub matches: usize,
+}
+
+/// Statistics for one ignore file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgnoreFileStats {
+    pub scope: Scope,
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification time, where the platform reports one
+    pub modified: Option<SystemTime>,
+    /// Every pattern of the file, in file order
+    pub patterns: Vec<PatternUsage>,
+}
+
+impl IgnoreFileStats {
+    /// Patterns that match no file in the work tree
+    pub fn unused(&self) -> impl Iterator<Item = &PatternUsage> {
+        self.patterns.iter().filter(|usage| usage.matches == 0)
+    }
+}
+
+/// Statistics for every ignore file of the current repository that
+/// exists: `.gitignore` files first (top-level one leading), then
+/// `info/exclude`, then the global excludes file
+pub fn collect() -> anyhow::Result<Vec<IgnoreFileStats>> {
+    collect_in(RepoContext::current()?)
+}
+
+/// [`collect`] for `repo`
+pub fn collect_in(repo: &RepoContext) -> anyhow::Result<Vec<IgnoreFileStats>> {
+    let _span = debug_span!("collect_stats").entered();
+    let root = repo.root()?;
+    let mut sources: Vec<(Scope, PathBuf, String)> = Vec::new();
+    for file in repo.find_gitignore_files()? {
+        let base = file
+            .parent()
+            .and_then(|dir| dir.strip_prefix(root).ok())
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        sources.push((Scope::Repo, file, base));
+    }
+    sources.push((Scope::Local, repo.exclude_file_path(), String::new()));
+    if let Some(global) = repo.excludes_file()? {
+        sources.push((git::excludes_file_scope(&global), global, String::new()));
+    }
+
+    let mut files = Vec::new();
+    let mut all_rules = Vec::new();
+    for (scope, path, base) in sources {
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        let rules = rules::read_rules(&path, &base)?;
+        files.push((scope, path, metadata, rules.len()));
+        all_rules.extend(rules);
+    }
+
+    let counts = count_matches(root, &all_rules)?;
+    let mut usages = all_rules
+        .into_iter()
+        .zip(counts)
+        .map(|(rule, matches)| PatternUsage { rule, matches });
+    Ok(files
+        .into_iter()
+        .map(|(scope, path, metadata, count)| IgnoreFileStats {
+            scope,
+            path,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            patterns: usages.by_ref().take(count).collect(),
+        })
+        .collect())
+}
+
+/// For each rule, the number of files under `root` it matches, directly
+/// or through a parent directory. `.git` and nested repositories are
+/// skipped.
+pub fn count_matches(root: &Path, rules: &[Rule]) -> anyhow::Result<Vec<usize>> {
+    let mut counts = vec![0; rules.len()];
+    // Directories still to visit, with the rules matching them or a parent
+    let mut pending: Vec<(String, Vec<usize>)> = vec![(String::new(), Vec::new())];
+    while let Some((dir, matched)) = pending.pop() {
+        let absolute = root.join(&dir);
+        let read_dir = std::fs::read_dir(&absolute)
+            .with_context(|| format!("Failed to read directory: {}", absolute.display()))?;
+        for entry in read_dir {
+            let entry = entry
+                .with_context(|| format!("Failed to read directory: {}", absolute.display()))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == ".git" {
+                continue;
+            }
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let path = if dir.is_empty() {
+                name
+            } else {
+                format!("{dir}/{name}")
+            };
+            let mut matching = matched.clone();
+            matching.extend(
+                (0..rules.len())
+                    .filter(|index| !matched.contains(index))
+                    .filter(|&index| rules[index].matches(&path, is_dir)),
+            );
+            if !is_dir {
+                for index in matching {
+                    counts[index] += 1;
+                }
+            } else if !root.join(&path).join(".git").exists() {
+                pending.push((path, matching));
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// How long ago something happened, e.g. `3 days ago`
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86_399 => (seconds / 3600, "hour"),
+        86_400..=2_591_999 => (seconds / 86_400, "day"),
+        2_592_000..=31_535_999 => (seconds / 2_592_000, "month"),
+        _ => (seconds / 31_536_000, "year"),
+    };
+    if count == 1 {
+        format!("1 {unit} ago")
+    } else {
+        format!("{count} {unit}s ago")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_count_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("target/debug/app"), "").unwrap();
+        fs::write(root.join("target/debug/app.log"), "").unwrap();
+        fs::write(root.join("a.log"), "").unwrap();
+        fs::create_dir_all(root.join("src/vendor/.git")).unwrap();
+        fs::write(root.join("src/vendor/b.log"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+
+        let rules: Vec<Rule> = [
+            ("*.log", ""),
+            ("target/", ""),
+            ("*.tmp", ""),
+            ("*.rs", "src"),
+        ]
+        .iter()
+        .map(|(text, base)| Rule {
+            pattern: crate::Pattern::parse(text).unwrap(),
+            source: PathBuf::from(".gitignore"),
+            line_number: 1,
+            base: base.to_string(),
+        })
+        .collect();
+        assert_eq!(count_matches(root, &rules).unwrap(), vec![2, 2, 0, 1]);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(5)), "just now");
+        assert_eq!(format_age(Duration::from_secs(60)), "1 minute ago");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2 hours ago");
+        assert_eq!(format_age(Duration::from_secs(3 * 86_400)), "3 days ago");
+        assert_eq!(format_age(Duration::from_secs(400 * 86_400)), "1 year ago");
+    }
+}
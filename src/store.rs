@@ -0,0 +1,424 @@
+//! Where ignore file content is read from and written to
+//!
+//! Adding and removing patterns and committing a
+//! [`Transaction`](crate::ignore::Transaction) read and write through an
+//! [`IgnoreStore`]. The path-based functions of [`ignore`](crate::ignore)
+//! use [`FsStore`]; [`add_patterns`] and [`remove_patterns`] here, and
+//! [`Transaction::in_store`](crate::ignore::Transaction::in_store), run the
+//! same code against any store, such as a [`MemoryStore`] in tests or a
+//! host that keeps files elsewhere, with no temporary directories or git
+//! repository. The other edits (templates, sorting, formatting) work on
+//! files on disk only.
+//!
+//! ```
+//! use git_ignore_tool::store::{self, MemoryStore};
+//! use git_ignore_tool::ignore::Placement;
+//! use std::path::Path;
+//!
+//! let path = Path::new(".gitignore");
+//! let mut files = MemoryStore::new().with_file(path, "*.log\n");
+//! let added = store::add_patterns(
+//!     &mut files,
+//!     path,
+//!     &["*.log".to_string(), "target/".to_string()],
+//!     true,
+//!     Placement::Append,
+//! )?;
+//! assert_eq!(added, ["target/"]);
+//! assert_eq!(files.get(path), Some("*.log\n\ntarget/\n"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::ignore::{self, IgnoreEntry, Placement};
+use crate::lock::{self, FileLock};
+use crate::{Error, RemovalReport};
+use anyhow::{bail, Context};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Storage for ignore files, addressed by path.
+///
+/// Only [`read`](IgnoreStore::read), [`write`](IgnoreStore::write) and
+/// [`remove`](IgnoreStore::remove) are required. The compare-and-swap
+/// writes have defaults built on them that are correct for a store only
+/// one thread uses; a store shared with other writers overrides them, as
+/// [`FsStore`] does.
+pub trait IgnoreStore {
+    /// The content of `path`, or `None` if there is no such file
+    fn read(&self, path: &Path) -> anyhow::Result<Option<String>>;
+
+    /// Replace the content of `path`, creating it if needed
+    fn write(&mut self, path: &Path, content: &str) -> anyhow::Result<()>;
+
+    /// Delete `path`; a missing file is not an error
+    fn remove(&mut self, path: &Path) -> anyhow::Result<()>;
+
+    /// Keep other edits of `path` out until the lock is dropped; `None`
+    /// when the store has no locking
+    fn lock(&self, _path: &Path) -> anyhow::Result<Option<FileLock>> {
+        Ok(None)
+    }
+
+    /// Replace `path` with `content`, unless it no longer holds `expected`
+    /// (`None` meaning it should not exist yet)
+    fn write_if_unchanged(
+        &mut self,
+        path: &Path,
+        expected: Option<&str>,
+        content: &str,
+    ) -> anyhow::Result<WriteOutcome> {
+        let current = self.read(path)?;
+        if current.as_deref() != expected {
+            return Ok(WriteOutcome::Changed(current));
+        }
+        self.write(path, content)?;
+        Ok(WriteOutcome::Written)
+    }
+
+    /// Apply every one of `writes`, or none of them if any file no longer
+    /// holds its original content or a write fails
+    fn write_all_if_unchanged(&mut self, writes: &[StagedWrite]) -> anyhow::Result<()> {
+        for write in writes {
+            if self.read(&write.path)? != write.original {
+                bail!(concurrent_edit(&write.path));
+            }
+        }
+        for (index, write) in writes.iter().enumerate() {
+            if let Err(e) = self.write(&write.path, &write.content) {
+                // Put back the files already replaced
+                let mut unrestored = Vec::new();
+                for done in &writes[..index] {
+                    let restored = match &done.original {
+                        Some(content) => self.write(&done.path, content),
+                        None => self.remove(&done.path),
+                    };
+                    if let Err(restore_error) = restored {
+                        unrestored.push((done.path.as_path(), restore_error));
+                    }
+                }
+                return Err(e).context(rollback_outcome(&unrestored));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: IgnoreStore + ?Sized> IgnoreStore for &mut S {
+    fn read(&self, path: &Path) -> anyhow::Result<Option<String>> {
+        (**self).read(path)
+    }
+
+    fn write(&mut self, path: &Path, content: &str) -> anyhow::Result<()> {
+        (**self).write(path, content)
+    }
+
+    fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+        (**self).remove(path)
+    }
+
+    fn lock(&self, path: &Path) -> anyhow::Result<Option<FileLock>> {
+        (**self).lock(path)
+    }
+
+    fn write_if_unchanged(
+        &mut self,
+        path: &Path,
+        expected: Option<&str>,
+        content: &str,
+    ) -> anyhow::Result<WriteOutcome> {
+        (**self).write_if_unchanged(path, expected, content)
+    }
+
+    fn write_all_if_unchanged(&mut self, writes: &[StagedWrite]) -> anyhow::Result<()> {
+        (**self).write_all_if_unchanged(writes)
+    }
+}
+
+/// Result of [`IgnoreStore::write_if_unchanged`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written,
+    /// The file no longer matched the expected content; holds what it
+    /// holds now
+    Changed(Option<String>),
+}
+
+/// New content for one file of [`IgnoreStore::write_all_if_unchanged`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedWrite {
+    pub path: PathBuf,
+    /// The file's content when the change was computed; `None` if it didn't
+    /// exist
+    pub original: Option<String>,
+    pub content: String,
+}
+
+/// The context for a failed transaction write, naming the files whose
+/// original content couldn't be put back and why
+pub(crate) fn rollback_outcome(unrestored: &[(&Path, anyhow::Error)]) -> String {
+    if unrestored.is_empty() {
+        return "Transaction rolled back; no file was changed".to_string();
+    }
+    let files: Vec<String> = unrestored
+        .iter()
+        .map(|(path, error)| format!("{} ({error:#})", path.display()))
+        .collect();
+    format!(
+        "Transaction rollback incomplete; these files may be left modified: {}",
+        files.join(", ")
+    )
+}
+
+/// The error for a file that changed while it was being edited
+pub(crate) fn concurrent_edit(path: &Path) -> String {
+    format!(
+        "Concurrent edit conflict: {} changed on disk while it was being edited",
+        path.display()
+    )
+}
+
+/// The real filesystem. Writes replace files atomically through a
+/// temporary file, take a backup first and hold the file's
+/// [`lock`](crate::lock).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsStore;
+
+impl IgnoreStore for FsStore {
+    fn read(&self, path: &Path) -> anyhow::Result<Option<String>> {
+        ignore::read_optional(path)
+    }
+
+    fn write(&mut self, path: &Path, content: &str) -> anyhow::Result<()> {
+        ignore::write_ignore_file(path, content)
+    }
+
+    fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(Error::io("remove", path, e).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn lock(&self, path: &Path) -> anyhow::Result<Option<FileLock>> {
+        lock::lock(path).map(Some)
+    }
+
+    fn write_if_unchanged(
+        &mut self,
+        path: &Path,
+        expected: Option<&str>,
+        content: &str,
+    ) -> anyhow::Result<WriteOutcome> {
+        ignore::replace_file_if_unchanged(path, expected, content)
+    }
+
+    fn write_all_if_unchanged(&mut self, writes: &[StagedWrite]) -> anyhow::Result<()> {
+        ignore::replace_files_if_unchanged(writes)
+    }
+}
+
+/// Files held in memory; paths are used as given, without resolving
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// The store with `path` holding `content`
+    pub fn with_file(
+        mut self,
+        path: impl Into<PathBuf>,
+        content: impl Into<String>,
+    ) -> MemoryStore {
+        self.files.insert(path.into(), content.into());
+        self
+    }
+
+    /// The content of `path`, if the store has it
+    pub fn get(&self, path: &Path) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+
+    /// The stored paths, in sorted order
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+}
+
+impl IgnoreStore for MemoryStore {
+    fn read(&self, path: &Path) -> anyhow::Result<Option<String>> {
+        Ok(self.files.get(path).cloned())
+    }
+
+    fn write(&mut self, path: &Path, content: &str) -> anyhow::Result<()> {
+        self.files.insert(path.to_path_buf(), content.to_string());
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.files.remove(path);
+        Ok(())
+    }
+}
+
+/// [`add_patterns_with_placement`](crate::ignore::add_patterns_with_placement)
+/// on a file in `store`. The file is written only if a pattern was added.
+pub fn add_patterns(
+    store: &mut dyn IgnoreStore,
+    path: &Path,
+    patterns: &[String],
+    avoid_duplicates: bool,
+    placement: Placement,
+) -> anyhow::Result<Vec<String>> {
+    ignore::add_patterns_in(store, path, patterns, avoid_duplicates, placement, &mut ())
+}
+
+/// [`remove_patterns_from_ignore_file`](crate::ignore::remove_patterns_from_ignore_file)
+/// on a file in `store`. A missing file has nothing to remove.
+pub fn remove_patterns(
+    store: &mut dyn IgnoreStore,
+    path: &Path,
+    patterns: &[String],
+) -> anyhow::Result<RemovalReport> {
+    ignore::remove_patterns_in(store, path, patterns, &mut ())
+}
+
+/// [`read_ignore_entries`](crate::ignore::read_ignore_entries) on a file in
+/// `store`
+pub fn read_entries(store: &dyn IgnoreStore, path: &Path) -> anyhow::Result<Vec<IgnoreEntry>> {
+    Ok(store
+        .read(path)?
+        .map(|content| ignore::parse_ignore_entries(&content))
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn test_memory_store_edits() {
+        let gitignore = Path::new("repo/.gitignore");
+        let mut store = MemoryStore::new().with_file(gitignore, "# Logs\n*.log\n");
+
+        let added = add_patterns(
+            &mut store,
+            gitignore,
+            &patterns(&["*.log", "build"]),
+            true,
+            Placement::Append,
+        )
+        .unwrap();
+        assert_eq!(added, ["build"]);
+        assert_eq!(store.get(gitignore), Some("# Logs\n*.log\n\nbuild\n"));
+
+        // A missing file is created, and only once something is added
+        let other = Path::new("repo/.dockerignore");
+        let none = add_patterns(&mut store, other, &[], true, Placement::Append).unwrap();
+        assert!(none.is_empty());
+        assert_eq!(store.get(other), None);
+        add_patterns(
+            &mut store,
+            other,
+            &patterns(&["target/"]),
+            true,
+            Placement::Append,
+        )
+        .unwrap();
+        assert_eq!(store.paths().count(), 2);
+
+        let report =
+            remove_patterns(&mut store, gitignore, &patterns(&["build/", "dist"])).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.not_found, ["dist"]);
+        let entries = read_entries(&store, gitignore).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].comments, ["# Logs"]);
+
+        // Conflicted content is refused as it is on disk
+        let conflicted = Path::new("conflicted");
+        store
+            .write(conflicted, "<<<<<<< ours\na\n=======\nb\n>>>>>>> theirs\n")
+            .unwrap();
+        assert!(add_patterns(
+            &mut store,
+            conflicted,
+            &patterns(&["c"]),
+            true,
+            Placement::Append
+        )
+        .is_err());
+    }
+
+    /// A [`MemoryStore`] whose writes start failing after `writes_left`
+    struct FailingStore {
+        inner: MemoryStore,
+        writes_left: usize,
+    }
+
+    impl IgnoreStore for FailingStore {
+        fn read(&self, path: &Path) -> anyhow::Result<Option<String>> {
+            self.inner.read(path)
+        }
+
+        fn write(&mut self, path: &Path, content: &str) -> anyhow::Result<()> {
+            if self.writes_left == 0 {
+                bail!("disk full");
+            }
+            self.writes_left -= 1;
+            self.inner.write(path, content)
+        }
+
+        fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+            self.inner.remove(path)
+        }
+    }
+
+    #[test]
+    fn test_rollback_names_unrestored_files() {
+        let staged = |path: &str, original: Option<&str>| StagedWrite {
+            path: PathBuf::from(path),
+            original: original.map(str::to_string),
+            content: "new\n".to_string(),
+        };
+
+        // A new file is removed again
+        let mut store = FailingStore {
+            inner: MemoryStore::new().with_file("b", "old\n"),
+            writes_left: 1,
+        };
+        let error = store
+            .write_all_if_unchanged(&[staged("a", None), staged("b", Some("old\n"))])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Transaction rolled back; no file was changed"
+        );
+        assert_eq!(store.inner.get(Path::new("a")), None);
+
+        // Old content that can't be written back is reported
+        let mut store = FailingStore {
+            inner: MemoryStore::new()
+                .with_file("a", "old\n")
+                .with_file("b", "old\n"),
+            writes_left: 1,
+        };
+        let error = store
+            .write_all_if_unchanged(&[staged("a", Some("old\n")), staged("b", Some("old\n"))])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Transaction rollback incomplete; these files may be left modified: a (disk full)"
+        );
+    }
+}
@@ -0,0 +1,497 @@
+//! Patterns made redundant by broader ones
+//!
+//! `build/foo.o` adds nothing to a file that already ignores `build/`, and
+//! neither does `src/*.log` next to `*.log`. Spotting that takes more than
+//! comparing strings, so [`covers`] proves containment structurally: the
+//! broader pattern's segments are aligned with the narrower pattern's, `**`
+//! absorbing any run of segments, and within each aligned pair of path
+//! components every character the narrower glob can match has to be
+//! accepted by the broader glob at the same position, `*` absorbing any
+//! run of characters. A broader pattern that runs out of segments first
+//! matches a parent directory, which ignores everything below it. Pairs
+//! the alignment can't prove are treated as not covered, since `dedupe`
+//! deletes what this reports.
+
+use crate::{
+    ignore::template_marker_name,
+    ignore_file,
+    pattern::{self, Pattern, Segment},
+};
+use std::fmt;
+
+/// A pattern line that a broader line of the same file already covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Redundancy {
+    /// 1-based line number of the redundant pattern
+    pub line_number: usize,
+    pub pattern: String,
+    /// The line that makes it redundant, which is itself not redundant
+    pub covered_by: String,
+    pub covered_by_line: usize,
+}
+
+/// Whether every path `narrower` matches is also ignored by `broader`,
+/// either directly or because `broader` matches one of its parent
+/// directories. Negations never cover or are covered.
+pub fn covers(broader: &Pattern, narrower: &Pattern) -> bool {
+    if broader.negated || narrower.negated {
+        return false;
+    }
+    let (Some(outer), Some(inner)) = (components(broader), components(narrower)) else {
+        return false;
+    };
+    if outer.is_empty() || inner.is_empty() {
+        return false;
+    }
+
+    // `covered[i][j]`: whether `outer[i..]` covers `inner[j..]`
+    let (n, m) = (outer.len(), inner.len());
+    let mut covered = vec![vec![false; m + 1]; n + 1];
+    for (j, cell) in covered[n].iter_mut().enumerate() {
+        // Out of broader segments with narrower ones left: the broader
+        // pattern matched a parent directory
+        *cell = j < m || !broader.dir_only || narrower.dir_only;
+    }
+    for i in (0..n).rev() {
+        for j in (0..=m).rev() {
+            covered[i][j] = match (&outer[i], inner.get(j)) {
+                // A trailing `**` matches one or more components
+                (Component::DoubleStar, _) if i + 1 == n => {
+                    j < m && (!broader.dir_only || narrower.dir_only)
+                }
+                (Component::DoubleStar, next) => {
+                    covered[i + 1][j] || (next.is_some() && covered[i][j + 1])
+                }
+                (Component::Glob(outer), Some(Component::Glob(inner))) => {
+                    glob_covers(outer, inner) && covered[i + 1][j + 1]
+                }
+                _ => false,
+            };
+        }
+    }
+    covered[0][0]
+}
+
+/// One path component of a pattern, as [`covers`] aligns them
+enum Component {
+    DoubleStar,
+    Glob(Vec<Token>),
+}
+
+/// One element of a single-component glob
+enum Token {
+    /// `*`, any run of characters
+    Star,
+    /// Exactly one character out of a set
+    Char(CharSet),
+}
+
+enum CharSet {
+    /// `?`
+    Any,
+    Literal(char),
+    /// A bracket expression as written, `[` to `]`
+    Class(Vec<char>),
+}
+
+impl CharSet {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            CharSet::Any => true,
+            CharSet::Literal(literal) => *literal == c,
+            CharSet::Class(class) => pattern::match_class(class, c).is_some_and(|(hit, _)| hit),
+        }
+    }
+
+    /// Characters at which membership can change: every character the
+    /// set names and the one after it
+    fn boundaries(&self) -> Vec<char> {
+        let named: &[char] = match self {
+            CharSet::Any => &[],
+            CharSet::Literal(c) => std::slice::from_ref(c),
+            CharSet::Class(class) => class,
+        };
+        named
+            .iter()
+            .flat_map(|&c| [Some(c), char::from_u32(c as u32 + 1)])
+            .flatten()
+            .collect()
+    }
+
+    /// Whether every character of `other` is in this set. Membership only
+    /// changes at the characters either set names or at the end of ASCII
+    /// (where the `[:class:]` names stop), so checking ASCII and those
+    /// boundaries decides it exactly.
+    fn includes(&self, other: &CharSet) -> bool {
+        (0..=128u8)
+            .map(char::from)
+            .chain(self.boundaries())
+            .chain(other.boundaries())
+            .all(|c| !other.contains(c) || self.contains(c))
+    }
+}
+
+/// The components of `pattern` as an anchored path, an unanchored pattern
+/// becoming `**/<name>`, or `None` if a component can never match
+fn components(pattern: &Pattern) -> Option<Vec<Component>> {
+    let mut components = Vec::new();
+    if !pattern.anchored {
+        components.push(Component::DoubleStar);
+    }
+    for segment in &pattern.segments {
+        components.push(match segment {
+            Segment::DoubleStar => Component::DoubleStar,
+            Segment::Literal(text) => Component::Glob(
+                text.chars()
+                    .map(|c| Token::Char(CharSet::Literal(c)))
+                    .collect(),
+            ),
+            Segment::Glob(glob) => Component::Glob(tokens(glob)?),
+        });
+    }
+    Some(components)
+}
+
+/// Split glob text into tokens the way the matcher reads it, or `None` for
+/// an unterminated bracket expression, which never matches
+fn tokens(glob: &str) -> Option<Vec<Token>> {
+    let glob: Vec<char> = glob.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < glob.len() {
+        let (token, len) = match glob[i] {
+            '*' => {
+                let run = glob[i..].iter().take_while(|&&c| c == '*').count();
+                (Token::Star, run)
+            }
+            '?' => (Token::Char(CharSet::Any), 1),
+            '[' => {
+                let (_, len) = pattern::match_class(&glob[i..], 'a')?;
+                (Token::Char(CharSet::Class(glob[i..i + len].to_vec())), len)
+            }
+            '\\' if i + 1 < glob.len() => (Token::Char(CharSet::Literal(glob[i + 1])), 2),
+            c => (Token::Char(CharSet::Literal(c)), 1),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Some(tokens)
+}
+
+/// Whether every component `inner` matches is matched by `outer`: each
+/// character `inner` can match is accepted by the `outer` token it aligns
+/// with, and `*` in `outer` absorbs any run of `inner` tokens
+fn glob_covers(outer: &[Token], inner: &[Token]) -> bool {
+    let (n, m) = (outer.len(), inner.len());
+    let mut covered = vec![vec![false; m + 1]; n + 1];
+    covered[n][m] = true;
+    for i in (0..n).rev() {
+        for j in (0..=m).rev() {
+            covered[i][j] = match (&outer[i], inner.get(j)) {
+                (Token::Star, next) => covered[i + 1][j] || (next.is_some() && covered[i][j + 1]),
+                (Token::Char(outer), Some(Token::Char(inner))) => {
+                    outer.includes(inner) && covered[i + 1][j + 1]
+                }
+                _ => false,
+            };
+        }
+    }
+    covered[0][0]
+}
+
+/// Find the pattern lines of ignore file content that broader lines make
+/// redundant. Of two equivalent lines the first is kept. Lines inside
+/// template blocks are never reported, though they can cover others. A
+/// negation after either line of a pair could make the narrower one
+/// matter again, so such pairs are left alone.
+pub fn find_redundant(content: &str) -> Vec<Redundancy> {
+    let lines = pattern_lines(content);
+    let last_negation = lines.iter().rposition(|(_, _, pattern, _)| pattern.negated);
+    let coverers = |i: usize| -> Vec<usize> {
+        let (_, _, narrower, in_template) = &lines[i];
+        if *in_template {
+            return Vec::new();
+        }
+        (0..lines.len())
+            .filter(|&j| j != i)
+            .filter(|&j| last_negation.map_or(true, |negation| negation < i.min(j)))
+            .filter(|&j| covers(&lines[j].2, narrower))
+            // Equivalent lines cover each other; only the later one goes
+            .filter(|&j| j < i || !covers(narrower, &lines[j].2))
+            .collect()
+    };
+
+    let candidates: Vec<(usize, Vec<usize>)> = (0..lines.len())
+        .map(|i| (i, coverers(i)))
+        .filter(|(_, coverers)| !coverers.is_empty())
+        .collect();
+    let redundant: Vec<usize> = candidates.iter().map(|(i, _)| *i).collect();
+    candidates
+        .into_iter()
+        .filter_map(|(i, coverers)| {
+            let keeper = coverers.into_iter().find(|j| !redundant.contains(j))?;
+            Some(Redundancy {
+                line_number: lines[i].0,
+                pattern: lines[i].1.clone(),
+                covered_by: lines[keeper].1.clone(),
+                covered_by_line: lines[keeper].0,
+            })
+        })
+        .collect()
+}
+
+/// The pattern lines of `content`: line number, text, pattern and whether
+/// the line is inside a template block
+fn pattern_lines(content: &str) -> Vec<(usize, String, Pattern, bool)> {
+    let mut lines = Vec::new();
+    let mut in_template = false;
+    for (index, line) in content.lines().enumerate() {
+        let text = line.trim();
+        if template_marker_name(text, "# BEGIN").is_some() {
+            in_template = true;
+        } else if template_marker_name(text, "# END").is_some() {
+            in_template = false;
+        }
+        if text.is_empty() || ignore_file::is_comment(line) {
+            continue;
+        }
+        if let Ok(pattern) = Pattern::parse(text) {
+            lines.push((index + 1, text.to_string(), pattern, in_template));
+        }
+    }
+    lines
+}
+
+/// How a pattern being added relates to an existing line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Coverage {
+    /// The existing line already ignores everything the new pattern would
+    AlreadyCovered,
+    /// The new pattern ignores everything the existing line does, leaving
+    /// the line redundant
+    CoversExisting,
+}
+
+/// A pattern being added that overlaps a line already in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Overlap {
+    /// The new pattern
+    pub pattern: String,
+    pub coverage: Coverage,
+    /// The existing line and its 1-based line number
+    pub existing: String,
+    pub existing_line: usize,
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.coverage {
+            Coverage::AlreadyCovered => write!(
+                f,
+                "'{}' is already ignored by '{}' (line {})",
+                self.pattern, self.existing, self.existing_line
+            ),
+            Coverage::CoversExisting => write!(
+                f,
+                "'{}' makes '{}' (line {}) redundant",
+                self.pattern, self.existing, self.existing_line
+            ),
+        }
+    }
+}
+
+/// Check patterns about to be appended to ignore file `content` against
+/// the lines already there. A new pattern that an existing line covers is
+/// reported once, as [`Coverage::AlreadyCovered`]; otherwise every
+/// existing line it covers is reported as [`Coverage::CoversExisting`],
+/// except lines inside template blocks. Lines followed by a negation are
+/// skipped, since the new pattern, coming after that negation, changes
+/// what it re-includes.
+pub fn find_redundant_patterns(new: &[String], content: &str) -> Vec<Overlap> {
+    let lines = pattern_lines(content);
+    let after_negations = match lines.iter().rposition(|(_, _, pattern, _)| pattern.negated) {
+        Some(last) => &lines[last + 1..],
+        None => &lines[..],
+    };
+
+    let mut overlaps = Vec::new();
+    for text in new {
+        let text = text.trim();
+        let Ok(pattern) = Pattern::parse(text) else {
+            continue;
+        };
+        let overlap =
+            |(line_number, existing, _, _): &(usize, String, Pattern, bool), coverage| Overlap {
+                pattern: text.to_string(),
+                coverage,
+                existing: existing.clone(),
+                existing_line: *line_number,
+            };
+        if let Some(line) = after_negations
+            .iter()
+            .find(|(_, _, existing, _)| covers(existing, &pattern))
+        {
+            overlaps.push(overlap(line, Coverage::AlreadyCovered));
+            continue;
+        }
+        overlaps.extend(
+            after_negations
+                .iter()
+                .filter(|(_, _, existing, in_template)| !in_template && covers(&pattern, existing))
+                .map(|line| overlap(line, Coverage::CoversExisting)),
+        );
+    }
+    overlaps
+}
+
+/// `content` without the given 1-based lines
+pub fn without_lines(content: &str, line_numbers: &[usize]) -> String {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let kept: Vec<&str> = content
+        .lines()
+        .enumerate()
+        .filter(|(index, _)| !line_numbers.contains(&(index + 1)))
+        .map(|(_, line)| line)
+        .collect();
+    let mut updated = kept.join(newline);
+    if !kept.is_empty() && content.ends_with('\n') {
+        updated.push_str(newline);
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covered(broader: &str, narrower: &str) -> bool {
+        covers(
+            &Pattern::parse(broader).unwrap(),
+            &Pattern::parse(narrower).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_covers() {
+        assert!(covered("build/", "build/foo.o"));
+        assert!(covered("build/", "/build/"));
+        assert!(covered("*.log", "src/*.log"));
+        assert!(covered("*.log", "debug.log"));
+        assert!(covered("*", "ex*"));
+        assert!(covered("build", "build/"));
+        assert!(covered("docs/", "docs/**/*.md"));
+
+        assert!(!covered("ex*", "*"));
+        assert!(!covered("/build/", "build/"));
+        assert!(!covered("build/", "build"));
+        assert!(!covered("*.log", "*.txt"));
+        assert!(!covered("a?", "a*"));
+        assert!(!covered("[ab].o", "[a-c].o"));
+        assert!(!covered("!build/", "build/foo.o"));
+    }
+
+    #[test]
+    fn test_covers_character_classes() {
+        assert!(covered("f[0-9]", "f[0-8]"));
+        assert!(covered("f[0-9]", "f7"));
+        assert!(covered("[a-c]", "[ab]"));
+        assert!(covered("*.[a-y]", "*.[a-x]"));
+        assert!(covered("a?c", "a[!z]c"));
+        assert!(covered("*.[[:alpha:]]", "*.[a-z]"));
+        assert!(covered("*", "[!a]"));
+
+        assert!(!covered("f[0-8]", "f[0-9]"));
+        assert!(!covered("[ab]", "[a-c]"));
+        assert!(!covered("*.[a-x]", "*.[a-y]"));
+        assert!(!covered("a[!z]c", "a?c"));
+        assert!(!covered("[!a]", "[!b]"));
+        assert!(!covered("[a-z]", "[[:alpha:]]"));
+        // An unterminated class matches nothing and proves nothing
+        assert!(!covered("*", "[ab"));
+    }
+
+    #[test]
+    fn test_covers_double_star() {
+        assert!(covered("**/build", "src/build"));
+        assert!(covered("src/**", "src/a/b.o"));
+        assert!(covered("a/**/b", "a/x/**/b"));
+        assert!(covered("logs/", "logs/**"));
+
+        assert!(!covered("src/**", "src"));
+        assert!(!covered("src/**/", "src/x"));
+        assert!(!covered("a/*/b", "a/**/b"));
+    }
+
+    #[test]
+    fn test_find_redundant() {
+        let content = "build/\n*.log\nbuild/foo.o\nsrc/*.log\n/build/\nbuild\n*.tmp\n";
+        let redundant = find_redundant(content);
+        let found: Vec<(usize, &str, &str)> = redundant
+            .iter()
+            .map(|r| (r.line_number, r.pattern.as_str(), r.covered_by.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, "build/", "build"),
+                (3, "build/foo.o", "build"),
+                (4, "src/*.log", "*.log"),
+                (5, "/build/", "build"),
+            ]
+        );
+        assert_eq!(
+            without_lines(content, &[1, 3, 4, 5]),
+            "*.log\nbuild\n*.tmp\n"
+        );
+    }
+
+    #[test]
+    fn test_find_redundant_patterns() {
+        let new = |patterns: &[&str]| -> Vec<String> {
+            patterns.iter().map(|pattern| pattern.to_string()).collect()
+        };
+        let content = "# Build\nbuild/\ntarget/debug/\ntarget/release/\n";
+        let found: Vec<String> =
+            find_redundant_patterns(&new(&["build/foo/", "target/", "*.log", "# note"]), content)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+        assert_eq!(
+            found,
+            [
+                "'build/foo/' is already ignored by 'build/' (line 2)",
+                "'target/' makes 'target/debug/' (line 3) redundant",
+                "'target/' makes 'target/release/' (line 4) redundant",
+            ]
+        );
+
+        // A negation in between changes what the addition means
+        assert!(find_redundant_patterns(&new(&["build/x"]), "build/\n!build/keep\n").is_empty());
+        let content =
+            "# BEGIN git-ignore template: Rust\ntarget/debug/\n# END git-ignore template: Rust\n";
+        assert!(find_redundant_patterns(&new(&["target/"]), content).is_empty());
+    }
+
+    #[test]
+    fn test_find_redundant_respects_negations() {
+        // The second *.log re-ignores what !keep.log re-included
+        assert!(find_redundant("*.log\n!keep.log\nsrc/*.log\n").is_empty());
+        assert_eq!(find_redundant("!keep.log\n*.log\nsrc/*.log\n").len(), 1);
+        // Neither class contains the other
+        assert_eq!(find_redundant("f[0-8]\nf[0-9]\n").len(), 1);
+        assert_eq!(find_redundant("f[0-8]\nf[0-9]\n")[0].pattern, "f[0-8]");
+        // Template lines stay, though they cover others
+        let content = "# BEGIN git-ignore template: Rust\ntarget/\n# END git-ignore template: Rust\n/target/\n";
+        let found = find_redundant(content);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pattern, "/target/");
+    }
+}
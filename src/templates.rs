@@ -0,0 +1,725 @@
+//! Language and framework templates from github/gitignore
+//!
+//! `git-ignore template rust python` writes each template into an ignore
+//! file as a marked block (see [`ignore::add_template_to_ignore_file`]), so
+//! applying it again updates it in place and `remove-template` takes it
+//! out. Names are matched case-insensitively against the templates in
+//! [github/gitignore](https://github.com/github/gitignore), including the
+//! editor and operating system ones under `Global/` and the contributed
+//! ones under `community/`.
+//!
+//! A template is looked up in the download cache, then among the
+//! [`BUNDLED`] copies compiled into the binary, and only then downloaded
+//! with `curl`, so the common ones work without network access.
+//! Downloaded templates are cached; [`refresh`] downloads the current
+//! version of the bundled and cached ones.
+//!
+//! [`apply_template`] does what `git-ignore template` does for one scope of
+//! a repository:
+//!
+//! ```no_run
+//! use git_ignore_tool::{templates, RepoContext, Scope};
+//! use std::path::Path;
+//!
+//! let repo = RepoContext::discover(Path::new("/path/to/repo"))?;
+//! let added = templates::apply_template(&repo, &"rust".into(), Scope::Repo)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::{
+    ignore::{self, normalize_pattern_for_dedup},
+    json, paths, scope_file_in, Error, Observer, RepoContext, Scope, SkipReason,
+};
+use anyhow::{bail, Context};
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tracing::debug_span;
+
+/// Where template files are downloaded from
+pub const TEMPLATE_SOURCE: &str = "https://raw.githubusercontent.com/github/gitignore/main";
+
+/// Lists every file of the template repository
+const TEMPLATE_INDEX: &str =
+    "https://api.github.com/repos/github/gitignore/git/trees/main?recursive=1";
+
+/// Seconds a single download may take
+const DOWNLOAD_TIMEOUT_SECS: &str = "60";
+
+/// Templates compiled into the binary, by path in the template repository
+pub const BUNDLED: &[(&str, &str)] = &[
+    ("C.gitignore", include_str!("../templates/C.gitignore")),
+    ("C++.gitignore", include_str!("../templates/C++.gitignore")),
+    ("Go.gitignore", include_str!("../templates/Go.gitignore")),
+    (
+        "Java.gitignore",
+        include_str!("../templates/Java.gitignore"),
+    ),
+    (
+        "Node.gitignore",
+        include_str!("../templates/Node.gitignore"),
+    ),
+    (
+        "Python.gitignore",
+        include_str!("../templates/Python.gitignore"),
+    ),
+    (
+        "Ruby.gitignore",
+        include_str!("../templates/Ruby.gitignore"),
+    ),
+    (
+        "Rust.gitignore",
+        include_str!("../templates/Rust.gitignore"),
+    ),
+    (
+        "Global/JetBrains.gitignore",
+        include_str!("../templates/Global/JetBrains.gitignore"),
+    ),
+    (
+        "Global/Linux.gitignore",
+        include_str!("../templates/Global/Linux.gitignore"),
+    ),
+    (
+        "Global/macOS.gitignore",
+        include_str!("../templates/Global/macOS.gitignore"),
+    ),
+    (
+        "Global/Vim.gitignore",
+        include_str!("../templates/Global/Vim.gitignore"),
+    ),
+    (
+        "Global/VisualStudioCode.gitignore",
+        include_str!("../templates/Global/VisualStudioCode.gitignore"),
+    ),
+    (
+        "Global/Windows.gitignore",
+        include_str!("../templates/Global/Windows.gitignore"),
+    ),
+];
+
+/// Where a template's content came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TemplateSource {
+    /// Compiled into the binary
+    Bundled,
+    /// Downloaded earlier and kept in the [`cache_dir`]
+    Cached,
+    /// Just downloaded
+    Remote,
+}
+
+impl std::fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TemplateSource::Bundled => "bundled",
+            TemplateSource::Cached => "cached",
+            TemplateSource::Remote => "remote",
+        })
+    }
+}
+
+/// A template ready to apply
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Template {
+    /// Name as spelled upstream, e.g. `Rust` or `macOS`; it names the block
+    pub name: String,
+    /// Path in the template repository, e.g. `Global/macOS.gitignore`
+    pub path: String,
+    /// The template file
+    pub content: String,
+    /// Where the content came from
+    pub source: TemplateSource,
+}
+
+/// Names a template, matched against the template repository without
+/// regard to case: `rust`, `Rust` and `RUST` all pick `Rust.gitignore`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemplateId(String);
+
+impl TemplateId {
+    pub fn new(name: impl Into<String>) -> TemplateId {
+        TemplateId(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for TemplateId {
+    fn from(name: &str) -> TemplateId {
+        TemplateId::new(name)
+    }
+}
+
+impl From<String> for TemplateId {
+    fn from(name: String) -> TemplateId {
+        TemplateId(name)
+    }
+}
+
+impl std::fmt::Display for TemplateId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Outcome of writing a template into an ignore file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AppliedTemplate {
+    /// Name of the template block
+    pub name: String,
+    /// Patterns in the block, in template order
+    pub patterns: Vec<String>,
+    /// Template patterns left out because the file already had them
+    /// outside the block, or the template repeats them
+    pub skipped: Vec<String>,
+    /// Whether the file changed; `false` when the block was up to date
+    pub changed: bool,
+}
+
+/// Name of the template stored at `path`: its file name without
+/// `.gitignore`
+pub fn template_name(path: &str) -> &str {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.strip_suffix(".gitignore").unwrap_or(file)
+}
+
+/// Paths of every template in the template repository
+pub fn remote_template_paths() -> anyhow::Result<Vec<String>> {
+    let index = http_get(TEMPLATE_INDEX)?;
+    let paths: Vec<String> = json::string_fields(&index, "path")
+        .into_iter()
+        .filter(|path| path.ends_with(".gitignore") && !template_name(path).is_empty())
+        .collect();
+    if paths.is_empty() {
+        bail!("Failed to read the template list from {TEMPLATE_INDEX}");
+    }
+    Ok(paths)
+}
+
+/// The path among `paths` of the template called `name`, ignoring case.
+/// Top-level templates win over `Global/` ones, which win over the rest.
+pub fn resolve_name<'a>(name: &str, paths: &'a [String]) -> Option<&'a str> {
+    paths
+        .iter()
+        .filter(|path| template_name(path).eq_ignore_ascii_case(name))
+        .min_by_key(|path| {
+            let rank = match path.split_once('/') {
+                None => 0,
+                Some(("Global", _)) => 1,
+                Some(_) => 2,
+            };
+            (rank, path.as_str())
+        })
+        .map(String::as_str)
+}
+
+/// The template called `name`: the cached copy, else the bundled one,
+/// else downloaded (and cached)
+pub fn find_template(name: &str) -> anyhow::Result<Template> {
+    if let Some(template) = cached_template(name)? {
+        return Ok(template);
+    }
+    if let Some(template) = bundled_template(name) {
+        return Ok(template);
+    }
+    let template = fetch_template(name)?;
+    store_in_cache(&template)?;
+    Ok(template)
+}
+
+/// The bundled copy of the template called `name`, if there is one
+pub fn bundled_template(name: &str) -> Option<Template> {
+    let paths: Vec<String> = BUNDLED.iter().map(|(path, _)| path.to_string()).collect();
+    let path = resolve_name(name, &paths)?;
+    let (path, content) = BUNDLED.iter().find(|(bundled, _)| *bundled == path)?;
+    Some(Template {
+        name: template_name(path).to_string(),
+        path: path.to_string(),
+        content: content.to_string(),
+        source: TemplateSource::Bundled,
+    })
+}
+
+/// Download the current version of the template called `name`
+pub fn fetch_template(name: &str) -> anyhow::Result<Template> {
+    let paths = remote_template_paths()?;
+    fetch_from(name, &paths)
+}
+
+/// Download the template called `name`, found among the remote `paths`
+fn fetch_from(name: &str, paths: &[String]) -> anyhow::Result<Template> {
+    let Some(path) = resolve_name(name, paths) else {
+        bail!("{}", unknown_template_message(name, paths));
+    };
+    let content = http_get(&format!("{TEMPLATE_SOURCE}/{path}"))?;
+    Ok(Template {
+        name: template_name(path).to_string(),
+        path: path.to_string(),
+        content,
+        source: TemplateSource::Remote,
+    })
+}
+
+/// Download the current version of each of `names` into the cache, or of
+/// every bundled and cached template when `names` is empty. Returns the
+/// downloaded templates.
+pub fn refresh(names: &[String]) -> anyhow::Result<Vec<Template>> {
+    let mut wanted: Vec<String> = names.to_vec();
+    if wanted.is_empty() {
+        wanted.extend(
+            BUNDLED
+                .iter()
+                .map(|(path, _)| template_name(path).to_string()),
+        );
+        for cached in cached_templates()? {
+            if !wanted
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&cached.name))
+            {
+                wanted.push(cached.name);
+            }
+        }
+    }
+
+    let paths = remote_template_paths()?;
+    let mut refreshed = Vec::new();
+    for name in &wanted {
+        let template = fetch_from(name, &paths)?;
+        store_in_cache(&template)?;
+        refreshed.push(template);
+    }
+    Ok(refreshed)
+}
+
+/// Directory downloaded templates are kept in:
+/// `$XDG_CACHE_HOME/git-ignore/templates`, else
+/// `~/.cache/git-ignore/templates`
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME").filter(|xdg| !xdg.is_empty()) {
+        Some(xdg_cache) => paths::native_path(PathBuf::from(xdg_cache)),
+        None => paths::home_dir()?.join(".cache"),
+    };
+    Some(base.join("git-ignore").join("templates"))
+}
+
+/// Every template in the cache, by name
+pub fn cached_templates() -> anyhow::Result<Vec<Template>> {
+    let Some(dir) = cache_dir() else {
+        return Ok(Vec::new());
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut templates = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read: {}", dir.display()))?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(name) = file_name.strip_suffix(".gitignore") else {
+            continue;
+        };
+        let content = fs::read_to_string(entry.path())
+            .with_context(|| format!("Failed to read: {}", entry.path().display()))?;
+        templates.push(Template {
+            name: name.to_string(),
+            path: file_name.clone(),
+            content,
+            source: TemplateSource::Cached,
+        });
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// The cached copy of the template called `name`, if there is one
+fn cached_template(name: &str) -> anyhow::Result<Option<Template>> {
+    Ok(cached_templates()?
+        .into_iter()
+        .find(|template| template.name.eq_ignore_ascii_case(name)))
+}
+
+/// Keep a downloaded template in the cache as `<name>.gitignore`
+fn store_in_cache(template: &Template) -> anyhow::Result<()> {
+    let Some(dir) = cache_dir() else {
+        return Ok(());
+    };
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    let file = dir.join(format!("{}.gitignore", template.name));
+    fs::write(&file, &template.content)
+        .with_context(|| format!("Failed to write: {}", file.display()))
+}
+
+/// A template available by name, and where it can come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemplateListing {
+    /// Name as spelled upstream
+    pub name: String,
+    /// Path in the template repository; for a template only found in the
+    /// cache, its file name there
+    pub path: String,
+    /// Every source that has it, in the order bundled, cached, remote
+    pub sources: Vec<TemplateSource>,
+}
+
+/// Every bundled and cached template, plus those among `remote_paths`
+/// (from [`remote_template_paths`]; pass none to stay offline), sorted by
+/// name. A name found in several places is listed once.
+pub fn list_templates(remote_paths: &[String]) -> anyhow::Result<Vec<TemplateListing>> {
+    let mut listings: Vec<TemplateListing> = Vec::new();
+    let mut add = |name: &str, path: &str, source: TemplateSource| match listings
+        .iter_mut()
+        .find(|listing| listing.name.eq_ignore_ascii_case(name))
+    {
+        Some(listing) if !listing.sources.contains(&source) => listing.sources.push(source),
+        Some(_) => {}
+        None => listings.push(TemplateListing {
+            name: name.to_string(),
+            path: path.to_string(),
+            sources: vec![source],
+        }),
+    };
+
+    for (path, _) in BUNDLED {
+        add(template_name(path), path, TemplateSource::Bundled);
+    }
+    for cached in cached_templates()? {
+        add(&cached.name, &cached.path, TemplateSource::Cached);
+    }
+    for name in remote_paths.iter().map(|path| template_name(path)) {
+        if let Some(path) = resolve_name(name, remote_paths) {
+            add(template_name(path), path, TemplateSource::Remote);
+        }
+    }
+
+    for listing in &mut listings {
+        listing.sources.sort_by_key(|source| *source as u8);
+        // The upstream location is more telling than a cache file name
+        if let Some(path) = resolve_name(&listing.name, remote_paths) {
+            listing.path = path.to_string();
+        }
+    }
+    listings.sort_by(|a, b| {
+        a.name
+            .to_ascii_lowercase()
+            .cmp(&b.name.to_ascii_lowercase())
+    });
+    Ok(listings)
+}
+
+/// The listings whose name or path contains `term`, ignoring case
+pub fn search<'a>(listings: &'a [TemplateListing], term: &str) -> Vec<&'a TemplateListing> {
+    let term = term.to_ascii_lowercase();
+    listings
+        .iter()
+        .filter(|listing| {
+            listing.name.to_ascii_lowercase().contains(&term)
+                || listing.path.to_ascii_lowercase().contains(&term)
+        })
+        .collect()
+}
+
+/// Error text for a name matching no template, suggesting similar ones
+fn unknown_template_message(name: &str, paths: &[String]) -> String {
+    let term = name.to_ascii_lowercase();
+    let mut similar: Vec<&str> = paths
+        .iter()
+        .map(|path| template_name(path))
+        .filter(|candidate| {
+            let candidate = candidate.to_ascii_lowercase();
+            candidate.contains(&term) || term.contains(&candidate)
+        })
+        .collect();
+    similar.sort_unstable();
+    similar.dedup();
+    similar.truncate(5);
+    if similar.is_empty() {
+        format!("Unknown template '{name}' (see git-ignore template --list)")
+    } else {
+        format!(
+            "Unknown template '{name}'; did you mean {}?",
+            similar.join(", ")
+        )
+    }
+}
+
+/// Write `template` into `file_path` as a template block, leaving out
+/// patterns the file already has elsewhere. Comments and blank lines of the
+/// template are kept.
+pub fn apply_to_file(file_path: &Path, template: &Template) -> anyhow::Result<AppliedTemplate> {
+    apply_to_file_observed(file_path, template, &mut ())
+}
+
+/// [`apply_to_file`], reporting skipped patterns, the patterns the block
+/// gained and the commit of the file to `observer`
+pub fn apply_to_file_observed(
+    file_path: &Path,
+    template: &Template,
+    observer: &mut dyn Observer,
+) -> anyhow::Result<AppliedTemplate> {
+    let before: HashSet<String> = ignore::template_block_patterns(file_path, &template.name)?
+        .into_iter()
+        .collect();
+    let mut seen: HashSet<String> = ignore::patterns_outside_template(file_path, &template.name)?
+        .iter()
+        .map(|pattern| normalize_pattern_for_dedup(pattern).to_string())
+        .collect();
+
+    let mut lines = Vec::new();
+    let mut patterns = Vec::new();
+    let mut skipped = Vec::new();
+    for line in template.content.trim_matches(['\n', '\r']).lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            lines.push(line.trim_end().to_string());
+        } else if seen.insert(normalize_pattern_for_dedup(trimmed).to_string()) {
+            lines.push(trimmed.to_string());
+            patterns.push(trimmed.to_string());
+        } else {
+            skipped.push(trimmed.to_string());
+        }
+    }
+
+    let changed = ignore::add_template_to_ignore_file(file_path, &template.name, &lines)?;
+    for pattern in &skipped {
+        observer.pattern_skipped(pattern, SkipReason::Duplicate);
+    }
+    if changed {
+        for pattern in patterns.iter().filter(|pattern| !before.contains(*pattern)) {
+            observer.line_written(file_path, pattern);
+        }
+        observer.file_committed(file_path);
+    }
+    Ok(AppliedTemplate {
+        name: template.name.clone(),
+        patterns,
+        skipped,
+        changed,
+    })
+}
+
+/// Find the template `id` and write it into the file for `scope` of
+/// `repo`, as `git-ignore template` does. Returns the patterns the file
+/// gained: none when the block was already up to date, and only the new
+/// ones when an older version of the block is updated.
+pub fn apply_template(
+    repo: &RepoContext,
+    id: &TemplateId,
+    scope: Scope,
+) -> anyhow::Result<Vec<String>> {
+    let _span = debug_span!("apply_template", template = %id, %scope).entered();
+    let template = find_template(id.as_str())?;
+    let path = scope_file_in(Some(repo), scope)?;
+    if scope == Scope::Local {
+        ignore::ensure_info_exclude_exists(&path)?;
+    }
+
+    struct Written(Vec<String>);
+    impl Observer for Written {
+        fn line_written(&mut self, _path: &Path, line: &str) {
+            self.0.push(line.to_string());
+        }
+    }
+    let mut written = Written(Vec::new());
+    apply_to_file_observed(&path, &template, &mut written)?;
+    Ok(written.0)
+}
+
+/// Fetch `url` with curl
+fn http_get(url: &str) -> anyhow::Result<String> {
+    let _span = debug_span!("template_download", %url).entered();
+    let output = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--max-time",
+            DOWNLOAD_TIMEOUT_SECS,
+            "--user-agent",
+            "git-ignore",
+            url,
+        ])
+        .output()
+        .context(Error::config(
+            "curl not found in PATH; it is needed to download templates",
+        ))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to download {url}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn paths() -> Vec<String> {
+        [
+            "Python.gitignore",
+            "Global/macOS.gitignore",
+            "community/Python/JupyterNotebooks.gitignore",
+            "community/Rust.gitignore",
+            "Rust.gitignore",
+        ]
+        .iter()
+        .map(|path| path.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_resolve_name() {
+        let paths = paths();
+        assert_eq!(resolve_name("rust", &paths), Some("Rust.gitignore"));
+        assert_eq!(
+            resolve_name("MACOS", &paths),
+            Some("Global/macOS.gitignore")
+        );
+        assert_eq!(
+            resolve_name("jupyternotebooks", &paths),
+            Some("community/Python/JupyterNotebooks.gitignore")
+        );
+        assert_eq!(resolve_name("ruby", &paths), None);
+
+        assert_eq!(
+            unknown_template_message("pyth", &paths),
+            "Unknown template 'pyth'; did you mean Python?"
+        );
+        assert_eq!(
+            unknown_template_message("ruby", &paths),
+            "Unknown template 'ruby' (see git-ignore template --list)"
+        );
+    }
+
+    #[test]
+    fn test_bundled_templates() {
+        for (path, content) in BUNDLED {
+            assert!(!content.trim().is_empty(), "{path} is empty");
+            assert_eq!(bundled_template(template_name(path)).unwrap().path, *path);
+        }
+        let template = bundled_template("macos").unwrap();
+        assert_eq!(template.name, "macOS");
+        assert!(template.content.contains(".DS_Store"));
+        assert!(bundled_template("Elixir").is_none());
+    }
+
+    #[test]
+    fn test_list_and_search() {
+        let listings = list_templates(&paths()).unwrap();
+        let rust = listings.iter().find(|l| l.name == "Rust").unwrap();
+        assert_eq!(rust.path, "Rust.gitignore");
+        assert!(rust.sources.starts_with(&[TemplateSource::Bundled]));
+        assert_eq!(rust.sources.last(), Some(&TemplateSource::Remote));
+        let notebooks = listings
+            .iter()
+            .find(|l| l.name == "JupyterNotebooks")
+            .unwrap();
+        assert_eq!(notebooks.sources.last(), Some(&TemplateSource::Remote));
+        let windows = listings.iter().find(|l| l.name == "Windows").unwrap();
+        assert_eq!(windows.sources[0], TemplateSource::Bundled);
+        assert_eq!(windows.path, "Global/Windows.gitignore");
+
+        let names: Vec<&str> = search(&listings, "PYTHON")
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect();
+        assert_eq!(names, ["JupyterNotebooks", "Python"]);
+        assert!(search(&listings, "global/")
+            .iter()
+            .all(|l| l.path.starts_with("Global/")));
+    }
+
+    #[test]
+    fn test_apply_to_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".gitignore");
+        std::fs::write(&path, "target\n").unwrap();
+        let template = Template {
+            name: "Rust".to_string(),
+            path: "Rust.gitignore".to_string(),
+            content: "# Build output\ntarget/\n\n# Backups\n**/*.rs.bk\n*.pdb\n*.pdb\n".to_string(),
+            source: TemplateSource::Bundled,
+        };
+
+        let applied = apply_to_file(&path, &template).unwrap();
+        assert!(applied.changed);
+        assert_eq!(applied.patterns, ["**/*.rs.bk", "*.pdb"]);
+        assert_eq!(applied.skipped, ["target/", "*.pdb"]);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "target\n\n# BEGIN git-ignore template: Rust\n# Build output\n\n# Backups\n**/*.rs.bk\n*.pdb\n# END git-ignore template: Rust\n"
+        );
+
+        // The block's own patterns don't count as duplicates next time
+        let applied = apply_to_file(&path, &template).unwrap();
+        assert!(!applied.changed);
+        assert_eq!(applied.patterns, ["**/*.rs.bk", "*.pdb"]);
+
+        #[derive(Default)]
+        struct Recorder(Vec<String>);
+        impl Observer for Recorder {
+            fn pattern_skipped(&mut self, pattern: &str, reason: SkipReason) {
+                self.0.push(format!("skipped {pattern} {reason:?}"));
+            }
+            fn line_written(&mut self, _path: &Path, line: &str) {
+                self.0.push(format!("wrote {line}"));
+            }
+            fn file_committed(&mut self, _path: &Path) {
+                self.0.push("committed".to_string());
+            }
+        }
+        let updated = Template {
+            content: format!("{}*.o\n", template.content),
+            ..template
+        };
+        let mut recorder = Recorder::default();
+        apply_to_file_observed(&path, &updated, &mut recorder).unwrap();
+        assert_eq!(
+            recorder.0,
+            [
+                "skipped target/ Duplicate",
+                "skipped *.pdb Duplicate",
+                "wrote *.o",
+                "committed"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_template() {
+        let dir = TempDir::new().unwrap();
+        std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(dir.path())
+            .status()
+            .unwrap();
+        let repo = RepoContext::discover(dir.path()).unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "Cargo.lock\n").unwrap();
+
+        // A bundled template, so no download is needed
+        let id = TemplateId::from("macos");
+        let added = apply_template(&repo, &id, Scope::Repo).unwrap();
+        assert!(added.contains(&".DS_Store".to_string()));
+        let content = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        assert!(content.contains("# BEGIN git-ignore template: macOS"));
+        assert!(apply_template(&repo, &id, Scope::Repo).unwrap().is_empty());
+
+        let added = apply_template(&repo, &id, Scope::Local).unwrap();
+        assert!(added.contains(&".DS_Store".to_string()));
+        let exclude = std::fs::read_to_string(repo.exclude_file_path()).unwrap();
+        assert!(exclude.contains("# BEGIN git-ignore template: macOS"));
+    }
+}
@@ -0,0 +1,553 @@
+//! Full-screen editor for the three ignore files, behind `git ignore tui`
+//!
+//! [`Editor`] shows the repository `.gitignore`, `.git/info/exclude` and the
+//! global gitignore side by side. Lines can be added, deleted and moved
+//! between scopes, and the work-tree files the pattern under the cursor
+//! matches are previewed as the cursor moves. Nothing touches the disk
+//! until [`Editor::save`], which writes every changed file or none.
+//!
+//! As with [`picker`](crate::picker), the caller owns the terminal: it
+//! feeds the keys [`decode_keys`](crate::keys::decode_keys) finds to
+//! [`Editor::handle`] and draws the
+//! lines [`Editor::render`] returns.
+
+use crate::{
+    ignore,
+    ignore_file::{classify_line, EntryKind},
+    keys::Key,
+    Pattern, Scope,
+};
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+/// Matching paths shown in the preview
+const PREVIEW_PATHS: usize = 6;
+
+/// One ignore file as edited in the TUI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeFile {
+    pub scope: Scope,
+    /// `None` when the scope has no file, like an unconfigured global
+    /// gitignore
+    pub path: Option<PathBuf>,
+    /// Content on disk when loaded; `None` if the file didn't exist
+    pub original: Option<String>,
+    pub lines: Vec<String>,
+}
+
+impl ScopeFile {
+    /// Read the file of `scope`; a missing file starts out empty
+    pub fn load(scope: Scope, path: Option<PathBuf>) -> anyhow::Result<ScopeFile> {
+        let original = match &path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to read: {}", path.display()))
+                }
+            },
+            None => None,
+        };
+        let lines = original
+            .as_deref()
+            .map(|content| content.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        Ok(ScopeFile {
+            scope,
+            path,
+            original,
+            lines,
+        })
+    }
+
+    /// The file content the edited lines make up
+    pub fn content(&self) -> String {
+        let newline = match &self.original {
+            Some(original) if original.contains("\r\n") => "\r\n",
+            _ => "\n",
+        };
+        let mut content = self.lines.join(newline);
+        if !self.lines.is_empty() {
+            content.push_str(newline);
+        }
+        content
+    }
+
+    /// Whether saving would change the file
+    pub fn is_modified(&self) -> bool {
+        match &self.original {
+            Some(original) => self.content() != *original,
+            None => !self.lines.is_empty(),
+        }
+    }
+}
+
+/// Work-tree paths a pattern matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub pattern: String,
+    /// Matching files, and directories (ending in `/`) the pattern
+    /// matches as a whole, in work-tree order
+    pub paths: Vec<String>,
+    /// Number of files matched, counting those inside matched directories
+    pub files: usize,
+}
+
+/// Match `pattern` against `files`, `/`-separated paths relative to the
+/// repository root. A file inside a directory the pattern matches counts,
+/// but the directory is listed instead of each of its files.
+pub fn preview(pattern: &Pattern, files: &[String]) -> Preview {
+    let mut paths: Vec<String> = Vec::new();
+    let mut count = 0;
+    for file in files {
+        let mut matched = None;
+        let mut end = 0;
+        for component in file.split('/') {
+            end += component.len();
+            let is_dir = end < file.len();
+            if pattern.matches(&file[..end], is_dir) {
+                matched = Some(if is_dir {
+                    format!("{}/", &file[..end])
+                } else {
+                    file.clone()
+                });
+                break;
+            }
+            end += 1;
+        }
+        if let Some(path) = matched {
+            count += 1;
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Preview {
+        pattern: pattern.to_string(),
+        paths,
+        files: count,
+    }
+}
+
+/// What the editor is doing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Browse,
+    /// Typing a pattern to add to the focused file
+    Adding(String),
+}
+
+/// What the caller should do after a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Continue,
+    /// Write the changes and leave
+    Save,
+    /// Leave without writing
+    Discard,
+}
+
+/// The three ignore files side by side
+#[derive(Debug, Clone)]
+pub struct Editor {
+    pub files: Vec<ScopeFile>,
+    /// Index into `files` of the focused column
+    pub focus: usize,
+    /// Cursor line in each file
+    pub cursors: Vec<usize>,
+    pub mode: Mode,
+    /// Feedback on the last action, shown until the next key
+    pub message: Option<String>,
+    work_tree: Vec<String>,
+}
+
+impl Editor {
+    /// Edit `files`, previewing matches against `work_tree`, the
+    /// repository's files relative to its root
+    pub fn new(files: Vec<ScopeFile>, work_tree: Vec<String>) -> Editor {
+        Editor {
+            cursors: vec![0; files.len()],
+            files,
+            focus: 0,
+            mode: Mode::Browse,
+            message: None,
+            work_tree,
+        }
+    }
+
+    /// React to one key
+    pub fn handle(&mut self, key: Key) -> Outcome {
+        self.message = None;
+        if key == Key::Interrupt {
+            return Outcome::Discard;
+        }
+        if let Mode::Adding(buffer) = &mut self.mode {
+            match key {
+                Key::Char(c) => buffer.push(c),
+                Key::Backspace => {
+                    buffer.pop();
+                }
+                Key::Escape => self.mode = Mode::Browse,
+                Key::Enter => {
+                    let pattern = buffer.trim().to_string();
+                    self.mode = Mode::Browse;
+                    if !pattern.is_empty() {
+                        self.add_line(self.focus, pattern);
+                    }
+                }
+                _ => {}
+            }
+            return Outcome::Continue;
+        }
+
+        let count = self.files.len();
+        match key {
+            Key::Up | Key::Char('k') => {
+                self.cursors[self.focus] = self.cursors[self.focus].saturating_sub(1)
+            }
+            Key::Down | Key::Char('j') => {
+                let last = self.files[self.focus].lines.len().saturating_sub(1);
+                self.cursors[self.focus] = (self.cursors[self.focus] + 1).min(last);
+            }
+            Key::Left | Key::Char('h') => self.focus = (self.focus + count - 1) % count,
+            Key::Right | Key::Tab | Key::Char('l') => self.focus = (self.focus + 1) % count,
+            Key::Char('a') => {
+                if self.files[self.focus].path.is_some() {
+                    self.mode = Mode::Adding(String::new());
+                } else {
+                    self.message = Some(format!(
+                        "No {} file configured",
+                        self.files[self.focus].scope
+                    ));
+                }
+            }
+            Key::Char('d') | Key::Delete => {
+                self.take_line();
+            }
+            Key::Char('<') | Key::Char('>') => {
+                let target = if key == Key::Char('<') {
+                    (self.focus + count - 1) % count
+                } else {
+                    (self.focus + 1) % count
+                };
+                if self.files[target].path.is_none() {
+                    self.message = Some(format!("No {} file configured", self.files[target].scope));
+                } else if let Some(line) = self.take_line() {
+                    self.add_line(target, line);
+                }
+            }
+            Key::Char('q') => return Outcome::Save,
+            Key::Escape | Key::Char('Q') => return Outcome::Discard,
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    /// Append `line` to file `index`, unless it's a pattern already there
+    fn add_line(&mut self, index: usize, line: String) {
+        let file = &mut self.files[index];
+        let is_pattern = classify_line(&line) == EntryKind::Pattern;
+        if is_pattern
+            && file
+                .lines
+                .iter()
+                .any(|existing| existing.trim() == line.trim())
+        {
+            self.message = Some(format!("{} already has {line}", file.scope));
+            return;
+        }
+        file.lines.push(line);
+        self.cursors[index] = file.lines.len() - 1;
+    }
+
+    /// Remove and return the line under the cursor in the focused file
+    fn take_line(&mut self) -> Option<String> {
+        let file = &mut self.files[self.focus];
+        let cursor = self.cursors[self.focus];
+        if cursor >= file.lines.len() {
+            return None;
+        }
+        let line = file.lines.remove(cursor);
+        self.cursors[self.focus] = cursor.min(file.lines.len().saturating_sub(1));
+        Some(line)
+    }
+
+    /// Paths matched by the pattern under the cursor, if it is a pattern
+    pub fn preview(&self) -> Option<Preview> {
+        let line = self.files[self.focus].lines.get(self.cursors[self.focus])?;
+        let pattern = Pattern::parse(line).ok()?;
+        Some(preview(&pattern, &self.work_tree))
+    }
+
+    /// Write every changed file. If one write fails, the files already
+    /// written get their old content back, so the change lands in full or
+    /// not at all. Returns the files written.
+    pub fn save(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut written: Vec<&ScopeFile> = Vec::new();
+        for file in self.files.iter().filter(|file| file.is_modified()) {
+            let Some(path) = &file.path else { continue };
+            if let Err(e) = ignore::write_ignore_file_if_unchanged(
+                path,
+                file.original.as_deref(),
+                &file.content(),
+            ) {
+                for done in written {
+                    if let Some(path) = &done.path {
+                        let _ = restore(path, done.original.as_deref());
+                    }
+                }
+                return Err(e);
+            }
+            written.push(file);
+        }
+        Ok(written
+            .iter()
+            .filter_map(|file| file.path.clone())
+            .collect())
+    }
+
+    /// The screen as `height` lines of at most `width` characters
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let columns = self.files.len().max(1);
+        let column_width = width.saturating_sub(3 * (columns - 1)) / columns;
+        // Header, separator, two preview lines and the status line
+        let rows = height.saturating_sub(5).max(1);
+
+        let mut lines = Vec::new();
+        let headers: Vec<String> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| {
+                let name = file
+                    .path
+                    .as_deref()
+                    .and_then(Path::file_name)
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "(not configured)".to_string());
+                let focus = if index == self.focus { "*" } else { " " };
+                let modified = if file.is_modified() {
+                    " [modified]"
+                } else {
+                    ""
+                };
+                fit(
+                    &format!("{focus}{} {name}{modified}", file.scope),
+                    column_width,
+                )
+            })
+            .collect();
+        lines.push(headers.join(" | "));
+
+        for row in 0..rows {
+            let cells: Vec<String> = self
+                .files
+                .iter()
+                .enumerate()
+                .map(|(index, file)| {
+                    let cursor = self.cursors[index];
+                    let start = cursor.saturating_sub(rows - 1);
+                    let Some(line) = file.lines.get(start + row) else {
+                        return fit("", column_width);
+                    };
+                    let marker = if index == self.focus && start + row == cursor {
+                        "> "
+                    } else {
+                        "  "
+                    };
+                    fit(&format!("{marker}{line}"), column_width)
+                })
+                .collect();
+            lines.push(cells.join(" | "));
+        }
+
+        lines.push("-".repeat(width));
+        match self.preview() {
+            Some(preview) if preview.files == 0 => {
+                lines.push(fit(&format!("{} matches no files", preview.pattern), width));
+                lines.push(String::new());
+            }
+            Some(preview) => {
+                lines.push(fit(
+                    &format!(
+                        "{} matches {} file{}:",
+                        preview.pattern,
+                        preview.files,
+                        if preview.files == 1 { "" } else { "s" }
+                    ),
+                    width,
+                ));
+                let shown: Vec<&str> = preview
+                    .paths
+                    .iter()
+                    .take(PREVIEW_PATHS)
+                    .map(String::as_str)
+                    .collect();
+                let more = preview.paths.len().saturating_sub(shown.len());
+                let mut list = format!("  {}", shown.join("  "));
+                if more > 0 {
+                    list.push_str(&format!("  (+{more} more)"));
+                }
+                lines.push(fit(&list, width));
+            }
+            None => lines.extend([String::new(), String::new()]),
+        }
+
+        let status = match (&self.mode, &self.message) {
+            (Mode::Adding(buffer), _) => format!(
+                "Add to {} (enter adds, esc cancels): {buffer}_",
+                self.files[self.focus].scope
+            ),
+            (Mode::Browse, Some(message)) => message.clone(),
+            (Mode::Browse, None) => {
+                "a add  d delete  < > move to scope  tab switch  q save and quit  esc discard"
+                    .to_string()
+            }
+        };
+        lines.push(fit(&status, width));
+        lines
+    }
+}
+
+/// Put back the content a file had before the editor saved it
+fn restore(path: &Path, original: Option<&str>) -> anyhow::Result<()> {
+    match original {
+        Some(content) => ignore::write_ignore_file(path, content),
+        None => std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove: {}", path.display())),
+    }
+}
+
+/// Pad or cut `text` to exactly `width` characters
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width).collect();
+    let len = fitted.chars().count();
+    fitted.extend(std::iter::repeat(' ').take(width - len));
+    fitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::decode_keys;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn scope_file(scope: Scope, path: &Path, content: Option<&str>) -> ScopeFile {
+        if let Some(content) = content {
+            fs::write(path, content).unwrap();
+        }
+        ScopeFile::load(scope, Some(path.to_path_buf())).unwrap()
+    }
+
+    fn type_keys(editor: &mut Editor, input: &str) -> Outcome {
+        let mut outcome = Outcome::Continue;
+        for key in decode_keys(input.as_bytes()) {
+            outcome = editor.handle(key);
+        }
+        outcome
+    }
+
+    #[test]
+    fn test_preview_lists_matched_directories_once() {
+        let files: Vec<String> = ["build/a.o", "build/b.o", "src/a.o", "src/main.c"]
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        let result = preview(&Pattern::parse("build/").unwrap(), &files);
+        assert_eq!(result.paths, vec!["build/"]);
+        assert_eq!(result.files, 2);
+        let result = preview(&Pattern::parse("*.o").unwrap(), &files);
+        assert_eq!(result.paths, vec!["build/a.o", "build/b.o", "src/a.o"]);
+        assert_eq!(result.files, 3);
+    }
+
+    #[test]
+    fn test_add_delete_and_move() {
+        let dir = TempDir::new().unwrap();
+        let mut editor = Editor::new(
+            vec![
+                scope_file(
+                    Scope::Repo,
+                    &dir.path().join("gitignore"),
+                    Some("*.log\ntarget/\n"),
+                ),
+                scope_file(Scope::Local, &dir.path().join("exclude"), None),
+                ScopeFile::load(Scope::Global, None).unwrap(),
+            ],
+            vec!["target/debug/x".to_string(), "a.log".to_string()],
+        );
+        assert_eq!(editor.preview().unwrap().paths, vec!["a.log"]);
+
+        // Move target/ to the exclude file, then add a pattern there
+        assert_eq!(type_keys(&mut editor, "j>"), Outcome::Continue);
+        assert_eq!(editor.files[0].lines, vec!["*.log"]);
+        assert_eq!(editor.files[1].lines, vec!["target/"]);
+        assert_eq!(editor.focus, 0);
+        type_keys(&mut editor, "\tanotes/\r");
+        assert_eq!(editor.files[1].lines, vec!["target/", "notes/"]);
+        assert_eq!(editor.preview().unwrap().files, 0);
+
+        // Duplicates are refused, and the global file isn't there to take lines
+        type_keys(&mut editor, "anotes/\r");
+        assert_eq!(editor.message.as_deref(), Some("local already has notes/"));
+        type_keys(&mut editor, ">");
+        assert_eq!(editor.message.as_deref(), Some("No global file configured"));
+        type_keys(&mut editor, "d");
+        assert_eq!(editor.files[1].lines, vec!["target/"]);
+
+        assert_eq!(type_keys(&mut editor, "q"), Outcome::Save);
+        let written = editor.save().unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("gitignore")).unwrap(),
+            "*.log\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("exclude")).unwrap(),
+            "target/\n"
+        );
+    }
+
+    #[test]
+    fn test_save_is_all_or_nothing() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("gitignore");
+        let exclude = dir.path().join("exclude");
+        let mut editor = Editor::new(
+            vec![
+                scope_file(Scope::Repo, &repo, Some("*.log\n")),
+                scope_file(Scope::Local, &exclude, Some("notes/\n")),
+            ],
+            Vec::new(),
+        );
+        type_keys(&mut editor, "d\td");
+
+        // Someone else edits the second file meanwhile
+        fs::write(&exclude, "notes/\nscratch/\n").unwrap();
+        let err = editor.save().unwrap_err();
+        assert!(err.to_string().contains("Concurrent edit conflict"));
+        assert_eq!(fs::read_to_string(&repo).unwrap(), "*.log\n");
+        assert_eq!(fs::read_to_string(&exclude).unwrap(), "notes/\nscratch/\n");
+    }
+
+    #[test]
+    fn test_render() {
+        let dir = TempDir::new().unwrap();
+        let mut editor = Editor::new(
+            vec![
+                scope_file(Scope::Repo, &dir.path().join(".gitignore"), Some("*.log\n")),
+                scope_file(Scope::Local, &dir.path().join("exclude"), None),
+            ],
+            vec!["a.log".to_string()],
+        );
+        type_keys(&mut editor, "abuild/\r");
+        let lines = editor.render(41, 10);
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], "*repo .gitignore [m |  local exclude     ");
+        assert_eq!(lines[1], "  *.log             |                    ");
+        assert_eq!(lines[2], "> build/            |                    ");
+        assert_eq!(lines[7], "build/ matches no files                  ");
+        assert!(lines[9].starts_with("a add  d delete"));
+    }
+}
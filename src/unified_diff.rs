@@ -0,0 +1,160 @@
+//! Unified diffs of ignore file content, as `diff -u` prints them
+
+/// Unchanged lines shown around each change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// The unified diff turning `old` into `new`, with `old_label` and
+/// `new_label` on the `---`/`+++` lines; empty when the two are equal
+pub(crate) fn unified_diff(old_label: &str, new_label: &str, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    // Op index ranges to print, each change with its context, merged
+    // where the contexts meet
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, _) in ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+    {
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + 1 + CONTEXT).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    for (start, end) in hunks {
+        let count =
+            |range: &[(Op, &str)], skip: Op| range.iter().filter(|(op, _)| *op != skip).count();
+        let old_before = count(&ops[..start], Op::Insert);
+        let new_before = count(&ops[..start], Op::Delete);
+        let old_len = count(&ops[start..end], Op::Insert);
+        let new_len = count(&ops[start..end], Op::Delete);
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_before, old_len),
+            hunk_range(new_before, new_len)
+        ));
+        for (op, line) in &ops[start..end] {
+            out.push(match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            });
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// `start,len` of a hunk as `diff -u` writes it: 1-based, the length left
+/// out when it is 1, and the line before the hunk for an empty one
+fn hunk_range(before: usize, len: usize) -> String {
+    match len {
+        0 => format!("{before},0"),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{len}", before + 1),
+    }
+}
+
+/// Line-by-line edit script from a longest common subsequence. Shared
+/// leading and trailing lines are matched first, so the usual edit (a
+/// few lines added to a long file) costs next to nothing.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j]: longest common subsequence of old_middle[i..], new_middle[j..]
+    let mut lcs = vec![vec![0usize; new_middle.len() + 1]; old_middle.len() + 1];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lcs[i][j] = if old_middle[i] == new_middle[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(Op, &str)> = old[..prefix]
+        .iter()
+        .map(|line| (Op::Equal, *line))
+        .collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            ops.push((Op::Equal, old_middle[i]));
+            i += 1;
+            j += 1;
+        } else if j == new_middle.len() || (i < old_middle.len() && lcs[i + 1][j] >= lcs[i][j + 1])
+        {
+            ops.push((Op::Delete, old_middle[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, new_middle[j]));
+            j += 1;
+        }
+    }
+    ops.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| (Op::Equal, *line)),
+    );
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(unified_diff("a", "b", "x\n", "x\n"), "");
+        assert_eq!(
+            unified_diff("/dev/null", ".gitignore", "", "*.log\n"),
+            "--- /dev/null\n+++ .gitignore\n@@ -0,0 +1 @@\n+*.log\n"
+        );
+
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\nTWO\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n";
+        assert_eq!(
+            unified_diff("a", "b", old, new),
+            "--- a\n+++ b\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+TWO\n 3\n 4\n 5\n\
+             @@ -10,3 +10,4 @@\n 10\n 11\n 12\n+13\n"
+        );
+
+        assert_eq!(
+            unified_diff("a", "b", "*.log", "*.log\ntarget/\n"),
+            "--- a\n+++ b\n@@ -1 +1,2 @@\n-*.log\n\\ No newline at end of file\n+*.log\n+target/\n"
+        );
+    }
+}
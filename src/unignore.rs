@@ -0,0 +1,194 @@
+//! Negation chains that re-include an ignored path
+//!
+//! Git doesn't look inside an excluded directory, so `!dir/sub/file` alone
+//! does nothing while `dir/` is ignored. Each excluded directory on the way
+//! down has to be re-included and its other contents excluded again:
+//!
+//! ```text
+//! !/dir/
+//! /dir/*
+//! !/dir/sub/
+//! /dir/sub/*
+//! !/dir/sub/file
+//! ```
+//!
+//! [`negation_chain`] builds that sequence, [`plan`] works out which
+//! directories need it, and [`unignore`] appends it to an ignore file and
+//! checks with git that the path really is no longer ignored.
+
+use crate::{
+    git::{self, IgnoreMatch},
+    ignore::{self, Placement},
+    pattern::escape_literal,
+    Error,
+};
+use anyhow::bail;
+use std::path::{Component, Path, PathBuf};
+
+/// Patterns that re-include `path` when the directories in `ignored_dirs`
+/// are excluded. Paths are relative to the repository root; `path` ends in
+/// `/` for a directory. Everything from the top-most ignored directory down
+/// is re-included and its other contents excluded again, so only `path`
+/// comes back.
+pub fn negation_chain(path: &str, ignored_dirs: &[String]) -> Vec<String> {
+    let trimmed = path.trim_end_matches('/');
+    let ancestors: Vec<&str> = trimmed
+        .match_indices('/')
+        .map(|(index, _)| &trimmed[..index])
+        .collect();
+
+    let mut chain = Vec::new();
+    if let Some(top) = ancestors.iter().position(|dir| {
+        ignored_dirs
+            .iter()
+            .any(|ignored| ignored.trim_end_matches('/') == *dir)
+    }) {
+        for dir in &ancestors[top..] {
+            let dir = escape_literal(dir);
+            chain.push(format!("!/{dir}/"));
+            chain.push(format!("/{dir}/*"));
+        }
+    }
+    chain.push(format!("!/{}", escape_literal(path)));
+    chain
+}
+
+/// The negations that would re-include `path` (relative to the current
+/// directory), or `None` if it isn't ignored. The second value is the rule
+/// that ignores it now.
+pub fn plan(path: &str) -> anyhow::Result<Option<(Vec<String>, IgnoreMatch)>> {
+    let relative = root_relative(path)?;
+    let repo_root = git::get_repo_root()?;
+
+    // The path itself, then each directory above it
+    let mut queries = vec![relative.clone()];
+    let trimmed = relative.trim_end_matches('/');
+    queries.extend(
+        trimmed
+            .match_indices('/')
+            .map(|(index, _)| format!("{}/", &trimmed[..index])),
+    );
+    let absolute: Vec<String> = queries
+        .iter()
+        .map(|query| repo_root.join(query).to_string_lossy().into_owned())
+        .collect();
+    let checks = git::check_ignore(&absolute, true)?;
+
+    let Some(matched) = checks
+        .first()
+        .filter(|check| check.is_ignored())
+        .and_then(|check| check.matched.clone())
+    else {
+        return Ok(None);
+    };
+    let ignored_dirs: Vec<String> = queries
+        .iter()
+        .zip(&checks)
+        .skip(1)
+        .filter(|(_, check)| check.is_ignored())
+        .map(|(dir, _)| dir.clone())
+        .collect();
+    Ok(Some((negation_chain(&relative, &ignored_dirs), matched)))
+}
+
+/// Append the negations that re-include `path` to `file`, then ask git
+/// whether it worked. If the path is still ignored, typically because a
+/// file git reads with higher precedence ignores it, `file` is put back as
+/// it was and the rule in the way is reported.
+///
+/// Returns the patterns added; `None` if the path wasn't ignored.
+pub fn unignore(path: &str, file: &Path) -> anyhow::Result<Option<Vec<String>>> {
+    let Some((chain, _)) = plan(path)? else {
+        return Ok(None);
+    };
+    let original = match std::fs::read_to_string(file) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let added = ignore::add_patterns_with_placement(file, &chain, true, Placement::Append)?;
+
+    if let Some((_, still)) = plan(path)? {
+        match original {
+            Some(content) => ignore::write_ignore_file(file, &content)?,
+            None => std::fs::remove_file(file)?,
+        }
+        bail!(Error::check_failed(format!("{path} is still ignored by {} ({}:{}); a negation in {} can't override it, so nothing was changed",
+            still.pattern,
+            still.source.display(),
+            still.line_number,
+            file.display())));
+    }
+    Ok(Some(added))
+}
+
+/// `path`, relative to the current directory, as a `/`-separated path
+/// relative to the repository root, keeping a trailing `/`
+pub(crate) fn root_relative(path: &str) -> anyhow::Result<String> {
+    let repo_root = git::get_repo_root()?;
+    let mut absolute = PathBuf::new();
+    for component in git::current_dir()?.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            Component::CurDir => {}
+            other => absolute.push(other),
+        }
+    }
+    let Ok(relative) = absolute.strip_prefix(&repo_root) else {
+        bail!("{path} is outside the repository ({})", repo_root.display());
+    };
+    let mut relative: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if relative.is_empty() {
+        bail!("The repository root is never ignored");
+    }
+    if path.ends_with('/') {
+        relative.last_mut().unwrap().push('/');
+    }
+    Ok(relative.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negation_chain() {
+        assert_eq!(negation_chain("debug.log", &[]), vec!["!/debug.log"]);
+        assert_eq!(
+            negation_chain(
+                "build/gen/keep.txt",
+                &["build/".to_string(), "build/gen/".to_string()]
+            ),
+            vec![
+                "!/build/",
+                "/build/*",
+                "!/build/gen/",
+                "/build/gen/*",
+                "!/build/gen/keep.txt"
+            ]
+        );
+        // Directories above the top-most ignored one are left alone
+        assert_eq!(
+            negation_chain("src/out/", &["src/out/".to_string()]),
+            vec!["!/src/out/"]
+        );
+        assert_eq!(
+            negation_chain(
+                "src/out/a/b.o",
+                &["src/out/".to_string(), "src/out/a/".to_string()]
+            ),
+            vec![
+                "!/src/out/",
+                "/src/out/*",
+                "!/src/out/a/",
+                "/src/out/a/*",
+                "!/src/out/a/b.o"
+            ]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Validation rules supplied by the application using the library
+//!
+//! A [`RuleSet`] holds rules that run after the built-in checks. Hand it to
+//! an [`IgnoreManager`](crate::IgnoreManager) with
+//! [`rules`](crate::IgnoreManager::rules), to
+//! [`validate_ignore_patterns_with`] or to
+//! [`lint_content_with`](crate::lint::lint_content_with). Issues the rules
+//! raise carry [`PatternRule::Custom`] with the rule's id, so reports and
+//! SARIF output name them like the built-in rules.
+//!
+//! ```
+//! use git_ignore_tool::{ignore::validate_ignore_patterns_with, validation::RuleSet, PatternSeverity};
+//!
+//! // Private keys must never be re-included
+//! let rules = RuleSet::new().with_fn("ORG001", "Never un-ignore private keys", |pattern| {
+//!     (pattern.starts_with('!') && pattern.ends_with(".pem")).then(|| {
+//!         (PatternSeverity::Error, "Private keys must stay ignored".to_string())
+//!     })
+//! });
+//! let issues = validate_ignore_patterns_with(&["!deploy.pem".to_string()], &rules);
+//! assert_eq!(issues[0].rule.id(), "ORG001");
+//! ```
+//!
+//! [`validate_ignore_patterns_with`]: crate::ignore::validate_ignore_patterns_with
+
+use crate::{PatternIssue, PatternRule, PatternSeverity};
+use std::{borrow::Cow, fmt, sync::Arc};
+
+/// A check run on every pattern alongside the built-in ones
+pub trait ValidationRule: Send + Sync {
+    /// Stable identifier, e.g. `ORG001`; adding another rule with the same
+    /// id to a [`RuleSet`] replaces this one
+    fn id(&self) -> &str;
+
+    /// Short kebab-case name; the id unless overridden
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    /// One-sentence description of what the rule checks
+    fn description(&self) -> &str;
+
+    /// The severity and message of the problem with `pattern`, if any.
+    /// `pattern` is sanitized as it would be written.
+    fn check(&self, pattern: &str) -> Option<(PatternSeverity, String)>;
+}
+
+/// [`ValidationRule`] made from a closure by [`RuleSet::with_fn`]
+struct FnRule<F> {
+    id: Cow<'static, str>,
+    description: Cow<'static, str>,
+    check: F,
+}
+
+impl<F> ValidationRule for FnRule<F>
+where
+    F: Fn(&str) -> Option<(PatternSeverity, String)> + Send + Sync,
+{
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn check(&self, pattern: &str) -> Option<(PatternSeverity, String)> {
+        (self.check)(pattern)
+    }
+}
+
+/// Application rules, in the order they were added. Cloning is cheap; the
+/// rules themselves are shared.
+#[derive(Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Arc<dyn ValidationRule>>,
+}
+
+impl fmt::Debug for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.rules.iter().map(|rule| rule.id()))
+            .finish()
+    }
+}
+
+impl RuleSet {
+    /// An empty set: only the built-in checks run
+    pub fn new() -> RuleSet {
+        RuleSet::default()
+    }
+
+    /// Add `rule`, replacing any rule with the same id
+    pub fn add(&mut self, rule: impl ValidationRule + 'static) {
+        self.rules.retain(|existing| existing.id() != rule.id());
+        self.rules.push(Arc::new(rule));
+    }
+
+    /// [`add`](RuleSet::add), builder style
+    pub fn with(mut self, rule: impl ValidationRule + 'static) -> RuleSet {
+        self.add(rule);
+        self
+    }
+
+    /// [`with`](RuleSet::with) a closure as the check
+    pub fn with_fn(
+        self,
+        id: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        check: impl Fn(&str) -> Option<(PatternSeverity, String)> + Send + Sync + 'static,
+    ) -> RuleSet {
+        self.with(FnRule {
+            id: id.into(),
+            description: description.into(),
+            check,
+        })
+    }
+
+    /// Drop the rule with `id`; returns whether there was one
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|rule| rule.id() != id);
+        self.rules.len() != before
+    }
+
+    /// The rules, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = &dyn ValidationRule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// The rule with `id`
+    pub fn get(&self, id: &str) -> Option<&dyn ValidationRule> {
+        self.iter().find(|rule| rule.id() == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Issues the rules raise for the sanitized `pattern`
+    pub(crate) fn check(&self, pattern: &str) -> Vec<PatternIssue> {
+        self.iter()
+            .filter_map(|rule| {
+                let (severity, message) = rule.check(pattern)?;
+                Some(PatternIssue {
+                    pattern: pattern.to_string(),
+                    severity,
+                    message,
+                    rule: PatternRule::Custom(Cow::Owned(rule.id().to_string())),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::{validate_ignore_patterns, validate_ignore_patterns_with};
+
+    struct NoLogs;
+
+    impl ValidationRule for NoLogs {
+        fn id(&self) -> &str {
+            "TEST002"
+        }
+
+        fn name(&self) -> &str {
+            "no-logs"
+        }
+
+        fn description(&self) -> &str {
+            "Log files are handled elsewhere"
+        }
+
+        fn check(&self, pattern: &str) -> Option<(PatternSeverity, String)> {
+            (pattern == "debug.log").then(|| (PatternSeverity::Warning, "Not here".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_rules_run_with_builtin_checks() {
+        let mut rules = RuleSet::new()
+            .with_fn("TEST001", "No re-included keys", |pattern| {
+                (pattern.starts_with('!') && pattern.ends_with(".pem"))
+                    .then(|| (PatternSeverity::Error, "Keys stay ignored".to_string()))
+            })
+            .with(NoLogs);
+        let patterns = ["!deploy.pem".to_string(), "debug.log".to_string()];
+
+        let issues = validate_ignore_patterns_with(&patterns, &rules);
+        let found: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|issue| (issue.rule.id(), issue.pattern.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [("TEST001", "!deploy.pem"), ("TEST002", "debug.log")]
+        );
+        assert_eq!(rules.get("TEST002").unwrap().name(), "no-logs");
+        assert_eq!(
+            rules.get("TEST001").unwrap().description(),
+            "No re-included keys"
+        );
+
+        // Other validations don't see the set
+        assert!(validate_ignore_patterns(&patterns).is_empty());
+
+        assert!(rules.remove("TEST001"));
+        assert!(!rules.remove("TEST001"));
+        assert_eq!(validate_ignore_patterns_with(&patterns, &rules).len(), 1);
+    }
+
+    #[test]
+    fn test_adding_same_id_replaces_rule() {
+        let rules = RuleSet::new()
+            .with_fn("TEST003", "First", |_| None)
+            .with_fn(String::from("TEST003"), "Second", |_| None);
+        assert_eq!(rules.iter().count(), 1);
+        assert_eq!(rules.get("TEST003").unwrap().description(), "Second");
+    }
+}
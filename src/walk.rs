@@ -0,0 +1,220 @@
+//! Lazy traversal of the paths a repository's ignore rules exclude
+//!
+//! [`IgnoredWalk`] visits the work tree one directory at a time, applying
+//! the global excludes file, `.git/info/exclude` and every `.gitignore` on
+//! the way down, and yields each ignored path with the rule responsible.
+//! Ignored directories are reported once and not descended into, just as
+//! git stops at an excluded directory.
+//!
+//! ```no_run
+//! use git_ignore_tool::walk::IgnoredWalk;
+//!
+//! for entry in IgnoredWalk::new()? {
+//!     let entry = entry?;
+//!     println!("{} ({}:{})", entry.path.display(), entry.rule.source.display(), entry.rule.line_number);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::{
+    rules::{self, Rule, RuleStack},
+    RepoContext,
+};
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+/// A path excluded by the ignore rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgnoredEntry {
+    /// Path relative to the walk root
+    pub path: PathBuf,
+    /// Whether the path is a directory (whose contents are not visited)
+    pub is_dir: bool,
+    /// The rule that excludes the path
+    pub rule: Rule,
+}
+
+/// Iterator over ignored paths in a work tree.
+///
+/// Only ignore rules are considered: tracked files that happen to match a
+/// pattern are reported too, although git itself does not ignore them.
+/// Nested repositories and symbolic links are not followed.
+pub struct IgnoredWalk {
+    root: PathBuf,
+    stack: Vec<Frame>,
+    /// Rules to apply to the root directory, consumed on first use
+    pending_root: Option<RuleStack>,
+}
+
+struct Frame {
+    /// Directory relative to the root, `/`-separated
+    dir: String,
+    /// Remaining entries, in reverse name order
+    entries: Vec<(String, bool)>,
+    rules: RuleStack,
+}
+
+impl IgnoredWalk {
+    /// Walk the work tree of the current repository
+    pub fn new() -> anyhow::Result<IgnoredWalk> {
+        IgnoredWalk::in_repo(RepoContext::current()?)
+    }
+
+    /// Walk the work tree of `repo`
+    pub fn in_repo(repo: &RepoContext) -> anyhow::Result<IgnoredWalk> {
+        let mut base_files = Vec::new();
+        if let Some(excludes_file) = repo.excludes_file()? {
+            base_files.push(excludes_file);
+        }
+        base_files.push(repo.exclude_file_path());
+        IgnoredWalk::with_base_files(repo.root()?, &base_files)
+    }
+
+    /// Walk `root` with `.gitignore` files plus the given repository-wide
+    /// ignore files, listed from lowest to highest precedence
+    pub fn with_base_files(root: &Path, base_files: &[PathBuf]) -> anyhow::Result<IgnoredWalk> {
+        let mut rules = RuleStack::default();
+        for file in base_files {
+            rules = rules.push(rules::read_rules(file, "")?);
+        }
+        Ok(IgnoredWalk {
+            root: root.to_path_buf(),
+            stack: Vec::new(),
+            pending_root: Some(rules),
+        })
+    }
+
+    /// Start visiting directory `dir`, loading its `.gitignore`
+    fn enter(&mut self, dir: String, parent_rules: &RuleStack) -> anyhow::Result<()> {
+        let absolute = self.root.join(&dir);
+        let rules = parent_rules.push(rules::read_rules(&absolute.join(".gitignore"), &dir)?);
+
+        let mut entries = Vec::new();
+        let read_dir = std::fs::read_dir(&absolute)
+            .with_context(|| format!("Failed to read directory: {}", absolute.display()))?;
+        for entry in read_dir {
+            let entry = entry
+                .with_context(|| format!("Failed to read directory: {}", absolute.display()))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == ".git" {
+                continue;
+            }
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            entries.push((name, is_dir));
+        }
+        entries.sort_by(|a, b| b.0.cmp(&a.0));
+
+        self.stack.push(Frame {
+            dir,
+            entries,
+            rules,
+        });
+        Ok(())
+    }
+}
+
+impl Iterator for IgnoredWalk {
+    type Item = anyhow::Result<IgnoredEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(rules) = self.pending_root.take() {
+            if let Err(e) = self.enter(String::new(), &rules) {
+                return Some(Err(e));
+            }
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+            let Some((name, is_dir)) = frame.entries.pop() else {
+                self.stack.pop();
+                continue;
+            };
+
+            let path = if frame.dir.is_empty() {
+                name
+            } else {
+                format!("{}/{name}", frame.dir)
+            };
+
+            if let Some(rule) = frame.rules.decide(&path, is_dir) {
+                if !rule.pattern.negated {
+                    return Some(Ok(IgnoredEntry {
+                        path: PathBuf::from(&path),
+                        is_dir,
+                        rule: rule.clone(),
+                    }));
+                }
+            }
+
+            // Nested repositories manage their own ignore rules
+            if is_dir && !self.root.join(&path).join(".git").exists() {
+                let rules = frame.rules.clone();
+                if let Err(e) = self.enter(path, &rules) {
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn collect(walk: IgnoredWalk) -> Vec<(String, String)> {
+        walk.map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.path.to_string_lossy().replace('\\', "/"),
+                entry.rule.pattern.to_string(),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_walk_reports_ignored_paths_with_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join(".gitignore"), "*.log\n!keep.log\ntarget/\n").unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("target/debug/app"), "").unwrap();
+        fs::write(root.join("a.log"), "").unwrap();
+        fs::write(root.join("keep.log"), "").unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/.gitignore"), "generated.rs\n").unwrap();
+        fs::write(root.join("src/generated.rs"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/trace.log"), "").unwrap();
+
+        let walk = IgnoredWalk::with_base_files(root, &[]).unwrap();
+        assert_eq!(
+            collect(walk),
+            vec![
+                ("a.log".to_string(), "*.log".to_string()),
+                ("src/generated.rs".to_string(), "generated.rs".to_string()),
+                ("src/trace.log".to_string(), "*.log".to_string()),
+                ("target".to_string(), "target/".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_base_files_and_git_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".git/ignored-looking.tmp"), "").unwrap();
+        fs::write(root.join("notes.tmp"), "").unwrap();
+        fs::write(root.join("exclude"), "*.tmp\n").unwrap();
+
+        let walk = IgnoredWalk::with_base_files(root, &[root.join("exclude")]).unwrap();
+        assert_eq!(
+            collect(walk),
+            vec![("notes.tmp".to_string(), "*.tmp".to_string())]
+        );
+    }
+}
@@ -40,6 +40,51 @@ fn test_help_output() {
         .stdout(predicate::str::contains("Usage: git-ignore"));
 }
 
+#[test]
+fn test_add_subcommand_and_bare_alias() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args(["add", "--sorted", "list"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    git_ignore_cmd()
+        .args(["build/", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    git_ignore_cmd()
+        .args(["--local", "add", "notes.txt"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "list\n\nbuild/\n*.log\n"
+    );
+
+    // Global options may precede any subcommand
+    git_ignore_cmd()
+        .args(["--local", "list", "--color", "never"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("    notes.txt\n"));
+
+    git_ignore_cmd()
+        .args(["--sorted", "list"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--sorted only applies to add, not to list",
+        ));
+
+    Ok(())
+}
+
 #[test]
 fn test_version_output() {
     let expected_version = env!("CARGO_PKG_VERSION");
@@ -191,9 +236,7 @@ fn test_outside_git_repo() -> Result<(), Box<dyn std::error::Error>> {
         .assert()
         .failure()
         .code(2)
-        .stderr(predicate::str::contains(
-            "Git error while determining target file",
-        ));
+        .stderr(predicate::str::starts_with("Not in a git repository"));
 
     Ok(())
 }
@@ -358,3 +401,3433 @@ fn test_local_exclude_file_in_worktree_is_respected_by_git_status(
 
     Ok(())
 }
+
+#[test]
+fn test_local_exclude_in_submodule() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let lib_repo = temp_dir.path().join("lib");
+    fs::create_dir(&lib_repo)?;
+    init_git_repo(&lib_repo)?;
+    fs::write(lib_repo.join("README.md"), "lib")?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&lib_repo)
+        .output()?;
+    Command::new("git")
+        .args(["commit", "-m", "initial commit"])
+        .current_dir(&lib_repo)
+        .output()?;
+
+    let main_repo = temp_dir.path().join("main");
+    fs::create_dir(&main_repo)?;
+    init_git_repo(&main_repo)?;
+    let submodule_output = Command::new("git")
+        .args([
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "../lib",
+            "lib",
+        ])
+        .current_dir(&main_repo)
+        .output()?;
+    assert!(
+        submodule_output.status.success(),
+        "submodule add failed: {}",
+        String::from_utf8_lossy(&submodule_output.stderr)
+    );
+
+    // The submodule's .git is a gitdir pointer file; info/exclude lives
+    // under the superproject's .git/modules directory.
+    let submodule_dir = main_repo.join("lib");
+    git_ignore_cmd()
+        .args(["--local", "*.tmp"])
+        .current_dir(&submodule_dir)
+        .assert()
+        .success();
+
+    let exclude = main_repo
+        .join(".git")
+        .join("modules")
+        .join("lib")
+        .join("info")
+        .join("exclude");
+    assert!(fs::read_to_string(&exclude)?.ends_with("*.tmp\n"));
+
+    fs::write(submodule_dir.join("a.tmp"), "scratch")?;
+    let status_output = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(&submodule_dir)
+        .output()?;
+    let status = String::from_utf8_lossy(&status_output.stdout);
+    assert!(
+        !status.contains("a.tmp"),
+        "expected a.tmp to be ignored by git status, got: {status}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_directory_flag() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path().join("repo");
+    fs::create_dir_all(repo.join("sub"))?;
+    init_git_repo(&repo)?;
+    fs::write(repo.join("sub").join("a.log"), "log")?;
+
+    git_ignore_cmd()
+        .args(["-C", "repo", "add", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(repo.join(".gitignore"))?, "*.log\n");
+
+    // Repeated -C flags nest, and paths are taken from the last one
+    git_ignore_cmd()
+        .args(["-C", "repo", "-C", "sub", "check", "a.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("a.log: ignored by *.log"));
+
+    git_ignore_cmd()
+        .args(["-C", "missing", "list"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing"));
+
+    Ok(())
+}
+
+#[test]
+fn test_json_log_format() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args(["--log-format", "json", "*.pyc"])
+        .env("RUST_LOG", "debug")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(r#""name":"git_command""#))
+        .stderr(predicate::str::contains(r#""message":"patterns written""#));
+
+    Ok(())
+}
+
+#[test]
+fn test_check_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args(["--check", "*.pyc"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .code(5)
+        .stdout(predicate::str::contains(
+            "Would add 1 pattern to .gitignore (",
+        ))
+        .stderr(predicate::str::contains("Check failed"));
+    assert!(!temp_dir.path().join(".gitignore").exists());
+
+    git_ignore_cmd()
+        .args(["*.pyc"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    git_ignore_cmd()
+        .args(["--check", "*.pyc"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("is up to date"));
+
+    Ok(())
+}
+
+#[test]
+fn test_sorted_placement_from_flag_and_config() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore_path = temp_dir.path().join(".gitignore");
+    fs::write(&gitignore_path, "a.log\nc.log\n")?;
+
+    git_ignore_cmd()
+        .args(["--sorted", "b.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&gitignore_path)?,
+        "a.log\nb.log\nc.log\n"
+    );
+
+    Command::new("git")
+        .args(["config", "ignore.sorted", "true"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    git_ignore_cmd()
+        .args(["0.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&gitignore_path)?,
+        "0.log\na.log\nb.log\nc.log\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_stdin_null_delimited() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore_path = temp_dir.path().join(".gitignore");
+
+    assert_cmd::Command::cargo_bin("git-ignore")?
+        .args(["--stdin", "-z", "extra/"])
+        .current_dir(temp_dir.path())
+        .write_stdin("a b.txt\0*.tmp\0")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&gitignore_path)?,
+        "extra/\na b.txt\n\\*.tmp\n"
+    );
+
+    assert_cmd::Command::cargo_bin("git-ignore")?
+        .args(["--stdin", "--null"])
+        .current_dir(temp_dir.path())
+        .write_stdin("bad\nname\0")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("contains a line break"));
+
+    assert_cmd::Command::cargo_bin("git-ignore")?
+        .arg("--stdin")
+        .current_dir(temp_dir.path())
+        .write_stdin("one\ntwo\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added 2 patterns"));
+
+    git_ignore_cmd()
+        .args(["--null", "x"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_why_explains_pattern() {
+    git_ignore_cmd()
+        .args(["why", "/build/"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Anchored by the leading `/`"))
+        .stdout(predicate::str::contains("directories only"))
+        .stdout(predicate::str::contains(
+            "Matches, for example:\n  build/\n",
+        ))
+        .stdout(predicate::str::contains(
+            "Does not match:\n  build (a file)\n",
+        ));
+
+    git_ignore_cmd()
+        .args(["why", "!*.log"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Negated"))
+        .stdout(predicate::str::contains("re-included, not ignored"));
+
+    git_ignore_cmd()
+        .args(["why", "# comment"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_why_path_decision_chain() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    fs::create_dir_all(home.path().join(".config/git"))?;
+    fs::write(home.path().join(".config/git/ignore"), "*.log\n")?;
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), "!keep.log\nbuild/\n")?;
+    fs::create_dir_all(temp_dir.path().join("src"))?;
+    fs::write(temp_dir.path().join("src/.gitignore"), "keep.log\n")?;
+
+    git_ignore_cmd()
+        .args(["why", "--path", "keep.log"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path().join("src"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/keep.log: ignored by keep.log (src/.gitignore:1)\n",
+        ))
+        // The global file lies outside the repository and keeps its full path
+        .stdout(predicate::str::contains(
+            "/.config/git/ignore:1: *.log ignores src/keep.log\n  2. .gitignore:1: !keep.log re-includes src/keep.log\n  3. src/.gitignore:1: keep.log ignores src/keep.log (decides)\n",
+        ));
+
+    git_ignore_cmd()
+        .args(["why", "--path", "build/out/app.o"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "build/out/app.o: ignored, because its directory build/ is excluded by build/ (.gitignore:2)\n  1. .gitignore:2: build/ ignores build/ (decides)\n",
+        ));
+
+    git_ignore_cmd()
+        .args(["why", "--path", "README.md"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("README.md: not ignored, no pattern matches\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_remove_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore_path = temp_dir.path().join(".gitignore");
+    fs::write(&gitignore_path, "target/\n*.log\nnode_modules/\n")?;
+
+    git_ignore_cmd()
+        .args(["remove", "*.log", "dist/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Removed 1 pattern from .gitignore",
+        ))
+        .stdout(predicate::str::contains("*.log (line 2)"))
+        .stderr(predicate::str::contains("Not found: dist/"));
+    assert_eq!(
+        fs::read_to_string(&gitignore_path)?,
+        "target/\nnode_modules/\n"
+    );
+
+    git_ignore_cmd()
+        .args(["remove", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No matching patterns"));
+
+    Ok(())
+}
+
+#[test]
+fn test_remove_template() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore_path = temp_dir.path().join(".gitignore");
+    fs::write(
+        &gitignore_path,
+        "*.log\n\n# BEGIN git-ignore template: Rust\ntarget/\n**/*.rs.bk\n# END git-ignore template: Rust\n\nscratch/\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["remove-template", "Rust"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Removed template 'Rust' from .gitignore",
+        ));
+    assert_eq!(fs::read_to_string(&gitignore_path)?, "*.log\n\nscratch/\n");
+
+    git_ignore_cmd()
+        .args(["remove-template", "Rust"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Template 'Rust' not found"));
+
+    Ok(())
+}
+
+#[test]
+fn test_list_groups_by_file_and_section() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "# Build output\ntarget/\n*.o\n\n*.log\n",
+    )?;
+    fs::create_dir(temp_dir.path().join("docs"))?;
+    fs::write(temp_dir.path().join("docs/.gitignore"), "_build/\n")?;
+
+    git_ignore_cmd()
+        .args(["list", "--color", "never"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("): 3 patterns"))
+        .stdout(predicate::str::contains(
+            "  # Build output (2 patterns)\n    target/\n    *.o\n",
+        ))
+        .stdout(predicate::str::contains(
+            "  (no section) (1 pattern)\n    *.log\n",
+        ))
+        .stdout(predicate::str::contains("docs/.gitignore ("))
+        .stdout(predicate::str::contains("    _build/\n"))
+        .stdout(predicate::str::contains("\x1b[").not());
+
+    git_ignore_cmd()
+        .args(["list", "--color", "always"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[33m*\x1b[0m.o"));
+
+    Ok(())
+}
+
+#[test]
+fn test_list_line_numbers_and_all_scopes() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    fs::create_dir_all(home.path().join(".config/git"))?;
+    fs::write(home.path().join(".config/git/ignore"), ".DS_Store\n")?;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "# Build output\ntarget/\n\n\n\n\n\n\n\n\n*.log\n",
+    )?;
+    fs::write(temp_dir.path().join(".git/info/exclude"), "notes.txt\n")?;
+
+    git_ignore_cmd()
+        .args(["list", "--color", "never", "-n"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  # Build output (1 pattern)\n     2: target/\n",
+        ))
+        .stdout(predicate::str::contains("    11: *.log\n"));
+
+    git_ignore_cmd()
+        .args(["list", "--color", "never", "--all"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("    target/\n"))
+        .stdout(predicate::str::contains("    notes.txt\n"))
+        .stdout(predicate::str::contains("    .DS_Store\n"));
+
+    // A missing global file is reported without hiding the other scopes
+    fs::remove_file(home.path().join(".config/git/ignore"))?;
+    git_ignore_cmd()
+        .args(["list", "--color", "never", "--all"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("    notes.txt\n"))
+        .stdout(predicate::str::contains("global gitignore: not configured"));
+
+    git_ignore_cmd()
+        .args(["list", "--all", "--local"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--all already lists every scope"));
+
+    Ok(())
+}
+
+#[test]
+fn test_check_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "# Logs\n*.log\n!keep.log\n",
+    )?;
+    fs::write(temp_dir.path().join(".git/info/exclude"), "notes/\n")?;
+
+    git_ignore_cmd()
+        .args(["check", "debug.log", "keep.log", "notes/", "src/main.rs"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stdout(predicate::str::contains(
+            "debug.log: ignored by *.log (.gitignore:2, repo)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "keep.log: not ignored, re-included by !keep.log (.gitignore:3, repo)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "notes/: ignored by notes/ (.git/info/exclude:1, local)\n",
+        ))
+        .stdout(predicate::str::contains("src/main.rs: not ignored\n"))
+        .stderr(predicate::str::contains("2 of 4 path(s) not ignored"));
+
+    git_ignore_cmd()
+        .args(["check", "--quiet", "debug.log", "notes/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    // Tracked files are never ignored, whatever the patterns say
+    fs::write(temp_dir.path().join("tracked.log"), "")?;
+    Command::new("git")
+        .args(["add", "-f", "tracked.log"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    git_ignore_cmd()
+        .args(["check", "tracked.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5);
+    git_ignore_cmd()
+        .args(["check", "--no-index", "tracked.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[test]
+fn test_interactive_modes_need_terminal() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .arg("--interactive")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing selected"));
+
+    fs::write(temp_dir.path().join("debug.log"), "")?;
+    git_ignore_cmd()
+        .args(["add", "-i"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("--interactive needs a terminal"));
+    git_ignore_cmd()
+        .arg("tui")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("tui needs a terminal"));
+    git_ignore_cmd()
+        .args(["-i", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+fn test_move_between_scopes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let exclude = temp_dir.path().join(".git/info/exclude");
+    fs::write(&exclude, "build/\nnotes.txt\n")?;
+    fs::write(temp_dir.path().join(".gitignore"), "*.log\n")?;
+
+    git_ignore_cmd()
+        .args(["move", "--from", "local", "--to", "gitignore", "build/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Moved 1 pattern from .git/info/exclude",
+        ))
+        .stdout(predicate::str::contains("  build/ (line 1)\n"));
+    assert_eq!(fs::read_to_string(&exclude)?, "notes.txt\n");
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "*.log\n\nbuild/\n"
+    );
+
+    // Nothing changes unless every pattern can be moved
+    git_ignore_cmd()
+        .args([
+            "move",
+            "--from",
+            "local",
+            "--to",
+            "repo",
+            "notes.txt",
+            "missing",
+        ])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Pattern not found"));
+    assert_eq!(fs::read_to_string(&exclude)?, "notes.txt\n");
+
+    git_ignore_cmd()
+        .args([
+            "move",
+            "--local",
+            "--from",
+            "local",
+            "--to",
+            "repo",
+            "notes.txt",
+        ])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--from and --to"));
+    git_ignore_cmd()
+        .args(["move", "--from", "repo", "--to", "repo", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("same file"));
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_ignore_file() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore = temp_dir.path().join(".gitignore");
+    fs::write(&gitignore, "# Build\nbuild/\n/docs/api\n")?;
+    fs::write(
+        temp_dir.path().join("other.gitignore"),
+        "# Output\nbuild\ndocs/api/\n*.o\n\n# Logs\n*.log\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["merge", "--dry-run", "other.gitignore"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would merge 2 patterns"));
+    assert_eq!(
+        fs::read_to_string(&gitignore)?,
+        "# Build\nbuild/\n/docs/api\n"
+    );
+
+    git_ignore_cmd()
+        .args(["merge", "other.gitignore"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Merged 2 patterns"))
+        .stdout(predicate::str::contains("Skipped build (same as build/)"))
+        .stdout(predicate::str::contains(
+            "Skipped docs/api/ (same as /docs/api)",
+        ));
+    assert_eq!(
+        fs::read_to_string(&gitignore)?,
+        "# Build\nbuild/\n/docs/api\n\n# Output\n*.o\n\n# Logs\n*.log\n"
+    );
+
+    git_ignore_cmd()
+        .args(["merge", "other.gitignore"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("already has every pattern"));
+
+    git_ignore_cmd()
+        .args(["merge", "missing.gitignore"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(4);
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_ignore_sources() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let home = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::create_dir_all(home.path().join(".config/git"))?;
+    fs::write(
+        home.path().join(".config/git/ignore"),
+        "*.log\n.DS_Store\nbuild/\n",
+    )?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "build\n!keep.log\ndist/\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["diff", "--global", ".gitignore"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Only in global gitignore ("))
+        .stdout(predicate::str::contains("  .DS_Store (line 2)\n"))
+        .stdout(predicate::str::contains(
+            "Only in .gitignore (2):\n  !keep.log (line 2)\n  dist/ (line 3)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "In both (1):\n  build/ (line 3) = build (line 1)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "  !keep.log (.gitignore:2) re-includes keep.log, which *.log (global gitignore",
+        ));
+
+    fs::write(temp_dir.path().join("copy"), "build/\n")?;
+    fs::write(temp_dir.path().join("other"), "/build\n")?;
+    git_ignore_cmd()
+        .args(["diff", "copy", "other"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Only in copy (1)"))
+        .stdout(predicate::str::contains("Only in other (1)"));
+    fs::write(temp_dir.path().join("other"), "build\n")?;
+    git_ignore_cmd()
+        .args(["diff", "copy", "other"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "copy and other have the same patterns",
+        ));
+
+    git_ignore_cmd()
+        .args(["diff", "--local", "copy", "other"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not both"));
+    git_ignore_cmd()
+        .args(["diff", "missing"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(4);
+
+    Ok(())
+}
+
+#[test]
+fn test_fmt_ignore_file() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore = temp_dir.path().join(".gitignore");
+    fs::write(
+        &gitignore,
+        "# Build\ntarget/  \n# docs output\ndocs/api/\n\n\n\n*.log\n\n# build\nbuild/\n*.log\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["fmt", "--check"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("is not formatted"));
+
+    git_ignore_cmd()
+        .arg("fmt")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Formatted .gitignore"));
+    assert_eq!(
+        fs::read_to_string(&gitignore)?,
+        "# Build\nbuild/\n# docs output\ndocs/api/\ntarget/\n\n*.log\n"
+    );
+
+    git_ignore_cmd()
+        .args(["fmt", "--check"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("already formatted"));
+
+    Ok(())
+}
+
+#[test]
+fn test_fmt_keeps_indented_hash_pattern() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore = temp_dir.path().join(".gitignore");
+    // git reads `  #notes` as a pattern: only a `#` in column 0 is a comment
+    fs::write(&gitignore, "*.log\n  #notes\n# real comment\nbuild/\n")?;
+
+    let paths = ["  #notes", "#notes", "# real comment", "a.log", "build"];
+    let ignored = || -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git")
+            .args(["check-ignore", "--no-index"])
+            .args(paths)
+            .current_dir(temp_dir.path())
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let before = ignored()?;
+    assert!(before.lines().any(|line| line == "  #notes"));
+
+    git_ignore_cmd()
+        .arg("fmt")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&gitignore)?
+        .lines()
+        .any(|line| line == "  #notes"));
+    assert_eq!(ignored()?, before);
+
+    Ok(())
+}
+
+#[test]
+fn test_dedupe_subsumed_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore = temp_dir.path().join(".gitignore");
+    fs::write(
+        &gitignore,
+        "build/\n*.log\n# objects\nbuild/foo.o\nsrc/*.log\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["dedupe", "--dry-run"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  build/foo.o (line 4) is covered by build/ (line 1)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "  src/*.log (line 5) is covered by *.log (line 2)\n",
+        ));
+
+    // Asking needs a terminal
+    git_ignore_cmd()
+        .arg("dedupe")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3);
+
+    git_ignore_cmd()
+        .args(["dedupe", "--yes"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 2 patterns"));
+    assert_eq!(
+        fs::read_to_string(&gitignore)?,
+        "build/\n*.log\n# objects\n"
+    );
+
+    git_ignore_cmd()
+        .arg("dedupe")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No redundant patterns"));
+
+    Ok(())
+}
+
+#[test]
+fn test_dedupe_keeps_wider_character_class() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore = temp_dir.path().join(".gitignore");
+    fs::write(&gitignore, "f[0-8]\nf[0-9]\n")?;
+    fs::write(temp_dir.path().join("f9"), "")?;
+
+    git_ignore_cmd()
+        .args(["dedupe", "--yes"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "f[0-8] (line 1) is covered by f[0-9] (line 2)",
+        ));
+    assert_eq!(fs::read_to_string(&gitignore)?, "f[0-9]\n");
+
+    let status = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(!String::from_utf8(status.stdout)?.contains("f9"));
+
+    Ok(())
+}
+
+#[test]
+fn test_unignore_nested_path() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::create_dir_all(temp_dir.path().join("build/gen"))?;
+    fs::write(temp_dir.path().join("build/gen/keep.txt"), "")?;
+    fs::write(temp_dir.path().join("build/gen/other.txt"), "")?;
+    fs::write(temp_dir.path().join(".gitignore"), "build/\n")?;
+
+    git_ignore_cmd()
+        .args(["unignore", "gen/keep.txt"])
+        .current_dir(temp_dir.path().join("build"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Unignored gen/keep.txt in .gitignore",
+        ));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "build/\n\n!/build/\n/build/*\n!/build/gen/\n/build/gen/*\n!/build/gen/keep.txt\n"
+    );
+    git_ignore_cmd()
+        .args(["check", "build/gen/keep.txt"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5);
+    git_ignore_cmd()
+        .args(["check", "build/gen/other.txt"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    git_ignore_cmd()
+        .args(["unignore", "build/gen/keep.txt"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("build/gen/keep.txt is not ignored\n");
+
+    // A deeper .gitignore wins over the root one; nothing is left behind
+    fs::write(temp_dir.path().join("build/gen/.gitignore"), "keep.txt\n")?;
+    let before = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    git_ignore_cmd()
+        .args(["unignore", "build/gen/keep.txt"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("still ignored by keep.txt"));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        before
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_audit_tracked_ignored() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::create_dir_all(temp_dir.path().join("target"))?;
+    fs::write(temp_dir.path().join("target/app"), "")?;
+    fs::write(temp_dir.path().join("debug.log"), "")?;
+    fs::write(temp_dir.path().join("main.rs"), "")?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .arg("audit")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("No tracked files match the ignore rules\n");
+
+    fs::write(temp_dir.path().join(".gitignore"), "# Build\ntarget/\n")?;
+    fs::write(temp_dir.path().join(".git/info/exclude"), "*.log\n")?;
+    git_ignore_cmd()
+        .arg("audit")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stdout(
+            "PATH        PATTERN  SOURCE               SCOPE\n\
+             debug.log   *.log    .git/info/exclude:1  local\n\
+             target/app  target/  .gitignore:2         repo\n",
+        )
+        .stderr(predicate::str::contains("2 tracked file(s)"));
+
+    git_ignore_cmd()
+        .args(["audit", "--format", "json"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stdout(predicate::str::contains(
+            r#"{"path":"target/app","pattern":"target/","source":".gitignore","line":2,"scope":"repo"}"#,
+        ));
+
+    git_ignore_cmd()
+        .args(["audit", "--format", "sarif"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stdout(predicate::str::contains(r#""version":"2.1.0""#))
+        .stdout(predicate::str::contains(
+            r#""ruleId":"GI018","ruleIndex":17,"level":"warning","message":{"text":"Tracked file 'target/app' matches this pattern"#,
+        ))
+        .stdout(predicate::str::contains(
+            r#""uri":".git/info/exclude","uriBaseId":"%SRCROOT%"},"region":{"startLine":1}"#,
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_untrack_ignored_files() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::create_dir_all(temp_dir.path().join("target/debug"))?;
+    fs::write(temp_dir.path().join("target/debug/app"), "")?;
+    fs::write(temp_dir.path().join("debug.log"), "")?;
+    fs::write(temp_dir.path().join("main.rs"), "")?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(temp_dir.path())
+        .output()?;
+    fs::write(temp_dir.path().join(".gitignore"), "target/\n")?;
+
+    git_ignore_cmd()
+        .args(["untrack", "--dry-run"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("Would stop tracking 1 file(s):\n  target/debug/app\n");
+
+    // Confirmation can't be asked for without a terminal
+    git_ignore_cmd()
+        .arg("untrack")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("pass --yes"));
+
+    git_ignore_cmd()
+        .args(["untrack", "--yes", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  debug.log\nUntracked 1 file(s)"));
+    git_ignore_cmd()
+        .args(["untrack", "-y"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let tracked = Command::new("git")
+        .args(["ls-files"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert_eq!(String::from_utf8(tracked.stdout)?, "main.rs\n");
+    assert!(temp_dir.path().join("target/debug/app").exists());
+
+    git_ignore_cmd()
+        .arg("untrack")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("No tracked files match the ignore rules\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_add_pattern_matching_tracked_files() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::create_dir_all(temp_dir.path().join("dist"))?;
+    fs::write(temp_dir.path().join("dist/app.js"), "")?;
+    fs::write(temp_dir.path().join("dist/app.css"), "")?;
+    fs::write(temp_dir.path().join("debug.log"), "")?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .arg("dist/")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: 'dist/' matches 2 tracked file(s), which will not become ignored; pass --untrack",
+        ));
+
+    git_ignore_cmd()
+        .args(["--untrack", "*.log", "dist/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("tracked file(s)").not())
+        .stdout(predicate::str::contains(
+            "Untracked 3 file(s); commit to record the removal",
+        ));
+
+    let tracked = Command::new("git")
+        .args(["ls-files"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert_eq!(String::from_utf8(tracked.stdout)?, "");
+    assert!(temp_dir.path().join("dist/app.js").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_suggest_and_apply() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join("Cargo.toml"), "[package]\n")?;
+    fs::create_dir(temp_dir.path().join("web"))?;
+    fs::write(temp_dir.path().join("web/package.json"), "{}\n")?;
+    fs::write(temp_dir.path().join(".gitignore"), "node_modules/\n")?;
+
+    git_ignore_cmd()
+        .arg("suggest")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  target/  Rust (Cargo.toml)\n"))
+        .stdout(predicate::str::contains("node_modules").not());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "node_modules/\n"
+    );
+
+    git_ignore_cmd()
+        .args(["suggest", "--apply"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added 1 pattern"));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "node_modules/\n\ntarget/\n"
+    );
+
+    git_ignore_cmd()
+        .arg("suggest")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "already has every suggested pattern",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), "target/\n")?;
+
+    git_ignore_cmd()
+        .args(["--dry-run", "*.log", "target", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Would add 1 pattern to .gitignore",
+        ))
+        .stdout(predicate::str::contains("  *.log\n"))
+        .stdout(predicate::str::contains(
+            "Would skip \"target\" (duplicate)",
+        ));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "target/\n"
+    );
+
+    git_ignore_cmd()
+        .args(["--dry-run", "target/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No new patterns would be added"));
+
+    git_ignore_cmd()
+        .args(["--dry-run", "--check", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_and_restore() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    fs::create_dir_all(home.path().join(".config/git"))?;
+    fs::write(home.path().join(".config/git/ignore"), ".DS_Store\n")?;
+
+    let source = TempDir::new()?;
+    init_git_repo(source.path())?;
+    fs::write(source.path().join(".gitignore"), "target/\n*.log")?;
+    fs::create_dir(source.path().join("docs"))?;
+    fs::write(source.path().join("docs/.gitignore"), "_build/\n")?;
+    Command::new("git")
+        .args(["config", "ignore.sorted", "true"])
+        .current_dir(source.path())
+        .output()?;
+    Command::new("git")
+        .args(["config", "ignore.validator", "false"])
+        .current_dir(source.path())
+        .output()?;
+
+    let snapshot_path = home.path().join("ignore.snap");
+    git_ignore_cmd()
+        .args(["snapshot", "-o"])
+        .arg(&snapshot_path)
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(source.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Saved 4 ignore file(s) and 1 setting(s)",
+        ));
+
+    // Restore into a fresh clone on a machine without a global gitignore
+    fs::remove_file(home.path().join(".config/git/ignore"))?;
+    let target = TempDir::new()?;
+    init_git_repo(target.path())?;
+    git_ignore_cmd()
+        .arg("restore")
+        .arg(&snapshot_path)
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(target.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set ignore.sorted = true"));
+
+    assert_eq!(
+        fs::read_to_string(target.path().join(".gitignore"))?,
+        "target/\n*.log"
+    );
+    assert_eq!(
+        fs::read_to_string(target.path().join("docs/.gitignore"))?,
+        "_build/\n"
+    );
+    assert_eq!(
+        fs::read_to_string(home.path().join(".config/git/ignore"))?,
+        ".DS_Store\n"
+    );
+    let validator = Command::new("git")
+        .args(["config", "ignore.validator"])
+        .current_dir(target.path())
+        .output()?;
+    assert!(!validator.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn test_restore_refuses_non_ignore_config() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let snapshot_path = temp_dir.path().join("evil.snap");
+    fs::write(
+        &snapshot_path,
+        "git-ignore snapshot 1\nconfig core.hooksPath /tmp/evil\nfile repo 6 .gitignore\n*.log\n\n",
+    )?;
+
+    git_ignore_cmd()
+        .arg("restore")
+        .arg(&snapshot_path)
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("core.hooksPath"));
+    let hooks_path = Command::new("git")
+        .args(["config", "core.hooksPath"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(!hooks_path.status.success());
+    assert!(!temp_dir.path().join(".gitignore").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_refuses_file_with_conflict_markers() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore_path = temp_dir.path().join(".gitignore");
+    let content = "*.log\n<<<<<<< HEAD\ntarget/\n=======\nbuild/\n>>>>>>> feature\n";
+    fs::write(&gitignore_path, content)?;
+
+    git_ignore_cmd()
+        .arg("dist/")
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Merge conflict markers"))
+        .stderr(predicate::str::contains("line 2"));
+    assert_eq!(fs::read_to_string(&gitignore_path)?, content);
+
+    Ok(())
+}
+
+#[test]
+fn test_no_create_refuses_missing_target() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore_path = temp_dir.path().join(".gitignore");
+
+    git_ignore_cmd()
+        .args(["--no-create", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(6)
+        .stderr(predicate::str::contains("Target file does not exist"));
+    assert!(!gitignore_path.exists());
+
+    Command::new("git")
+        .args(["config", "ignore.noCreate", "true"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    git_ignore_cmd()
+        .arg("*.log")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(6);
+
+    // Existing files are still amended
+    fs::write(&gitignore_path, "target/\n")?;
+    git_ignore_cmd()
+        .arg("*.log")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&gitignore_path)?.contains("*.log"));
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_private_exclude() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let main = temp_dir.path().join("main");
+    let linked = temp_dir.path().join("linked");
+    fs::create_dir(&main)?;
+    init_git_repo(&main)?;
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "init"])
+        .current_dir(&main)
+        .output()?;
+    Command::new("git")
+        .args(["worktree", "add", "../linked"])
+        .current_dir(&main)
+        .output()?;
+    fs::write(linked.join("scratch.txt"), "")?;
+    fs::write(main.join("scratch.txt"), "")?;
+
+    git_ignore_cmd()
+        .args(["--local", "--worktree", "scratch.txt"])
+        .current_dir(&linked)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("worktree exclude"));
+
+    let check_ignore = |dir: &Path| -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(Command::new("git")
+            .args(["check-ignore", "-q", "scratch.txt"])
+            .current_dir(dir)
+            .status()?
+            .success())
+    };
+    assert!(check_ignore(&linked)?);
+    assert!(!check_ignore(&main)?);
+
+    // Shared exclude still applies to every worktree
+    git_ignore_cmd()
+        .args(["--local", "scratch.txt"])
+        .current_dir(&linked)
+        .assert()
+        .success();
+    assert!(check_ignore(&main)?);
+
+    git_ignore_cmd()
+        .args(["--local", "--worktree", "other.txt"])
+        .current_dir(&main)
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("needs a linked worktree"));
+
+    git_ignore_cmd()
+        .args(["--worktree", "other.txt"])
+        .current_dir(&linked)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--worktree only applies to --local",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_exclude_keeps_global_excludes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let home = TempDir::new()?;
+    let main = temp_dir.path().join("main");
+    let linked = temp_dir.path().join("linked");
+    fs::create_dir(&main)?;
+    init_git_repo(&main)?;
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "init"])
+        .current_dir(&main)
+        .output()?;
+    Command::new("git")
+        .args(["worktree", "add", "../linked"])
+        .current_dir(&main)
+        .output()?;
+    fs::create_dir_all(home.path().join(".config").join("git"))?;
+    fs::write(
+        home.path().join(".config").join("git").join("ignore"),
+        "*.swp\n",
+    )?;
+    fs::write(linked.join("scratch.txt"), "")?;
+    fs::write(linked.join("notes.swp"), "")?;
+
+    // Replacing the global excludes file needs the user's say-so
+    git_ignore_cmd()
+        .args(["--local", "--worktree", "scratch.txt"])
+        .current_dir(&linked)
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("--keep-excludes"));
+    let excludes = Command::new("git")
+        .args(["config", "--worktree", "core.excludesFile"])
+        .current_dir(&linked)
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .output()?;
+    assert!(!excludes.status.success());
+
+    git_ignore_cmd()
+        .args(["--local", "--worktree", "--keep-excludes", "scratch.txt"])
+        .current_dir(&linked)
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Copied 1 pattern(s)"));
+
+    let check_ignore = |path: &str| -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(Command::new("git")
+            .args(["check-ignore", "-q", path])
+            .current_dir(&linked)
+            .env("HOME", home.path())
+            .env_remove("XDG_CONFIG_HOME")
+            .status()?
+            .success())
+    };
+    assert!(check_ignore("scratch.txt")?);
+    assert!(check_ignore("notes.swp")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_default_target_from_config() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    Command::new("git")
+        .args(["config", "ignore.defaultTarget", "local"])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .args(["scratch/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Added 1 pattern to .git/info/exclude (",
+        ));
+
+    // An explicit scope flag still wins over the configured default
+    git_ignore_cmd()
+        .args(["--repo", "target/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added 1 pattern to .gitignore ("));
+
+    let gitignore = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    assert!(gitignore.contains("target/"));
+    assert!(!gitignore.contains("scratch/"));
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_default_target() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    Command::new("git")
+        .args(["config", "ignore.defaultTarget", "elsewhere"])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .args(["scratch/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("ignore.defaultTarget"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_validator_hook_can_veto() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    Command::new("git")
+        .args([
+            "config",
+            "ignore.validator",
+            "! grep -q '^!' || { echo 'negations are not allowed' >&2; false; }",
+        ])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .args(["!keep.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("negations are not allowed"));
+    assert!(!temp_dir.path().join(".gitignore").exists());
+
+    git_ignore_cmd()
+        .args(["*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added 1 pattern to .gitignore ("));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_silent_or_hanging_validator_hook_is_reported() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let set_validator = |command: &str| {
+        Command::new("git")
+            .args(["config", "ignore.validator", command])
+            .current_dir(temp_dir.path())
+            .output()
+    };
+
+    set_validator("false")?;
+    git_ignore_cmd()
+        .args(["*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Error: rejected by validator hook 'false'",
+        ));
+
+    set_validator("exec sleep 30")?;
+    let started = std::time::Instant::now();
+    git_ignore_cmd()
+        .args(["--git-timeout", "1", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "validator hook 'exec sleep 30' timed out after 1s",
+        ));
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+    assert!(!temp_dir.path().join(".gitignore").exists());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_validator_hook_output_is_validated() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    // The hook rewrites every pattern into one git could never match
+    Command::new("git")
+        .args(["config", "ignore.validator", "echo '*.[ch'"])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .args(["*.c"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Unterminated bracket expression"));
+    assert!(!temp_dir.path().join(".gitignore").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_global_excludes_file_with_env_vars() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    let dotfiles = home.path().join("dotfiles");
+    fs::create_dir(&dotfiles)?;
+    fs::write(dotfiles.join("gitignore"), ".DS_Store\n")?;
+    Command::new("git")
+        .args([
+            "config",
+            "--global",
+            "core.excludesfile",
+            "${DOTFILES}/gitignore",
+        ])
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args(["--global", "*.swp"])
+        .env("HOME", home.path())
+        .env("DOTFILES", &dotfiles)
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert!(fs::read_to_string(dotfiles.join("gitignore"))?.contains("*.swp"));
+
+    git_ignore_cmd()
+        .args(["--global", "*.tmp"])
+        .env("HOME", home.path())
+        .env_remove("DOTFILES")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("$DOTFILES"));
+
+    Ok(())
+}
+
+#[test]
+fn test_relative_global_excludes_file() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    Command::new("git")
+        .args(["config", "--global", "core.excludesfile", "ignores/global"])
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+    // A fallback git would never read
+    fs::write(home.path().join(".gitignore_global"), "*.bak\n")?;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let subdir = temp_dir.path().join("src");
+    fs::create_dir_all(temp_dir.path().join("ignores"))?;
+    fs::create_dir(&subdir)?;
+
+    git_ignore_cmd()
+        .args(["--global", "*.swp"])
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(&subdir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("core.excludesFile points to"))
+        .stderr(predicate::str::contains("which does not exist"));
+    let global = temp_dir.path().join("ignores/global");
+    assert!(fs::read_to_string(&global)?.contains("*.swp"));
+    assert!(!fs::read_to_string(home.path().join(".gitignore_global"))?.contains("*.swp"));
+
+    // Git agrees on which file that is
+    Command::new("git")
+        .args(["check-ignore", "-q", "notes.swp"])
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(&subdir)
+        .assert()
+        .success();
+
+    git_ignore_cmd()
+        .args(["--global", "*.tmp"])
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(&subdir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("does not exist").not());
+
+    Ok(())
+}
+
+#[test]
+fn test_global_gitignore_follows_git_lookup() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    let xdg = TempDir::new()?;
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    // With XDG_CONFIG_HOME set, git reads only $XDG_CONFIG_HOME/git/ignore;
+    // ~/.config/git/ignore and ~/.gitignore_global are not consulted
+    fs::create_dir_all(home.path().join(".config/git"))?;
+    fs::write(home.path().join(".config/git/ignore"), "*.home\n")?;
+    fs::write(home.path().join(".gitignore_global"), "*.bak\n")?;
+    fs::create_dir_all(xdg.path().join("git"))?;
+    fs::write(xdg.path().join("git/ignore"), "*.xdg\n")?;
+    git_ignore_cmd()
+        .args(["list", "--global"])
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", xdg.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("*.xdg"))
+        .stdout(predicate::str::contains("*.home").not());
+
+    // GIT_CONFIG_GLOBAL replaces ~/.gitconfig as the source of the setting
+    let config = temp_dir.path().join("isolated.gitconfig");
+    let excludes = temp_dir.path().join("isolated.ignore");
+    fs::write(
+        &config,
+        format!("[core]\n\texcludesFile = {}\n", excludes.display()),
+    )?;
+    git_ignore_cmd()
+        .args(["--global", "*.swp"])
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", xdg.path())
+        .env("GIT_CONFIG_GLOBAL", &config)
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&excludes)?, "*.swp\n");
+    assert_eq!(
+        fs::read_to_string(xdg.path().join("git/ignore"))?,
+        "*.xdg\n"
+    );
+
+    // Without XDG_CONFIG_HOME or a setting, ~/.gitignore_global is not a
+    // global gitignore, however it is named
+    fs::remove_dir_all(home.path().join(".config"))?;
+    git_ignore_cmd()
+        .args(["list", "--global"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("global gitignore: not configured"));
+
+    Ok(())
+}
+
+#[test]
+fn test_global_create() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args(["--global", "*.swp"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("--create"));
+
+    git_ignore_cmd()
+        .args(["--global", "--create", "*.swp"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set core.excludesFile to"));
+    let global = home.path().join(".config/git/ignore");
+    assert!(fs::read_to_string(&global)?.ends_with("*.swp\n"));
+
+    // Git now reads the new file
+    Command::new("git")
+        .args(["check-ignore", "-q", "notes.swp"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    // Once set up, --create just adds
+    git_ignore_cmd()
+        .args(["--global", "--create", "*.bak"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set core.excludesFile").not());
+    assert!(fs::read_to_string(&global)?.ends_with("*.swp\n\n*.bak\n"));
+
+    git_ignore_cmd()
+        .args(["--create", "*.bak"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--global"));
+
+    Ok(())
+}
+
+#[test]
+fn test_global_create_keeps_configured_file() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let existing = home.path().join("existing.ignore");
+    fs::write(&existing, "*.swp\n")?;
+    Command::new("git")
+        .args(["config", "--global", "core.excludesFile"])
+        .arg(&existing)
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+
+    let new = home.path().join("new.ignore");
+    git_ignore_cmd()
+        .args(["--global"])
+        .arg(format!("--create={}", new.display()))
+        .arg("*.bak")
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("already points to"));
+    assert!(!new.exists());
+    let configured = Command::new("git")
+        .args(["config", "--global", "core.excludesFile"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+    assert_eq!(
+        String::from_utf8_lossy(&configured.stdout).trim(),
+        existing.to_string_lossy()
+    );
+
+    // Naming the configured file itself is fine
+    git_ignore_cmd()
+        .args(["--global"])
+        .arg(format!("--create={}", existing.display()))
+        .arg("*.bak")
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set core.excludesFile").not());
+    assert!(fs::read_to_string(&existing)?.ends_with("*.bak\n"));
+
+    // So is another spelling of it, and the setting keeps its own
+    Command::new("git")
+        .args([
+            "config",
+            "--global",
+            "core.excludesFile",
+            "~/existing.ignore",
+        ])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+    let dotted = home.path().join("sub/../existing.ignore");
+    fs::create_dir(home.path().join("sub"))?;
+    git_ignore_cmd()
+        .args(["--global"])
+        .arg(format!("--create={}", dotted.display()))
+        .arg("*.tmp")
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set core.excludesFile").not());
+    assert!(fs::read_to_string(&existing)?.ends_with("*.tmp\n"));
+    let configured = Command::new("git")
+        .args(["config", "--global", "core.excludesFile"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+    assert_eq!(
+        String::from_utf8_lossy(&configured.stdout).trim(),
+        "~/existing.ignore"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_lint_text_and_sarif() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "*.log\n./build\n*.log\n",
+    )?;
+
+    git_ignore_cmd()
+        .arg("lint")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            ".gitignore:2: info[GI003]: Pattern starts with './' which is redundant",
+        ))
+        .stdout(predicate::str::contains(
+            ".gitignore:3: info[GI008]: Duplicate of line 1",
+        ));
+
+    git_ignore_cmd()
+        .args(["lint", "--format", "sarif"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""version":"2.1.0""#))
+        .stdout(predicate::str::contains(
+            r#""artifactLocation":{"uri":".gitignore","uriBaseId":"%SRCROOT%"},"region":{"startLine":3}"#,
+        ));
+
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "<<<<<<< HEAD\na\n=======\nb\n>>>>>>> other\n",
+    )?;
+    git_ignore_cmd()
+        .arg("lint")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stdout(predicate::str::contains(".gitignore:1: error[GI007]"));
+
+    Ok(())
+}
+
+#[test]
+fn test_lint_file_strict() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::create_dir_all(temp_dir.path().join("vendor"))?;
+    fs::write(temp_dir.path().join("vendor/ignore-list"), "*.o\n./dist\n")?;
+
+    git_ignore_cmd()
+        .args(["lint", "vendor/ignore-list"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "vendor/ignore-list:2: info[GI003]: Pattern starts with './' which is redundant\n  help: write '/dist' instead",
+        ));
+
+    git_ignore_cmd()
+        .args(["lint", "--strict", "vendor/ignore-list"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("1 finding(s)"));
+
+    // The repository's own .gitignore is clean
+    fs::write(temp_dir.path().join(".gitignore"), "*.o\n")?;
+    git_ignore_cmd()
+        .args(["lint", "--strict"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    git_ignore_cmd()
+        .args(["lint", "missing"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("File not found: missing"));
+
+    Ok(())
+}
+
+#[test]
+fn test_lint_file_outside_repository() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    fs::write(temp_dir.path().join("ignore-list"), "./dist\n")?;
+
+    git_ignore_cmd()
+        .args(["lint", "ignore-list"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ignore-list:1: info[GI003]"));
+
+    git_ignore_cmd()
+        .args(["lint", "--format", "sarif", "ignore-list"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""artifactLocation":{"uri":"ignore-list","uriBaseId":"%SRCROOT%"}"#,
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_lint_fix() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let gitignore = temp_dir.path().join(".gitignore");
+    fs::write(
+        &gitignore,
+        "./dist\nbuild\\output\nlogs/**/**/*.log\n*.tmp  \n*.bak\\ \n*\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["lint", "--fix"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed 4 lines in .gitignore"))
+        .stdout(predicate::str::contains(".gitignore:6: warning[GI005]"))
+        .stdout(predicate::str::contains("GI003").not());
+    assert_eq!(
+        fs::read_to_string(&gitignore)?,
+        "/dist\nbuild/output\nlogs/**/*.log\n*.tmp\n*.bak\\ \n*\n"
+    );
+
+    // Nothing left to fix
+    git_ignore_cmd()
+        .args(["lint", "--fix"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed").not());
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_counts_matches() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "*.log\ntarget/\n*.tmp\n",
+    )?;
+    fs::create_dir_all(temp_dir.path().join("target/debug"))?;
+    fs::write(temp_dir.path().join("target/debug/app"), "")?;
+    fs::write(temp_dir.path().join("target/debug/app.d"), "")?;
+    fs::write(temp_dir.path().join("a.log"), "")?;
+    fs::write(temp_dir.path().join(".git/info/exclude"), "notes/\n")?;
+
+    git_ignore_cmd()
+        .arg("stats")
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("repo: 3 patterns in 1 file"))
+        .stdout(predicate::str::contains("local: 1 pattern in 1 file"))
+        .stdout(predicate::str::contains("global: no ignore file"))
+        .stdout(predicate::str::contains(
+            ".gitignore: 3 patterns, 20 bytes, modified just now",
+        ))
+        .stdout(predicate::str::contains(
+            "  1  *.log\n  2  target/\n  0  *.tmp\n",
+        ))
+        .stdout(predicate::str::contains(
+            "Unused patterns (2):\n  .gitignore:3: *.tmp\n  .git/info/exclude:1: notes/\n",
+        ));
+
+    git_ignore_cmd()
+        .args(["stats", "--local"])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("repo:").not())
+        .stdout(predicate::str::contains("local: 1 pattern in 1 file"));
+
+    Ok(())
+}
+
+#[test]
+fn test_blame_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .env("GIT_AUTHOR_DATE", "2024-03-01T12:00:00+00:00")
+            .current_dir(temp_dir.path())
+            .output()
+    };
+    fs::write(temp_dir.path().join(".gitignore"), "# Build\ntarget/\n")?;
+    git(&["add", ".gitignore"])?;
+    git(&["commit", "-m", "Ignore build output"])?;
+    fs::write(temp_dir.path().join(".gitignore"), "# Build\ntarget/\n*\n")?;
+
+    let output = git_ignore_cmd()
+        .arg("blame")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output)?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("2  "));
+    assert!(lines[0].ends_with("  2024-03-01  Test User          target/"));
+    assert!(lines[1].starts_with("3  0000000  "));
+    assert!(lines[1].ends_with("  Not Committed Yet  *"));
+
+    fs::write(temp_dir.path().join("extra.ignore"), "*.o\n")?;
+    git_ignore_cmd()
+        .args(["blame", "extra.ignore"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("isn't tracked by git"));
+
+    Ok(())
+}
+
+#[test]
+fn test_auto_target_routes_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    fs::create_dir_all(home.path().join(".config/git"))?;
+    fs::write(home.path().join(".config/git/ignore"), "")?;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    Command::new("git")
+        .args(["config", "ignore.autoLocal", "*.prof"])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .args([
+            "--auto-target",
+            ".DS_Store",
+            "scratch/",
+            "target/",
+            "perf.prof",
+        ])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            ".DS_Store -> global (operating system file)",
+        ))
+        .stdout(predicate::str::contains(
+            "perf.prof -> local (matches ignore.autoLocal entry '*.prof')",
+        ));
+
+    let global = fs::read_to_string(home.path().join(".config/git/ignore"))?;
+    let exclude = fs::read_to_string(temp_dir.path().join(".git/info/exclude"))?;
+    let gitignore = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    assert!(global.contains(".DS_Store"));
+    assert!(exclude.contains("scratch/") && exclude.contains("perf.prof"));
+    assert_eq!(gitignore, "target/\n");
+
+    git_ignore_cmd()
+        .args(["--auto-target", "--local", "x"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_per_pattern_targets() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    fs::create_dir_all(home.path().join(".config/git"))?;
+    fs::write(home.path().join(".config/git/ignore"), "")?;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args([
+            "*.log",
+            "--local",
+            "scratch/",
+            "--global",
+            ".DS_Store",
+            "dist/@repo",
+        ])
+        .env("HOME", home.path())
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let gitignore = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    let exclude = fs::read_to_string(temp_dir.path().join(".git/info/exclude"))?;
+    let global = fs::read_to_string(home.path().join(".config/git/ignore"))?;
+    assert_eq!(gitignore, "*.log\ndist/\n");
+    assert!(exclude.contains("scratch/"));
+    assert_eq!(global, ".DS_Store\n");
+
+    // A scope flag after every pattern still applies to all of them
+    git_ignore_cmd()
+        .args(["a.tmp", "b.tmp", "--local"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let exclude = fs::read_to_string(temp_dir.path().join(".git/info/exclude"))?;
+    assert!(exclude.contains("a.tmp") && exclude.contains("b.tmp"));
+
+    Ok(())
+}
+
+#[test]
+fn test_commit_mode_in_bare_repo() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    fs::create_dir(&source)?;
+    init_git_repo(&source)?;
+    fs::write(source.join(".gitignore"), "*.o\n")?;
+    fs::write(source.join("README"), "hello\n")?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&source)
+        .output()?;
+    Command::new("git")
+        .args(["commit", "-m", "init"])
+        .current_dir(&source)
+        .output()?;
+    Command::new("git")
+        .args(["clone", "--bare", "source", "bare.git"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    let bare = temp_dir.path().join("bare.git");
+
+    git_ignore_cmd()
+        .args(["--commit", "--author", "Ignore Bot <bot@example.com>"])
+        .args(["*.log", "*.o"])
+        .current_dir(&bare)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Committed 1 pattern to .gitignore on refs/heads/",
+        ));
+
+    let git = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::new("git").args(args).current_dir(&bare).output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    assert_eq!(git(&["show", "HEAD:.gitignore"])?, "*.o\n\n*.log\n");
+    assert_eq!(git(&["show", "HEAD:README"])?, "hello\n");
+    assert_eq!(
+        git(&["log", "-1", "--format=%an <%ae>|%s"])?.trim(),
+        "Ignore Bot <bot@example.com>|Ignore *.log"
+    );
+
+    // The checked-out branch of a normal clone is left alone
+    git_ignore_cmd()
+        .args(["--commit", "dist/"])
+        .current_dir(&source)
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("is checked out here"));
+
+    Ok(())
+}
+
+#[test]
+fn test_bare_repo_scopes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    Command::new("git")
+        .args(["init", "--bare", "bare.git"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    let bare = temp_dir.path().join("bare.git");
+
+    git_ignore_cmd()
+        .arg("*.log")
+        .current_dir(&bare)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("is a bare repository"))
+        .stderr(predicate::str::contains("--local"));
+
+    git_ignore_cmd()
+        .args(["--local", "*.log"])
+        .current_dir(&bare)
+        .assert()
+        .success();
+    let exclude = fs::read_to_string(bare.join("info").join("exclude"))?;
+    assert!(exclude.ends_with("*.log\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_git_dir_and_work_tree_env() -> Result<(), Box<dyn std::error::Error>> {
+    // The bare-repository dotfiles setup: GIT_DIR names the repository and
+    // GIT_WORK_TREE the directory it tracks
+    let temp_dir = TempDir::new()?;
+    Command::new("git")
+        .args(["init", "--bare", "dotfiles.git"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    let git_dir = temp_dir.path().join("dotfiles.git");
+    let home = temp_dir.path().join("home");
+    fs::create_dir_all(home.join(".config"))?;
+
+    git_ignore_cmd()
+        .arg("*.swp")
+        .env("GIT_DIR", &git_dir)
+        .env("GIT_WORK_TREE", &home)
+        .current_dir(home.join(".config"))
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(home.join(".gitignore"))?, "*.swp\n");
+
+    git_ignore_cmd()
+        .args(["--local", "secrets/"])
+        .env("GIT_DIR", &git_dir)
+        .env("GIT_WORK_TREE", &home)
+        .current_dir(&home)
+        .assert()
+        .success();
+    let exclude = fs::read_to_string(git_dir.join("info").join("exclude"))?;
+    assert!(exclude.ends_with("secrets/\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_system_scope() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path().join("repo");
+    fs::create_dir(&repo)?;
+    init_git_repo(&repo)?;
+    let system_config = temp_dir.path().join("gitconfig");
+    let system_ignore = temp_dir.path().join("gitignore");
+
+    // Nothing configured: say how to set it up rather than guess a file
+    git_ignore_cmd()
+        .args(["--system", "*.bak"])
+        .env("GIT_CONFIG_SYSTEM", &system_config)
+        .current_dir(&repo)
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("no system gitignore configured"))
+        .stderr(predicate::str::contains(
+            system_ignore.to_string_lossy().as_ref(),
+        ));
+
+    Command::new("git")
+        .args(["config", "--system", "core.excludesFile"])
+        .arg(&system_ignore)
+        .env("GIT_CONFIG_SYSTEM", &system_config)
+        .current_dir(&repo)
+        .output()?;
+    git_ignore_cmd()
+        .args(["--system", "*.bak"])
+        .env("GIT_CONFIG_SYSTEM", &system_config)
+        .current_dir(&repo)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("system gitignore"));
+    assert_eq!(fs::read_to_string(&system_ignore)?, "*.bak\n");
+
+    git_ignore_cmd()
+        .args(["check", "a.bak"])
+        .env("GIT_CONFIG_SYSTEM", &system_config)
+        .current_dir(&repo)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("system)"));
+
+    git_ignore_cmd()
+        .args(["--system", "--local", "*.bak"])
+        .env("GIT_CONFIG_SYSTEM", &system_config)
+        .current_dir(&repo)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot combine --system"));
+
+    Ok(())
+}
+
+#[cfg(all(unix, feature = "forge"))]
+#[test]
+fn test_create_pr_pushes_branch_and_opens_request() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("source");
+    fs::create_dir(&source)?;
+    init_git_repo(&source)?;
+    fs::write(source.join(".gitignore"), "*.o\n")?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(&source)
+        .output()?;
+    Command::new("git")
+        .args(["commit", "-m", "init"])
+        .current_dir(&source)
+        .output()?;
+    Command::new("git")
+        .args(["clone", "--bare", "source", "remote.git"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    let remote = temp_dir.path().join("remote.git");
+    for (key, value) in [
+        ("remote.origin.url", "git@github.com:octo/app.git"),
+        ("remote.origin.pushurl", remote.to_str().unwrap()),
+    ] {
+        Command::new("git")
+            .args(["config", key, value])
+            .current_dir(&source)
+            .output()?;
+    }
+
+    // Stand-in for curl that records the request and answers like GitHub
+    let bin = temp_dir.path().join("bin");
+    fs::create_dir(&bin)?;
+    let request = temp_dir.path().join("request");
+    let curl = bin.join("curl");
+    fs::write(
+        &curl,
+        format!(
+            "#!/bin/sh\ncat > '{}'\nprintf '{{\"html_url\": \"https://github.com/octo/app/pull/7\"}}\\n201'\n",
+            request.display()
+        ),
+    )?;
+    fs::set_permissions(&curl, fs::Permissions::from_mode(0o755))?;
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH")?);
+
+    git_ignore_cmd()
+        .args(["--create-pr", "*.log"])
+        .current_dir(&source)
+        .env("PATH", &path)
+        .env("GITHUB_TOKEN", "secret")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Opened pull request https://github.com/octo/app/pull/7 adding 1 pattern:",
+        ));
+
+    let request = fs::read_to_string(&request)?;
+    assert!(request.contains("url = \"https://api.github.com/repos/octo/app/pulls\""));
+    assert!(request.contains("Authorization: Bearer secret"));
+    assert!(request.contains(r#"\"head\":\"git-ignore/log\""#));
+    assert!(request.contains("| `*.log` | project file, shared with the team |"));
+
+    let pushed = Command::new("git")
+        .args(["show", "git-ignore/log:.gitignore"])
+        .current_dir(&remote)
+        .output()?;
+    assert_eq!(String::from_utf8(pushed.stdout)?, "*.o\n\n*.log\n");
+    // The work tree's copy is untouched
+    assert_eq!(fs::read_to_string(source.join(".gitignore"))?, "*.o\n");
+
+    // Without a token nothing is committed
+    git_ignore_cmd()
+        .args(["--create-pr", "dist/"])
+        .current_dir(&source)
+        .env("PATH", &path)
+        .env_remove("GITHUB_TOKEN")
+        .env_remove("GH_TOKEN")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("set GITHUB_TOKEN or GH_TOKEN"));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "forge"))]
+#[test]
+fn test_create_pr_needs_forge_feature() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args(["--create-pr", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("forge feature"));
+
+    Ok(())
+}
+
+#[test]
+fn test_template_dir_seeds_new_repositories() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    let run = |args: &[&str]| {
+        let mut cmd = git_ignore_cmd();
+        cmd.args(args)
+            .env("HOME", home.path())
+            .env_remove("GIT_CONFIG_GLOBAL")
+            .env_remove("GIT_TEMPLATE_DIR")
+            .current_dir(home.path());
+        cmd
+    };
+
+    run(&["template-dir", ".DS_Store"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("init.templateDir"));
+
+    Command::new("git")
+        .args(["config", "--global", "init.templateDir", "~/.git-template"])
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+    run(&["template-dir", ".DS_Store", "*.swp"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Added 2 patterns to template exclude file",
+        ));
+
+    let template = home.path().join("Local.gitignore");
+    fs::write(&template, "# Scratch space\nscratch/\n")?;
+    run(&["template-dir", "--from", template.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Applied template 'Local'"));
+
+    Command::new("git")
+        .args(["init", "fresh"])
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .env_remove("GIT_TEMPLATE_DIR")
+        .current_dir(home.path())
+        .output()?;
+    let exclude = fs::read_to_string(home.path().join("fresh/.git/info/exclude"))?;
+    assert!(exclude.contains(".DS_Store\n*.swp\n"));
+    assert!(exclude.contains(
+        "# BEGIN git-ignore template: Local\n# Scratch space\nscratch/\n# END git-ignore template: Local\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_negation_conflict_warning() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), "*.log\n!keep.log\n")?;
+
+    git_ignore_cmd()
+        .arg("keep.*")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: 'keep.*' would re-ignore keep.log, which '!keep.log' (line 2) re-includes",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_ineffective_negation_warning() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), "*.log\n")?;
+
+    // .gitignore takes precedence over .git/info/exclude
+    git_ignore_cmd()
+        .args(["--local", "!important.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: '!important.log' cannot re-include important.log: '*.log' in",
+        ));
+
+    // A later pattern added with it
+    git_ignore_cmd()
+        .args(["!keep.tmp", "*.tmp"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "!keep.tmp: Negation has no effect: '*.tmp' comes after it and ignores keep.tmp again",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_pattern_sets() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    for pattern in ["__pycache__/", "*.pyc"] {
+        Command::new("git")
+            .args(["config", "--add", "ignore.python.pattern", pattern])
+            .current_dir(temp_dir.path())
+            .output()?;
+    }
+
+    git_ignore_cmd()
+        .args(["@python", "dist/", "@python@local"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "__pycache__/\n*.pyc\ndist/\n"
+    );
+    let exclude = fs::read_to_string(temp_dir.path().join(".git/info/exclude"))?;
+    assert!(exclude.ends_with("__pycache__/\n*.pyc\n"), "{exclude}");
+
+    git_ignore_cmd()
+        .arg("@pyhton")
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown pattern set '@pyhton'"));
+    // --literal takes the name as written
+    git_ignore_cmd()
+        .args(["--literal", "@python"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert!(fs::read_to_string(temp_dir.path().join(".gitignore"))?.ends_with("dist/\n\n@python\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_literal_file_names() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join("#draft [1].txt"), "")?;
+    fs::write(temp_dir.path().join("draft 1.txt"), "")?;
+
+    git_ignore_cmd()
+        .args(["--literal", "#draft [1].txt", "!keep", "notes "])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let content = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    assert_eq!(content, "\\#draft \\[1\\].txt\n\\!keep\nnotes\\ \n");
+
+    let status = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    let status = String::from_utf8(status.stdout)?;
+    assert!(!status.contains("#draft"), "{status}");
+    assert!(status.contains("draft 1.txt"), "{status}");
+
+    Ok(())
+}
+
+#[test]
+fn test_overlapping_pattern_warning() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), "build/\nlogs/app.log\n")?;
+
+    git_ignore_cmd()
+        .arg("build/foo/")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: 'build/foo/' is already ignored by 'build/' (line 1)",
+        ));
+    git_ignore_cmd()
+        .arg("logs/")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Note: 'logs/' makes 'logs/app.log' (line 2) redundant",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_group_block() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), "target/\n")?;
+
+    git_ignore_cmd()
+        .args(["--group", "Protobuf generated", "*.pb.go", "*_pb2.py"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    git_ignore_cmd()
+        .args(["--sorted", "build/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "target/\n\n# git-ignore group: Protobuf generated\n*.pb.go\n*_pb2.py\n\nbuild/\n"
+    );
+
+    git_ignore_cmd()
+        .args(["--group", "x", "--sorted", "a"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_attributes_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+
+    git_ignore_cmd()
+        .args(["--attributes", "*.png   binary", "*.sh text eol=lf"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Added 2 patterns to .gitattributes",
+        ));
+    git_ignore_cmd()
+        .args(["--attributes", "*.png binary"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("all patterns already exist"));
+    git_ignore_cmd()
+        .args(["--attributes", "!*.md text"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(1);
+    git_ignore_cmd()
+        .args(["remove", "--attributes", "*.sh  text eol=lf"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitattributes"))?,
+        "*.png binary\n"
+    );
+    assert!(!temp_dir.path().join(".gitignore").exists());
+
+    git_ignore_cmd()
+        .args(["--attributes", "--local", "*.tmp -diff"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".git/info/attributes"))?,
+        "*.tmp -diff\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_jj_workspace_without_colocation() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let store = temp_dir.path().join(".jj/repo/store");
+    fs::create_dir_all(&store)?;
+    Command::new("git")
+        .args(["init", "--bare", "git"])
+        .current_dir(&store)
+        .output()?;
+    fs::write(store.join("git_target"), "git")?;
+    fs::create_dir(temp_dir.path().join("src"))?;
+
+    git_ignore_cmd()
+        .args(["*.log"])
+        .current_dir(temp_dir.path().join("src"))
+        .assert()
+        .success();
+    git_ignore_cmd()
+        .args(["--local", ".envrc"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "*.log\n"
+    );
+    assert!(fs::read_to_string(store.join("git/info/exclude"))?.contains(".envrc\n"));
+
+    // Only the git backend has ignore files
+    fs::remove_file(store.join("git_target"))?;
+    git_ignore_cmd()
+        .args(["*.tmp"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("doesn't use the git backend"));
+
+    Ok(())
+}
+
+#[test]
+fn test_protected_patterns_need_force() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), ".env\n*.log\n")?;
+    Command::new("git")
+        .args(["config", "--add", "ignore.protected", ".env"])
+        .current_dir(temp_dir.path())
+        .output()?;
+
+    git_ignore_cmd()
+        .args(["remove", "*.log", ".env/"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("refusing to remove .env/"));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        ".env\n*.log\n"
+    );
+
+    git_ignore_cmd()
+        .args(["remove", "--force-protected", ".env"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "*.log\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_sort_command() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "# Libs\nlib10/\nlib2/\n*.log\n\n# Editors\n*.swp\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["sort", "--check"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5);
+    git_ignore_cmd()
+        .args(["sort", "--strategy", "natural"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "# Libs\n*.log\nlib2/\nlib10/\n\n# Editors\n*.swp\n"
+    );
+
+    Command::new("git")
+        .args(["config", "ignore.sortStrategy", "directories-first"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    Command::new("git")
+        .args(["config", "--add", "ignore.sectionOrder", "editors"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    git_ignore_cmd()
+        .args(["sort"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "# Editors\n*.swp\n\n# Libs\nlib2/\nlib10/\n*.log\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_affinity_placement() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "# Build\ntarget/\n*.o\n\n# Logs\n*.log\n",
+    )?;
+
+    git_ignore_cmd()
+        .args(["--affinity", "*.so", "debug.log", "target/doc/", ".env"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "# Build\ntarget/\ntarget/doc/\n*.o\n*.so\n\n# Logs\n*.log\ndebug.log\n\n.env\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_prune_global() -> Result<(), Box<dyn std::error::Error>> {
+    let home = TempDir::new()?;
+    let global = home.path().join("gitignore_global");
+    fs::write(&global, "")?;
+    Command::new("git")
+        .args(["config", "--global", "core.excludesfile"])
+        .arg(&global)
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .output()?;
+
+    let src = TempDir::new()?;
+    for (name, gitignore) in [
+        ("a", ".DS_Store\n*.swp\ntarget/\n"),
+        ("b", ".DS_Store\ntarget/\n"),
+        ("c", "node_modules/\n"),
+    ] {
+        let repo = src.path().join(name);
+        fs::create_dir(&repo)?;
+        init_git_repo(&repo)?;
+        fs::write(repo.join(".gitignore"), gitignore)?;
+    }
+
+    git_ignore_cmd()
+        .args(["prune-global", "--root"])
+        .arg(src.path())
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(3 repositories scanned)"))
+        .stdout(predicate::str::contains(
+            ".DS_Store  in 2 of 3 (operating system file)",
+        ))
+        .stdout(predicate::str::contains("*.swp").not())
+        .stdout(predicate::str::contains("target/").not());
+    assert_eq!(fs::read_to_string(&global)?, "");
+
+    Command::new("git")
+        .args(["config", "ignore.protected", ".DS_Store"])
+        .current_dir(src.path().join("b"))
+        .output()?;
+    git_ignore_cmd()
+        .args(["prune-global", "--apply", "--root"])
+        .arg(src.path())
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Protected pattern"));
+    assert_eq!(fs::read_to_string(&global)?, "");
+
+    git_ignore_cmd()
+        .args(["prune-global", "--apply", "--force-protected"])
+        .arg(src.path().join("a"))
+        .arg(src.path().join("b"))
+        .env("HOME", home.path())
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&global)?, ".DS_Store\n");
+    assert_eq!(
+        fs::read_to_string(src.path().join("a/.gitignore"))?,
+        "*.swp\ntarget/\n"
+    );
+    assert_eq!(
+        fs::read_to_string(src.path().join("b/.gitignore"))?,
+        "target/\n"
+    );
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_git_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    // A git that hangs, like one waiting on a stuck credential helper
+    let bin = TempDir::new()?;
+    let fake_git = bin.path().join("git");
+    fs::write(&fake_git, "#!/bin/sh\nexec sleep 30\n")?;
+    fs::set_permissions(&fake_git, fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        bin.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let started = std::time::Instant::now();
+    // A library backend finds the repository and reads config without git
+    let library_backend = cfg!(any(feature = "git2", feature = "gix"));
+    let add = git_ignore_cmd()
+        .args(["--git-timeout", "1", "*.log"])
+        .env("PATH", &path)
+        .current_dir(temp_dir.path())
+        .assert();
+    if library_backend {
+        add.success();
+    } else {
+        add.code(2).stderr(predicate::str::contains(
+            "Git command timed out after 1s: git",
+        ));
+    }
+    git_ignore_cmd()
+        .args(["check", "build.log"])
+        .env("PATH", &path)
+        .env("GIT_IGNORE_TIMEOUT", "1")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("timed out"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+    assert_eq!(temp_dir.path().join(".gitignore").exists(), library_backend);
+
+    Ok(())
+}
+
+#[test]
+fn test_backup_before_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let backups = |dir: &Path| -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(".gitignore.bak."))
+            .collect();
+        names.sort();
+        names
+    };
+
+    // Creating a file has nothing to back up
+    git_ignore_cmd()
+        .args(["--backup", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert!(backups(temp_dir.path()).is_empty());
+
+    for pattern in ["*.tmp", "*.swp", "build/"] {
+        git_ignore_cmd()
+            .args(["--backup", "--backup-keep", "2", pattern])
+            .current_dir(temp_dir.path())
+            .assert()
+            .success();
+    }
+    // Kept under the git directory, out of the work tree
+    let store_dir = temp_dir.path().join(".git/git-ignore/backups");
+    assert!(backups(temp_dir.path()).is_empty());
+    let kept = backups(&store_dir);
+    assert_eq!(kept.len(), 2);
+    assert_eq!(
+        fs::read_to_string(store_dir.join(&kept[1]))?,
+        "*.log\n\n*.tmp\n\n*.swp\n"
+    );
+    Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .stdout(predicate::str::contains(".bak.").not());
+
+    // Configured location, used by other commands too
+    let store = TempDir::new()?;
+    Command::new("git")
+        .args(["config", "ignore.backupDir"])
+        .arg(store.path())
+        .current_dir(temp_dir.path())
+        .output()?;
+    Command::new("git")
+        .args(["config", "ignore.backup", "true"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    git_ignore_cmd()
+        .args(["remove", "*.tmp"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let mirrored = temp_dir
+        .path()
+        .components()
+        .filter(|part| matches!(part, std::path::Component::Normal(_)))
+        .fold(store.path().to_path_buf(), |dir, part| dir.join(part));
+    assert_eq!(backups(&mirrored).len(), 1);
+    assert_eq!(backups(&store_dir).len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_repository_warning() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let nested = temp_dir.path().join("vendor/lib");
+    fs::create_dir_all(&nested)?;
+    init_git_repo(&nested)?;
+
+    git_ignore_cmd()
+        .args(["vendor/", "*.log"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "vendor/: Matches vendor/lib, a separate git repository",
+        ))
+        .stderr(predicate::str::contains(
+            "git submodule add <url> vendor/lib",
+        ))
+        .stderr(predicate::str::contains("*.log:").not());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "vendor/\n*.log\n"
+    );
+
+    // Once ignored, the repository is no longer reported
+    git_ignore_cmd()
+        .args(["lib"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("separate git repository").not());
+
+    Ok(())
+}
+
+/// A directory holding a `curl` that answers for github/gitignore without
+/// the network, and the PATH that puts it first
+#[cfg(unix)]
+fn fake_template_curl() -> Result<(TempDir, String), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = TempDir::new()?;
+    let fake_curl = bin.path().join("curl");
+    fs::write(
+        &fake_curl,
+        r#"#!/bin/sh
+for url; do :; done
+case "$url" in
+  *git/trees*) echo '{"tree":[{"path":"Elixir.gitignore"},{"path":"Rust.gitignore"},{"path":"Global/Emacs.gitignore"}]}' ;;
+  */Elixir.gitignore) printf '# Build\n_build/\ndeps/\n' ;;
+  */Rust.gitignore) printf 'target/\nnewer-upstream-pattern\n' ;;
+  */Global/Emacs.gitignore) printf '*~\n' ;;
+  *) echo "curl: (22) The requested URL returned error: 404" >&2; exit 22 ;;
+esac
+"#,
+    )?;
+    fs::set_permissions(&fake_curl, fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        bin.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    Ok((bin, path))
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_from_github() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join(".gitignore"), "deps\n")?;
+    let cache = TempDir::new()?;
+    let (_bin, path) = fake_template_curl()?;
+
+    git_ignore_cmd()
+        .args(["template", "elixir", "EMACS"])
+        .env("PATH", &path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Applied template 'Elixir' to .gitignore",
+        ))
+        .stdout(predicate::str::contains(
+            "skipped 1 pattern already in the file: deps/",
+        ))
+        .stdout(predicate::str::contains("Applied template 'Emacs'"));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        "deps\n\n\
+         # BEGIN git-ignore template: Elixir\n# Build\n_build/\n# END git-ignore template: Elixir\n\n\
+         # BEGIN git-ignore template: Emacs\n*~\n# END git-ignore template: Emacs\n"
+    );
+    assert!(cache
+        .path()
+        .join("git-ignore/templates/Elixir.gitignore")
+        .exists());
+
+    git_ignore_cmd()
+        .args(["template", "elixir"])
+        .env("PATH", &path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("already up to date"));
+
+    git_ignore_cmd()
+        .args(["template", "elix"])
+        .env("PATH", &path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unknown template 'elix'; did you mean Elixir?",
+        ));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_bundled_templates_offline() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    let cache = TempDir::new()?;
+    let (_bin, path) = fake_template_curl()?;
+
+    // Without a network only the bundled copy can be used
+    let offline = TempDir::new()?;
+    let failing_curl = offline.path().join("curl");
+    fs::write(
+        &failing_curl,
+        "#!/bin/sh\necho 'curl: (6) Could not resolve host' >&2\nexit 6\n",
+    )?;
+    fs::set_permissions(&failing_curl, fs::Permissions::from_mode(0o755))?;
+    let offline_path = format!(
+        "{}:{}",
+        offline.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    git_ignore_cmd()
+        .args(["template", "rust"])
+        .env("PATH", &offline_path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Applied template 'Rust'"));
+    git_ignore_cmd()
+        .args(["template", "elixir"])
+        .env("PATH", &offline_path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Could not resolve host"));
+    let content = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    assert!(content.contains("**/*.rs.bk\n"));
+
+    // A refreshed copy replaces the bundled one from then on
+    git_ignore_cmd()
+        .args(["template", "--refresh", "rust"])
+        .env("PATH", &path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Refreshed 1 template(s)"));
+    let content = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    assert!(content.contains("newer-upstream-pattern\n"));
+    assert!(!content.contains("**/*.rs.bk"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_init_from_detected_languages() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+    init_git_repo(temp_dir.path())?;
+    fs::write(temp_dir.path().join("Cargo.toml"), "[package]\n")?;
+    fs::write(temp_dir.path().join("mix.exs"), "defmodule X do\nend\n")?;
+    let cache = TempDir::new()?;
+
+    // Offline, the non-bundled Elixir template falls back to suggestions
+    let offline = TempDir::new()?;
+    let failing_curl = offline.path().join("curl");
+    fs::write(
+        &failing_curl,
+        "#!/bin/sh\necho 'curl: (6) Could not resolve host' >&2\nexit 6\n",
+    )?;
+    fs::set_permissions(&failing_curl, fs::Permissions::from_mode(0o755))?;
+    let offline_path = format!(
+        "{}:{}",
+        offline.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    git_ignore_cmd()
+        .arg("init")
+        .env("PATH", &offline_path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Applied template 'Rust'"))
+        .stdout(predicate::str::contains("  _build/\n  deps/\n"))
+        .stderr(predicate::str::contains("skipping template 'Elixir'"));
+    let content = fs::read_to_string(temp_dir.path().join(".gitignore"))?;
+    assert!(content.contains("# BEGIN git-ignore template: Rust\n"));
+    assert!(content.ends_with("\n_build/\ndeps/\n"));
+
+    // An existing file is only changed with --merge
+    git_ignore_cmd()
+        .arg("init")
+        .env("PATH", &offline_path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("pass --merge"));
+    git_ignore_cmd()
+        .args(["init", "--merge"])
+        .env("PATH", &offline_path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Template 'Rust' is already up to date",
+        ));
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".gitignore"))?,
+        content
+    );
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_list_and_search() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let cache = TempDir::new()?;
+    let (_bin, path) = fake_template_curl()?;
+
+    git_ignore_cmd()
+        .args(["template", "--list"])
+        .env("PATH", &path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?m)^Elixir +remote$")?)
+        .stdout(predicate::str::is_match(r"(?m)^Rust +bundled, remote$")?)
+        .stdout(predicate::str::is_match(
+            r"(?m)^Emacs +remote  \(Global\)$",
+        )?)
+        .stdout(predicate::str::is_match(
+            r"(?m)^macOS +bundled  \(Global\)$",
+        )?);
+
+    git_ignore_cmd()
+        .args(["template", "--search", "EMA"])
+        .env("PATH", &path)
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("Emacs  remote  (Global)\n");
+
+    // Offline, the bundled templates are still listed
+    git_ignore_cmd()
+        .args(["template", "--search", "rust"])
+        .env("PATH", "/nonexistent")
+        .env("XDG_CACHE_HOME", cache.path())
+        .current_dir(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("Rust  bundled\n")
+        .stderr(predicate::str::contains(
+            "Warning: showing bundled and cached templates only",
+        ));
+
+    Ok(())
+}